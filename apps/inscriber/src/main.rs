@@ -6,10 +6,9 @@ use std::{
     fmt::{self, Display},
     io::{self, IsTerminal},
     path::PathBuf,
-    process::Command,
     sync::OnceLock,
 };
-use stellarator::{buf::IoBuf, fs, rent};
+use stellarator::{buf::IoBuf, fs, process::Command, rent};
 use zstd::stream::raw::{Decoder, Operation};
 
 #[derive(Parser, Clone)]
@@ -65,7 +64,7 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
 
-    let disks = list_external_disks()?;
+    let disks = list_external_disks().await?;
     let disk = if let Some(disk) = args.disk {
         disks
             .into_iter()
@@ -86,7 +85,7 @@ async fn main() -> anyhow::Result<()> {
         let disk = query.run()?;
         disks.into_iter().find(|f| f.to_string() == disk).unwrap()
     };
-    disk.unmount()?;
+    disk.unmount().await?;
 
     let image_name = args
         .image
@@ -181,11 +180,12 @@ struct ExternalDisk {
 
 impl ExternalDisk {
     #[cfg(target_os = "macos")]
-    pub fn unmount(&self) -> anyhow::Result<()> {
+    pub async fn unmount(&self) -> anyhow::Result<()> {
         let output = Command::new("diskutil")
             .arg("unmountDisk")
             .arg(&self.identifier)
-            .output()?;
+            .output()
+            .await?;
 
         if !output.status.success() {
             return Err(anyhow::anyhow!("Failed to run diskutil list"));
@@ -194,8 +194,8 @@ impl ExternalDisk {
     }
 
     #[cfg(target_os = "linux")]
-    pub fn unmount(&self) -> anyhow::Result<()> {
-        let output = Command::new("umount").arg(&self.path).output()?;
+    pub async fn unmount(&self) -> anyhow::Result<()> {
+        let output = Command::new("umount").arg(&self.path).output().await?;
 
         if !output.status.success() {
             return Err(anyhow::anyhow!("Failed to run diskutil list"));
@@ -217,8 +217,8 @@ impl Display for ExternalDisk {
 }
 
 #[cfg(target_os = "macos")]
-fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
-    let output = Command::new("diskutil").arg("list").output()?;
+async fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
+    let output = Command::new("diskutil").arg("list").output().await?;
 
     if !output.status.success() {
         return Err(anyhow::anyhow!("Failed to run diskutil list"));
@@ -266,10 +266,11 @@ fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
 }
 
 #[cfg(target_os = "linux")]
-fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
+async fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
     let output = Command::new("lsblk")
         .args(["-o", "NAME,SIZE,TYPE,MODEL,MOUNTPOINT", "-d", "-n", "-p"])
-        .output()?;
+        .output()
+        .await?;
 
     if !output.status.success() {
         return Err(anyhow::anyhow!("Failed to run lsblk"));
@@ -283,7 +284,7 @@ fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
         if parts.len() >= 3 && parts[2] == "disk" {
             let path = parts[0].to_string();
 
-            let is_external = is_external_disk_linux(&path)?;
+            let is_external = is_external_disk_linux(&path).await?;
 
             if is_external {
                 let identifier = path.split('/').last().unwrap_or("").to_string();
@@ -313,7 +314,7 @@ fn list_external_disks() -> anyhow::Result<Vec<ExternalDisk>> {
 }
 
 #[cfg(target_os = "linux")]
-fn is_external_disk_linux(path: &str) -> anyhow::Result<bool> {
+async fn is_external_disk_linux(path: &str) -> anyhow::Result<bool> {
     let device_name = path.split('/').last().unwrap_or("");
     if device_name.is_empty() {
         return Ok(false);
@@ -335,7 +336,7 @@ fn is_external_disk_linux(path: &str) -> anyhow::Result<bool> {
     }
 
     if device_name == "sda" {
-        let output = Command::new("ls").arg("/sys/block/").output()?;
+        let output = Command::new("ls").arg("/sys/block/").output().await?;
         let output_str = String::from_utf8_lossy(&output.stdout);
         let disk_count = output_str
             .split_whitespace()
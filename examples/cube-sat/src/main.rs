@@ -1,8 +1,4 @@
-use std::{
-    net::SocketAddr,
-    ops::Add,
-    time::{Duration, Instant},
-};
+use std::{net::SocketAddr, ops::Add, time::Duration};
 
 use metor_proto::types::{ComponentId, LenPacket, Msg, OwnedPacket, PacketId, Timestamp};
 use metor_proto_bbq::RxExt;
@@ -15,7 +11,13 @@ use nox::{
 use rand_distr::Distribution;
 use metor_fsw::{AsVTable, Metadatatize, tcp::SinkExt};
 use metor_fsw_adcs::{mekf, yang_lqr::YangLQR};
-use stellarator::{io::SplitExt, net::TcpStream, rent, struc_con::stellar};
+use stellarator::{
+    io::SplitExt,
+    net::TcpStream,
+    rent,
+    struc_con::stellar,
+    time::{Interval, MissedTickBehavior},
+};
 use tracing_subscriber::EnvFilter;
 use zerocopy::{Immutable, IntoBytes, KnownLayout};
 
@@ -487,8 +489,10 @@ pub async fn main() -> anyhow::Result<()> {
     .0?;
     let mut cube_sat = CubeSat::default();
     let mut pkt = LenPacket::new(metor_proto::types::PacketTy::Table, id, size_of::<CubeSat>());
+    let mut interval = Interval::new(Duration::from_secs_f64(DT))
+        .with_missed_tick_behavior(MissedTickBehavior::Skip);
     loop {
-        let start = Instant::now();
+        interval.tick().await;
         while let Some(pkt) = rx.try_recv_pkt() {
             match pkt {
                 OwnedPacket::Msg(m) if m.id == UpdateComponent::ID => {
@@ -517,11 +521,6 @@ pub async fn main() -> anyhow::Result<()> {
         pkt.extend_from_slice(cube_sat.as_bytes());
         rent!(tx.send(pkt).await, pkt)?;
         pkt.clear();
-
-        let sleep = Duration::from_secs_f64(DT).saturating_sub(start.elapsed());
-        if sleep > Duration::ZERO {
-            stellarator::sleep(sleep).await;
-        }
     }
 }
 
@@ -0,0 +1,324 @@
+use std::{collections::HashMap, net::SocketAddr, path::Path};
+
+use anyhow::{anyhow, bail};
+use metor_proto::{
+    types::{ComponentId, LenPacket, PacketId, PrimType, Timestamp},
+    vtable::{
+        VTable,
+        builder::{component, raw_field, raw_table, schema, timestamp, vtable},
+    },
+};
+use metor_proto_wkt::Capabilities;
+use serde::Deserialize;
+use stellarator::net::{CanFilter, CanFrame, CanId};
+use zerocopy::IntoBytes;
+
+/// The bridge's config file, e.g.
+///
+/// ```toml
+/// interface = "can0"
+///
+/// [[frame]]
+/// id = 0x181
+///
+/// [[frame.signal]]
+/// component = "bms.pack_voltage"
+/// type = "u16"
+/// offset = 0
+/// byte-order = "big"
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(default = "default_db")]
+    pub db: SocketAddr,
+    pub interface: String,
+    /// Receives CAN FD frames as well as classic frames
+    #[serde(default)]
+    pub fd: bool,
+    /// Compresses the tables sent to the db, which is worth it over a slow link
+    #[serde(default)]
+    pub compression: bool,
+    #[serde(rename = "frame")]
+    pub frames: Vec<FrameConfig>,
+}
+
+fn default_db() -> SocketAddr {
+    SocketAddr::new([127, 0, 0, 1].into(), 2240)
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FrameConfig {
+    pub id: u32,
+    #[serde(default)]
+    pub extended: bool,
+    #[serde(rename = "signal")]
+    pub signals: Vec<SignalConfig>,
+}
+
+impl FrameConfig {
+    fn can_id(&self) -> Option<CanId> {
+        if self.extended {
+            CanId::extended(self.id)
+        } else {
+            u16::try_from(self.id).ok().and_then(CanId::standard)
+        }
+    }
+}
+
+/// A component read out of a frame's payload
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SignalConfig {
+    pub component: String,
+    #[serde(rename = "type")]
+    pub ty: PrimType,
+    /// The byte offset of the first element in the payload
+    #[serde(default)]
+    pub offset: usize,
+    /// The number of elements, more than one is stored as an array
+    #[serde(default = "one")]
+    pub len: usize,
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ByteOrder {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Config {
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let config = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&config)?)
+    }
+
+    /// The capabilities to negotiate with the db
+    pub fn capabilities(&self) -> Capabilities {
+        if self.compression {
+            Capabilities::TIME_SYNC | Capabilities::COMPRESSION_LZ4 | Capabilities::COMPRESSION_ZSTD
+        } else {
+            Capabilities::TIME_SYNC
+        }
+    }
+
+    /// Lays out a table for each frame, numbering the vtable ids up from `first_vtable_id`
+    pub fn frame_maps(&self, first_vtable_id: u16) -> anyhow::Result<HashMap<CanId, FrameMap>> {
+        let mut maps = HashMap::new();
+        for (i, frame) in self.frames.iter().enumerate() {
+            let id = frame
+                .can_id()
+                .ok_or_else(|| anyhow!("can id {:#x} is out of range", frame.id))?;
+            let vtable_id = first_vtable_id.wrapping_add(i as u16).to_le_bytes();
+            let map = FrameMap::new(vtable_id, frame, self.fd)?;
+            if maps.insert(id, map).is_some() {
+                bail!("can id {:#x} is mapped twice", frame.id);
+            }
+        }
+        Ok(maps)
+    }
+
+    /// Filters that only let the configured frames through
+    pub fn filters(&self) -> Vec<CanFilter> {
+        self.frames
+            .iter()
+            .filter_map(FrameConfig::can_id)
+            .map(CanFilter::exact)
+            .collect()
+    }
+}
+
+/// Where each signal of a frame lands in the table sent to the db
+pub struct FrameMap {
+    pub vtable_id: PacketId,
+    fields: Vec<FieldMap>,
+    table_len: usize,
+}
+
+struct FieldMap {
+    component_id: ComponentId,
+    name: String,
+    ty: PrimType,
+    len: usize,
+    frame_offset: usize,
+    table_offset: usize,
+    byte_order: ByteOrder,
+}
+
+impl FieldMap {
+    fn size(&self) -> usize {
+        self.ty.size() * self.len
+    }
+}
+
+impl FrameMap {
+    fn new(vtable_id: PacketId, frame: &FrameConfig, fd: bool) -> anyhow::Result<Self> {
+        let max_len = if fd { 64 } else { 8 };
+        // the timestamp comes first, like `metor_fsw::tcp::timestamped_table`
+        let mut table_len = size_of::<Timestamp>();
+        let mut fields = vec![];
+        for signal in &frame.signals {
+            if matches!(signal.ty, PrimType::Bytes | PrimType::Utf8) {
+                bail!("{} has a variable length type", signal.component);
+            }
+            table_len += signal.ty.padding(table_len);
+            let field = FieldMap {
+                component_id: ComponentId::new(&signal.component),
+                name: signal.component.clone(),
+                ty: signal.ty,
+                len: signal.len,
+                frame_offset: signal.offset,
+                table_offset: table_len,
+                byte_order: signal.byte_order,
+            };
+            if field.frame_offset + field.size() > max_len {
+                bail!(
+                    "{} doesn't fit in a {max_len} byte payload",
+                    signal.component
+                );
+            }
+            table_len += field.size();
+            fields.push(field);
+        }
+        Ok(FrameMap {
+            vtable_id,
+            fields,
+            table_len,
+        })
+    }
+
+    pub fn vtable(&self) -> VTable {
+        vtable(self.fields.iter().map(|field| {
+            let dim = if field.len == 1 {
+                &[][..]
+            } else {
+                &[field.len as u64][..]
+            };
+            raw_field(
+                field.table_offset as u32,
+                field.size() as u32,
+                timestamp(
+                    raw_table(0, size_of::<Timestamp>() as u32),
+                    schema(field.ty, dim, component(field.component_id)),
+                ),
+            )
+        }))
+    }
+
+    /// The `(component id, name)` of each signal
+    pub fn components(&self) -> impl Iterator<Item = (ComponentId, &str)> {
+        self.fields
+            .iter()
+            .map(|field| (field.component_id, field.name.as_str()))
+    }
+
+    /// Builds a table from `frame`'s signals, returning `None` if the payload is too short
+    pub fn table(&self, frame: &CanFrame, timestamp: Timestamp) -> Option<LenPacket> {
+        let data = frame.data();
+        if self
+            .fields
+            .iter()
+            .any(|field| field.frame_offset + field.size() > data.len())
+        {
+            return None;
+        }
+        let mut buf = vec![0u8; self.table_len];
+        buf[..size_of::<Timestamp>()].copy_from_slice(timestamp.as_bytes());
+        for field in &self.fields {
+            let src = &data[field.frame_offset..field.frame_offset + field.size()];
+            let dst = &mut buf[field.table_offset..field.table_offset + field.size()];
+            dst.copy_from_slice(src);
+            if field.byte_order == ByteOrder::Big {
+                for elem in dst.chunks_mut(field.ty.size()) {
+                    elem.reverse();
+                }
+            }
+        }
+        let mut table = LenPacket::table(self.vtable_id, self.table_len);
+        table.extend_from_slice(&buf);
+        Some(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        interface = "vcan0"
+
+        [[frame]]
+        id = 0x181
+
+        [[frame.signal]]
+        component = "bms.pack_voltage"
+        type = "u16"
+        byte-order = "big"
+
+        [[frame.signal]]
+        component = "bms.cell_temps"
+        type = "i8"
+        offset = 2
+        len = 4
+
+        [[frame]]
+        id = 0x18ff50e5
+        extended = true
+
+        [[frame.signal]]
+        component = "motor.speed"
+        type = "f32"
+        offset = 4
+    "#;
+
+    #[test]
+    fn test_write_table() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert_eq!(config.db, default_db());
+        let maps = config.frame_maps(10).unwrap();
+        assert_eq!(config.filters().len(), 2);
+
+        let map = &maps[&CanId::Standard(0x181)];
+        assert_eq!(map.vtable_id, 10u16.to_le_bytes());
+        let frame = CanFrame::new(CanId::Standard(0x181), &[0x01, 0x02, 1, 2, 3, 4]).unwrap();
+        let table = map.table(&frame, Timestamp(7)).unwrap();
+        let mut expected = LenPacket::table(map.vtable_id, map.table_len);
+        expected.extend_from_slice(Timestamp(7).as_bytes());
+        expected.extend_from_slice(0x0102u16.as_bytes());
+        expected.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(table.inner, expected.inner);
+
+        let short = CanFrame::new(CanId::Standard(0x181), &[0x01, 0x02]).unwrap();
+        assert!(map.table(&short, Timestamp(8)).is_none());
+
+        let map = &maps[&CanId::Extended(0x18ff50e5)];
+        assert_eq!(map.vtable_id, 11u16.to_le_bytes());
+        assert_eq!(map.table_len, 12);
+    }
+
+    #[test]
+    fn test_rejects_oversized_signal() {
+        let config: Config = toml::from_str(
+            r#"
+            interface = "vcan0"
+            [[frame]]
+            id = 1
+            [[frame.signal]]
+            component = "too_big"
+            type = "f64"
+            offset = 4
+            "#,
+        )
+        .unwrap();
+        assert!(config.frame_maps(0).is_err());
+    }
+}
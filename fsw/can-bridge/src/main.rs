@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use metor_fsw::tcp::DbClock;
+use metor_proto_stellar::Client;
+use metor_proto_wkt::{SetComponentMetadata, VTableMsg};
+use stellarator::net::{CanFrame, CanId, CanSocket};
+
+mod config;
+
+use config::{Config, FrameMap};
+
+/// How many frames to forward between clock syncs with the db
+const RESYNC_INTERVAL: u64 = 1000;
+
+/// How often dropped frames are reported, so a misbehaving node can't flood the log
+const DROP_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Counts the frames too short for their mapping, reporting them once per
+/// [`DROP_REPORT_INTERVAL`]
+#[derive(Default)]
+struct DroppedFrames {
+    count: u64,
+    last_report: Option<Instant>,
+}
+
+impl DroppedFrames {
+    fn record(&mut self, frame: &CanFrame) {
+        self.count += 1;
+        let now = Instant::now();
+        if self
+            .last_report
+            .is_some_and(|last| now - last < DROP_REPORT_INTERVAL)
+        {
+            return;
+        }
+        eprintln!("dropped {} short frames, latest {frame:?}", self.count);
+        self.count = 0;
+        self.last_report = Some(now);
+    }
+}
+
+async fn connect(config: &Config, maps: &HashMap<CanId, FrameMap>) -> anyhow::Result<()> {
+    let mut socket = CanSocket::open(&config.interface)?;
+    socket.set_fd_frames(config.fd)?;
+    socket.set_filters(&config.filters())?;
+
+    let mut client = Client::connect_with_capabilities(config.db, config.capabilities())
+        .await
+        .map_err(anyhow::Error::from)?;
+    for map in maps.values() {
+        for (component_id, name) in map.components() {
+            client
+                .send(&SetComponentMetadata::new(component_id, name))
+                .await
+                .0?;
+        }
+        client
+            .send(&VTableMsg {
+                id: map.vtable_id,
+                vtable: map.vtable(),
+            })
+            .await
+            .0?;
+    }
+    let mut db_clock = DbClock::default();
+    db_clock.sync(&mut client, 8).await?;
+    let mut dropped = DroppedFrames::default();
+
+    for count in 1u64.. {
+        let frame = socket.recv().await?;
+        if count % RESYNC_INTERVAL == 0 {
+            db_clock.sync(&mut client, 1).await?;
+        }
+        // remote frames ask for a frame with their id rather than carry any data
+        if frame.is_remote() {
+            continue;
+        }
+        let Some(map) = maps.get(&frame.id()) else {
+            continue;
+        };
+        let Some(table) = map.table(&frame, db_clock.now()) else {
+            dropped.record(&frame);
+            continue;
+        };
+        client.send(table).await.0?;
+    }
+    Ok(())
+}
+
+#[stellarator::main]
+async fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "can-bridge.toml".to_string());
+    let config = Config::read(&path)?;
+    let maps = config.frame_maps(fastrand::u16(..))?;
+    loop {
+        if let Err(err) = connect(&config, &maps).await {
+            eprintln!("error bridging {err:?}");
+            stellarator::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
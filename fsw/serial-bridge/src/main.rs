@@ -1,11 +1,13 @@
 use metor_proto::types::{LenPacket, Msg, PacketId};
-use metor_proto_wkt::{MsgStream, SetComponentMetadata};
-use metor_fsw::tcp::SinkExt;
+use metor_proto_frame::{FrameDecoder, FrameEncoder};
+use metor_proto_stellar::Client;
+use metor_proto_wkt::{CounterClock, MsgStream, SetComponentMetadata};
+use metor_fsw::tcp::{DbClock, SinkExt};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use stellarator::io::{AsyncRead, AsyncWrite};
-use stellarator::net::TcpStream;
 use stellarator::rent;
+use stellarator::serial::{Baud, SerialConfig, SerialPort};
 use stellarator::{io::SplitExt, struc_con::Joinable};
 use zerocopy::{FromBytes, Immutable, IntoBytes};
 
@@ -29,29 +31,30 @@ struct Command {
 }
 
 pub async fn connect() -> anyhow::Result<()> {
-    let stream = TcpStream::connect(SocketAddr::new([127, 0, 0, 1].into(), 2240))
+    let mut client = Client::connect(SocketAddr::new([127, 0, 0, 1].into(), 2240))
         .await
         .map_err(anyhow::Error::from)?;
-    let (rx, tx) = stream.split();
-    let tx = metor_proto_stellar::PacketSink::new(tx);
-    let mut rx = metor_proto_stellar::PacketStream::new(rx);
+    let mut db_clock = DbClock::default();
+    db_clock.sync(&mut client, 8).await?;
+    let Client { tx, mut rx, .. } = client;
 
     let id: PacketId = fastrand::u16(..).to_le_bytes();
     tx.send(&SetComponentMetadata::new("aleph", "aleph"))
         .await
         .0?;
-    tx.init_world::<Record>(id).await?;
+    tx.init_timestamped_world::<Record>(id).await?;
     tx.init_msg::<Command>().await?;
     tx.send(&MsgStream {
         msg_id: Command::ID,
     })
     .await
     .0?;
-    let mut port = stellarator::serial::SerialPort::open("/dev/ttyTHS0").await?;
-    port.set_baud(stellarator::serial::Baud::B115200)?;
+    let config = SerialConfig::new(Baud::B115200);
+    let port = SerialPort::open_with("/dev/ttyTHS0", &config).await?;
     let (port_rx, port_tx) = port.split();
 
     let write = stellarator::struc_con::stellar::<anyhow::Result<()>, _, _>(move || async move {
+        let encoder = FrameEncoder::default();
         let mut buf = vec![0; 256];
         loop {
             let pkt = rx.next(buf).await?;
@@ -59,7 +62,7 @@ pub async fn connect() -> anyhow::Result<()> {
                 metor_proto::types::OwnedPacket::Msg(m) if m.id == Command::ID => {
                     let cmd = m.parse::<Command>()?;
                     println!("cmd {cmd:?}");
-                    let buf = cobs::encode_vec(cmd.as_bytes());
+                    let buf = encoder.encode_vec(cmd.as_bytes());
                     println!("buf {buf:?}");
                     port_tx.write_all(buf).await.0?;
                 }
@@ -70,19 +73,35 @@ pub async fn connect() -> anyhow::Result<()> {
     });
     let read = stellarator::struc_con::stellar(move || async move {
         let mut buf = vec![0u8; 512];
-        let mut frame = metor_proto_frame::FrameDecoder::<Vec<u8>>::default();
+        let mut decoder = FrameDecoder::<Vec<u8>>::default();
+        // the record's millisecond counter is mapped onto the db's clock as records arrive
+        let mut record_clock = CounterClock::millis_u32();
+        let mut last_dropped = 0;
         loop {
             let n = rent!(port_rx.read(buf).await, buf)?;
-            let data = &buf[..n];
-            if let Some(decoded) = frame.push(data)? {
-                let mut table = LenPacket::table(id, 64);
-                if Record::ref_from_bytes(decoded).is_err() {
+            let mut data = &buf[..n];
+            while !data.is_empty() {
+                let (n, decoded) = decoder.push(data);
+                data = &data[n..];
+                let Some(decoded) = decoded else {
+                    continue;
+                };
+                let Ok(record) = Record::ref_from_bytes(decoded) else {
                     println!("failed to decode record");
                     continue;
                 };
+                let timestamp = record_clock.observe(record.ts as u64, db_clock.now());
+                let mut table = LenPacket::table(id, 72);
+                table.extend_from_slice(timestamp.as_bytes());
                 table.extend_from_slice(decoded);
                 tx.send(table).await.0?;
             }
+            let stats = decoder.stats();
+            let dropped = stats.crc_failures + stats.resyncs;
+            if dropped != last_dropped {
+                println!("dropped corrupted frames {stats:?}");
+                last_dropped = dropped;
+            }
         }
     });
     futures_lite::future::race(async { write.join().await.unwrap() }, async {
@@ -1,7 +1,10 @@
 use futures_concurrency::future::Join;
-use metor_proto::types::{LenPacket, PacketId};
+use metor_proto::types::{LenPacket, PacketId, Timestamp};
 use metor_proto_stellar::Client;
-use metor_fsw::{AsVTable, Metadatatize, tcp::SinkExt};
+use metor_fsw::{
+    AsVTable, Metadatatize,
+    tcp::{DbClock, SinkExt},
+};
 use std::{mem, net::SocketAddr, time::Duration};
 use stellarator::{fs::File, rent};
 use sysinfo::CpuRefreshKind;
@@ -16,13 +19,18 @@ pub struct Output {
     pub gpu_usage: f32,
 }
 
+/// How many samples to send between clock syncs with the db
+const RESYNC_INTERVAL: u64 = 1000;
+
 async fn connect() -> anyhow::Result<()> {
     let mut client = Client::connect(SocketAddr::new([127, 0, 0, 1].into(), 2240))
         .await
         .map_err(anyhow::Error::from)?;
     let id: PacketId = fastrand::u16(..).to_le_bytes();
-    client.init_world::<Output>(id).await?;
-    let mut table = LenPacket::table(id, mem::size_of::<Output>());
+    client.init_timestamped_world::<Output>(id).await?;
+    let mut db_clock = DbClock::default();
+    db_clock.sync(&mut client, 8).await?;
+    let mut table = LenPacket::table(id, mem::size_of::<(Timestamp, Output)>());
     let thermal_zone = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
         .map(|i| File::open(format!("/sys/devices/virtual/thermal/thermal_zone{i}/temp")))
         .join()
@@ -42,8 +50,14 @@ async fn connect() -> anyhow::Result<()> {
     let mut system = sysinfo::System::new_all();
     system.refresh_cpu_specifics(CpuRefreshKind::everything());
 
-    loop {
+    let mut interval = stellarator::time::interval(Duration::from_millis(5));
+    for sample in 1u64.. {
+        interval.tick().await;
+        if sample % RESYNC_INTERVAL == 0 {
+            db_clock.sync(&mut client, 1).await?;
+        }
         table.clear();
+        let timestamp = db_clock.now();
         let thermal_zones = thermal_zone.each_ref().map(maybe_read_to_float).join();
         let cpu_freq = cpu_freq.each_ref().map(maybe_read_to_float).join();
         let gpu_load = read_to_float(&gpu_load);
@@ -63,10 +77,11 @@ async fn connect() -> anyhow::Result<()> {
             gpu_usage: gpu_load,
             cpu_freq,
         };
+        table.extend_from_slice(timestamp.as_bytes());
         table.extend_from_slice(output.as_bytes());
         rent!(client.send(table).await, table)?;
-        stellarator::sleep(Duration::from_millis(5)).await;
     }
+    Ok(())
 }
 
 async fn maybe_read_to_float(file: &Option<File>) -> anyhow::Result<f32> {
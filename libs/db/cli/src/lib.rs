@@ -5,7 +5,9 @@ use arrow::{
     util::display::{ArrayFormatter, FormatOptions},
 };
 use metor_proto::{
+    buf::UmbraBuf,
     com_de::Decomponentize,
+    num::{C64, C128, F16, I128, U128},
     schema::Schema,
     types::{ComponentId, Msg, OwnedTimeSeries, PacketId, PrimType, Request, Timestamp, msg_id},
     vtable::{
@@ -32,7 +34,7 @@ use std::{
     borrow::Cow::{self, Borrowed, Owned},
     collections::HashMap,
     fmt::Display,
-    io::{self, Read},
+    io::{self, Read, Write},
     net::ToSocketAddrs,
     ops::Deref,
     path::PathBuf,
@@ -147,6 +149,14 @@ impl Client {
             PrimType::Bool => print_time_series_as_table::<bool>(&time_series, schema),
             PrimType::F32 => print_time_series_as_table::<f32>(&time_series, schema),
             PrimType::F64 => print_time_series_as_table::<f64>(&time_series, schema),
+            PrimType::F16 => print_time_series_as_table::<F16>(&time_series, schema),
+            PrimType::I128 => print_time_series_as_table::<I128>(&time_series, schema),
+            PrimType::U128 => print_time_series_as_table::<U128>(&time_series, schema),
+            PrimType::C64 => print_time_series_as_table::<C64>(&time_series, schema),
+            PrimType::C128 => print_time_series_as_table::<C128>(&time_series, schema),
+            ty @ (PrimType::Bytes | PrimType::Utf8) => {
+                Err(anyhow!("time series of {ty} components aren't supported"))
+            }
         }
     }
 
@@ -251,6 +261,43 @@ impl Client {
                 let buf = buf.as_bytes();
                 table.extend_from_slice(buf);
             }
+            PrimType::F16 => {
+                let buf: Vec<f32> = lua.from_value(buf)?;
+                let buf: Vec<F16> = buf.into_iter().map(F16::from_f32).collect();
+                table.extend_from_slice(buf.as_bytes());
+            }
+            // lua numbers can't hold more than 64 bits, so wide integers are widened from i64 / u64
+            PrimType::I128 => {
+                let buf: Vec<i64> = lua.from_value(buf)?;
+                let buf: Vec<I128> = buf.into_iter().map(|x| I128::new(x.into())).collect();
+                table.extend_from_slice(buf.as_bytes());
+            }
+            PrimType::U128 => {
+                let buf: Vec<u64> = lua.from_value(buf)?;
+                let buf: Vec<U128> = buf.into_iter().map(|x| U128::new(x.into())).collect();
+                table.extend_from_slice(buf.as_bytes());
+            }
+            PrimType::C64 => {
+                let buf: Vec<C64> = lua.from_value(buf)?;
+                let buf = buf.as_bytes();
+                table.extend_from_slice(buf);
+            }
+            PrimType::C128 => {
+                let buf: Vec<C128> = lua.from_value(buf)?;
+                let buf = buf.as_bytes();
+                table.extend_from_slice(buf);
+            }
+            PrimType::Bytes | PrimType::Utf8 => {
+                let buf = match prim_type {
+                    PrimType::Utf8 => lua.from_value::<String>(buf)?.into_bytes(),
+                    _ => lua.from_value::<Vec<u8>>(buf)?,
+                };
+                // long values are stored right after their `UmbraBuf`
+                table.extend_from_slice(UmbraBuf::new(&buf, size as u32).as_bytes());
+                if buf.len() > 12 {
+                    table.extend_from_slice(&buf);
+                }
+            }
         }
         self.client.send(table).await.0?;
         Ok(())
@@ -290,6 +337,46 @@ impl Client {
         Ok(())
     }
 
+    /// Writes every vtable and table of `stream` to `path` as the length prefixed packets they
+    /// were sent as, until enter is pressed
+    pub async fn capture_stream(
+        &mut self,
+        mut stream: Stream,
+        path: PathBuf,
+    ) -> anyhow::Result<()> {
+        if stream.id == 0 {
+            stream.id = fastrand::u64(..);
+        }
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        let stream = self.client.stream(&stream).await?;
+        let cancel = Arc::new(AtomicBool::new(true));
+        let canceler = cancel.clone();
+        std::thread::spawn(move || {
+            let mut stdin = io::stdin().lock();
+            let mut buf = [0u8];
+            let _ = stdin.read(&mut buf);
+            canceler.store(false, atomic::Ordering::SeqCst);
+        });
+
+        futures_lite::pin!(stream);
+        let mut packets = 0;
+        while cancel.load(atomic::Ordering::SeqCst) {
+            let pkt = match stream.next().await? {
+                StreamReply::Table(table) => {
+                    let mut pkt = LenPacket::table(table.id, table.buf.len());
+                    pkt.extend_from_slice(&table.buf[..]);
+                    pkt
+                }
+                StreamReply::VTable(msg) => (&msg).into_len_packet(),
+            };
+            file.write_all(&pkt.inner)?;
+            packets += 1;
+        }
+        file.flush()?;
+        println!("captured {packets} packets to {}", path.display());
+        Ok(())
+    }
+
     pub async fn vtable_stream(&mut self, vtable: VTable) -> anyhow::Result<()> {
         let id = fastrand::u16(..).to_le_bytes();
         let vtable_msg = VTableMsg { vtable, id };
@@ -523,6 +610,15 @@ impl UserData for Client {
             Ok(())
         });
 
+        methods.add_async_method_mut(
+            "capture_stream",
+            |lua, mut this, (stream, path): (Value, PathBuf)| async move {
+                let msg: Stream = lua.from_value(stream)?;
+                this.capture_stream(msg, path).await?;
+                Ok(())
+            },
+        );
+
         methods.add_async_method_mut(
             "vtable_stream",
             |_, mut this, fields: Vec<UserDataRef<LuaFieldBuilder>>| async move {
@@ -892,6 +988,13 @@ pub async fn run(args: Args) -> anyhow::Result<()> {
                             Color::Blue.bold().paint("GetSchema")
                         ),
                     );
+                    print_usage_line(
+                        "Client:capture_stream(Stream, path)",
+                        format!(
+                            "Writes the packets of a {} {{ behavior, id }} to a file until enter is pressed",
+                            Color::Blue.bold().paint("Stream")
+                        ),
+                    );
                     print_usage_line(
                         "Client:save_archive(path, format)",
                         r#"Dumps the database to arrow-ipc or parquet files at the specified path
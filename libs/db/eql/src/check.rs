@@ -0,0 +1,438 @@
+use std::{fmt, ops::Range};
+
+use metor_proto::types::{ClockDomain, PrimType};
+
+use crate::{AstNode, Context, Error, Expr, Spans, ast_parser};
+
+/// The result type of an EQL expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprType {
+    /// A component path that groups other components, but has no data itself
+    Group,
+    Array {
+        prim_type: PrimType,
+        shape: Vec<u64>,
+    },
+    Timestamp,
+    String,
+    Tuple(Vec<ExprType>),
+}
+
+impl ExprType {
+    fn scalar(prim_type: PrimType) -> Self {
+        ExprType::Array {
+            prim_type,
+            shape: vec![],
+        }
+    }
+}
+
+impl fmt::Display for ExprType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprType::Group => write!(f, "component"),
+            ExprType::Array { prim_type, shape } if shape.is_empty() => {
+                write!(f, "{}", prim_type.as_str())
+            }
+            ExprType::Array { prim_type, shape } => {
+                let shape = shape
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{}[{}]", prim_type.as_str(), shape)
+            }
+            ExprType::Timestamp => write!(f, "timestamp"),
+            ExprType::String => write!(f, "string"),
+            ExprType::Tuple(elems) => {
+                let elems = elems
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "({})", elems)
+            }
+        }
+    }
+}
+
+/// The type of an expression, along with the clock domain shared by every component it reads from
+#[derive(Clone, Debug)]
+struct Typed {
+    ty: ExprType,
+    clock_domain: Option<ClockDomain>,
+}
+
+impl Expr {
+    /// The subexpressions this expression is built out of
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::ComponentPart(_)
+            | Expr::Time(_)
+            | Expr::FloatLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::BoolLiteral(_) => vec![],
+            Expr::ArrayAccess(expr, _)
+            | Expr::Fft(expr)
+            | Expr::FftFreq(expr)
+            | Expr::Diff(expr)
+            | Expr::Derivative(expr)
+            | Expr::Integrate(expr)
+            | Expr::Last(expr, _)
+            | Expr::First(expr, _) => vec![expr],
+            Expr::Tuple(exprs) => exprs.iter().collect(),
+            Expr::BinaryOp(left, right, _) => vec![left, right],
+        }
+    }
+
+    /// Computes the result type of the expression, returning an error if the
+    /// operands of the expression can't be combined.
+    pub fn ty(&self) -> Result<ExprType, Error> {
+        self.typed().map(|typed| typed.ty)
+    }
+
+    fn typed(&self) -> Result<Typed, Error> {
+        let children = self
+            .children()
+            .into_iter()
+            .map(Expr::typed)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.typed_from(&children)
+    }
+
+    /// Types the expression from the types of its [`Expr::children`], so a tree can be
+    /// typed bottom-up without visiting any node twice.
+    fn typed_from(&self, children: &[Typed]) -> Result<Typed, Error> {
+        let clock_domain = match self {
+            Expr::ComponentPart(part) => part.component.as_ref().map(|c| c.clock_domain),
+            Expr::Time(component) => Some(component.clock_domain),
+            _ => {
+                let mut domain = None;
+                for child in children {
+                    match (domain, child.clock_domain) {
+                        (Some(a), Some(b)) if a != b => {
+                            return Err(Error::TypeMismatch(format!(
+                                "cannot join timestamps from the {a} and {b} clock domains"
+                            )));
+                        }
+                        (None, d) => domain = d,
+                        _ => {}
+                    }
+                }
+                domain
+            }
+        };
+        let ty = match self {
+            Expr::ComponentPart(part) => match &part.component {
+                Some(c) => ExprType::Array {
+                    prim_type: c.schema.prim_type(),
+                    shape: c.schema.dim().to_vec(),
+                },
+                None => ExprType::Group,
+            },
+            Expr::Time(_) => ExprType::Timestamp,
+            Expr::ArrayAccess(_, index) => match &children[0].ty {
+                ExprType::Array { prim_type, shape } => {
+                    let len = shape.iter().product::<u64>() as usize;
+                    if shape.is_empty() || *index >= len {
+                        return Err(Error::InvalidFieldAccess(format!(
+                            "index {index} out of bounds (length: {len})"
+                        )));
+                    }
+                    ExprType::scalar(*prim_type)
+                }
+                ty => {
+                    return Err(Error::InvalidFieldAccess(format!("cannot index into {ty}")));
+                }
+            },
+            Expr::Tuple(_) => ExprType::Tuple(children.iter().map(|c| c.ty.clone()).collect()),
+            Expr::FloatLiteral(_) => ExprType::scalar(PrimType::F64),
+            Expr::StringLiteral(_) => ExprType::String,
+            Expr::BoolLiteral(_) => ExprType::scalar(PrimType::Bool),
+            Expr::Fft(_) | Expr::FftFreq(_) => ExprType::scalar(PrimType::F64),
+            Expr::Diff(_) => children[0].ty.clone(),
+            Expr::Derivative(_) | Expr::Integrate(_) => ExprType::scalar(PrimType::F64),
+            Expr::Last(_, _) | Expr::First(_, _) => children[0].ty.clone(),
+            Expr::BinaryOp(_, _, op) => {
+                let (left, right) = (&children[0].ty, &children[1].ty);
+                let mismatch = || {
+                    Error::TypeMismatch(format!(
+                        "cannot apply '{}' to {left} and {right}",
+                        op.to_str()
+                    ))
+                };
+                let (
+                    ExprType::Array {
+                        prim_type: left_prim,
+                        shape: left_shape,
+                    },
+                    ExprType::Array {
+                        prim_type: right_prim,
+                        shape: right_shape,
+                    },
+                ) = (left, right)
+                else {
+                    return Err(mismatch());
+                };
+                if left_prim.is_var_len() || right_prim.is_var_len() {
+                    return Err(mismatch());
+                }
+                let shape = if left_shape == right_shape || right_shape.is_empty() {
+                    left_shape.clone()
+                } else if left_shape.is_empty() {
+                    right_shape.clone()
+                } else {
+                    return Err(mismatch());
+                };
+                let prim_type = if left_prim == right_prim {
+                    *left_prim
+                } else {
+                    PrimType::F64
+                };
+                ExprType::Array { prim_type, shape }
+            }
+        };
+        Ok(Typed { ty, clock_domain })
+    }
+}
+
+/// An error attached to a byte range of the checked input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// The type of the subexpression at a byte range of the checked input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeHint {
+    pub span: Range<usize>,
+    pub ty: ExprType,
+}
+
+/// The output of [`Context::check`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Check {
+    pub diagnostics: Vec<Diagnostic>,
+    /// Types of every well-typed subexpression, in post-order
+    pub types: Vec<TypeHint>,
+    /// The type of the whole expression, if it checked successfully
+    pub ty: Option<ExprType>,
+}
+
+impl Check {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Returns the innermost type hint that covers the byte offset
+    pub fn type_at(&self, offset: usize) -> Option<&TypeHint> {
+        self.types
+            .iter()
+            .filter(|hint| hint.span.contains(&offset))
+            .min_by_key(|hint| hint.span.len())
+    }
+}
+
+impl Context {
+    /// Parses and type-checks `input`, collecting diagnostics and the type of every
+    /// subexpression. Errors are reported on the innermost subexpression that fails.
+    pub fn check(&self, input: &str) -> Check {
+        let mut check = Check::default();
+        match ast_parser::spanned_expr(input) {
+            Ok(node) => {
+                check.ty = self
+                    .check_node(&node.ast, &node.spans, &mut check)
+                    .map(|(_, typed)| typed.ty);
+            }
+            Err(err) => {
+                let start = err.location.offset.min(input.len());
+                let end = input[start..]
+                    .chars()
+                    .next()
+                    .map(|c| start + c.len_utf8())
+                    .unwrap_or(start);
+                check.diagnostics.push(Diagnostic {
+                    span: start..end,
+                    message: format!("expected {}", err.expected),
+                });
+            }
+        }
+        check
+    }
+
+    /// Parses and types `node` out of its already checked children, so every node is only
+    /// visited once.
+    fn check_node(
+        &self,
+        ast: &AstNode<'_>,
+        spans: &Spans,
+        check: &mut Check,
+    ) -> Option<(Expr, Typed)> {
+        let mut children_ok = true;
+        let mut exprs = vec![];
+        let mut types = vec![];
+        for (child, child_spans) in ast.children().into_iter().zip(&spans.children) {
+            match self.check_node(child, child_spans, check) {
+                Some((expr, typed)) => {
+                    exprs.push(expr);
+                    types.push(typed);
+                }
+                None => children_ok = false,
+            }
+        }
+        if !children_ok {
+            return None;
+        }
+        let res = self.parse_node(ast, exprs).and_then(|expr| {
+            // an expression's own children are always the leading nodes it was parsed
+            // out of, e.g. the receiver of a method call and not its arguments
+            let typed = expr.typed_from(&types[..expr.children().len()])?;
+            Ok((expr, typed))
+        });
+        match res {
+            Ok((expr, typed)) => {
+                check.types.push(TypeHint {
+                    span: spans.span.clone(),
+                    ty: typed.ty.clone(),
+                });
+                Some((expr, typed))
+            }
+            Err(err) => {
+                check.diagnostics.push(Diagnostic {
+                    span: spans.span.clone(),
+                    message: err.to_string(),
+                });
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use metor_proto::{
+        schema::Schema,
+        types::{ComponentId, Timestamp},
+    };
+
+    use super::*;
+    use crate::Component;
+
+    fn context() -> Context {
+        Context::from_leaves(
+            [
+                Arc::new(Component::new(
+                    "a.world_pos".to_string(),
+                    ComponentId::new("a.world_pos"),
+                    Schema::new(PrimType::F64, vec![7u64]).unwrap(),
+                )),
+                Arc::new(Component::new(
+                    "a.mode".to_string(),
+                    ComponentId::new("a.mode"),
+                    Schema::new(PrimType::U8, Vec::<u64>::new()).unwrap(),
+                )),
+                Arc::new(Component {
+                    clock_domain: ClockDomain::Sim,
+                    ..Component::new(
+                        "a.sim_time".to_string(),
+                        ComponentId::new("a.sim_time"),
+                        Schema::new(PrimType::F64, Vec::<u64>::new()).unwrap(),
+                    )
+                }),
+            ],
+            Timestamp(0),
+            Timestamp(1000),
+        )
+    }
+
+    #[test]
+    fn test_check_types() {
+        let check = context().check("a.world_pos.x * 2.0");
+        assert!(check.is_ok());
+        assert_eq!(check.ty, Some(ExprType::scalar(PrimType::F64)));
+        assert_eq!(
+            check.type_at(0).map(|h| h.ty.to_string()),
+            Some("component".to_string())
+        );
+        assert_eq!(
+            check.type_at(3).map(|h| h.ty.to_string()),
+            Some("f64[7]".to_string())
+        );
+        assert_eq!(
+            check
+                .type_at(16)
+                .map(|h| (h.span.clone(), h.ty.to_string())),
+            Some((16..19, "f64".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_check_tuple() {
+        let check = context().check("(a.world_pos.time, a.mode)");
+        assert_eq!(
+            check.ty.map(|ty| ty.to_string()),
+            Some("(timestamp, u8)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_unknown_component() {
+        let check = context().check("a.world_pos.x + b.vel");
+        assert_eq!(
+            check.diagnostics,
+            vec![Diagnostic {
+                span: 16..17,
+                message: "entity not found: b".to_string(),
+            }]
+        );
+        assert_eq!(check.ty, None);
+    }
+
+    #[test]
+    fn test_check_out_of_bounds() {
+        let check = context().check("a.world_pos[9]");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert_eq!(check.diagnostics[0].span, 0..14);
+    }
+
+    #[test]
+    fn test_check_shape_mismatch() {
+        let check = context().check("a.world_pos + a.world_pos.time");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert_eq!(check.diagnostics[0].span, 0..30);
+        assert!(check.diagnostics[0].message.starts_with("type mismatch"));
+    }
+
+    #[test]
+    fn test_check_clock_domain_mismatch() {
+        let check = context().check("a.mode + a.sim_time");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert_eq!(check.diagnostics[0].span, 0..19);
+        assert!(check.diagnostics[0].message.contains("utc and sim"));
+
+        let check = context().check("(a.world_pos.time, a.sim_time)");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert!(context().check("a.sim_time * 2.0").is_ok());
+    }
+
+    #[test]
+    fn test_check_long_chain() {
+        let ctx = context();
+        let input = vec!["a.world_pos.x"; 500].join(" + ");
+        let check = ctx.check(&input);
+        assert!(check.is_ok());
+        assert_eq!(check.ty, ctx.parse_str(&input).unwrap().ty().ok());
+        // every term has 3 nodes, and they're joined by 499 additions
+        assert_eq!(check.types.len(), 500 * 3 + 499);
+    }
+
+    #[test]
+    fn test_check_syntax_error() {
+        let check = context().check("a.world_pos[");
+        assert_eq!(check.diagnostics.len(), 1);
+        assert_eq!(check.diagnostics[0].span, 12..12);
+    }
+}
@@ -0,0 +1,678 @@
+use std::sync::Arc;
+
+use metor_proto::types::{ComponentView, ElementValue, Timestamp};
+use metor_proto_wkt::ComponentValue;
+use nox::Array;
+use smallvec::{SmallVec, smallvec};
+
+use crate::{BinaryOp, Component, Error, Expr};
+
+/// A run of samples for a single component, with element data packed in the
+/// component's schema, as stored by metor-db and the editor's time series caches.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeSeriesRef<'a> {
+    pub timestamps: &'a [Timestamp],
+    pub data: &'a [u8],
+}
+
+/// Supplies component data to [`Expr::eval`] and [`Expr::eval_series`].
+pub trait Source {
+    /// Returns the most recent value of the component
+    fn latest(&self, component: &Component) -> Option<ComponentValue>;
+
+    /// Returns the timestamp of the most recent value of the component
+    fn latest_timestamp(&self, _component: &Component) -> Option<Timestamp> {
+        None
+    }
+
+    /// Returns the samples of the component, sorted by timestamp
+    fn time_series(&self, _component: &Component) -> Option<TimeSeriesRef<'_>> {
+        None
+    }
+}
+
+/// The result of evaluating an expression over time series data.
+#[derive(Clone, Debug, Default)]
+pub struct Series {
+    pub timestamps: Vec<Timestamp>,
+    pub values: Vec<ComponentValue>,
+}
+
+impl Series {
+    fn map(
+        self,
+        f: impl Fn(&ComponentValue) -> Result<ComponentValue, Error>,
+    ) -> Result<Series, Error> {
+        let values = self.values.iter().map(f).collect::<Result<Vec<_>, _>>()?;
+        Ok(Series {
+            timestamps: self.timestamps,
+            values,
+        })
+    }
+
+    /// Inner joins the series on timestamp, like the `JOIN .. ON a.time = b.time` that
+    /// [`Expr::to_sql`] generates.
+    fn join(series: Vec<Series>) -> (Vec<Timestamp>, Vec<Vec<ComponentValue>>) {
+        let mut cursors = vec![0usize; series.len()];
+        let mut timestamps = vec![];
+        let mut rows = vec![];
+        'outer: loop {
+            let mut max = Timestamp(i64::MIN);
+            for (s, &cursor) in series.iter().zip(&cursors) {
+                let Some(&ts) = s.timestamps.get(cursor) else {
+                    break 'outer;
+                };
+                max = max.max(ts);
+            }
+            let mut aligned = true;
+            for (s, cursor) in series.iter().zip(&mut cursors) {
+                while s.timestamps.get(*cursor).is_some_and(|&ts| ts < max) {
+                    *cursor += 1;
+                }
+                aligned &= s.timestamps.get(*cursor) == Some(&max);
+            }
+            if !aligned {
+                continue;
+            }
+            timestamps.push(max);
+            rows.push(
+                series
+                    .iter()
+                    .zip(&cursors)
+                    .map(|(s, &cursor)| s.values[cursor].clone())
+                    .collect(),
+            );
+            cursors.iter_mut().for_each(|c| *c += 1);
+        }
+        (timestamps, rows)
+    }
+
+    fn as_f64s(&self) -> Result<Vec<f64>, Error> {
+        self.values
+            .iter()
+            .map(|value| match value.shape() {
+                [] | [1] => Ok(value.get(0).map(|e| e.as_f64()).unwrap_or_default()),
+                shape => Err(Error::Eval(format!(
+                    "expected a scalar, found shape {shape:?}"
+                ))),
+            })
+            .collect()
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression against the latest value of each component, without
+    /// going through SQL.
+    pub fn eval(&self, source: &impl Source) -> Result<ComponentValue, Error> {
+        match self {
+            Expr::ComponentPart(part) => {
+                let component = part
+                    .component
+                    .as_ref()
+                    .ok_or_else(|| Error::Eval(format!("'{}' is not a component", part.name)))?;
+                source
+                    .latest(component)
+                    .ok_or_else(|| Error::Eval(format!("no value for '{}'", component.name)))
+            }
+            Expr::Time(component) => source
+                .latest_timestamp(component)
+                .map(|ts| scalar_i64(ts.0))
+                .ok_or_else(|| Error::Eval(format!("no timestamp for '{}'", component.name))),
+            Expr::ArrayAccess(expr, index) => array_access(&expr.eval(source)?, *index),
+            Expr::Tuple(exprs) => {
+                let values = exprs
+                    .iter()
+                    .map(|expr| expr.eval(source))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(concat(&values))
+            }
+            Expr::FloatLiteral(f) => Ok(scalar_f64(*f)),
+            Expr::BoolLiteral(b) => Ok(scalar_bool(*b)),
+            Expr::BinaryOp(left, right, op) => {
+                binary_op(&left.eval(source)?, &right.eval(source)?, *op)
+            }
+            // the latest value is always inside of the time window
+            Expr::Last(expr, _) | Expr::First(expr, _) => expr.eval(source),
+            Expr::StringLiteral(_)
+            | Expr::Fft(_)
+            | Expr::FftFreq(_)
+            | Expr::Diff(_)
+            | Expr::Derivative(_)
+            | Expr::Integrate(_) => Err(Error::Eval(format!(
+                "{} can't be evaluated on a single value",
+                self.name()
+            ))),
+        }
+    }
+
+    /// Evaluates the expression over the full time series of each component.
+    pub fn eval_series(&self, source: &impl Source) -> Result<Series, Error> {
+        match self {
+            Expr::ComponentPart(part) => {
+                let component = part
+                    .component
+                    .as_ref()
+                    .ok_or_else(|| Error::Eval(format!("'{}' is not a component", part.name)))?;
+                let time_series = source.time_series(component).ok_or_else(|| {
+                    Error::Eval(format!("no time series for '{}'", component.name))
+                })?;
+                decode_time_series(component, time_series)
+            }
+            Expr::Time(component) => {
+                let time_series = source.time_series(component).ok_or_else(|| {
+                    Error::Eval(format!("no time series for '{}'", component.name))
+                })?;
+                Ok(Series {
+                    timestamps: time_series.timestamps.to_vec(),
+                    values: time_series
+                        .timestamps
+                        .iter()
+                        .map(|ts| scalar_i64(ts.0))
+                        .collect(),
+                })
+            }
+            Expr::ArrayAccess(expr, index) => expr
+                .eval_series(source)?
+                .map(|value| array_access(value, *index)),
+            Expr::Tuple(exprs) => {
+                let series = exprs
+                    .iter()
+                    .map(|expr| expr.eval_series(source))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (timestamps, rows) = Series::join(series);
+                Ok(Series {
+                    timestamps,
+                    values: rows.iter().map(|row| concat(row)).collect(),
+                })
+            }
+            Expr::BinaryOp(left, right, op) => match (left.as_constant(), right.as_constant()) {
+                (Some(_), Some(_)) => Err(Error::Eval(
+                    "binary operation between constants has no time series".to_string(),
+                )),
+                (None, Some(right)) => left
+                    .eval_series(source)?
+                    .map(|left| binary_op(left, &right, *op)),
+                (Some(left), None) => right
+                    .eval_series(source)?
+                    .map(|right| binary_op(&left, right, *op)),
+                (None, None) => {
+                    let series = vec![left.eval_series(source)?, right.eval_series(source)?];
+                    let (timestamps, rows) = Series::join(series);
+                    let values = rows
+                        .iter()
+                        .map(|row| binary_op(&row[0], &row[1], *op))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Series { timestamps, values })
+                }
+            },
+            Expr::Diff(expr) => {
+                let series = expr.eval_series(source)?;
+                let values = series.as_f64s()?;
+                Ok(Series {
+                    timestamps: series.timestamps.iter().skip(1).copied().collect(),
+                    values: values.windows(2).map(|w| scalar_f64(w[1] - w[0])).collect(),
+                })
+            }
+            Expr::Derivative(expr) => {
+                let series = expr.eval_series(source)?;
+                let values = series.as_f64s()?;
+                let values = values
+                    .windows(2)
+                    .zip(series.timestamps.windows(2))
+                    .map(|(v, t)| scalar_f64((v[1] - v[0]) / ((t[1].0 - t[0].0) as f64 * 1e-6)))
+                    .collect();
+                Ok(Series {
+                    timestamps: series.timestamps.iter().skip(1).copied().collect(),
+                    values,
+                })
+            }
+            Expr::Integrate(expr) => {
+                let series = expr.eval_series(source)?;
+                let values = series.as_f64s()?;
+                let mut sum = 0.0;
+                let mut integral = Vec::with_capacity(values.len());
+                for i in 0..values.len() {
+                    if i > 0 {
+                        let dt =
+                            (series.timestamps[i].0 - series.timestamps[i - 1].0) as f64 * 1e-6;
+                        sum += (values[i] + values[i - 1]) * 0.5 * dt;
+                    }
+                    integral.push(scalar_f64(sum));
+                }
+                Ok(Series {
+                    timestamps: series.timestamps,
+                    values: integral,
+                })
+            }
+            // unlike `to_sql`, time windows are relative to the ends of the series itself
+            Expr::Last(expr, duration) => {
+                let series = expr.eval_series(source)?;
+                let Some(last) = series.timestamps.last() else {
+                    return Ok(series);
+                };
+                let lower_bound = last.0 - (duration.total_nanoseconds() / 1000) as i64;
+                let start = series.timestamps.partition_point(|ts| ts.0 < lower_bound);
+                Ok(Series {
+                    timestamps: series.timestamps[start..].to_vec(),
+                    values: series.values[start..].to_vec(),
+                })
+            }
+            Expr::First(expr, duration) => {
+                let series = expr.eval_series(source)?;
+                let Some(first) = series.timestamps.first() else {
+                    return Ok(series);
+                };
+                let upper_bound = first.0 + (duration.total_nanoseconds() / 1000) as i64;
+                let end = series.timestamps.partition_point(|ts| ts.0 <= upper_bound);
+                Ok(Series {
+                    timestamps: series.timestamps[..end].to_vec(),
+                    values: series.values[..end].to_vec(),
+                })
+            }
+            Expr::FloatLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::Fft(_)
+            | Expr::FftFreq(_) => Err(Error::Eval(format!(
+                "{} can't be evaluated as a time series",
+                self.name()
+            ))),
+        }
+    }
+
+    /// Returns the components the expression reads, which a [`Source`] has to supply.
+    pub fn components(&self) -> Vec<Arc<Component>> {
+        let mut components = vec![];
+        self.collect_components(&mut components);
+        components
+    }
+
+    fn collect_components(&self, components: &mut Vec<Arc<Component>>) {
+        let component = match self {
+            Expr::ComponentPart(part) => part.component.as_ref(),
+            Expr::Time(component) => Some(component),
+            Expr::ArrayAccess(expr, _)
+            | Expr::Fft(expr)
+            | Expr::FftFreq(expr)
+            | Expr::Diff(expr)
+            | Expr::Derivative(expr)
+            | Expr::Integrate(expr)
+            | Expr::Last(expr, _)
+            | Expr::First(expr, _) => {
+                expr.collect_components(components);
+                None
+            }
+            Expr::Tuple(exprs) => {
+                exprs
+                    .iter()
+                    .for_each(|expr| expr.collect_components(components));
+                None
+            }
+            Expr::BinaryOp(left, right, _) => {
+                left.collect_components(components);
+                right.collect_components(components);
+                None
+            }
+            Expr::FloatLiteral(_) | Expr::StringLiteral(_) | Expr::BoolLiteral(_) => None,
+        };
+        if let Some(component) = component {
+            if !components.iter().any(|c| c.id == component.id) {
+                components.push(component.clone());
+            }
+        }
+    }
+
+    /// Folds an expression that doesn't read any component into its value, so it can be
+    /// broadcast against a time series.
+    fn as_constant(&self) -> Option<ComponentValue> {
+        match self {
+            Expr::FloatLiteral(f) => Some(scalar_f64(*f)),
+            Expr::BoolLiteral(b) => Some(scalar_bool(*b)),
+            Expr::ArrayAccess(expr, index) => array_access(&expr.as_constant()?, *index).ok(),
+            Expr::Tuple(exprs) => {
+                let values = exprs
+                    .iter()
+                    .map(Expr::as_constant)
+                    .collect::<Option<Vec<_>>>()?;
+                Some(concat(&values))
+            }
+            Expr::BinaryOp(left, right, op) => {
+                binary_op(&left.as_constant()?, &right.as_constant()?, *op).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Expr::ComponentPart(_) => "component",
+            Expr::Time(_) => "time",
+            Expr::ArrayAccess(_, _) => "array access",
+            Expr::Tuple(_) => "tuple",
+            Expr::FloatLiteral(_) => "float literal",
+            Expr::StringLiteral(_) => "string literal",
+            Expr::BoolLiteral(_) => "bool literal",
+            Expr::Fft(_) => "fft",
+            Expr::FftFreq(_) => "fftfreq",
+            Expr::Diff(_) => "diff",
+            Expr::Derivative(_) => "derivative",
+            Expr::Integrate(_) => "integrate",
+            Expr::Last(_, _) => "last",
+            Expr::First(_, _) => "first",
+            Expr::BinaryOp(_, _, _) => "binary operation",
+        }
+    }
+}
+
+fn decode_time_series(
+    component: &Component,
+    time_series: TimeSeriesRef<'_>,
+) -> Result<Series, Error> {
+    let prim_type = component.schema.prim_type();
+    let shape = component.schema.shape();
+    if prim_type.is_var_len() {
+        return Err(Error::Eval(format!(
+            "time series of {prim_type} components like '{}' aren't supported",
+            component.name
+        )));
+    }
+    let element_size = component.schema.size();
+    if element_size == 0 {
+        return Ok(Series::default());
+    }
+    let len = time_series
+        .timestamps
+        .len()
+        .min(time_series.data.len() / element_size);
+    let values = time_series
+        .data
+        .chunks_exact(element_size)
+        .take(len)
+        .map(|buf| {
+            ComponentView::try_from_bytes_shape(buf, shape, prim_type)
+                .map(ComponentValue::from_view)
+                .map_err(|err| {
+                    Error::Eval(format!("invalid data for '{}': {err:?}", component.name))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Series {
+        timestamps: time_series.timestamps[..len].to_vec(),
+        values,
+    })
+}
+
+fn scalar_f64(value: f64) -> ComponentValue {
+    ComponentValue::F64(Array::from_shape_vec(smallvec![], vec![value]).unwrap())
+}
+
+fn scalar_bool(value: bool) -> ComponentValue {
+    ComponentValue::Bool(Array::from_shape_vec(smallvec![], vec![value]).unwrap())
+}
+
+fn scalar_i64(value: i64) -> ComponentValue {
+    ComponentValue::I64(Array::from_shape_vec(smallvec![], vec![value]).unwrap())
+}
+
+fn array_access(value: &ComponentValue, index: usize) -> Result<ComponentValue, Error> {
+    let element = value.get(index).ok_or_else(|| {
+        Error::Eval(format!(
+            "array index {index} out of bounds (shape: {:?})",
+            value.shape()
+        ))
+    })?;
+    macro_rules! scalar {
+        ($($variant:ident),*) => {
+            match element {
+                $(ElementValue::$variant(x) => {
+                    ComponentValue::$variant(Array::from_shape_vec(smallvec![], vec![x]).unwrap())
+                })*
+            }
+        };
+    }
+    Ok(scalar!(
+        U8, U16, U32, U64, I8, I16, I32, I64, F32, F64, Bool, F16, I128, U128, C64, C128
+    ))
+}
+
+/// Flattens the values into a single f64 array, like a tuple of SQL columns.
+fn concat(values: &[ComponentValue]) -> ComponentValue {
+    let data = values
+        .iter()
+        .flat_map(|value| value.iter().map(|e| e.as_f64()))
+        .collect::<Vec<_>>();
+    ComponentValue::F64(Array::from_shape_vec(smallvec![data.len()], data).unwrap())
+}
+
+fn binary_op(
+    left: &ComponentValue,
+    right: &ComponentValue,
+    op: BinaryOp,
+) -> Result<ComponentValue, Error> {
+    let (left_len, right_len) = (len(left), len(right));
+    // a single element doesn't broadcast against an empty operand, which has no element
+    // to pair it with
+    let shape: SmallVec<[usize; 4]> = if left_len == right_len || (right_len == 1 && left_len > 0) {
+        left.shape().into()
+    } else if left_len == 1 && right_len > 0 {
+        right.shape().into()
+    } else {
+        return Err(Error::Eval(format!(
+            "can't broadcast {:?} with {:?}",
+            left.shape(),
+            right.shape()
+        )));
+    };
+    let left = left.iter().map(|e| e.as_f64()).collect::<Vec<_>>();
+    let right = right.iter().map(|e| e.as_f64()).collect::<Vec<_>>();
+    let out_len = left_len.max(right_len);
+    let data = (0..out_len)
+        .map(|i| {
+            let l = left[if left_len == 1 { 0 } else { i }];
+            let r = right[if right_len == 1 { 0 } else { i }];
+            match op {
+                BinaryOp::Add => l + r,
+                BinaryOp::Sub => l - r,
+                BinaryOp::Mul => l * r,
+                BinaryOp::Div => l / r,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(ComponentValue::F64(
+        Array::from_shape_vec(shape, data).expect("shape matches data"),
+    ))
+}
+
+fn len(value: &ComponentValue) -> usize {
+    value.shape().iter().product()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use metor_proto::{
+        schema::Schema,
+        types::{ComponentId, PrimType},
+    };
+    use zerocopy::IntoBytes;
+
+    use super::*;
+    use crate::Context;
+
+    struct TestSource {
+        timestamps: Vec<Timestamp>,
+        data: HashMap<ComponentId, Vec<f64>>,
+    }
+
+    impl Source for TestSource {
+        fn latest(&self, component: &Component) -> Option<ComponentValue> {
+            let data = self.data.get(&component.id)?;
+            let len = component.schema.shape().iter().product::<usize>();
+            let last = data[data.len() - len..].to_vec();
+            Some(ComponentValue::F64(
+                Array::from_shape_vec(component.schema.shape().into(), last).unwrap(),
+            ))
+        }
+
+        fn latest_timestamp(&self, _component: &Component) -> Option<Timestamp> {
+            self.timestamps.last().copied()
+        }
+
+        fn time_series(&self, component: &Component) -> Option<TimeSeriesRef<'_>> {
+            Some(TimeSeriesRef {
+                timestamps: &self.timestamps,
+                data: self.data.get(&component.id)?.as_bytes(),
+            })
+        }
+    }
+
+    fn setup() -> (Context, TestSource) {
+        let context = Context::from_leaves(
+            [
+                Arc::new(Component::new(
+                    "a.pos".to_string(),
+                    ComponentId::new("a.pos"),
+                    Schema::new(PrimType::F64, vec![3u64]).unwrap(),
+                )),
+                Arc::new(Component::new(
+                    "a.speed".to_string(),
+                    ComponentId::new("a.speed"),
+                    Schema::new(PrimType::F64, Vec::<u64>::new()).unwrap(),
+                )),
+            ],
+            Timestamp(0),
+            Timestamp(3_000_000),
+        );
+        let source = TestSource {
+            timestamps: vec![Timestamp(0), Timestamp(1_000_000), Timestamp(2_000_000)],
+            data: [
+                (
+                    ComponentId::new("a.pos"),
+                    vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0],
+                ),
+                (ComponentId::new("a.speed"), vec![1.0, 3.0, 5.0]),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        (context, source)
+    }
+
+    fn f64s(value: &ComponentValue) -> Vec<f64> {
+        value.iter().map(|e| e.as_f64()).collect()
+    }
+
+    #[test]
+    fn test_eval_latest() {
+        let (context, source) = setup();
+        let expr = context.parse_str("a.pos.y * 2.0 + a.speed").unwrap();
+        assert_eq!(f64s(&expr.eval(&source).unwrap()), vec![19.0]);
+
+        let expr = context.parse_str("(a.pos.x, a.speed)").unwrap();
+        assert_eq!(f64s(&expr.eval(&source).unwrap()), vec![6.0, 5.0]);
+
+        let expr = context.parse_str("a.pos").unwrap();
+        assert_eq!(expr.eval(&source).unwrap().shape(), &[3]);
+    }
+
+    #[test]
+    fn test_eval_latest_requires_series() {
+        let (context, source) = setup();
+        let expr = context.parse_str("a.speed.diff()").unwrap();
+        assert!(matches!(expr.eval(&source), Err(Error::Eval(_))));
+    }
+
+    #[test]
+    fn test_eval_series() {
+        let (context, source) = setup();
+        let expr = context.parse_str("a.pos.z - a.speed").unwrap();
+        let series = expr.eval_series(&source).unwrap();
+        assert_eq!(series.timestamps.len(), 3);
+        assert_eq!(
+            series.values.iter().flat_map(f64s).collect::<Vec<_>>(),
+            vec![1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_eval_series_constant_operand() {
+        let (context, source) = setup();
+        let constant = Expr::BinaryOp(
+            Box::new(Expr::FloatLiteral(2.0)),
+            Box::new(Expr::FloatLiteral(1.0)),
+            BinaryOp::Add,
+        );
+        let expr = Expr::BinaryOp(
+            Box::new(context.parse_str("a.speed").unwrap()),
+            Box::new(constant),
+            BinaryOp::Mul,
+        );
+        let series = expr.eval_series(&source).unwrap();
+        assert_eq!(
+            series.values.iter().flat_map(f64s).collect::<Vec<_>>(),
+            vec![3.0, 9.0, 15.0]
+        );
+    }
+
+    #[test]
+    fn test_components() {
+        let (context, _) = setup();
+        let expr = context.parse_str("a.pos.x + a.speed * a.pos.y").unwrap();
+        let names = expr
+            .components()
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.pos", "a.speed"]);
+    }
+
+    #[test]
+    fn test_eval_series_calculus() {
+        let (context, source) = setup();
+        let derivative = context.parse_str("a.speed.derivative()").unwrap();
+        let series = derivative.eval_series(&source).unwrap();
+        assert_eq!(
+            series.timestamps,
+            vec![Timestamp(1_000_000), Timestamp(2_000_000)]
+        );
+        assert_eq!(
+            series.values.iter().flat_map(f64s).collect::<Vec<_>>(),
+            vec![2.0, 2.0]
+        );
+
+        let integral = context.parse_str("a.speed.integrate()").unwrap();
+        let series = integral.eval_series(&source).unwrap();
+        assert_eq!(
+            series.values.iter().flat_map(f64s).collect::<Vec<_>>(),
+            vec![0.0, 2.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_eval_series_last() {
+        let (context, source) = setup();
+        let expr = context.parse_str("a.speed.last(\"1s\")").unwrap();
+        let series = expr.eval_series(&source).unwrap();
+        assert_eq!(
+            series.values.iter().flat_map(f64s).collect::<Vec<_>>(),
+            vec![3.0, 5.0]
+        );
+    }
+
+    #[test]
+    fn test_binary_op_empty_operand() {
+        let scalar = ComponentValue::F64(Array::from_shape_vec(smallvec![], vec![1.0]).unwrap());
+        let empty = ComponentValue::F64(Array::from_shape_vec(smallvec![0], vec![]).unwrap());
+        assert!(matches!(
+            binary_op(&scalar, &empty, BinaryOp::Add),
+            Err(Error::Eval(_))
+        ));
+        assert!(matches!(
+            binary_op(&empty, &scalar, BinaryOp::Mul),
+            Err(Error::Eval(_))
+        ));
+        let out = binary_op(&empty, &empty, BinaryOp::Sub).unwrap();
+        assert_eq!(out.shape(), &[0]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
     collections::{BTreeMap, BTreeSet},
+    ops::Range,
     str::FromStr,
     sync::Arc,
 };
@@ -8,11 +9,16 @@ use unicode_ident::*;
 
 use metor_proto::{
     schema::Schema,
-    types::{ComponentId, Timestamp},
+    types::{ClockDomain, ComponentId, Timestamp},
 };
 use metor_proto_wkt::ComponentPath;
 use peg::error::ParseError;
 
+mod check;
+mod eval;
+pub use check::{Check, Diagnostic, ExprType, TypeHint};
+pub use eval::{Series, Source, TimeSeriesRef};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstNode<'input> {
     Ident(Cow<'input, str>),
@@ -26,6 +32,55 @@ pub enum AstNode<'input> {
     BoolLiteral(bool),
 }
 
+impl<'input> AstNode<'input> {
+    /// The nodes this node was built out of, in the order they appear in the input
+    pub fn children(&self) -> Vec<&AstNode<'input>> {
+        match self {
+            AstNode::Ident(_)
+            | AstNode::StringLiteral(_)
+            | AstNode::FloatLiteral(_)
+            | AstNode::BoolLiteral(_) => vec![],
+            AstNode::Field(node, _) | AstNode::ArrayIndex(node, _) => vec![&**node],
+            AstNode::MethodCall(recv, _, args) => {
+                let mut children = vec![&**recv];
+                children.extend(args);
+                children
+            }
+            AstNode::BinaryOp(left, right, _) => vec![&**left, &**right],
+            AstNode::Tuple(nodes) => nodes.iter().collect(),
+        }
+    }
+}
+
+/// An [`AstNode`] along with the byte ranges it and the nodes it was built out of were
+/// parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedNode<'input> {
+    pub ast: AstNode<'input>,
+    pub spans: Spans,
+}
+
+/// The byte range a node was parsed from, and the spans of its children in the order of
+/// [`AstNode::children`]. Kept apart from the tree of [`AstNode`]s, so each node is only built
+/// once while parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spans {
+    pub span: Range<usize>,
+    pub children: Vec<Spans>,
+}
+
+impl<'input> SpannedNode<'input> {
+    fn new(ast: AstNode<'input>, children: Vec<Spans>) -> Self {
+        Self {
+            ast,
+            spans: Spans {
+                span: 0..0,
+                children,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FmtNode<'input> {
     String(Cow<'input, str>),
@@ -78,26 +133,53 @@ peg::parser! {
         rule fmt_node() -> FmtNode<'input> = fmt_ast_node() / fmt_string_node()
         pub rule fmt_string() -> Vec<FmtNode<'input>> = s:fmt_node()+ { s }
 
-        pub rule expr() -> AstNode<'input> = precedence! {
-        a:(@) comma() b:@ { AstNode::Tuple(vec![a, b]) }
+        pub rule expr() -> AstNode<'input> = n:spanned_expr() { n.ast }
+
+        pub rule spanned_expr() -> SpannedNode<'input> = precedence! {
+        start:position!() n:@ end:position!() {
+            let mut n = n;
+            n.spans.span = start..end;
+            n
+        }
         --
-        a:(@) _ op:binary_op() _ b:@ { AstNode::BinaryOp(Box::new(a), Box::new(b), op) }
+        a:(@) comma() b:@ {
+            let ast = AstNode::Tuple(vec![a.ast, b.ast]);
+            SpannedNode::new(ast, vec![a.spans, b.spans])
+        }
         --
-        e:(@) "." i:ident_str() "(" args:expr() ** comma() ")" { AstNode::MethodCall(Box::new(e), i,  args) }
+        a:(@) _ op:binary_op() _ b:@ {
+            let ast = AstNode::BinaryOp(Box::new(a.ast), Box::new(b.ast), op);
+            SpannedNode::new(ast, vec![a.spans, b.spans])
+        }
         --
-        e:(@) "." i:ident_str() { AstNode::Field(Box::new(e), i) }
+        e:(@) "." i:ident_str() "(" args:spanned_expr() ** comma() ")" {
+            let (args, arg_spans): (Vec<_>, Vec<_>) =
+                args.into_iter().map(|arg| (arg.ast, arg.spans)).unzip();
+            let ast = AstNode::MethodCall(Box::new(e.ast), i, args);
+            let mut children = vec![e.spans];
+            children.extend(arg_spans);
+            SpannedNode::new(ast, children)
+        }
         --
-        e:(@) "[" i:uint() "]" { AstNode::ArrayIndex(Box::new(e), i) }
+        e:(@) "." i:ident_str() {
+            let ast = AstNode::Field(Box::new(e.ast), i);
+            SpannedNode::new(ast, vec![e.spans])
+        }
+        --
+        e:(@) "[" i:uint() "]" {
+            let ast = AstNode::ArrayIndex(Box::new(e.ast), i);
+            SpannedNode::new(ast, vec![e.spans])
+        }
         --
-        "(" _ e:expr() _ ")" { e }
+        "(" _ e:spanned_expr() _ ")" { e }
         --
-        b:bool() { AstNode::BoolLiteral(b) }
+        b:bool() { SpannedNode::new(AstNode::BoolLiteral(b), vec![]) }
         --
-        f:float() { AstNode::FloatLiteral(f) }
+        f:float() { SpannedNode::new(AstNode::FloatLiteral(f), vec![]) }
         --
-        s:string_literal() { AstNode::StringLiteral(s) }
+        s:string_literal() { SpannedNode::new(AstNode::StringLiteral(s), vec![]) }
         --
-        s:ident_str() { AstNode::Ident(s) }
+        s:ident_str() { SpannedNode::new(AstNode::Ident(s), vec![]) }
         }
     }
 }
@@ -117,6 +199,11 @@ pub enum Expr {
     Fft(Box<Expr>),
     FftFreq(Box<Expr>),
 
+    // calculus
+    Diff(Box<Expr>),
+    Derivative(Box<Expr>),
+    Integrate(Box<Expr>),
+
     // time limits
     Last(Box<Expr>, hifitime::Duration),
     First(Box<Expr>, hifitime::Duration),
@@ -126,6 +213,19 @@ pub enum Expr {
 }
 
 impl Expr {
+    /// Returns true if the expression yields a single value per timestamp,
+    /// which is what the windowed calculus methods operate on.
+    fn is_scalar_series(&self) -> bool {
+        match self {
+            Expr::ArrayAccess(_, _) => true,
+            Expr::ComponentPart(part) => part
+                .component
+                .as_ref()
+                .is_some_and(|c| c.schema.dim().is_empty()),
+            _ => false,
+        }
+    }
+
     fn to_field(&self) -> Result<String, Error> {
         match self {
             Expr::ComponentPart(component) => Ok(component.name.replace(".", "_")),
@@ -133,6 +233,9 @@ impl Expr {
             Expr::Time(_) => Ok("time".to_string()),
             Expr::Fft(e) => Ok(format!("fft({})", e.to_field()?)),
             Expr::FftFreq(e) => Ok(format!("fftfreq({})", e.to_field()?)),
+            Expr::Diff(e) => Ok(diff_sql(&e.to_field()?, "time")),
+            Expr::Derivative(e) => Ok(derivative_sql(&e.to_field()?, "time")),
+            Expr::Integrate(e) => Ok(integrate_sql(&e.to_field()?, "time")),
             Expr::BinaryOp(left, right, op) => Ok(format!(
                 "({} {} {})",
                 left.to_field()?,
@@ -163,6 +266,7 @@ impl Expr {
             Expr::Time(component) => Ok(component.name.replace(".", "_")),
             Expr::FftFreq(e) => e.to_table(),
             Expr::Fft(e) => e.to_table(),
+            Expr::Diff(e) | Expr::Derivative(e) | Expr::Integrate(e) => e.to_table(),
             Expr::BinaryOp(left, _, _) => left.to_table(),
 
             Expr::ArrayAccess(inner_expr, _) => match inner_expr.as_ref() {
@@ -183,6 +287,15 @@ impl Expr {
         match self {
             Expr::Fft(e) => Ok(format!("fft({})", e.to_qualified_field()?)),
             Expr::FftFreq(e) => Ok(format!("fftfreq({})", e.to_qualified_field()?)),
+            Expr::Diff(e) => Ok(diff_sql(&e.to_qualified_field()?, &e.to_sql_time_field()?)),
+            Expr::Derivative(e) => Ok(derivative_sql(
+                &e.to_qualified_field()?,
+                &e.to_sql_time_field()?,
+            )),
+            Expr::Integrate(e) => Ok(integrate_sql(
+                &e.to_qualified_field()?,
+                &e.to_sql_time_field()?,
+            )),
             Expr::BinaryOp(left, right, op) => Ok(format!(
                 "({} {} {})",
                 left.to_qualified_field()?,
@@ -202,6 +315,9 @@ impl Expr {
         match self {
             Expr::Fft(e) => Some(format!("fft({})", e.to_column_name()?)),
             Expr::FftFreq(e) => Some(format!("fftfreq({})", e.to_column_name()?)),
+            Expr::Diff(e) => Some(format!("diff({})", e.to_column_name()?)),
+            Expr::Derivative(e) => Some(format!("derivative({})", e.to_column_name()?)),
+            Expr::Integrate(e) => Some(format!("integrate({})", e.to_column_name()?)),
             Expr::ComponentPart(e) => Some(e.name.clone()),
             Expr::ArrayAccess(expr, index) => match expr.as_ref() {
                 Expr::ComponentPart(c) => {
@@ -349,6 +465,24 @@ impl Expr {
     }
 }
 
+/// Difference between consecutive samples, ordered by time.
+fn diff_sql(field: &str, time: &str) -> String {
+    format!("({field} - lag({field}) over (order by {time}))")
+}
+
+/// [`diff_sql`] divided by the timestamp delta in seconds.
+fn derivative_sql(field: &str, time: &str) -> String {
+    let dt = format!(
+        "((cast({time} as bigint) - lag(cast({time} as bigint)) over (order by {time})) * 0.000001)"
+    );
+    format!("({} / {dt})", diff_sql(field, time))
+}
+
+/// Running trapezoidal integral, backed by the `integrate` window function in metor-db.
+fn integrate_sql(field: &str, time: &str) -> String {
+    format!("integrate({field}, {time}) over (order by {time})")
+}
+
 pub enum FmtExpr {
     String(String),
     Expr(Expr),
@@ -368,6 +502,8 @@ pub struct Component {
     pub id: ComponentId,
     pub schema: Schema,
     pub element_names: Vec<String>,
+    /// The clock domain of the component's timestamps
+    pub clock_domain: ClockDomain,
 }
 
 impl Component {
@@ -378,6 +514,7 @@ impl Component {
             id,
             schema,
             element_names,
+            clock_domain: ClockDomain::default(),
         }
     }
 }
@@ -494,6 +631,19 @@ impl Context {
     }
 
     pub fn parse(&self, ast: &AstNode) -> Result<Expr, Error> {
+        let children = ast
+            .children()
+            .into_iter()
+            .map(|child| self.parse(child))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.parse_node(ast, children)
+    }
+
+    /// Builds the expression for `ast` out of its already parsed children, which are in the
+    /// order of [`AstNode::children`].
+    pub(crate) fn parse_node(&self, ast: &AstNode, children: Vec<Expr>) -> Result<Expr, Error> {
+        let mut children = children.into_iter();
+        let mut child = || children.next().expect("missing parsed child");
         match ast {
             AstNode::Ident(cow) => self
                 .component_parts
@@ -502,8 +652,8 @@ impl Context {
                 .cloned()
                 .map(Arc::new)
                 .map(Expr::ComponentPart),
-            AstNode::Field(ast_node, cow) => {
-                let expr = self.parse(ast_node)?;
+            AstNode::Field(_, cow) => {
+                let expr = child();
                 match &expr {
                     Expr::ComponentPart(part) => {
                         if let Some(c) = &part.component {
@@ -528,15 +678,21 @@ impl Context {
                     _ => Err(Error::InvalidFieldAccess(cow.to_string())),
                 }
             }
-            AstNode::MethodCall(recv, cow, ast_nodes) => {
-                let recv = self.parse(recv)?;
-                let args = ast_nodes
-                    .iter()
-                    .map(|ast_node| self.parse(ast_node))
-                    .collect::<Result<Vec<_>, _>>()?;
+            AstNode::MethodCall(_, cow, _) => {
+                let recv = child();
+                let args = children.collect::<Vec<_>>();
                 match (cow.as_ref(), &recv, &args[..]) {
                     ("fft", Expr::ArrayAccess(_, _), &[]) => Ok(Expr::Fft(Box::new(recv))),
                     ("fftfreq", Expr::Time(_), &[]) => Ok(Expr::FftFreq(Box::new(recv))),
+                    ("diff", recv_expr, &[]) if recv_expr.is_scalar_series() => {
+                        Ok(Expr::Diff(Box::new(recv)))
+                    }
+                    ("derivative", recv_expr, &[]) if recv_expr.is_scalar_series() => {
+                        Ok(Expr::Derivative(Box::new(recv)))
+                    }
+                    ("integrate", recv_expr, &[]) if recv_expr.is_scalar_series() => {
+                        Ok(Expr::Integrate(Box::new(recv)))
+                    }
                     ("last", _, &[Expr::StringLiteral(ref d)]) => {
                         Ok(Expr::Last(Box::new(recv), parse_duration(d)?))
                     }
@@ -546,25 +702,21 @@ impl Context {
                     _ => Err(Error::InvalidMethodCall(cow.to_string())),
                 }
             }
-            AstNode::Tuple(ast_nodes) => ast_nodes
-                .iter()
-                .map(|ast_node| self.parse(ast_node))
-                .collect::<Result<Vec<_>, _>>()
-                .map(Expr::Tuple),
+            AstNode::Tuple(_) => Ok(Expr::Tuple(children.collect())),
             AstNode::StringLiteral(s) => {
                 Ok(Expr::StringLiteral(s.to_string()))
                 // // Parse duration strings like "5m", "10s", "1h"
                 // self.parse_duration(s.as_ref()).map(Expr::DurationLiteral)
             }
-            AstNode::BinaryOp(left, right, op) => {
-                let left = self.parse(left)?;
-                let right = self.parse(right)?;
+            AstNode::BinaryOp(_, _, op) => {
+                let left = child();
+                let right = child();
                 Ok(Expr::BinaryOp(Box::new(left), Box::new(right), *op))
             }
             AstNode::FloatLiteral(f) => Ok(Expr::FloatLiteral(*f)),
             AstNode::BoolLiteral(b) => Ok(Expr::BoolLiteral(*b)),
-            AstNode::ArrayIndex(ast_node, index) => {
-                let expr = self.parse(ast_node)?;
+            AstNode::ArrayIndex(_, index) => {
+                let expr = child();
                 match &expr {
                     Expr::ComponentPart(_) => Ok(Expr::ArrayAccess(Box::new(expr), *index)),
                     _ => Err(Error::InvalidFieldAccess(
@@ -608,6 +760,9 @@ impl Context {
             Expr::ArrayAccess(_, _) => {
                 vec![
                     "fft()".to_string(),
+                    "diff()".to_string(),
+                    "derivative()".to_string(),
+                    "integrate()".to_string(),
                     "last(".to_string(),
                     "first(".to_string(),
                 ]
@@ -624,6 +779,9 @@ impl Context {
             Expr::FftFreq(_) => {
                 vec!["last".to_string(), "first".to_string()]
             }
+            Expr::Diff(_) | Expr::Derivative(_) | Expr::Integrate(_) => {
+                vec!["last".to_string(), "first".to_string()]
+            }
             _ => vec![],
         }
     }
@@ -717,6 +875,10 @@ pub enum Error {
     InvalidSwizzle(String),
     #[error("invalid method call: {0}")]
     InvalidMethodCall(String),
+    #[error("type mismatch: {0}")]
+    TypeMismatch(String),
+    #[error("eval: {0}")]
+    Eval(String),
     #[error("parse {0}")]
     Parse(#[from] ParseError<peg::str::LineCol>),
 }
@@ -904,7 +1066,7 @@ mod tests {
             name: "b.velocity".to_string(),
             id: ComponentId::new("b.velocity"),
             component: Some(component2),
-            children: HashMap::default(),
+            children: BTreeMap::default(),
         });
 
         // Test Tuple with components from different tables
@@ -958,7 +1120,7 @@ mod tests {
             name: "b.velocity".to_string(),
             id: ComponentId::new("b.velocity"),
             component: Some(component2),
-            children: HashMap::default(),
+            children: BTreeMap::default(),
         });
 
         let component3 = Arc::new(Component::new(
@@ -971,7 +1133,7 @@ mod tests {
             name: "c.acceleration".to_string(),
             id: ComponentId::new("c.acceleration"),
             component: Some(component3),
-            children: HashMap::default(),
+            children: BTreeMap::default(),
         });
 
         let expr = Expr::Tuple(vec![
@@ -987,6 +1149,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_diff_sql() {
+        let context = create_test_context();
+        let expr = context.parse_str("a.world_pos.x.diff()").unwrap();
+        assert_eq!(
+            expr.to_sql(&context).unwrap(),
+            "select (a_world_pos.a_world_pos[1] - lag(a_world_pos.a_world_pos[1]) over (order by a_world_pos.time)) as 'diff(a.world_pos.x)' from a_world_pos"
+        );
+    }
+
+    #[test]
+    fn test_derivative_sql() {
+        let context = create_test_context();
+        let expr = context.parse_str("a.world_pos.y.derivative()").unwrap();
+        assert_eq!(
+            expr.to_sql(&context).unwrap(),
+            "select ((a_world_pos.a_world_pos[2] - lag(a_world_pos.a_world_pos[2]) over (order by a_world_pos.time)) / ((cast(a_world_pos.time as bigint) - lag(cast(a_world_pos.time as bigint)) over (order by a_world_pos.time)) * 0.000001)) as 'derivative(a.world_pos.y)' from a_world_pos"
+        );
+    }
+
+    #[test]
+    fn test_integrate_sql() {
+        let context = create_test_context();
+        let expr = context
+            .parse_str("(a.world_pos.time, a.world_pos.z.integrate())")
+            .unwrap();
+        assert_eq!(
+            expr.to_sql(&context).unwrap(),
+            "select a_world_pos.time, integrate(a_world_pos.a_world_pos[3], a_world_pos.time) over (order by a_world_pos.time) as 'integrate(a.world_pos.z)' from a_world_pos"
+        );
+    }
+
+    #[test]
+    fn test_calculus_requires_scalar() {
+        let context = create_test_context();
+        assert!(matches!(
+            context.parse_str("a.world_pos.diff()"),
+            Err(Error::InvalidMethodCall(_))
+        ));
+        assert!(matches!(
+            context.parse_str("a.world_pos.x.diff().integrate()"),
+            Err(Error::InvalidMethodCall(_))
+        ));
+    }
+
     #[test]
     fn test_element_names() {
         assert_eq!(default_element_names(&[4]), vec!["x", "y", "z", "w"]);
@@ -0,0 +1,36 @@
+"""A pure Python client for metor-db
+
+`metor_db.msgs` is generated from the metor-proto schemas by `metor-db gen-py`.
+"""
+
+from .client import Client, Packet, PacketTy, QueryError, len_packet
+from .vtable import (
+    OpBuilder,
+    component,
+    component_id,
+    data,
+    ext,
+    raw_field,
+    raw_table,
+    schema,
+    timestamp,
+    vtable,
+)
+
+__all__ = [
+    "Client",
+    "OpBuilder",
+    "Packet",
+    "PacketTy",
+    "QueryError",
+    "component",
+    "component_id",
+    "data",
+    "ext",
+    "len_packet",
+    "raw_field",
+    "raw_table",
+    "schema",
+    "timestamp",
+    "vtable",
+]
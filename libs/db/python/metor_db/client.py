@@ -0,0 +1,115 @@
+"""LenPacket framing and a blocking socket client"""
+
+import enum
+import socket
+import struct
+from dataclasses import dataclass
+from typing import Iterator, List, Optional
+
+from . import msgs
+
+# len, ty, id, request id
+HEADER = struct.Struct("<IB2sB")
+# the bytes covered by the len prefix that come before the body
+PACKET_HEADER_LEN = 4
+
+SQL_QUERY_ID = bytes([224, 27])
+ARROW_IPC_ID = bytes([224, 28])
+ERROR_RESPONSE_ID = bytes([224, 29])
+
+
+class PacketTy(enum.IntEnum):
+    MSG = 0
+    TABLE = 1
+    TIME_SERIES = 2
+    MSG_WITH_TIMESTAMP = 3
+
+
+class QueryError(Exception):
+    """The database replied with an `ErrorResponse`"""
+
+
+@dataclass
+class Packet:
+    ty: int
+    id: bytes
+    req_id: int
+    body: bytes
+
+
+def len_packet(ty: int, id: bytes, body: bytes, req_id: int = 0) -> bytes:
+    """Frames a packet body, prefixing it with its length and header"""
+    if len(id) != 2:
+        raise ValueError(f"packet ids are two bytes, got {len(id)}")
+    return HEADER.pack(PACKET_HEADER_LEN + len(body), ty, id, req_id) + body
+
+
+class Client:
+    """A blocking connection to a metor-db server"""
+
+    def __init__(self, sock: socket.socket) -> None:
+        self.sock = sock
+
+    @classmethod
+    def connect(cls, host: str = "127.0.0.1", port: int = 2240) -> "Client":
+        return cls(socket.create_connection((host, port)))
+
+    def close(self) -> None:
+        self.sock.close()
+
+    def __enter__(self) -> "Client":
+        return self
+
+    def __exit__(self, *_) -> None:
+        self.close()
+
+    def send(self, packet: bytes) -> None:
+        self.sock.sendall(packet)
+
+    def send_msg(self, msg: msgs.Message, req_id: int = 0) -> None:
+        """Sends a generated message, using the id assigned by `metor-db gen-py`"""
+        if msg.ID is None:
+            raise ValueError(f"{type(msg).__name__} is not a message")
+        self.send(len_packet(PacketTy.MSG, msg.ID, msg.encode(), req_id))
+
+    def send_table(self, id: bytes, table: bytes) -> None:
+        """Sends a table laid out by the vtable registered with the same id"""
+        self.send(len_packet(PacketTy.TABLE, id, table))
+
+    def _recv_exact(self, n: int) -> bytes:
+        buf = bytearray()
+        while len(buf) < n:
+            chunk = self.sock.recv(n - len(buf))
+            if not chunk:
+                raise ConnectionError("connection closed")
+            buf += chunk
+        return bytes(buf)
+
+    def recv(self) -> Packet:
+        len_, ty, id, req_id = HEADER.unpack(self._recv_exact(HEADER.size))
+        if len_ < PACKET_HEADER_LEN:
+            raise ValueError(f"invalid packet len {len_}")
+        body = self._recv_exact(len_ - PACKET_HEADER_LEN)
+        return Packet(ty=ty, id=id, req_id=req_id, body=body)
+
+    def sql(self, query: str) -> List[bytes]:
+        """Runs a query, returning each record batch as an arrow ipc stream"""
+        return list(self.sql_stream(query))
+
+    def sql_stream(self, query: str) -> Iterator[bytes]:
+        w = msgs.Writer()
+        w.str(query)
+        self.send(len_packet(PacketTy.MSG, SQL_QUERY_ID, bytes(w.buf)))
+        while True:
+            packet = self.recv()
+            if packet.ty != PacketTy.MSG:
+                continue
+            r = msgs.Reader(packet.body)
+            if packet.id == ERROR_RESPONSE_ID:
+                raise QueryError(r.str())
+            if packet.id != ARROW_IPC_ID:
+                continue
+            batch: Optional[bytes] = r.bytes() if r.option() else None
+            if batch is None:
+                return
+            yield batch
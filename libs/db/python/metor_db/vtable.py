@@ -0,0 +1,124 @@
+"""A vtable builder mirroring `metor_proto::vtable::builder`
+
+Ops are deduplicated by identity, so reusing the same op, like a shared timestamp table,
+only adds it to the vtable once.
+"""
+
+import struct
+from dataclasses import dataclass, field
+from typing import Dict, Iterable, List, Optional, Sequence
+
+from . import msgs
+
+
+def component_id(name: str) -> int:
+    """Returns the id of a component, the 64 bit fnv1a hash of its name with the top bit cleared"""
+    h = 0xCBF29CE484222325
+    for byte in name.encode("utf-8"):
+        h ^= byte
+        h = (h * 0x100000001B3) & 0xFFFFFFFFFFFFFFFF
+    return h & ~(1 << 63)
+
+
+@dataclass(eq=False)
+class OpBuilder:
+    kind: str
+    args: Dict[str, object] = field(default_factory=dict)
+
+
+@dataclass
+class FieldBuilder:
+    offset: int
+    len: int
+    arg: OpBuilder
+
+    def offset_by(self, offset: int) -> "FieldBuilder":
+        return FieldBuilder(self.offset + offset, self.len, self.arg)
+
+
+def data(buf: bytes, align: int = 1) -> OpBuilder:
+    return OpBuilder("data", {"align": align, "data": bytes(buf)})
+
+
+def raw_table(offset: int, len: int) -> OpBuilder:
+    return OpBuilder("table", {"offset": offset, "len": len})
+
+
+def component(id: "int | str") -> OpBuilder:
+    if isinstance(id, str):
+        id = component_id(id)
+    return OpBuilder("component", {"component_id": data(struct.pack("<Q", id), 8)})
+
+
+def schema(ty: msgs.PrimType, dim: Sequence[int], arg: OpBuilder) -> OpBuilder:
+    return OpBuilder(
+        "schema",
+        {
+            "ty": data(struct.pack("<Q", int(ty)), 8),
+            "dim": data(struct.pack(f"<{len(dim)}Q", *dim), 8),
+            "arg": arg,
+        },
+    )
+
+
+def timestamp(source: OpBuilder, arg: OpBuilder) -> OpBuilder:
+    return OpBuilder("timestamp", {"source": source, "arg": arg})
+
+
+def ext(msg: msgs.Message, arg: OpBuilder) -> OpBuilder:
+    """Attaches a message to a field, see `metor_proto::vtable::builder::ext`"""
+    if msg.ID is None:
+        raise ValueError(f"{type(msg).__name__} is not a message")
+    return OpBuilder("ext", {"id": msg.ID, "data": data(msg.encode()), "arg": arg})
+
+
+def raw_field(offset: int, len: int, arg: OpBuilder) -> FieldBuilder:
+    return FieldBuilder(offset, len, arg)
+
+
+class _Builder:
+    def __init__(self) -> None:
+        self.ops: List[msgs.Op] = []
+        self.data = bytearray()
+        self.visited: Dict[int, int] = {}
+
+    def visit(self, op: OpBuilder) -> int:
+        existing: Optional[int] = self.visited.get(id(op))
+        if existing is not None:
+            return existing
+        args = op.args
+        if op.kind == "data":
+            align = args["align"]
+            self.data += bytes((align - len(self.data) % align) % align)
+            offset = len(self.data)
+            self.data += args["data"]
+            built: msgs.Op = msgs.OpData(offset=offset, len=len(args["data"]))
+        elif op.kind == "table":
+            built = msgs.OpTable(offset=args["offset"], len=args["len"])
+        elif op.kind == "component":
+            built = msgs.OpComponent(component_id=self.visit(args["component_id"]))
+        elif op.kind == "schema":
+            ty = self.visit(args["ty"])
+            dim = self.visit(args["dim"])
+            built = msgs.OpSchema(ty=ty, dim=dim, arg=self.visit(args["arg"]))
+        elif op.kind == "timestamp":
+            source = self.visit(args["source"])
+            built = msgs.OpTimestamp(source=source, arg=self.visit(args["arg"]))
+        elif op.kind == "ext":
+            arg = self.visit(args["arg"])
+            data_ref = self.visit(args["data"])
+            built = msgs.OpExt(arg=arg, id=tuple(args["id"]), data=data_ref)
+        else:
+            raise ValueError(f"unknown op {op.kind}")
+        op_ref = len(self.ops)
+        self.ops.append(built)
+        self.visited[id(op)] = op_ref
+        return op_ref
+
+
+def vtable(fields: Iterable[FieldBuilder]) -> msgs.VTable:
+    builder = _Builder()
+    built = [
+        msgs.Field(offset=f.offset, len=f.len, arg=builder.visit(f.arg)) for f in fields
+    ]
+    return msgs.VTable(ops=builder.ops, fields=built, data=bytes(builder.data))
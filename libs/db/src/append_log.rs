@@ -155,4 +155,10 @@ impl<E: IntoBytes + Immutable> AppendLog<E> {
     pub fn capacity(&self) -> usize {
         self.map.len() - size_of::<Header<E>>()
     }
+
+    /// Writes the log's dirty pages back to disk
+    pub fn flush(&self) -> Result<(), Error> {
+        self.map.flush()?;
+        Ok(())
+    }
 }
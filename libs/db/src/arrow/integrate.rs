@@ -0,0 +1,135 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, AsArray, Float64Array, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Float64Type, TimestampMicrosecondType};
+use arrow_schema::TimeUnit;
+use datafusion::common::Result as DataFusionResult;
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::function::{PartitionEvaluatorArgs, WindowUDFFieldArgs};
+use datafusion::logical_expr::{PartitionEvaluator, Signature, Volatility, WindowUDFImpl};
+
+/// Running trapezoidal integral of a value over its timestamps, in value-seconds.
+///
+/// Any numeric value is cast to `f64`. Rows with a null value or time are skipped, so the
+/// integral carries over them and the next sample is integrated from the last valid one.
+///
+/// EQL lowers `.integrate()` to `integrate(value, time) over (order by time)`, DataFusion
+/// doesn't allow `lag` to be nested inside a `sum() over` so this can't be expressed with
+/// the built-in window functions.
+#[derive(Debug)]
+pub struct IntegrateUDWF {
+    signature: Signature,
+}
+
+impl IntegrateUDWF {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl WindowUDFImpl for IntegrateUDWF {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "integrate"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        let [value, time] = arg_types else {
+            return Err(DataFusionError::Plan(
+                "integrate expects a value and a time column".to_string(),
+            ));
+        };
+        if !value.is_numeric() {
+            return Err(DataFusionError::Plan(format!(
+                "integrate expects a numeric value, got {value}"
+            )));
+        }
+        if !matches!(time, DataType::Timestamp(..)) {
+            return Err(DataFusionError::Plan(format!(
+                "integrate expects a timestamp, got {time}"
+            )));
+        }
+        Ok(vec![
+            DataType::Float64,
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+        ])
+    }
+
+    fn partition_evaluator(
+        &self,
+        _args: PartitionEvaluatorArgs,
+    ) -> DataFusionResult<Box<dyn PartitionEvaluator>> {
+        Ok(Box::new(IntegrateEvaluator))
+    }
+
+    fn field(&self, field_args: WindowUDFFieldArgs) -> DataFusionResult<Field> {
+        Ok(Field::new(field_args.name(), DataType::Float64, true))
+    }
+}
+
+#[derive(Debug)]
+struct IntegrateEvaluator;
+
+impl PartitionEvaluator for IntegrateEvaluator {
+    fn evaluate_all(
+        &mut self,
+        values: &[ArrayRef],
+        _num_rows: usize,
+    ) -> DataFusionResult<ArrayRef> {
+        let [values, time] = values else {
+            return Err(DataFusionError::Internal(
+                "integrate expects a value and a time column".to_string(),
+            ));
+        };
+        let values = values.as_primitive::<Float64Type>();
+        let time = time.as_primitive::<TimestampMicrosecondType>();
+        let array = trapezoid_cumsum(values, time);
+        Ok(Arc::new(array))
+    }
+}
+
+fn trapezoid_cumsum(values: &Float64Array, time: &TimestampMicrosecondArray) -> Float64Array {
+    let mut sum = 0.0;
+    let mut last = None;
+    values
+        .iter()
+        .zip(time.iter())
+        .map(|sample| {
+            if let (Some(value), Some(time)) = sample {
+                if let Some((last_value, last_time)) = last {
+                    let dt = (time - last_time) as f64 * 1e-6;
+                    sum += (value + last_value) * 0.5 * dt;
+                }
+                last = Some((value, time));
+            }
+            Some(sum)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Array;
+
+    use super::*;
+
+    #[test]
+    fn test_trapezoid_cumsum_skips_nulls() {
+        let values = Float64Array::from(vec![Some(2.0), None, Some(4.0), Some(4.0)]);
+        let time =
+            TimestampMicrosecondArray::from(vec![Some(0), Some(1_000_000), Some(2_000_000), None]);
+        let integral = trapezoid_cumsum(&values, &time);
+        assert_eq!(integral.null_count(), 0);
+        assert_eq!(integral.values(), &[0.0, 0.0, 6.0, 6.0]);
+    }
+}
@@ -1,7 +1,8 @@
 use arrow::{
     array::{
-        Array, ArrayRef, ArrowPrimitiveType, BooleanArray, FixedSizeListArray, PrimitiveArray,
-        RecordBatch, TimestampMicrosecondArray,
+        Array, ArrayRef, ArrowPrimitiveType, BinaryViewArray, BooleanArray, FixedSizeBinaryArray,
+        FixedSizeListArray, PrimitiveArray, RecordBatch, StringViewArray,
+        TimestampMicrosecondArray,
     },
     buffer::{BooleanBuffer, Buffer, ScalarBuffer},
     datatypes::*,
@@ -9,9 +10,13 @@ use arrow::{
 use convert_case::Casing;
 use datafusion::{datasource::MemTable, prelude::SessionContext, sql::TableReference};
 use futures_lite::{Stream, pin};
-use metor_proto::types::{PrimType, Timestamp};
-use metor_proto_wkt::ArchiveFormat;
+use metor_proto::{
+    buf::UmbraBuf,
+    types::{ClockDomain, PrimType, Timestamp},
+};
+use metor_proto_wkt::{ArchiveFormat, MetadataExt};
 use std::{
+    collections::HashMap,
     fs::File,
     ops::{Bound, RangeBounds},
     path::Path,
@@ -24,7 +29,9 @@ use zerocopy::{Immutable, IntoBytes};
 use crate::{Component, DB, Error, append_log::AppendLog, time_series_2::TimeSeriesNode};
 
 mod fft;
+mod integrate;
 use fft::{FftUDF, FrequencyDomainUDF};
+use integrate::IntegrateUDWF;
 
 impl<T: IntoBytes + Immutable> AppendLog<T> {
     pub fn as_arrow_buffer(&self, element_size: usize) -> Buffer {
@@ -62,17 +69,18 @@ impl TimeSeriesNode {
     pub fn as_data_array(
         &self,
         name: impl ToString,
-        schema: &crate::ComponentSchema,
+        component: &Component,
     ) -> (FieldRef, ArrayRef) {
-        self.as_data_array_range(name, .., schema)
+        self.as_data_array_range(name, .., component)
     }
 
     pub fn as_data_array_range<R: RangeBounds<usize>>(
         &self,
         name: impl ToString,
         range: R,
-        schema: &crate::ComponentSchema,
+        component: &Component,
     ) -> (FieldRef, ArrayRef) {
+        let schema = &component.schema;
         let size = schema.dim.iter().product::<usize>() as i32;
         let element_size = self.element_size();
         let array = match schema.prim_type {
@@ -87,6 +95,15 @@ impl TimeSeriesNode {
             PrimType::I16 => node_array_ref::<Int16Type>(self, range, element_size),
             PrimType::I8 => node_array_ref::<Int8Type>(self, range, element_size),
             PrimType::Bool => node_bool_ref(self, range, element_size),
+            PrimType::Bytes | PrimType::Utf8 => node_view_ref(self, range, component),
+            PrimType::F16 => node_array_ref::<Float16Type>(self, range, element_size),
+            PrimType::I128 | PrimType::U128 => node_fixed_binary_ref(self, range, element_size),
+            PrimType::C64 => {
+                node_complex_ref(node_array_ref::<Float32Type>(self, range, element_size))
+            }
+            PrimType::C128 => {
+                node_complex_ref(node_array_ref::<Float64Type>(self, range, element_size))
+            }
         };
 
         let inner_field = Arc::new(Field::new(
@@ -128,27 +145,37 @@ impl TimeSeriesNode {
     pub fn as_record_batch(
         &self,
         name: impl ToString,
-        schema: &crate::ComponentSchema,
+        component: &Component,
+        clock_domain: ClockDomain,
     ) -> RecordBatch {
-        self.as_record_batch_range(name, .., schema)
+        self.as_record_batch_range(name, .., component, clock_domain)
     }
 
+    /// Builds a batch of the `time` and value columns, the `time` field carries the component's
+    /// clock domain in its `clock_domain` metadata so it survives queries
     pub fn as_record_batch_range(
         &self,
         name: impl ToString,
         range: impl RangeBounds<usize> + Clone,
-        schema: &crate::ComponentSchema,
+        component: &Component,
+        clock_domain: ClockDomain,
     ) -> RecordBatch {
         let name = name.to_string();
         let (data_field, data_array) =
-            self.as_data_array_range(name.clone(), range.clone(), schema);
+            self.as_data_array_range(name.clone(), range.clone(), component);
         let time_array = self.as_time_series_array_range(range);
         let len = data_array.len().min(time_array.len());
-        let time_field = Arc::new(Field::new(
-            "time",
-            DataType::Timestamp(TimeUnit::Microsecond, None),
-            false,
-        ));
+        let time_field = Arc::new(
+            Field::new(
+                "time",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            )
+            .with_metadata(HashMap::from([(
+                "clock_domain".to_string(),
+                clock_domain.as_str().to_string(),
+            )])),
+        );
         let fields = vec![time_field, data_field];
         let columns = vec![time_array.slice(0, len), data_array.slice(0, len)];
 
@@ -158,7 +185,7 @@ impl TimeSeriesNode {
 }
 
 impl Component {
-    pub fn as_mem_table(&self, name: impl ToString) -> Option<MemTable> {
+    pub fn as_mem_table(&self, name: impl ToString, clock_domain: ClockDomain) -> Option<MemTable> {
         let name = name.to_string();
         let mut schema = None;
 
@@ -167,7 +194,7 @@ impl Component {
             .list
             .iter()
             .map(|node| {
-                let record_batch = node.as_record_batch(&name, &self.schema);
+                let record_batch = node.as_record_batch(&name, self, clock_domain);
                 if schema.is_none() {
                     schema = Some(record_batch.schema());
                 }
@@ -185,6 +212,16 @@ impl Component {
                 )]]),
         )
     }
+
+    /// Pairs the component and each of its archived versions with the table name they're exposed
+    /// under, archived versions are suffixed with `_v{version}`
+    fn versions_with_names(&self, name: &str) -> impl Iterator<Item = (String, &Component)> {
+        std::iter::once((name.to_string(), self)).chain(
+            self.history
+                .iter()
+                .map(move |c| (format!("{name}_v{}", c.version), c)),
+        )
+    }
 }
 
 impl DB {
@@ -199,6 +236,9 @@ impl DB {
         ctx.register_udf(datafusion::logical_expr::ScalarUDF::new_from_impl(
             FrequencyDomainUDF::new(),
         ));
+        ctx.register_udwf(datafusion::logical_expr::WindowUDF::new_from_impl(
+            IntegrateUDWF::new(),
+        ));
 
         self.with_state(|state| {
             for component in state.components.values() {
@@ -206,11 +246,12 @@ impl DB {
                     .component_metadata
                     .get(&component.component_id)
                     .unwrap();
-                if let Some(mem_table) = component.as_mem_table(&component_metadata.name) {
-                    ctx.register_table(
-                        TableReference::bare(component_metadata.name.clone()),
-                        Arc::new(mem_table),
-                    )?;
+                for (name, component) in component.versions_with_names(&component_metadata.name) {
+                    if let Some(mem_table) =
+                        component.as_mem_table(&name, component_metadata.clock_domain())
+                    {
+                        ctx.register_table(TableReference::bare(name), Arc::new(mem_table))?;
+                    }
                 }
             }
             Ok::<_, datafusion::error::DataFusionError>(())
@@ -236,58 +277,67 @@ impl DB {
                     continue;
                 };
 
-                let column_name = component_metadata.name.clone();
-                let mut schema = None;
-                let record_batches = component
-                    .time_series
-                    .list
-                    .iter()
-                    .map(|node| {
-                        let record_batch =
-                            node.as_record_batch(column_name.clone(), &component.schema);
-                        if schema.is_none() {
-                            schema = Some(record_batch.schema());
+                for (column_name, component) in
+                    component.versions_with_names(&component_metadata.name)
+                {
+                    let mut schema = None;
+                    let record_batches = component
+                        .time_series
+                        .list
+                        .iter()
+                        .map(|node| {
+                            let record_batch = node.as_record_batch(
+                                column_name.clone(),
+                                component,
+                                component_metadata.clock_domain(),
+                            );
+                            if schema.is_none() {
+                                schema = Some(record_batch.schema());
+                            }
+                            record_batch
+                        })
+                        .collect::<Vec<_>>();
+                    let Some(schema) = schema else { continue };
+
+                    match format {
+                        ArchiveFormat::ArrowIpc => {
+                            let file_name = format!("{column_name}.arrow");
+                            let file_path = path.join(file_name);
+                            let mut file = File::create(file_path)?;
+                            let mut writer =
+                                arrow::ipc::writer::FileWriter::try_new(&mut file, &schema)?;
+                            for record_batch in record_batches {
+                                writer.write(&record_batch)?;
+                            }
+                            writer.finish()?;
                         }
-                        record_batch
-                    })
-                    .collect::<Vec<_>>();
-                let Some(schema) = schema else { continue };
-
-                match format {
-                    ArchiveFormat::ArrowIpc => {
-                        let file_name = format!("{column_name}.arrow");
-                        let file_path = path.join(file_name);
-                        let mut file = File::create(file_path)?;
-                        let mut writer =
-                            arrow::ipc::writer::FileWriter::try_new(&mut file, &schema)?;
-                        for record_batch in record_batches {
-                            writer.write(&record_batch)?;
+                        #[cfg(feature = "parquet")]
+                        ArchiveFormat::Parquet => {
+                            let file_name = format!("{column_name}.parquet");
+                            let file_path = path.join(file_name);
+                            let mut file = File::create(file_path)?;
+                            let mut writer = parquet::arrow::ArrowWriter::try_new(
+                                &mut file,
+                                schema.clone(),
+                                None,
+                            )?;
+                            for record_batch in record_batches {
+                                writer.write(&record_batch)?;
+                            }
+                            writer.close()?;
                         }
-                        writer.finish()?;
-                    }
-                    #[cfg(feature = "parquet")]
-                    ArchiveFormat::Parquet => {
-                        let file_name = format!("{column_name}.parquet");
-                        let file_path = path.join(file_name);
-                        let mut file = File::create(file_path)?;
-                        let mut writer =
-                            parquet::arrow::ArrowWriter::try_new(&mut file, schema.clone(), None)?;
-                        for record_batch in record_batches {
-                            writer.write(&record_batch)?;
+                        ArchiveFormat::Csv => {
+                            let file_name = format!("{column_name}.csv");
+                            let file_path = path.join(file_name);
+                            let mut file = File::create(file_path)?;
+                            let mut writer = arrow::csv::Writer::new(&mut file);
+                            for record_batch in record_batches {
+                                writer.write(&record_batch)?;
+                            }
                         }
-                        writer.close()?;
+                        #[allow(unreachable_patterns)]
+                        _ => return Err(Error::UnsupportedArchiveFormat),
                     }
-                    ArchiveFormat::Csv => {
-                        let file_name = format!("{column_name}.csv");
-                        let file_path = path.join(file_name);
-                        let mut file = File::create(file_path)?;
-                        let mut writer = arrow::csv::Writer::new(&mut file);
-                        for record_batch in record_batches {
-                            writer.write(&record_batch)?;
-                        }
-                    }
-                    #[allow(unreachable_patterns)]
-                    _ => return Err(Error::UnsupportedArchiveFormat),
                 }
             }
             Ok(())
@@ -326,6 +376,41 @@ fn node_bool_ref(
     Arc::new(BooleanArray::new(buf, None))
 }
 
+/// Exposes 128-bit integers as 16 byte little-endian binary values, arrow has no native
+/// 128-bit integer type and its buffers would require an alignment the mmap can't guarantee
+fn node_fixed_binary_ref(
+    node: &TimeSeriesNode,
+    range: impl RangeBounds<usize>,
+    element_size: usize,
+) -> ArrayRef {
+    let buffer = node.data.as_arrow_buffer_range(range, element_size);
+    Arc::new(FixedSizeBinaryArray::new(16, buffer, None))
+}
+
+/// Groups the interleaved real and imaginary parts of a complex array into pairs
+fn node_complex_ref(parts: ArrayRef) -> ArrayRef {
+    let field = Arc::new(Field::new("item", parts.data_type().clone(), false));
+    Arc::new(FixedSizeListArray::new(field, 2, parts, None))
+}
+
+/// Resolves the variable length values in a node into an arrow view array
+fn node_view_ref(
+    node: &TimeSeriesNode,
+    range: impl RangeBounds<usize>,
+    component: &Component,
+) -> ArrayRef {
+    let buffer = node.data.as_arrow_buffer_range(range, node.element_size());
+    let values = buffer
+        .chunks_exact(size_of::<UmbraBuf>())
+        .map(|buf| component.resolve(buf).unwrap_or_default());
+    match component.schema.prim_type {
+        PrimType::Utf8 => Arc::new(StringViewArray::from_iter_values(
+            values.map(String::from_utf8_lossy),
+        )),
+        _ => Arc::new(BinaryViewArray::from_iter_values(values)),
+    }
+}
+
 #[pin_project::pin_project]
 pub struct ComponentStream {
     stream: Pin<
@@ -3,7 +3,7 @@ use std::{
     ptr,
     sync::{
         Arc,
-        atomic::{AtomicPtr, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
     },
 };
 use stellarator::sync::WaitQueue;
@@ -24,6 +24,7 @@ impl Disruptor {
             },
             readers: Readers::new(),
             new_data_queue: WaitQueue::new(),
+            closed: AtomicBool::new(false),
         });
         Self { core }
     }
@@ -67,6 +68,9 @@ impl Disruptor {
             % core.ringbuf.len() as u64) as usize;
         let _lock_guard = core.write_head.write_lock.lock().expect("poisoned");
         let max = core.ringbuf.len();
+        if core.closed.load(Ordering::Acquire) {
+            return Err(DisruptorError::Closed);
+        }
         if len > max {
             return Err(DisruptorError::InsufficientCapacity);
         }
@@ -101,6 +105,13 @@ impl Disruptor {
         }
     }
 
+    /// Rejects any further writes, letting readers drain what has already been committed
+    pub fn close(&self) {
+        let _lock_guard = self.core.write_head.write_lock.lock().expect("poisoned");
+        self.core.closed.store(true, Ordering::Release);
+        self.core.new_data_queue.wake_all();
+    }
+
     pub fn reader_count(&self) -> usize {
         let mut count = 0;
         let mut cursor = self.core.readers.0.next.clone();
@@ -117,6 +128,7 @@ pub struct DistruptorCore {
     write_head: WriteHead,
     readers: Readers,
     new_data_queue: WaitQueue,
+    closed: AtomicBool,
 }
 
 pub fn can_write(core: &DistruptorCore, len: usize, write: usize, max: usize) -> bool {
@@ -226,6 +238,28 @@ impl Reader {
         }
     }
 
+    /// Like [`Reader::next`], but returns `None` once the disruptor is closed and drained
+    pub async fn recv(&mut self) -> Option<ReadGrant<'_>> {
+        let range = self
+            .core
+            .new_data_queue
+            .wait_for_value(|| {
+                // checked before reading, so nothing committed before closing is missed
+                let closed = self.core.closed.load(Ordering::Acquire);
+                match self.pending() {
+                    Some(range) => Some(Some(range)),
+                    None if closed => Some(None),
+                    None => None,
+                }
+            })
+            .await
+            .expect("queue closed")?;
+        Some(ReadGrant {
+            range,
+            reader: self,
+        })
+    }
+
     pub fn try_next(&mut self) -> Option<ReadGrant<'_>> {
         let node = self.node.as_ref();
 
@@ -245,6 +279,21 @@ impl Reader {
             reader: self,
         })
     }
+
+    fn pending(&self) -> Option<Range<usize>> {
+        let node = self.node.as_ref();
+        let mut read = node.cursor.load(Ordering::Acquire);
+        let write = self.core.write_head.committed.load(Ordering::Acquire);
+        let high_water_mark = self.core.write_head.high_water_mark.load(Ordering::Acquire);
+        if read == high_water_mark && write < read {
+            read = 0;
+            node.cursor.store(0, Ordering::Release);
+        }
+        let len = if write < read { high_water_mark } else { write } - read;
+        let len = len as usize;
+        let read = read as usize;
+        (len > 0).then(|| read..read + len)
+    }
 }
 
 impl Drop for Reader {
@@ -469,6 +518,7 @@ impl<T> Clone for ArcAtomic<T> {
 pub enum DisruptorError {
     WouldBlock,
     InsufficientCapacity,
+    Closed,
 }
 
 #[cfg(test)]
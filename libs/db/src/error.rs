@@ -1,7 +1,7 @@
 use std::io;
 
-use metor_proto::types::{ComponentId, PacketId};
-use metor_proto_wkt::{ErrorResponse, StreamId};
+use metor_proto::types::{ComponentId, PacketId, PrimType};
+use metor_proto_wkt::{ErrorResponse, ProtocolVersion, StreamId};
 use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum Error {
@@ -44,6 +44,16 @@ pub enum Error {
     Parquet(#[from] parquet::errors::ParquetError),
     #[error("schema mismatch")]
     SchemaMismatch,
+    #[error("unknown msg id {0:?}")]
+    UnknownMsgId(PacketId),
+    #[error("msg id {0:?} needs a capability that wasn't negotiated")]
+    CapabilityNotNegotiated(PacketId),
+    #[error("unsupported prim type {0}")]
+    UnsupportedPrimType(PrimType),
+    #[error("incompatible protocol version {0}, expected {1}")]
+    IncompatibleVersion(ProtocolVersion, ProtocolVersion),
+    #[error("db is shutting down")]
+    ShuttingDown,
 }
 
 impl From<metor_proto_stellar::Error> for Error {
@@ -1,3 +1,4 @@
+use append_log::AppendLog;
 use datafusion::common::HashSet;
 use futures_lite::StreamExt;
 use metor_proto::registry::VTableRegistry;
@@ -7,16 +8,17 @@ use metor_proto::vtable::builder::{
 };
 use metor_proto::vtable::{RealizedField, builder};
 use metor_proto::{
+    buf::UmbraBuf,
     com_de::Decomponentize,
     registry,
     schema::Schema,
     types::{
         ComponentId, ComponentView, IntoLenPacket, LenPacket, Msg, OwnedPacket as Packet, PacketId,
-        PrimType, RequestId, Timestamp,
+        PrimType, RequestId, Timestamp, TimestampNs,
     },
     vtable::VTable,
 };
-use metor_proto_stellar::{PacketSink, PacketStream};
+use metor_proto_stellar::{Compression, PacketSink, PacketStream};
 use metor_proto_wkt::*;
 use msg_log_2::MsgLog;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
@@ -26,21 +28,24 @@ use std::{
     collections::HashMap,
     ffi::OsStr,
     net::{SocketAddr, ToSocketAddrs},
+    ops::Range,
     path::{Path, PathBuf},
     sync::{
         Arc, RwLock,
         atomic::{self, AtomicBool, AtomicI64, AtomicU64},
     },
-    time::{Duration, Instant},
+    time::Duration,
 };
 use stellarator::{
     buf::Slice,
     io::{AsyncRead, AsyncWrite, OwnedReader, OwnedWriter, SplitExt},
-    net::{TcpListener, TcpStream, UdpSocket},
+    net::{TcpListener, UdpSocket},
     rent,
+    rt::Runtime,
     struc_con::Joinable,
     sync::{Mutex, WaitQueue},
-    util::AtomicCell,
+    time::monotonic_now,
+    util::{AtomicCell, CancelToken, Either, select},
 };
 use time_series::TimeSeries;
 use tracing::{debug, info, trace, warn};
@@ -49,7 +54,7 @@ use zerocopy::{FromBytes, IntoBytes};
 
 pub use error::Error;
 
-use crate::disruptor::Disruptor;
+use crate::disruptor::{Disruptor, DisruptorError};
 
 pub mod append_log;
 mod arc_ring;
@@ -58,12 +63,15 @@ pub mod disruptor;
 mod error;
 //mod msg_log;
 pub mod msg_log_2;
+pub mod python;
 //pub(crate) mod time_series;
 pub mod time_series_2;
 pub use msg_log_2 as msg_log;
 pub use time_series_2 as time_series;
 
 mod vtable_stream;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
 pub struct DB {
     pub vtable_gen: AtomicCell<u64>,
@@ -75,12 +83,18 @@ pub struct DB {
     pub default_stream_time_step: AtomicU64,
     pub last_updated: AtomicCell<Timestamp>,
     pub earliest_timestamp: Timestamp,
+    /// Held while a component's schema changes, so each change archives a version only once
+    evolve_lock: Mutex<()>,
+    /// Orders the writes to the db's metadata files
+    write_queues: Arc<WriteQueues>,
 }
 
 #[derive(Default)]
 pub struct State {
     components: HashMap<ComponentId, Component>,
     component_metadata: HashMap<ComponentId, ComponentMetadata>,
+    /// The archived versions of components, see [`State::index_history`]
+    archived: HashMap<ComponentId, Component>,
 
     msg_logs: HashMap<PacketId, MsgLog>,
 
@@ -117,6 +131,8 @@ impl DB {
             default_stream_time_step,
             last_updated: AtomicCell::new(Timestamp(i64::MIN)),
             earliest_timestamp: Timestamp::now(),
+            evolve_lock: Mutex::new(()),
+            write_queues: Default::default(),
         };
         db.save_db_state()?;
         Ok(db)
@@ -141,6 +157,38 @@ impl DB {
         db_state.write(self.path.join("db_state"))
     }
 
+    /// Like [`DB::save_db_state`], but writes the state atomically without blocking the executor
+    pub fn persist_db_state(&self) -> impl Future<Output = Result<(), Error>> + 'static {
+        self.db_config()
+            .persist(self.path.join("db_state"), &self.write_queues)
+    }
+
+    /// Stops accepting new values, then waits for everything already received to be flushed to
+    /// disk
+    pub async fn shutdown(&self) -> Result<(), Error> {
+        // archived versions keep their storage open as well, so they are flushed along with the
+        // current ones
+        let (components, msg_logs) = self.with_state(|s| {
+            (
+                s.components
+                    .values()
+                    .flat_map(|c| c.history.iter().chain(std::iter::once(c)))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                s.msg_logs.values().cloned().collect::<Vec<_>>(),
+            )
+        });
+        for component in &components {
+            component.close().await?;
+        }
+        for msg_log in &msg_logs {
+            msg_log.close().await?;
+        }
+        self.persist_db_state().await?;
+        info!(path = ?self.path, "flushed db");
+        Ok(())
+    }
+
     pub fn open(path: PathBuf) -> Result<Self, Error> {
         let mut component_metadata = HashMap::new();
         let mut components = HashMap::new();
@@ -167,11 +215,11 @@ impl DB {
             let schema = ComponentSchema::read(path.join("schema"))?;
             let metadata = ComponentMetadata::read(path.join("metadata"))?;
             trace!("Read component metadata for {}", metadata.name);
-            component_metadata.insert(component_id, metadata);
 
             trace!("Opening component file {}", path.display());
 
             let component = Component::open(&path, component_id, schema.clone())?;
+            component_metadata.insert(component_id, component.with_schema_version(metadata));
             if let Some(latest) = component.time_series.latest() {
                 let timestamp = latest.timestamp();
                 last_updated = timestamp.0.max(last_updated);
@@ -205,12 +253,16 @@ impl DB {
 
         info!(db.path = ?path, "opened db");
         let db_state = DbConfig::read(path.join("db_state"))?;
-        let state = State {
+        let mut state = State {
             components,
             component_metadata,
             msg_logs,
             ..Default::default()
         };
+        let component_ids = state.components.keys().copied().collect::<Vec<_>>();
+        for component_id in component_ids {
+            state.index_history(component_id);
+        }
         let earliest_timestamp = if start_timestamp == i64::MAX {
             Timestamp::now()
         } else {
@@ -226,26 +278,127 @@ impl DB {
             ),
             last_updated: AtomicCell::new(Timestamp(last_updated)),
             earliest_timestamp,
+            evolve_lock: Mutex::new(()),
+            write_queues: Default::default(),
         })
     }
 
-    pub fn insert_vtable(&self, vtable: VTableMsg) -> Result<(), Error> {
+    pub async fn insert_vtable(&self, vtable: VTableMsg) -> Result<(), Error> {
         info!(id = ?vtable.id, "inserting vtable");
-        self.with_state_mut(|state| {
-            for res in vtable.vtable.realize_fields(None) {
+        vtable.vtable.validate()?;
+        let fields = vtable
+            .vtable
+            .realize_fields(None)
+            .map(|res| {
                 let RealizedField {
                     component_id,
                     shape,
                     ty,
                     ..
                 } = res?;
-                let component_schema = ComponentSchema::new(ty, shape);
-                state.insert_component(component_id, component_schema, &self.path)?;
-                self.vtable_gen.fetch_add(1, atomic::Ordering::SeqCst);
-            }
+                Ok((component_id, ComponentSchema::new(ty, shape)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for (component_id, schema) in fields {
+            self.evolve_component(component_id, &schema).await?;
+            self.insert_component(component_id, schema).await?;
+            self.vtable_gen.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+        self.with_state_mut(|state| {
             state.vtable_registry.map.insert(vtable.id, vtable.vtable);
-            Ok::<_, Error>(())
-        })?;
+        });
+        Ok(())
+    }
+
+    /// Creates a component with `schema` and default metadata, unless it already exists
+    async fn insert_component(
+        &self,
+        component_id: ComponentId,
+        schema: ComponentSchema,
+    ) -> Result<(), Error> {
+        let _inserting = self.evolve_lock.lock().await;
+        let existing = self.with_state(|state| {
+            state
+                .components
+                .get(&component_id)
+                .map(|c| c.schema.clone())
+        });
+        if let Some(existing) = existing {
+            // schema changes are archived by `DB::evolve_component` beforehand, so this one
+            // raced with another change
+            if existing != schema {
+                warn!(existing.schema = ?existing, new_component.schema = ?schema,
+                      ?component_id,
+                      "schema mismatch");
+                return Err(Error::SchemaMismatch);
+            }
+            return Ok(());
+        }
+        info!(component.id = ?component_id.0, "inserting");
+        let component =
+            Component::create(&self.path, component_id, schema, &self.write_queues).await?;
+        let metadata = self.with_state_mut(|state| {
+            state.components.insert(component_id, component);
+            if state.component_metadata.contains_key(&component_id) {
+                return None;
+            }
+            state.update_component_metadata(ComponentMetadata {
+                component_id,
+                name: component_id.to_string(),
+                metadata: Default::default(),
+            })
+        });
+        if let Some(metadata) = metadata {
+            let path = self.path.join(component_id.to_string()).join("metadata");
+            metadata.persist(path, &self.write_queues).await?;
+        }
+        Ok(())
+    }
+
+    /// Archives the current version of a component if its schema differs from `schema`, and
+    /// replaces it with an empty version with `schema`
+    ///
+    /// Values pushed to the component while its storage is archived are rejected.
+    async fn evolve_component(
+        &self,
+        component_id: ComponentId,
+        schema: &ComponentSchema,
+    ) -> Result<(), Error> {
+        let _evolving = self.evolve_lock.lock().await;
+        let Some(existing) = self.with_state(|state| {
+            state
+                .components
+                .get(&component_id)
+                .filter(|c| c.schema != *schema)
+                .cloned()
+        }) else {
+            return Ok(());
+        };
+        warn!(?existing.schema, new_component.schema = ?schema,
+              ?existing.component_id,
+              "schema changed, archiving previous version");
+        let component = existing
+            .evolve(&self.path, schema.clone(), &self.write_queues)
+            .await?;
+        let metadata = self.with_state_mut(|state| {
+            let metadata = state
+                .component_metadata
+                .get(&component_id)
+                .cloned()
+                .unwrap_or_else(|| ComponentMetadata {
+                    component_id,
+                    name: component_id.to_string(),
+                    metadata: Default::default(),
+                });
+            let metadata = component.with_schema_version(metadata);
+            state.components.insert(component_id, component);
+            state.index_history(component_id);
+            state.update_component_metadata(metadata)
+        });
+        if let Some(metadata) = metadata {
+            let path = self.path.join(component_id.to_string()).join("metadata");
+            metadata.persist(path, &self.write_queues).await?;
+        }
         Ok(())
     }
 
@@ -269,6 +422,18 @@ impl DB {
         Ok(())
     }
 
+    /// Refuses a reserved id that would start a new msg log, logs that already exist keep
+    /// working so the id of a message never has to change
+    fn check_msg_id(&self, id: PacketId) -> Result<(), Error> {
+        if is_reserved_msg_id(id)
+            && !is_known_msg_id(id)
+            && !self.with_state(|s| s.msg_logs.contains_key(&id))
+        {
+            return Err(Error::UnknownMsgId(id));
+        }
+        Ok(())
+    }
+
     pub fn get_or_insert_fixed_rate_state(
         &self,
         stream_id: StreamId,
@@ -295,61 +460,66 @@ impl DB {
 }
 
 impl State {
-    pub fn insert_component(
-        &mut self,
-        component_id: ComponentId,
-        schema: ComponentSchema,
-        db_path: &Path,
-    ) -> Result<(), Error> {
-        if let Some(existing_component) = self.components.get(&component_id) {
-            if existing_component.schema != schema {
-                warn!( ?existing_component.schema, new_component.schema = ?schema,
-                       ?existing_component.component_id,
-                      "schema mismatch");
-                return Err(Error::SchemaMismatch);
-            }
-            return Ok(());
-        }
-        info!(component.id = ?component_id.0, "inserting");
-        let component_metadata = ComponentMetadata {
-            component_id,
-            name: component_id.to_string(),
-            metadata: Default::default(),
-        };
-        let component = Component::create(db_path, component_id, schema)?;
-        if !self.component_metadata.contains_key(&component_id) {
-            self.set_component_metadata(component_metadata, db_path)?;
-        }
-        self.components.insert(component_id, component);
-        Ok(())
-    }
-
     pub fn get_component_metadata(&self, component_id: ComponentId) -> Option<&ComponentMetadata> {
         self.component_metadata.get(&component_id)
     }
 
+    /// Returns a component, or one of the archived versions exposed by [`State::index_history`]
     pub fn get_component(&self, component_id: ComponentId) -> Option<&Component> {
-        self.components.get(&component_id)
+        self.components
+            .get(&component_id)
+            .or_else(|| self.archived.get(&component_id))
+    }
+
+    /// Exposes the archived versions of a component as read only components of their own, named
+    /// `{name}_v{version}` like their SQL tables, so they can be fetched and queried like any
+    /// other component
+    fn index_history(&mut self, component_id: ComponentId) {
+        let Some(component) = self.components.get(&component_id) else {
+            return;
+        };
+        let name = self
+            .component_metadata
+            .get(&component_id)
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| component_id.to_string());
+        for archived in component.history.iter() {
+            let name = format!("{name}_v{}", archived.version);
+            let id = ComponentId::new(&name);
+            let metadata = ComponentMetadata {
+                component_id: id,
+                name,
+                metadata: Default::default(),
+            }
+            .with_schema_version(archived.version);
+            self.component_metadata.insert(id, metadata);
+            self.archived.insert(id, archived.clone());
+        }
     }
 
-    pub fn set_component_metadata(
+    /// Updates the in-memory metadata of a component, returning the metadata to persist if it
+    /// changed
+    pub fn update_component_metadata(
         &mut self,
         metadata: ComponentMetadata,
-        db_path: &Path,
-    ) -> Result<(), Error> {
-        let component_metadata_path = db_path.join(metadata.component_id.to_string());
-        std::fs::create_dir_all(&component_metadata_path)?;
-        let component_metadata_path = component_metadata_path.join("metadata");
-        if component_metadata_path.exists()
-            && ComponentMetadata::read(&component_metadata_path)? == metadata
-        {
-            return Ok(());
+    ) -> Option<ComponentMetadata> {
+        let metadata = self.with_schema_version(metadata);
+        if self.component_metadata.get(&metadata.component_id) == Some(&metadata) {
+            return None;
         }
         info!(component.name= ?metadata.name, component.id = ?metadata.component_id.0, "setting component metadata");
-        metadata.write(component_metadata_path)?;
         self.component_metadata
-            .insert(metadata.component_id, metadata);
-        Ok(())
+            .insert(metadata.component_id, metadata.clone());
+        Some(metadata)
+    }
+
+    /// Keeps the schema version the db tracks in `metadata`, since clients replace a component's
+    /// metadata as a whole
+    fn with_schema_version(&self, metadata: ComponentMetadata) -> ComponentMetadata {
+        match self.components.get(&metadata.component_id) {
+            Some(component) => component.with_schema_version(metadata),
+            None => metadata,
+        }
     }
 
     pub fn get_or_insert_msg_log(
@@ -370,15 +540,16 @@ impl State {
         })
     }
 
+    /// Sets a msg log's metadata, returning a future that persists it
     pub fn set_msg_metadata(
         &mut self,
         id: PacketId,
         metadata: MsgMetadata,
         db_path: &Path,
-    ) -> Result<(), Error> {
+        write_queues: &Arc<WriteQueues>,
+    ) -> Result<impl Future<Output = Result<(), Error>> + 'static, Error> {
         let msg_log = self.get_or_insert_msg_log(id, db_path)?;
-        msg_log.set_metadata(metadata)?;
-        Ok(())
+        Ok(msg_log.set_metadata(metadata, write_queues))
     }
 }
 
@@ -417,10 +588,14 @@ impl ComponentSchema {
         Ok(postcard::from_bytes(&data)?)
     }
 
-    fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
-        let data = postcard::to_allocvec(&self)?;
-        std::fs::write(path, data)?;
-        Ok(())
+    fn persist(
+        &self,
+        path: impl AsRef<Path>,
+        write_queues: &Arc<WriteQueues>,
+    ) -> impl Future<Output = Result<(), Error>> + 'static {
+        let write =
+            postcard::to_allocvec(&self).map(|data| write_queues.write(path.as_ref(), data));
+        async move { write?.await }
     }
 
     pub fn to_schema(&self) -> Schema<Vec<u64>> {
@@ -431,7 +606,14 @@ impl ComponentSchema {
         self.dim.iter().map(|&x| x as u64).collect()
     }
 
+    /// Parses a value of this schema from the start of `buf`
+    ///
+    /// Variable length values are stored outside of their time series, so they can't be parsed
+    /// from `buf` alone, see [`Component::resolve`] instead.
     pub fn parse_value<'a>(&'a self, buf: &'a [u8]) -> Result<(usize, ComponentView<'a>), Error> {
+        if self.prim_type.is_var_len() {
+            return Err(Error::UnsupportedPrimType(self.prim_type));
+        }
         let size = self.size();
         let buf = buf
             .get(..size)
@@ -482,6 +664,27 @@ impl ComponentSchema {
                 nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
                     .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
             ),
+            PrimType::F16 => ComponentView::F16(
+                nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
+                    .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
+            ),
+            PrimType::I128 => ComponentView::I128(
+                nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
+                    .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
+            ),
+            PrimType::U128 => ComponentView::U128(
+                nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
+                    .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
+            ),
+            PrimType::C64 => ComponentView::C64(
+                nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
+                    .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
+            ),
+            PrimType::C128 => ComponentView::C128(
+                nox::ArrayView::from_bytes_shape_unchecked(buf, dim)
+                    .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?,
+            ),
+            PrimType::Bytes | PrimType::Utf8 => unreachable!(),
         };
         Ok((size, view))
     }
@@ -513,12 +716,99 @@ pub trait MetadataExt: Sized + Serialize + DeserializeOwned {
         std::fs::write(path, data)?;
         Ok(())
     }
+    /// Writes the metadata to `path` atomically, so a crash never leaves a torn file behind
+    fn persist(
+        &self,
+        path: impl AsRef<Path>,
+        write_queues: &Arc<WriteQueues>,
+    ) -> impl Future<Output = Result<(), Error>> + 'static {
+        let write =
+            postcard::to_allocvec(&self).map(|data| write_queues.write(path.as_ref(), data));
+        async move { write?.await }
+    }
+}
+
+/// Orders the writes to a path made through [`WriteQueues::write`]
+#[derive(Default)]
+struct WriteQueue {
+    /// The last ticket handed out
+    next: AtomicU64,
+    /// The ticket of the newest write that landed, held while writing
+    written: Mutex<u64>,
+}
+
+/// The write queue of each path a [`DB`] has writes in flight for, a queue is removed once its
+/// last write lands
+#[derive(Default)]
+pub struct WriteQueues {
+    queues: std::sync::Mutex<HashMap<PathBuf, Arc<WriteQueue>>>,
+}
+
+impl WriteQueues {
+    /// Writes `data` to a uniquely named temporary file next to `path`, syncs it, renames it
+    /// over `path` and syncs the directory, so the rename is durable as well
+    ///
+    /// Writes to the same path land one at a time in the order `write` was called, a write
+    /// that's overtaken by a newer one is skipped.
+    pub fn write(
+        self: &Arc<Self>,
+        path: &Path,
+        data: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Error>> + 'static {
+        let path = path.to_path_buf();
+        // tickets are handed out under the map's lock, so the last write can tell it's the last
+        let (queue, ticket) = {
+            let mut queues = self.queues.lock().unwrap();
+            let queue = queues.entry(path.clone()).or_default().clone();
+            let ticket = queue.next.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+            (queue, ticket)
+        };
+        let this = self.clone();
+        async move {
+            let res = Self::write_ticket(&queue, ticket, &path, data).await;
+            let mut queues = this.queues.lock().unwrap();
+            if queue.next.load(atomic::Ordering::Relaxed) == ticket {
+                queues.remove(&path);
+            }
+            res
+        }
+    }
+
+    async fn write_ticket(
+        queue: &WriteQueue,
+        ticket: u64,
+        path: &Path,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        let mut written = queue.written.lock().await;
+        if *written > ticket {
+            return Ok(());
+        }
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(".{:016x}.tmp", fastrand::u64(..)));
+        let tmp_path = path.with_file_name(tmp_name);
+        let file = stellarator::fs::File::create(&tmp_path).await?;
+        file.write_all(data).await.0?;
+        file.sync_data().await?;
+        drop(file);
+        stellarator::fs::rename(&tmp_path, path).await?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        stellarator::fs::File::open(dir).await?.sync_all().await?;
+        *written = ticket;
+        Ok(())
+    }
 }
 
 impl MetadataExt for EntityMetadata {}
 impl MetadataExt for ComponentMetadata {}
 impl MetadataExt for MsgMetadata {}
 
+/// The directory inside a component's directory that holds its archived versions
+const VERSIONS_DIR: &str = "versions";
+
 #[derive(Clone)]
 pub struct Component {
     pub component_id: ComponentId,
@@ -526,27 +816,53 @@ pub struct Component {
     pub wal: Disruptor,
     pub schema: ComponentSchema,
     pub last_timestamp: Arc<AtomicCell<Timestamp>>,
+    /// Holds variable length values that are too long to be stored inline in the time series
+    pub data_log: Option<AppendLog<()>>,
+    /// Incremented each time the component's schema changes
+    pub version: u32,
+    /// The component's storage from before each schema change, oldest first
+    pub history: Arc<[Component]>,
+    /// Cancelled once the persist task has drained the wal
+    pub persisted: CancelToken,
 }
 
 impl Component {
-    pub fn create(
+    pub async fn create(
         db_path: &Path,
         component_id: ComponentId,
         schema: ComponentSchema,
+        write_queues: &Arc<WriteQueues>,
     ) -> Result<Self, Error> {
         let component_path = db_path.join(component_id.to_string());
-        std::fs::create_dir_all(&component_path)?;
+        stellarator::fs::create_dir_all(&component_path).await?;
         let component_schema_path = component_path.join("schema");
-        if !component_schema_path.exists() {
-            schema.write(component_schema_path)?;
+        if stellarator::fs::metadata(&component_schema_path)
+            .await
+            .is_err()
+        {
+            schema.persist(component_schema_path, write_queues).await?;
         }
         let time_series = TimeSeries::create(component_path.clone())?;
+        let data_log = if schema.prim_type.is_var_len() {
+            let data_log_path = component_path.join("data_log");
+            if data_log_path.exists() {
+                Some(AppendLog::open(data_log_path)?)
+            } else {
+                Some(AppendLog::create(data_log_path, ())?)
+            }
+        } else {
+            None
+        };
         let this = Component {
             wal: Disruptor::new(schema.size() * 1024),
             component_id,
             time_series,
             schema,
             last_timestamp: Arc::new(AtomicCell::new(Timestamp(i64::MIN))),
+            data_log,
+            version: 0,
+            history: Arc::new([]),
+            persisted: CancelToken::new(),
         };
         stellarator::spawn(this.clone().persist());
         Ok(this)
@@ -556,8 +872,27 @@ impl Component {
         path: impl AsRef<Path>,
         component_id: ComponentId,
         schema: ComponentSchema,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut this = Self::open_version(path, component_id, schema)?;
+        this.history = Self::open_history(path, component_id)?;
+        this.version = this.history.last().map(|c| c.version + 1).unwrap_or(0);
+        stellarator::spawn(this.persist());
+        Ok(this)
+    }
+
+    /// Opens the storage of a single version of the component, without persisting its wal
+    fn open_version(
+        path: &Path,
+        component_id: ComponentId,
+        schema: ComponentSchema,
     ) -> Result<Self, Error> {
         let time_series = TimeSeries::open(path)?;
+        let data_log = if schema.prim_type.is_var_len() {
+            Some(AppendLog::open(path.join("data_log"))?)
+        } else {
+            None
+        };
 
         let last_timestamp = time_series
             .latest()
@@ -569,18 +904,100 @@ impl Component {
             time_series,
             schema,
             last_timestamp: Arc::new(AtomicCell::new(last_timestamp)),
+            data_log,
+            version: 0,
+            history: Arc::new([]),
+            persisted: CancelToken::new(),
         };
-        stellarator::spawn(this.persist());
         Ok(this)
     }
 
+    /// Opens the archived versions of the component stored in `versions/<version>`
+    fn open_history(path: &Path, component_id: ComponentId) -> Result<Arc<[Component]>, Error> {
+        let versions_path = path.join(VERSIONS_DIR);
+        if !versions_path.exists() {
+            return Ok(Arc::new([]));
+        }
+        let mut history = vec![];
+        for elem in std::fs::read_dir(&versions_path)? {
+            let path = elem?.path();
+            let Some(version) = path
+                .file_name()
+                .and_then(|p| p.to_str())
+                .and_then(|p| p.parse().ok())
+            else {
+                trace!("Skipping non-version directory: {}", path.display());
+                continue;
+            };
+            let schema = ComponentSchema::read(path.join("schema"))?;
+            let component = Self::open_version(&path, component_id, schema)?;
+            // archived versions are read only, so there's no wal to persist
+            component.persisted.cancel();
+            history.push(Component {
+                version,
+                ..component
+            });
+        }
+        history.sort_by_key(|c| c.version);
+        Ok(history.into())
+    }
+
+    /// Moves the component's storage to `versions/<version>` and creates an empty version of the
+    /// component with `schema`, the old data remains readable through [`Component::history`]
+    ///
+    /// The component is closed first, so everything already pushed is persisted before its
+    /// storage moves. Going back to an archived schema, like after a firmware rollback, archives
+    /// the current version the same way and starts a new one.
+    pub async fn evolve(
+        &self,
+        db_path: &Path,
+        schema: ComponentSchema,
+        write_queues: &Arc<WriteQueues>,
+    ) -> Result<Self, Error> {
+        self.close().await?;
+        let component_path = db_path.join(self.component_id.to_string());
+        let archive_path = component_path
+            .join(VERSIONS_DIR)
+            .join(self.version.to_string());
+        stellarator::fs::create_dir_all(&archive_path).await?;
+        for elem in stellarator::fs::read_dir(&component_path).await? {
+            let Some(name) = elem.file_name().to_str() else {
+                continue;
+            };
+            let is_node = elem.file_type().is_dir() && name.parse::<i64>().is_ok();
+            if is_node || name == "schema" || name == "data_log" {
+                stellarator::fs::rename(elem.path(), archive_path.join(name)).await?;
+            }
+        }
+        let archived = Self::open_version(&archive_path, self.component_id, self.schema.clone())?;
+        archived.persisted.cancel();
+        let mut history = self.history.to_vec();
+        history.push(Component {
+            version: self.version,
+            ..archived
+        });
+        let mut component = Self::create(db_path, self.component_id, schema, write_queues).await?;
+        component.version = self.version + 1;
+        component.history = history.into();
+        Ok(component)
+    }
+
+    /// Records the component's schema version in its metadata, once its schema has changed
+    pub fn with_schema_version(&self, metadata: ComponentMetadata) -> ComponentMetadata {
+        if self.version == 0 {
+            return metadata;
+        }
+        metadata.with_schema_version(self.version)
+    }
+
     pub fn persist(&self) -> impl Future<Output = ()> + 'static {
         let mut reader = self.wal.reader();
         let writer = self.time_series.writer().expect("writer already created");
         let msg_size = self.schema.size() + size_of::<Timestamp>();
+        let persisted = self.persisted.clone().drop_guard();
         async move {
-            loop {
-                let buf = reader.next().await;
+            let _persisted = persisted;
+            while let Some(buf) = reader.recv().await {
                 let mut buf = &buf[..];
                 'parse: while let Some(msg) = buf.get(..msg_size) {
                     let Some(timestamp) = msg.get(..size_of::<Timestamp>()) else {
@@ -601,6 +1018,18 @@ impl Component {
         }
     }
 
+    /// Stops accepting values, waits for the wal to be persisted and flushes the time series to
+    /// disk
+    pub async fn close(&self) -> Result<(), Error> {
+        self.wal.close();
+        self.persisted.wait().await;
+        self.time_series.flush()?;
+        if let Some(data_log) = &self.data_log {
+            data_log.flush()?;
+        }
+        Ok(())
+    }
+
     fn as_vtable_op(&self) -> Arc<OpBuilder> {
         schema(
             self.schema.prim_type,
@@ -613,12 +1042,16 @@ impl Component {
         if timestamp < self.last_timestamp.latest() {
             return Err(Error::TimeTravel);
         }
-        let Ok(mut grant) = self.wal.try_grant(value_buf.len() + size_of::<Timestamp>()) else {
-            let reader_count = self.wal.reader_count();
-            warn!(?timestamp, ?reader_count, "skipped buf due to overflow");
-            // TODO(sphw): we should probably wait here, log, or even error out
-            // not sure what is best
-            return Ok(());
+        let mut grant = match self.wal.try_grant(value_buf.len() + size_of::<Timestamp>()) {
+            Ok(grant) => grant,
+            Err(DisruptorError::Closed) => return Err(Error::ShuttingDown),
+            Err(_) => {
+                let reader_count = self.wal.reader_count();
+                warn!(?timestamp, ?reader_count, "skipped buf due to overflow");
+                // TODO(sphw): we should probably wait here, log, or even error out
+                // not sure what is best
+                return Ok(());
+            }
         };
         grant[..size_of::<Timestamp>()].copy_from_slice(timestamp.as_bytes());
         grant[size_of::<Timestamp>()..].copy_from_slice(value_buf);
@@ -626,6 +1059,74 @@ impl Component {
         self.last_timestamp.update_max(timestamp);
         Ok(())
     }
+
+    /// Pushes a value into the component's wal
+    ///
+    /// Variable length values are pushed as an [`UmbraBuf`], values that don't fit inline are
+    /// written to the component's `data_log` first.
+    pub fn push_value(&self, timestamp: Timestamp, value: ComponentView<'_>) -> Result<(), Error> {
+        if value.prim_type().is_var_len() != self.data_log.is_some() {
+            return Err(Error::SchemaMismatch);
+        }
+        let Some(data_log) = &self.data_log else {
+            if value.as_bytes().len() != self.schema.size() {
+                return Err(Error::SchemaMismatch);
+            }
+            return self.push_buf(timestamp, value.as_bytes());
+        };
+        if timestamp < self.last_timestamp.latest() {
+            return Err(Error::TimeTravel);
+        }
+        let value = value.as_bytes();
+        let offset = if value.len() > 12 {
+            u32::try_from(data_log.write(value)?).map_err(|_| Error::MapOverflow)?
+        } else {
+            0
+        };
+        self.push_buf(timestamp, UmbraBuf::new(value, offset).as_bytes())
+    }
+
+    /// Returns the value stored in `buf`, an element of the component's time series
+    ///
+    /// Variable length values are resolved from their [`UmbraBuf`], other values are returned as is.
+    pub fn resolve<'a>(&'a self, buf: &'a [u8]) -> Option<&'a [u8]> {
+        match &self.data_log {
+            Some(data_log) => UmbraBuf::resolve(buf, data_log.data()),
+            None => Some(buf),
+        }
+    }
+}
+
+/// Collects the variable length values of a table, so they can be appended after its fixed size fields
+#[derive(Default)]
+struct VarLenTail {
+    buf: Vec<u8>,
+    fields: Vec<(usize, Range<usize>)>,
+}
+
+impl VarLenTail {
+    /// Pushes `value`'s [`UmbraBuf`] into `table`, deferring values that don't fit inline
+    fn push(&mut self, table: &mut LenPacket, value: &[u8]) {
+        if value.len() > 12 {
+            let start = self.buf.len();
+            self.fields
+                .push((table.as_packet().body.len(), start..start + value.len()));
+            self.buf.extend_from_slice(value);
+        }
+        table.extend_from_slice(UmbraBuf::new(value, 0).as_bytes());
+    }
+
+    /// Appends the deferred values to `table`, and points their [`UmbraBuf`]s at them
+    fn finish(&mut self, table: &mut LenPacket) {
+        let start = table.as_packet().body.len();
+        table.extend_from_slice(&self.buf);
+        let body = &mut table.as_mut_packet().body;
+        for (offset, range) in self.fields.drain(..) {
+            let umbra = UmbraBuf::new(&self.buf[range.clone()], (start + range.start) as u32);
+            body[offset..offset + size_of::<UmbraBuf>()].copy_from_slice(umbra.as_bytes());
+        }
+        self.buf.clear();
+    }
 }
 
 struct DBSink<'a> {
@@ -644,13 +1145,12 @@ impl Decomponentize for DBSink<'_> {
         timestamp: Option<Timestamp>,
     ) -> Result<(), Error> {
         let timestamp = timestamp.unwrap_or(self.table_received);
-        let value_buf = value.as_bytes();
         let Some(component) = self.components.get(&component_id) else {
             return Err(Error::ComponentNotFound(component_id));
         };
 
         let time_series_empty = component.time_series.is_empty();
-        component.push_buf(timestamp, value_buf)?;
+        component.push_value(timestamp, value)?;
 
         if time_series_empty {
             debug!("sunk new time series for component {}", component_id);
@@ -685,18 +1185,47 @@ impl Server {
         Ok(Server { listener, db })
     }
 
+    /// Serves connections on a worker thread per core, use [`Server::run_on`] to configure the
+    /// runtime
     pub async fn run(self) -> Result<(), Error> {
+        self.run_on(Runtime::builder().build()?).await
+    }
+
+    /// Serves connections on `runtime`, handing each one to its least loaded worker
+    pub async fn run_on(self, runtime: Runtime) -> Result<(), Error> {
+        self.run_until(runtime, std::future::pending()).await
+    }
+
+    /// Like [`Server::run_on`], but stops accepting connections once `shutdown` completes and
+    /// flushes the db to disk before returning
+    pub async fn run_until(
+        self,
+        runtime: Runtime,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), Error> {
         let Self { listener, db } = self;
         let addr = listener.local_addr()?;
         let udp_db = db.clone();
         stellarator::struc_con::stellar(move || Self::handle_udp(addr, udp_db));
-        loop {
-            let stream = listener.accept().await?;
-            let peer_addr = stream.peer_addr()?;
-            trace!(?peer_addr, "accepted connection");
-            let conn_db = db.clone();
-            stellarator::struc_con::stellar(move || handle_conn(stream, conn_db));
+        let conn_db = db.clone();
+        let accept = runtime.accept(&listener, move |stream| {
+            if let Ok(peer_addr) = stream.peer_addr() {
+                trace!(?peer_addr, "accepted connection");
+            }
+            handle_conn(stream, conn_db.clone())
+        });
+        let res = select(accept, shutdown).await;
+        if let Either::Right(()) = res {
+            info!("shutting down");
+            drop(listener);
         }
+        // stops the connections first, so none of them write to the db after it's flushed
+        runtime.shutdown().await;
+        match res {
+            Either::Left(res) => res?,
+            Either::Right(()) => db.shutdown().await?,
+        }
+        Ok(())
     }
 
     pub async fn handle_udp(addr: SocketAddr, db: Arc<DB>) -> Result<(), Error> {
@@ -715,7 +1244,7 @@ pub async fn serve_tmp_db(addr: SocketAddr) -> Result<(), Error> {
     server.run().await
 }
 
-pub async fn handle_conn(stream: TcpStream, db: Arc<DB>) {
+pub async fn handle_conn<A: AsyncRead + AsyncWrite + 'static>(stream: A, db: Arc<DB>) {
     let (rx, tx) = stream.split();
     let rx = PacketStream::new(rx);
     let tx = Arc::new(Mutex::new(PacketSink::new(tx)));
@@ -735,6 +1264,8 @@ async fn handle_conn_inner<A: AsyncRead + AsyncWrite + 'static>(
 ) -> Result<(), Error> {
     let mut buf = vec![0u8; 1024 * 1024 * 1024];
     let mut resp_pkt = LenPacket::new(PacketTy::Msg, [0, 0], 1024 * 1024 * 1024);
+    // peers that never send a hello predate the handshake
+    let mut capabilities = Capabilities::DEFAULT;
     loop {
         let pkt = rx.next(buf).await?;
         let req_id = pkt.req_id();
@@ -743,7 +1274,7 @@ async fn handle_conn_inner<A: AsyncRead + AsyncWrite + 'static>(
             tx,
             pkt: Some(resp_pkt),
         };
-        let result = handle_packet(&pkt, &db, &mut pkt_tx).await;
+        let result = handle_packet(&pkt, &db, &mut pkt_tx, &mut capabilities).await;
         buf = pkt.into_buf().into_inner();
         match result {
             Ok(_) => {}
@@ -842,12 +1373,40 @@ async fn handle_packet<A: AsyncWrite + 'static>(
     pkt: &Packet<Slice<Vec<u8>>>,
     db: &Arc<DB>,
     tx: &mut PacketTx<A>,
+    capabilities: &mut Capabilities,
 ) -> Result<(), Error> {
     trace!(?pkt, "handling pkt");
     match &pkt {
+        Packet::Msg(m) if !capabilities.contains(Capabilities::required_by(m.id)) => {
+            return Err(Error::CapabilityNotNegotiated(m.id));
+        }
+        Packet::Msg(m) if m.id == Hello::ID => {
+            let hello = m.parse::<Hello>()?;
+            let reply = Hello::new(Capabilities::SUPPORTED)
+                .negotiate(&hello)
+                .ok_or(Error::IncompatibleVersion(hello.version, PROTOCOL_VERSION))?;
+            debug!(version = %hello.version, capabilities = ?reply.capabilities, "hello received");
+            *capabilities = reply.capabilities;
+            tx.send_msg(&reply).await?;
+            // the reply is sent uncompressed so the peer can learn the negotiated capabilities
+            tx.tx
+                .lock()
+                .await
+                .set_compression(Compression::negotiate(reply.capabilities));
+        }
+        Packet::Msg(m) if m.id == TimeSyncRequest::ID => {
+            let server_recv = TimestampNs::now();
+            let req = m.parse::<TimeSyncRequest>()?;
+            let reply = TimeSyncResponse {
+                client_send: req.client_send,
+                server_recv,
+                server_send: TimestampNs::now(),
+            };
+            tx.send_msg(&reply).await?;
+        }
         Packet::Msg(m) if m.id == VTableMsg::ID => {
             let vtable = m.parse::<VTableMsg>()?;
-            db.insert_vtable(vtable)?;
+            db.insert_vtable(vtable).await?;
         }
         Packet::Msg(m) if m.id == UdpUnicast::ID => {
             let udp_broadcast = m.parse::<UdpUnicast>()?;
@@ -894,11 +1453,8 @@ async fn handle_packet<A: AsyncWrite + 'static>(
             let get_schema = m.parse::<GetSchema>()?;
             let schema = db.with_state(|state| {
                 state
-                    .components
-                    .iter()
-                    .filter(|(component_id, _)| *component_id == &get_schema.component_id)
-                    .map(|(_, component)| component.schema.to_schema())
-                    .next()
+                    .get_component(get_schema.component_id)
+                    .map(|component| component.schema.to_schema())
                     .ok_or(Error::ComponentNotFound(get_schema.component_id))
             })?;
             tx.send_msg(&SchemaMsg(schema)).await?;
@@ -912,11 +1468,14 @@ async fn handle_packet<A: AsyncWrite + 'static>(
                 id,
             } = get_time_series;
             let component = db.with_state(|state| {
-                let Some(component) = state.components.get(&component_id) else {
+                let Some(component) = state.get_component(component_id) else {
                     return Err(Error::ComponentNotFound(component_id));
                 };
                 Ok(component.clone())
             })?;
+            if component.schema.prim_type.is_var_len() {
+                return Err(Error::UnsupportedPrimType(component.schema.prim_type));
+            }
 
             let req_id = tx.req_id;
             tx.send_with_builder(move |pkt| {
@@ -979,7 +1538,14 @@ async fn handle_packet<A: AsyncWrite + 'static>(
         }
         Packet::Msg(m) if m.id == SetComponentMetadata::ID => {
             let SetComponentMetadata(metadata) = m.parse::<SetComponentMetadata>()?;
-            db.with_state_mut(|state| state.set_component_metadata(metadata, &db.path))?;
+            let changed = db.with_state_mut(|state| state.update_component_metadata(metadata));
+            if let Some(metadata) = changed {
+                let dir = db.path.join(metadata.component_id.to_string());
+                stellarator::fs::create_dir_all(&dir).await?;
+                metadata
+                    .persist(dir.join("metadata"), &db.write_queues)
+                    .await?;
+            }
         }
         Packet::Msg(m) if m.id == GetComponentMetadata::ID => {
             let GetComponentMetadata { component_id } = m.parse::<GetComponentMetadata>()?;
@@ -1022,10 +1588,15 @@ async fn handle_packet<A: AsyncWrite + 'static>(
         }
         Packet::Msg(m) if m.id == DumpSchema::ID => {
             let msg = db.with_state(|state| {
+                let archived = state
+                    .archived
+                    .iter()
+                    .map(|(id, c)| (*id, c.schema.to_schema()));
                 let schemas = state
                     .components
                     .values()
                     .map(|c| (c.component_id, c.schema.to_schema()))
+                    .chain(archived)
                     .collect();
                 DumpSchemaResp { schemas }
             });
@@ -1067,7 +1638,7 @@ async fn handle_packet<A: AsyncWrite + 'static>(
             db.with_state_mut(|s| {
                 s.db_config.metadata.extend(metadata);
             });
-            db.save_db_state()?;
+            db.persist_db_state().await?;
             tx.send_msg(&db.db_config()).await?;
         }
         Packet::Msg(m) if m.id == GetEarliestTimestamp::ID => {
@@ -1132,7 +1703,10 @@ async fn handle_packet<A: AsyncWrite + 'static>(
         }
         Packet::Msg(m) if m.id == SetMsgMetadata::ID => {
             let SetMsgMetadata { id, metadata } = m.parse::<SetMsgMetadata>()?;
-            db.with_state_mut(|s| s.set_msg_metadata(id, metadata, &db.path))?;
+            db.check_msg_id(id)?;
+            let persist = db
+                .with_state_mut(|s| s.set_msg_metadata(id, metadata, &db.path, &db.write_queues))?;
+            persist.await?;
         }
         Packet::Msg(m) if m.id == MsgStream::ID => {
             let MsgStream { msg_id } = m.parse::<MsgStream>()?;
@@ -1251,6 +1825,7 @@ async fn handle_packet<A: AsyncWrite + 'static>(
             })?;
         }
         Packet::Msg(m) => {
+            db.check_msg_id(m.id)?;
             let timestamp = m.timestamp.unwrap_or(Timestamp::now());
             db.push_msg(timestamp, m.id, &m.buf)?
         }
@@ -1294,7 +1869,7 @@ pub async fn handle_fixed_rate_msg_stream<A: AsyncWrite>(
         if !stream_state.wait_for_playing().await {
             return Ok(());
         }
-        let start = Instant::now();
+        let start = monotonic_now();
         let current_timestamp = stream_state.current_timestamp();
         let Some(msg_ref) = msg_log.get_nearest(current_timestamp) else {
             continue;
@@ -1306,7 +1881,7 @@ pub async fn handle_fixed_rate_msg_stream<A: AsyncWrite>(
 
         if Some(msg_timestamp) == last_sent_timestamp {
             stream_state
-                .wait_for_tick(start.elapsed(), current_timestamp)
+                .wait_for_tick(monotonic_now() - start, current_timestamp)
                 .await;
             continue;
         }
@@ -1321,7 +1896,7 @@ pub async fn handle_fixed_rate_msg_stream<A: AsyncWrite>(
         last_sent_timestamp = Some(msg_timestamp);
 
         stream_state
-            .wait_for_tick(start.elapsed(), current_timestamp)
+            .wait_for_tick(monotonic_now() - start, current_timestamp)
             .await;
     }
 }
@@ -1526,6 +2101,7 @@ async fn handle_real_time_component<A: AsyncWrite>(
     }
 
     let mut table = LenPacket::table(vtable_id, 2048 - 16);
+    let mut tail = VarLenTail::default();
     loop {
         let _ = waiter.wait().await;
         let Some(latest) = component.time_series.latest() else {
@@ -1533,7 +2109,17 @@ async fn handle_real_time_component<A: AsyncWrite>(
         };
         table.push_aligned(latest.timestamp());
         table.pad_for_type(prim_type);
-        table.extend_from_slice(latest.data());
+        if prim_type.is_var_len() {
+            let Some(value) = component.resolve(latest.data()) else {
+                warn!(component.id = ?component.component_id, "invalid variable length value");
+                table.clear();
+                continue;
+            };
+            tail.push(&mut table, value);
+            tail.finish(&mut table);
+        } else {
+            table.extend_from_slice(latest.data());
+        }
         {
             let stream = stream.lock().await;
             if let Err(err) = rent!(stream.send(table.with_request_id(req_id)).await, table) {
@@ -1562,7 +2148,7 @@ async fn handle_fixed_stream<A: AsyncWrite>(
         if !state.wait_for_playing().await {
             return Ok(());
         }
-        let start = Instant::now();
+        let start = monotonic_now();
         let current_timestamp = state.current_timestamp();
         let vtable_gen = db.vtable_gen.latest();
         if vtable_gen != current_vtable_gen {
@@ -1604,7 +2190,7 @@ async fn handle_fixed_stream<A: AsyncWrite>(
             rent!(stream.send(table.with_request_id(req_id)).await, table)?;
         }
         state
-            .wait_for_tick(start.elapsed(), current_timestamp)
+            .wait_for_tick(monotonic_now() - start, current_timestamp)
             .await;
     }
 }
@@ -1638,6 +2224,7 @@ impl DBVisitor {
         timestamp: Timestamp,
     ) -> Result<usize, Error> {
         let mut fields = 0;
+        let mut tail = VarLenTail::default();
         self.visit(components, |entity| {
             let Some(start_timestamp) = entity.time_series.start_timestamp() else {
                 return Ok(());
@@ -1648,10 +2235,18 @@ impl DBVisitor {
             };
             table.push_aligned(nearest.timestamp());
             table.pad_for_type(entity.schema.prim_type);
-            table.extend_from_slice(nearest.data());
+            if entity.schema.prim_type.is_var_len() {
+                let value = entity
+                    .resolve(nearest.data())
+                    .ok_or(metor_proto::error::Error::BufferUnderflow)?;
+                tail.push(table, value);
+            } else {
+                table.extend_from_slice(nearest.data());
+            }
             fields += 1;
             Ok(())
         })?;
+        tail.finish(table);
         Ok(fields)
     }
 
@@ -5,7 +5,8 @@ use metor_proto::vtable;
 use metor_db::Server;
 use miette::IntoDiagnostic;
 use postcard_c_codegen::SchemaExt;
-use tracing::info;
+use stellarator::signal::Signals;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser, Clone)]
@@ -23,6 +24,10 @@ enum Commands {
     Lua(metor_proto_cli::Args),
     #[command(about = "Generate C++ header files")]
     GenCpp,
+    #[command(about = "Generate C99 header files")]
+    GenC,
+    #[command(about = "Generate the Python client's message module")]
+    GenPy,
 }
 
 #[derive(clap::Args, Clone, Debug)]
@@ -35,6 +40,26 @@ struct RunArgs {
     pub config: Option<PathBuf>,
     #[clap(long, hide = true)]
     reset: bool,
+    #[clap(long, help = "Number of worker threads, defaults to one per core")]
+    workers: Option<usize>,
+    #[clap(long, help = "Don't pin each worker thread to its own core")]
+    no_pin_threads: bool,
+    #[cfg(feature = "websocket")]
+    #[clap(long, help = "Address to serve the WebSocket bridge on")]
+    ws_addr: Option<SocketAddr>,
+    #[cfg(feature = "websocket")]
+    #[clap(
+        long,
+        help = "Origin browsers may connect to the WebSocket bridge from, can be repeated"
+    )]
+    ws_allow_origin: Vec<String>,
+    #[cfg(feature = "websocket")]
+    #[clap(
+        long,
+        default_value_t = metor_db::websocket::DEFAULT_MAX_CONNECTIONS,
+        help = "Number of connections the WebSocket bridge serves at once"
+    )]
+    ws_max_connections: usize,
 }
 
 #[stellarator::main]
@@ -59,7 +84,18 @@ async fn main() -> miette::Result<()> {
             path,
             config,
             reset,
+            workers,
+            no_pin_threads,
+            #[cfg(feature = "websocket")]
+            ws_addr,
+            #[cfg(feature = "websocket")]
+            ws_allow_origin,
+            #[cfg(feature = "websocket")]
+            ws_max_connections,
         }) => {
+            // signals are only routed to the signal stream on threads spawned after it's created,
+            // so this has to happen before the runtime starts any workers
+            let signals = Signals::shutdown().into_diagnostic()?;
             let path = path.unwrap_or_else(|| {
                 let dirs =
                     directories::ProjectDirs::from("systems", "metor", "db").expect("no dirs");
@@ -68,12 +104,32 @@ async fn main() -> miette::Result<()> {
             if reset && path.exists() {
                 info!(?path, "resetting db");
                 std::fs::remove_dir_all(&path).unwrap_or_else(|_| {
-                    tracing::warn!("failed to remove existing data directory");
+                    warn!("failed to remove existing data directory");
                 });
             }
             info!(?path, "starting db");
             let server = Server::new(path, addr).into_diagnostic()?;
-            let db = stellarator::spawn(server.run());
+            #[cfg(feature = "websocket")]
+            if let Some(ws_addr) = ws_addr {
+                let listener = std::net::TcpListener::bind(ws_addr).into_diagnostic()?;
+                info!(?ws_addr, "serving websocket bridge");
+                metor_db::websocket::serve(listener, addr, ws_allow_origin, ws_max_connections);
+            }
+            let mut runtime = stellarator::rt::Runtime::builder().pin_threads(!no_pin_threads);
+            if let Some(workers) = workers {
+                runtime = runtime.workers(workers);
+            }
+            let runtime = runtime.build().into_diagnostic()?;
+            let shutdown = async move {
+                match signals.recv().await {
+                    Ok(signal) => info!(?signal, "received signal"),
+                    Err(err) => {
+                        warn!(?err, "failed to wait for signals");
+                        std::future::pending::<()>().await
+                    }
+                }
+            };
+            let db = stellarator::spawn(server.run_until(runtime, shutdown));
             if let Some(lua_config) = config {
                 let args = metor_proto_cli::Args {
                     path: Some(lua_config),
@@ -115,5 +171,38 @@ async fn main() -> miette::Result<()> {
                 .into_diagnostic()?;
             Ok(())
         }
+        Commands::GenC => {
+            let header = postcard_c_codegen::h_header(
+                "ELODIN_DB",
+                [
+                    include_str!("../../postcard-c/postcard.h").to_string(),
+                    metor_proto_wkt::InitialTimestamp::to_c()?,
+                    metor_proto_wkt::FixedRateBehavior::to_c()?,
+                    metor_proto_wkt::StreamBehavior::to_c()?,
+                    metor_proto_wkt::Stream::to_c()?,
+                    metor_proto_wkt::MsgStream::to_c()?,
+                    vtable::Field::to_c()?,
+                    vtable::Op::to_c()?,
+                    vtable::OpRef::to_c()?,
+                    metor_proto::types::PrimType::to_c()?,
+                    vtable::VTable::<Vec<vtable::Op>, Vec<u8>, Vec<vtable::Field>>::to_c()?,
+                    metor_proto_wkt::VTableMsg::to_c()?,
+                    metor_proto_wkt::VTableStream::to_c()?,
+                    metor_proto_wkt::ComponentMetadata::to_c()?,
+                    metor_proto_wkt::SetComponentMetadata::to_c()?,
+                ],
+            )?;
+            std::io::stdout()
+                .write_all(header.as_bytes())
+                .into_diagnostic()?;
+            Ok(())
+        }
+        Commands::GenPy => {
+            let module = metor_db::python::msgs_module()?;
+            std::io::stdout()
+                .write_all(module.as_bytes())
+                .into_diagnostic()?;
+            Ok(())
+        }
     }
 }
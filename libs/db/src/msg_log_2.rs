@@ -8,15 +8,15 @@ use std::{
 
 use metor_proto::{buf::UmbraBuf, types::Timestamp};
 use metor_proto_wkt::MsgMetadata;
-use stellarator::sync::WaitQueue;
+use stellarator::{sync::WaitQueue, util::CancelToken};
 use tracing::warn;
 use zerocopy::{FromBytes, IntoBytes};
 
 use crate::{
-    Error, MetadataExt,
+    Error, MetadataExt, WriteQueues,
     append_log::AppendLog,
     arc_ring::{AtomicNode, AtomicStack, AtomicStackIter},
-    disruptor::{ArcAtomic, Disruptor, Reader},
+    disruptor::{ArcAtomic, Disruptor, DisruptorError, Reader},
 };
 
 #[derive(Clone)]
@@ -26,6 +26,8 @@ pub struct MsgLog {
     data_waker: Arc<WaitQueue>,
     metadata: Option<MsgMetadata>,
     wal: Disruptor,
+    /// Cancelled once the persist task has drained the wal
+    persisted: CancelToken,
 }
 
 #[derive(Clone)]
@@ -232,6 +234,7 @@ impl MsgLog {
             data_waker: Arc::new(WaitQueue::new()),
             metadata: None,
             wal: Disruptor::new(1024 * 1024), // 1MB WAL buffer
+            persisted: CancelToken::new(),
         };
         stellarator::spawn(this.clone().persist());
         Ok(this)
@@ -269,6 +272,7 @@ impl MsgLog {
             data_waker: Arc::new(WaitQueue::new()),
             metadata,
             wal: Disruptor::new(1024 * 1024), // 1MB WAL buffer
+            persisted: CancelToken::new(),
         };
         stellarator::spawn(this.clone().persist());
         Ok(this)
@@ -277,9 +281,11 @@ impl MsgLog {
     pub fn push(&self, timestamp: Timestamp, msg: &[u8]) -> Result<(), Error> {
         let grant_size = size_of::<Timestamp>() + size_of::<u32>() + msg.len();
 
-        let Ok(mut grant) = self.wal.try_grant(grant_size) else {
+        let mut grant = match self.wal.try_grant(grant_size) {
+            Ok(grant) => grant,
+            Err(DisruptorError::Closed) => return Err(Error::ShuttingDown),
             // WAL is full, could handle this differently (wait, error, etc.)
-            return Err(Error::MapOverflow);
+            Err(_) => return Err(Error::MapOverflow),
         };
 
         let mut offset = 0;
@@ -405,12 +411,19 @@ impl MsgLog {
         self.data_waker.clone()
     }
 
-    pub fn set_metadata(&mut self, metadata: MsgMetadata) -> Result<(), Error> {
-        let metadata = self.metadata.insert(metadata);
-        std::fs::create_dir_all(&self.path)?;
-        let metadata_path = self.path.join("metadata");
-        metadata.write(&metadata_path)?;
-        Ok(())
+    /// Sets the log's metadata, returning a future that persists it
+    pub fn set_metadata(
+        &mut self,
+        metadata: MsgMetadata,
+        write_queues: &Arc<WriteQueues>,
+    ) -> impl Future<Output = Result<(), Error>> + 'static {
+        let path = self.path.clone();
+        let persist = metadata.persist(path.join("metadata"), write_queues);
+        self.metadata = Some(metadata);
+        async move {
+            stellarator::fs::create_dir_all(&path).await?;
+            persist.await
+        }
     }
 
     pub fn metadata(&self) -> Option<&MsgMetadata> {
@@ -434,9 +447,10 @@ impl MsgLog {
 
     pub fn persist(self) -> impl Future<Output = ()> {
         let mut reader = self.wal.reader();
+        let persisted = self.persisted.clone().drop_guard();
         async move {
-            loop {
-                let buf = reader.next().await;
+            let _persisted = persisted;
+            while let Some(buf) = reader.recv().await {
                 let mut buf = &buf[..];
 
                 'parse: while buf.len() >= size_of::<Timestamp>() + size_of::<u32>() {
@@ -473,6 +487,18 @@ impl MsgLog {
         }
     }
 
+    /// Stops accepting messages, waits for the wal to be persisted and flushes the log to disk
+    pub async fn close(&self) -> Result<(), Error> {
+        self.wal.close();
+        self.persisted.wait().await;
+        for node in self.list.iter() {
+            node.timestamps.flush()?;
+            node.bufs.offsets.flush()?;
+            node.bufs.data_log.flush()?;
+        }
+        Ok(())
+    }
+
     fn persist_msg(&self, timestamp: Timestamp, msg: &[u8]) -> Result<(), Error> {
         loop {
             if self.try_push(timestamp, msg)? {
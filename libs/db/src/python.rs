@@ -0,0 +1,129 @@
+//! The Python module backing the client in `python/metor_db`
+
+use metor_proto::{
+    types::{Msg, PacketId},
+    vtable,
+};
+use metor_proto_wkt::*;
+use postcard_py_codegen::{SchemaExt, py_msg_id};
+
+/// The well-known messages with a class in `msgs.py`
+const PY_MSGS: &[(&str, PacketId)] = &[
+    ("VTableMsg", VTableMsg::ID),
+    ("Stream", Stream::ID),
+    ("VTableStream", VTableStream::ID),
+    ("SetComponentMetadata", SetComponentMetadata::ID),
+    ("MsgStream", MsgStream::ID),
+    ("FixedRateMsgStream", FixedRateMsgStream::ID),
+    ("UdpUnicast", UdpUnicast::ID),
+    ("UdpVTableStream", UdpVTableStream::ID),
+    ("SaveArchive", SaveArchive::ID),
+    ("ArchiveSaved", ArchiveSaved::ID),
+];
+
+/// The well-known messages left out of `msgs.py`, they don't derive a postcard schema so there's
+/// nothing to generate a class from
+const SKIPPED_MSGS: &[&str] = &[
+    "SetStreamState",
+    "GetTimeSeries",
+    "SchemaMsg",
+    "GetSchema",
+    "GetComponentMetadata",
+    "DumpMetadata",
+    "DumpMetadataResp",
+    "SubscribeLastUpdated",
+    "LastUpdated",
+    "SetDbConfig",
+    "DbConfig",
+    "GetDbSettings",
+    "NewConnection",
+    "GetEarliestTimestamp",
+    "EarliestTimestamp",
+    "DumpSchema",
+    "DumpSchemaResp",
+    "StreamTimestamp",
+    "SQLQuery",
+    "ArrowIPC",
+    "ErrorResponse",
+    "MsgMetadata",
+    "SetMsgMetadata",
+    "GetMsgMetadata",
+    "GetMsgs",
+    "MsgBatch",
+    "UpdateComponent",
+    "Hello",
+    "TimeSyncRequest",
+    "TimeSyncResponse",
+];
+
+/// Generates `metor_db/msgs.py`, with a class for every well-known type that has a postcard
+/// schema and the ids of the messages among them
+///
+/// Messages without a schema are listed in [`SKIPPED_MSGS`], the Python client writes the few
+/// it needs by hand.
+pub fn msgs_module() -> miette::Result<String> {
+    let ids = PY_MSGS
+        .iter()
+        .map(|(name, id)| py_msg_id(name, *id))
+        .collect::<Vec<_>>()
+        .join("\n");
+    postcard_py_codegen::py_module(
+        "metor_db.msgs",
+        [
+            include_str!("../../postcard-py/postcard.py").to_string(),
+            metor_proto::types::PrimType::to_py()?,
+            vtable::Op::to_py()?,
+            vtable::Field::to_py()?,
+            vtable::VTable::<Vec<vtable::Op>, Vec<u8>, Vec<vtable::Field>>::to_py()?,
+            InitialTimestamp::to_py()?,
+            FixedRateBehavior::to_py()?,
+            StreamBehavior::to_py()?,
+            FixedRateOp::to_py()?,
+            MeanOp::to_py()?,
+            ArchiveFormat::to_py()?,
+            ComponentMetadata::to_py()?,
+            EntityMetadata::to_py()?,
+            VTableMsg::to_py()?,
+            Stream::to_py()?,
+            VTableStream::to_py()?,
+            SetComponentMetadata::to_py()?,
+            MsgStream::to_py()?,
+            FixedRateMsgStream::to_py()?,
+            UdpUnicast::to_py()?,
+            UdpVTableStream::to_py()?,
+            SaveArchive::to_py()?,
+            ArchiveSaved::to_py()?,
+            ids,
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_msg_is_generated_or_skipped() {
+        for (name, id) in KNOWN_MSG_IDS {
+            let generated = PY_MSGS.iter().any(|(n, i)| n == name && i == id);
+            let skipped = SKIPPED_MSGS.contains(name);
+            assert!(
+                generated != skipped,
+                "{name} must be either generated or skipped"
+            );
+        }
+        assert_eq!(PY_MSGS.len() + SKIPPED_MSGS.len(), KNOWN_MSG_IDS.len());
+    }
+
+    #[test]
+    fn test_msgs_module() {
+        let module = msgs_module().unwrap();
+        for (name, id) in PY_MSGS {
+            assert!(
+                module.contains(&format!("class {name}(")),
+                "{name} is missing"
+            );
+            assert!(module.contains(&py_msg_id(name, *id)));
+        }
+    }
+}
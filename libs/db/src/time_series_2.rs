@@ -177,7 +177,13 @@ impl TimeSeries {
             for entry in entries {
                 let entry = entry?;
                 let node_path = entry.path();
-                if node_path.is_dir() {
+                // nodes are named after their start timestamp, other directories (like a
+                // component's archived versions) aren't part of the time series
+                let is_node = node_path
+                    .file_name()
+                    .and_then(|p| p.to_str())
+                    .is_some_and(|p| p.parse::<i64>().is_ok());
+                if node_path.is_dir() && is_node {
                     match TimeSeriesNode::open(&node_path) {
                         Ok(node) => {
                             list.push(node);
@@ -309,6 +315,15 @@ impl TimeSeries {
         self.list.head().is_none()
     }
 
+    /// Writes every node's dirty pages back to disk
+    pub fn flush(&self) -> Result<(), Error> {
+        for node in self.list.iter() {
+            node.index.flush()?;
+            node.data.flush()?;
+        }
+        Ok(())
+    }
+
     pub fn writer(&self) -> Option<TimerSeriesWriter> {
         if self.has_writer.swap(true, Ordering::Acquire) {
             None
@@ -13,7 +13,7 @@ use metor_proto::{
         ComponentView, IntoLenPacket, LenPacket, Msg, PACKET_HEADER_LEN, PacketId, PrimType,
         RequestId, Timestamp,
     },
-    vtable::{Op, RealizedComponent, RealizedOp, VTable},
+    vtable::{RealizedComponent, RealizedOp, VTable},
 };
 use metor_proto_stellar::PacketSink;
 use metor_proto_wkt::{ComponentValue, FixedRateBehavior, FixedRateOp, MeanOp, VTableMsg};
@@ -34,24 +34,15 @@ pub async fn handle_vtable_stream<A: AsyncWrite + 'static>(
     req_id: RequestId,
 ) -> Result<(), Error> {
     trace!("spawning vtable stream");
-    let table_len = vtable
-        .fields
-        .iter()
-        .map(|f| f.offset.to_index() + f.len as usize)
-        .chain(vtable.ops.iter().map(|op| match op {
-            Op::Table { offset, len } => offset.to_index() + *len as usize,
-            _ => 0,
-        }))
-        .max();
-    let table_len = table_len.unwrap_or(0);
+    let table_len = vtable.table_len()?;
     let table = FieldTable::new(vtable.fields.len(), table_len, id);
     for (i, field) in vtable.fields.iter().enumerate() {
         let mut realized_op = vtable.realize(field.arg, None)?;
         let mut plan = vec![];
         let mut timestamp: Option<Range<usize>> = None;
         let mut schema: Option<(&[u64], PrimType)> = None;
-        // loops until a pair is found, or the maximum number of iterations is reached
-        'find: for _ in 0..u16::MAX {
+        // vtables are validated when they're inserted, so each op is visited at most once
+        'find: for _ in 0..vtable.ops.len() {
             match realized_op {
                 RealizedOp::Component(RealizedComponent { component_id }) => {
                     let component = db
@@ -139,6 +130,10 @@ pub async fn handle_vtable_stream<A: AsyncWrite + 'static>(
             return Err(Error::Impeller(metor_proto::error::Error::InvalidOp));
         }
         let prim_type = component.schema.prim_type;
+        if prim_type.is_var_len() {
+            // stream tables have a fixed size, so they can't hold variable length values
+            return Err(Error::UnsupportedPrimType(prim_type));
+        }
         stellarator::spawn(handle_plan(plan, shard, timestamp, prim_type));
     }
     // Send vtable before streaming
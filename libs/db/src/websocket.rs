@@ -0,0 +1,259 @@
+//! A WebSocket bridge for clients that can't open TCP sockets, like `metor-proto-wasm` running
+//! in a browser
+//!
+//! Each WebSocket connection gets its own TCP connection to the database. Every binary
+//! WebSocket message carries exactly one packet without the `u32` length prefix, the bridge
+//! adds the prefix on the way in and strips it on the way out. Packets are forwarded as is,
+//! so clients must not negotiate compression in their `Hello`.
+//!
+//! Browsers let any page open a WebSocket to any host, so handshakes with an `Origin` header
+//! are only accepted from the allowed origins. Clients outside a browser don't send one.
+//!
+//! Every connection is served by its own pair of threads, so the number of open connections
+//! is capped and connections past the cap are closed right after they're accepted.
+
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use tracing::{debug, warn};
+use tungstenite::{
+    Message, WebSocket,
+    handshake::server::{Callback, ErrorResponse, Request, Response},
+    http::StatusCode,
+    protocol::Role,
+};
+
+/// The number of connections the bridge serves at once, unless configured otherwise
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// How long a client has to finish the WebSocket handshake, so connections that never do don't
+/// hold on to a slot
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the bridge on `listener`, forwarding connections to the database at `db_addr`
+///
+/// `allowed_origins` are the exact `Origin`s, like `https://dash.example.com`, browsers may
+/// connect from. At most `max_connections` connections are served at once.
+pub fn serve(
+    listener: TcpListener,
+    db_addr: SocketAddr,
+    allowed_origins: Vec<String>,
+    max_connections: usize,
+) -> JoinHandle<()> {
+    let db_addr = connectable(db_addr);
+    let allowed_origins: Arc<[String]> = allowed_origins.into();
+    let open = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!(?err, "error accepting websocket connection");
+                    continue;
+                }
+            };
+            let Some(slot) = ConnectionSlot::take(&open, max_connections) else {
+                warn!(
+                    max_connections,
+                    "rejecting websocket connection, too many open"
+                );
+                let _ = stream.shutdown(Shutdown::Both);
+                continue;
+            };
+            let allowed_origins = allowed_origins.clone();
+            thread::spawn(move || {
+                if let Err(err) = bridge(stream, db_addr, &allowed_origins) {
+                    debug!(?err, "websocket connection closed");
+                }
+                drop(slot);
+            });
+        }
+    })
+}
+
+/// One of the bridge's open connections, freed on drop
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn take(open: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        open.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+            (n < max).then_some(n + 1)
+        })
+        .ok()?;
+        Some(ConnectionSlot(open.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Maps an unspecified bind address, like `[::]:2240`, to loopback
+fn connectable(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(v4) if v4.ip().is_unspecified() => (Ipv4Addr::LOCALHOST, v4.port()).into(),
+        SocketAddr::V6(v6) if v6.ip().is_unspecified() => (Ipv6Addr::LOCALHOST, v6.port()).into(),
+        addr => addr,
+    }
+}
+
+fn ws_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+/// Rejects handshakes from browsers on origins that aren't allowed
+struct OriginCheck<'a> {
+    allowed_origins: &'a [String],
+}
+
+impl Callback for OriginCheck<'_> {
+    fn on_request(self, req: &Request, resp: Response) -> Result<Response, ErrorResponse> {
+        let Some(origin) = req.headers().get("origin") else {
+            return Ok(resp);
+        };
+        if self
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+        {
+            return Ok(resp);
+        }
+        warn!(
+            ?origin,
+            "rejecting websocket connection from disallowed origin"
+        );
+        let mut resp = ErrorResponse::new(Some("origin not allowed".to_string()));
+        *resp.status_mut() = StatusCode::FORBIDDEN;
+        Err(resp)
+    }
+}
+
+/// A WebSocket's socket, shared between the side reading from the client and the side sending
+/// packets from the database
+///
+/// Each write holds the lock until the whole buffer is written, and tungstenite only writes
+/// whole frames, so frames from the two sides never interleave.
+struct WsStream {
+    reader: TcpStream,
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+impl WsStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(WsStream {
+            reader: self.reader.try_clone()?,
+            writer: self.writer.clone(),
+        })
+    }
+}
+
+impl Read for WsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for WsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut writer = self.writer.lock().expect("websocket writer poisoned");
+        writer.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer
+            .lock()
+            .expect("websocket writer poisoned")
+            .flush()
+    }
+}
+
+fn bridge(stream: TcpStream, db_addr: SocketAddr, allowed_origins: &[String]) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let stream = WsStream {
+        writer: Arc::new(Mutex::new(stream.try_clone()?)),
+        reader: stream,
+    };
+    let mut ws = tungstenite::accept_hdr(stream, OriginCheck { allowed_origins }).map_err(
+        |err| match err {
+            tungstenite::HandshakeError::Failure(err) => ws_error(err),
+            tungstenite::HandshakeError::Interrupted(_) => io::ErrorKind::WouldBlock.into(),
+        },
+    )?;
+    ws.get_ref().reader.set_read_timeout(None)?;
+
+    let mut db = TcpStream::connect(db_addr)?;
+    db.set_nodelay(true)?;
+    let sender = WebSocket::from_raw_socket(ws.get_ref().try_clone()?, Role::Server, None);
+    let reader = db.try_clone()?;
+    let sender = thread::spawn(move || send_packets(reader, sender));
+
+    let res = recv_packets(&mut ws, &mut db);
+    // wakes up the sending side, which is blocked reading from the database
+    let _ = db.shutdown(Shutdown::Both);
+    let _ = ws.get_ref().reader.shutdown(Shutdown::Both);
+    // the connection keeps its slot until both sides are done
+    let _ = sender.join();
+    res
+}
+
+/// Forwards the packets sent by the client to the database, until the client closes the
+/// connection
+fn recv_packets(ws: &mut WebSocket<WsStream>, db: &mut TcpStream) -> io::Result<()> {
+    loop {
+        match ws.read().map_err(ws_error)? {
+            Message::Binary(packet) => {
+                let len = u32::try_from(packet.len())
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+                db.write_all(&len.to_le_bytes())?;
+                db.write_all(&packet)?;
+            }
+            Message::Close(_) => {
+                // sends the reply to the client's close frame
+                let _ = ws.flush();
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Forwards the packets sent by the database to the client, closing the WebSocket when the
+/// database closes the connection
+fn send_packets(mut db: TcpStream, mut ws: WebSocket<WsStream>) -> io::Result<()> {
+    loop {
+        let packet = match read_packet(&mut db) {
+            Ok(packet) => packet,
+            Err(err) => {
+                let _ = ws.close(None);
+                let _ = ws.flush();
+                // wakes up the receiving side, which is blocked reading from the client
+                let _ = ws.get_ref().reader.shutdown(Shutdown::Read);
+                return Err(err);
+            }
+        };
+        ws.send(Message::binary(packet)).map_err(ws_error)?;
+    }
+}
+
+/// Reads a length prefixed packet from the database, returning it without its prefix
+fn read_packet(db: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; size_of::<u32>()];
+    db.read_exact(&mut len)?;
+    let mut packet = vec![0; u32::from_le_bytes(len) as usize];
+    db.read_exact(&mut packet)?;
+    Ok(packet)
+}
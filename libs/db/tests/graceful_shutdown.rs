@@ -0,0 +1,85 @@
+//! Runs the `metor-db` binary, since the signal handling in `main` is part of what's tested
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    process::Command,
+    time::Duration,
+};
+
+use metor_proto::{
+    types::{ComponentId, LenPacket, PrimType},
+    vtable::builder::{component, raw_field, schema, vtable},
+};
+use metor_proto_stellar::Client;
+use metor_proto_wkt::{GetComponentMetadata, VTableMsg};
+use zerocopy::IntoBytes;
+
+/// Connects to the db once it's listening
+async fn connect(addr: SocketAddr) -> Client {
+    for _ in 0..100 {
+        if let Ok(client) = Client::connect(addr).await {
+            return client;
+        }
+        stellarator::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("db never started listening on {addr}");
+}
+
+#[stellarator::test]
+async fn test_graceful_shutdown() {
+    let temp_dir =
+        std::env::temp_dir().join(format!("metor_db_shutdown_test_{}", fastrand::u64(..)));
+    let vtable_id = 1u16.to_le_bytes();
+    let component_id = ComponentId::new("shutdown_test");
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut db = Command::new(env!("CARGO_BIN_EXE_metor-db"))
+        .arg("run")
+        .arg(addr.to_string())
+        .arg(&temp_dir)
+        .args(["--workers", "2"])
+        .spawn()
+        .unwrap();
+
+    let mut client = connect(addr).await;
+    let vtable = vtable([raw_field(
+        0,
+        8,
+        schema(PrimType::F64, &[1], component(component_id)),
+    )]);
+    client
+        .send(&VTableMsg {
+            id: vtable_id,
+            vtable,
+        })
+        .await
+        .0
+        .unwrap();
+    let mut pkt = LenPacket::table(vtable_id, 8);
+    pkt.extend_aligned(&[42.0f64]);
+    client.send(pkt).await.0.unwrap();
+    // packets on a connection are handled in order, so once this is answered the value has
+    // been written to the wal
+    client
+        .request(&GetComponentMetadata { component_id })
+        .await
+        .unwrap();
+
+    let killed = Command::new("kill")
+        .args(["-TERM", &db.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let status = db.wait().unwrap();
+    assert!(status.success(), "db exited with {status}");
+
+    let db = metor_db::DB::open(temp_dir).unwrap();
+    db.with_state(|state| {
+        let component = state.get_component(component_id).unwrap();
+        let latest = component.time_series.latest().unwrap();
+        assert_eq!(latest.data(), 42.0f64.as_bytes());
+    });
+}
@@ -3,14 +3,28 @@ mod tests {
 
     use arrow::{array::AsArray, datatypes::Float64Type};
     use metor_proto::{
-        types::{ComponentId, IntoLenPacket, LenPacket, Msg, PrimType, Timestamp},
+        buf::UmbraBuf,
+        types::{
+            ClockDomain, ComponentId, IntoLenPacket, LenPacket, Msg, OwnedPacket, PrimType,
+            Timestamp, TimestampNs,
+        },
         vtable::builder::{component, raw_field, raw_table, schema, timestamp, vtable},
     };
-    use metor_proto_stellar::Client;
+    use metor_proto_stellar::{Client, PacketSink, PacketStream};
     use metor_db::{DB, Error, Server};
     use postcard_schema::{Schema, schema::owned::OwnedNamedType};
     use std::{net::SocketAddr, sync::Arc, time::Duration};
-    use stellarator::{net::TcpListener, sleep, spawn, struc_con::stellar, test};
+    use stellarator::{
+        io::SplitExt,
+        net::TcpListener,
+        rt::Runtime,
+        sim::net::{SimListener, SimStream},
+        sleep, spawn,
+        struc_con::{Joinable, stellar},
+        test,
+        time::monotonic_now,
+        util::CancelToken,
+    };
     use zerocopy::FromBytes;
     use zerocopy::IntoBytes;
 
@@ -41,6 +55,83 @@ mod tests {
         let (_client, _db) = setup_test_db().await.unwrap();
     }
 
+    #[test]
+    async fn test_hello_handshake() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let client = Client::connect_with_capabilities(addr, Capabilities::SQL)
+            .await
+            .unwrap();
+        assert_eq!(client.capabilities(), Capabilities::SQL);
+    }
+
+    #[test]
+    async fn test_hello_incompatible_version() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+        let hello = Hello {
+            version: ProtocolVersion {
+                major: PROTOCOL_VERSION.major + 1,
+                minor: 0,
+            },
+            capabilities: Capabilities::SUPPORTED,
+        };
+        let err = client.request(&hello).await.unwrap_err();
+        assert!(matches!(err, metor_proto_stellar::Error::Response(_)));
+    }
+
+    #[test]
+    async fn test_unknown_reserved_msg_id() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+        let pkt = LenPacket::msg([224, 255], 0).with_request_id(3);
+        client.send(pkt).await.0.unwrap();
+        let err = client.recv::<ErrorResponse>(3).await.unwrap_err();
+        let metor_proto_stellar::Error::Response(resp) = err else {
+            panic!("expected error response, got {err:?}");
+        };
+        assert_eq!(resp.description, "unknown msg id [224, 255]");
+    }
+
+    #[test]
+    async fn test_capability_not_negotiated() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect_with_capabilities(addr, Capabilities::TIME_SYNC)
+            .await
+            .unwrap();
+        let err = client
+            .request(&SQLQuery("select * from test".to_string()))
+            .await
+            .unwrap_err();
+        let metor_proto_stellar::Error::Response(resp) = err else {
+            panic!("expected error response, got {err:?}");
+        };
+        assert_eq!(
+            resp.description,
+            format!(
+                "msg id {:?} needs a capability that wasn't negotiated",
+                SQLQuery::ID
+            )
+        );
+    }
+
+    #[test]
+    async fn test_time_sync() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect_with_capabilities(addr, Capabilities::TIME_SYNC)
+            .await
+            .unwrap();
+        assert!(client.capabilities().contains(Capabilities::TIME_SYNC));
+        let req = TimeSyncRequest {
+            client_send: TimestampNs::now(),
+        };
+        let resp = client.request(&req).await.unwrap();
+        let sample = resp.sample(TimestampNs::now());
+        assert_eq!(resp.client_send, req.client_send);
+        assert!(resp.server_recv <= resp.server_send);
+        // the client and db share a clock, so the offset is within the round trip
+        assert!(sample.offset_ns.abs() <= sample.delay_ns.max(1_000_000));
+    }
+
     #[test]
     async fn test_send_data() {
         let (addr, db) = setup_test_db().await.unwrap();
@@ -72,6 +163,45 @@ mod tests {
         })
     }
 
+    #[test]
+    async fn test_send_var_len_data() {
+        let (addr, db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+        let vtable = vtable([
+            raw_field(0, 16, schema(PrimType::Utf8, &[], component("name"))),
+            raw_field(16, 16, schema(PrimType::Bytes, &[], component("raw"))),
+        ]);
+        client
+            .send(&VTableMsg {
+                id: 1u16.to_le_bytes(),
+                vtable,
+            })
+            .await
+            .0
+            .unwrap();
+        let name = "a name that doesn't fit inline";
+        let raw = [1u8, 2, 3];
+        let mut pkt = LenPacket::table(1u16.to_le_bytes(), 64);
+        pkt.extend_from_slice(UmbraBuf::new(name.as_bytes(), 32).as_bytes());
+        pkt.extend_from_slice(UmbraBuf::new(&raw, 0).as_bytes());
+        pkt.extend_from_slice(name.as_bytes());
+        client.send(pkt).await.0.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        db.with_state(|state| {
+            let c = state
+                .get_component(ComponentId::new("name"))
+                .expect("missing component");
+            let latest = c.time_series.latest().expect("missing latest value");
+            assert_eq!(c.resolve(latest.data()), Some(name.as_bytes()));
+
+            let c = state
+                .get_component(ComponentId::new("raw"))
+                .expect("missing component");
+            let latest = c.time_series.latest().expect("missing latest value");
+            assert_eq!(c.resolve(latest.data()), Some(&raw[..]));
+        })
+    }
+
     #[test]
     async fn test_vtable_stream() {
         let (addr, _db) = setup_test_db().await.unwrap();
@@ -117,6 +247,51 @@ mod tests {
         }
     }
 
+    #[test(sim)]
+    async fn test_fixed_rate_stream_sim() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("metor_db_sim_test_{}", fastrand::u64(..)));
+        let db = Arc::new(DB::create(temp_dir).unwrap());
+        let listener = SimListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr();
+        spawn(async move {
+            loop {
+                let stream = listener.accept().await.unwrap();
+                spawn(metor_db::handle_conn(stream, db.clone()));
+            }
+        });
+
+        let (rx, tx) = SimStream::connect(addr).await.unwrap().split();
+        let tx = PacketSink::new(tx);
+        let mut rx = PacketStream::new(rx);
+        let stream = Stream {
+            behavior: StreamBehavior::FixedRate(FixedRateBehavior {
+                initial_timestamp: InitialTimestamp::Manual(Timestamp(0)),
+                timestep: Duration::from_millis(1).as_nanos() as u64,
+                frequency: 100,
+            }),
+            id: 1,
+        };
+        tx.send(stream.with_request_id(1)).await.0.unwrap();
+
+        let start = monotonic_now();
+        let mut timestamps = vec![];
+        let mut buf = vec![0u8; 1024 * 16];
+        while timestamps.len() < 50 {
+            let pkt = rx.next(buf).await.unwrap();
+            if let OwnedPacket::Msg(m) = &pkt {
+                if m.id == StreamTimestamp::ID {
+                    timestamps.push(m.parse::<StreamTimestamp>().unwrap().timestamp);
+                }
+            }
+            buf = pkt.into_buf().into_inner();
+        }
+        // the first tick is sent right away, the rest are 10ms apart on the virtual clock
+        assert_eq!(monotonic_now() - start, Duration::from_millis(490));
+        let expected = (0..50).map(|i| Timestamp(i * 1000)).collect::<Vec<_>>();
+        assert_eq!(timestamps, expected);
+    }
+
     #[test]
     async fn test_dump_metadata() {
         let (addr, _db) = setup_test_db().await.unwrap();
@@ -145,6 +320,85 @@ mod tests {
         )
     }
 
+    #[test]
+    async fn test_websocket_bridge() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ws_addr = listener.local_addr().unwrap();
+        metor_db::websocket::serve(
+            listener,
+            addr,
+            vec![],
+            metor_db::websocket::DEFAULT_MAX_CONNECTIONS,
+        );
+
+        let (mut ws, _) = tungstenite::connect(format!("ws://{ws_addr}")).unwrap();
+        let bridge_packet =
+            |packet: LenPacket| tungstenite::Message::binary(packet.inner[4..].to_vec());
+        let component_id = ComponentId::new("ws_component");
+        let component_metadata = SetComponentMetadata::new(component_id, "WebSocket Component");
+        ws.send(bridge_packet((&component_metadata).into_len_packet()))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        ws.send(bridge_packet((&DumpMetadata).into_len_packet()))
+            .unwrap();
+
+        let reply = ws.read().unwrap().into_data();
+        let metor_proto::types::OwnedPacket::Msg(msg) =
+            metor_proto::types::OwnedPacket::parse(reply.to_vec()).unwrap()
+        else {
+            panic!("unexpected reply type");
+        };
+        let response = msg.parse::<DumpMetadataResp>().unwrap();
+        assert!(
+            response
+                .component_metadata
+                .iter()
+                .any(|c| c.component_id == component_id && c.name == "WebSocket Component")
+        );
+    }
+
+    #[test]
+    async fn test_websocket_bridge_origin() {
+        use tungstenite::client::IntoClientRequest;
+
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ws_addr = listener.local_addr().unwrap();
+        metor_db::websocket::serve(
+            listener,
+            addr,
+            vec!["https://dash.example.com".to_string()],
+            metor_db::websocket::DEFAULT_MAX_CONNECTIONS,
+        );
+
+        let request = |origin: &str| {
+            let mut req = format!("ws://{ws_addr}").into_client_request().unwrap();
+            req.headers_mut().insert("origin", origin.parse().unwrap());
+            req
+        };
+        let err = tungstenite::connect(request("https://evil.example.com")).unwrap_err();
+        assert!(
+            matches!(&err, tungstenite::Error::Http(resp) if resp.status() == 403),
+            "unexpected error {err:?}"
+        );
+        tungstenite::connect(request("https://dash.example.com")).unwrap();
+    }
+
+    #[test]
+    async fn test_websocket_bridge_max_connections() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ws_addr = listener.local_addr().unwrap();
+        metor_db::websocket::serve(listener, addr, vec![], 1);
+
+        let (ws, _) = tungstenite::connect(format!("ws://{ws_addr}")).unwrap();
+        tungstenite::connect(format!("ws://{ws_addr}")).unwrap_err();
+        drop(ws);
+        std::thread::sleep(Duration::from_millis(100));
+        tungstenite::connect(format!("ws://{ws_addr}")).unwrap();
+    }
+
     #[test]
     async fn test_sql_query() {
         let (addr, _db) = setup_test_db().await.unwrap();
@@ -208,6 +462,181 @@ mod tests {
         assert_eq!(arr.values(), &[0.0, 10.0, 20.0, 30.0, 40.0]);
     }
 
+    const PYTHON_CLIENT: &str = r#"
+import sys
+from metor_db import Client, component, component_id, raw_field, schema, vtable
+from metor_db.msgs import ComponentMetadata, PrimType, SetComponentMetadata, VTableMsg
+import struct
+import time
+
+with Client.connect(sys.argv[1], int(sys.argv[2])) as client:
+    metadata = ComponentMetadata(
+        component_id=component_id("cpu_temperature"), name="cpu_temperature", metadata={}
+    )
+    client.send_msg(SetComponentMetadata(value=metadata))
+    table = vtable([raw_field(0, 8, schema(PrimType.F64, [1], component("cpu_temperature")))])
+    client.send_msg(VTableMsg(id=(1, 0), vtable=table))
+    for i in range(5):
+        client.send_table(bytes([1, 0]), struct.pack("<d", i * 10.0))
+    time.sleep(0.1)
+    for batch in client.sql("SELECT * FROM cpu_temperature"):
+        print(batch.hex())
+"#;
+
+    #[test]
+    #[ignore = "needs python3"]
+    async fn test_python_client() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+
+        let dir = std::env::temp_dir().join(format!("metor_db_python_{}", fastrand::u64(..)));
+        let package = dir.join("metor_db");
+        std::fs::create_dir_all(&package).unwrap();
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../python/metor_db");
+        for entry in std::fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "py") {
+                std::fs::copy(&path, package.join(path.file_name().unwrap())).unwrap();
+            }
+        }
+        let msgs = metor_db::python::msgs_module().unwrap();
+        std::fs::write(package.join("msgs.py"), msgs).unwrap();
+        std::fs::write(dir.join("client.py"), PYTHON_CLIENT).unwrap();
+
+        let output = std::process::Command::new("python3")
+            .arg(dir.join("client.py"))
+            .arg(addr.ip().to_string())
+            .arg(addr.port().to_string())
+            .output()
+            .unwrap_or_else(|err| panic!("failed to run python3: {err}"));
+        assert!(
+            output.status.success(),
+            "python client failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let mut batches = vec![];
+        for line in String::from_utf8(output.stdout).unwrap().lines() {
+            let batch = (0..line.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>();
+            let mut decoder = arrow::ipc::reader::StreamDecoder::new();
+            let mut buffer = arrow::buffer::Buffer::from(batch);
+            if let Some(batch) = decoder.decode(&mut buffer).unwrap() {
+                batches.push(batch);
+            }
+        }
+        let batch = &batches[0];
+        let arr = batch
+            .column_by_name("cpu_temperature")
+            .unwrap()
+            .as_fixed_size_list();
+        let arr = arr.values();
+        let arr = arr.as_primitive::<Float64Type>();
+        assert_eq!(arr.values(), &[0.0, 10.0, 20.0, 30.0, 40.0]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    async fn test_sql_integrate_window() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+
+        let component_id = ComponentId::new("accel");
+        let vtable = vtable([raw_field(
+            0,
+            8,
+            schema(PrimType::F64, &[], component(component_id)),
+        )]);
+        client
+            .send(&SetComponentMetadata::new(component_id, "accel"))
+            .await
+            .0
+            .unwrap();
+        let vtable_id = 1u16.to_le_bytes();
+        client
+            .send(&VTableMsg {
+                id: vtable_id,
+                vtable,
+            })
+            .await
+            .0
+            .unwrap();
+
+        for _ in 0..5 {
+            let mut pkt = LenPacket::table(vtable_id, 8);
+            pkt.extend_aligned(&[2.0f64]);
+            client.send(pkt).await.0.unwrap();
+            sleep(Duration::from_millis(10)).await;
+        }
+        sleep(Duration::from_millis(100)).await;
+
+        let sql =
+            "SELECT integrate(accel.accel, accel.time) over (order by accel.time) as v FROM accel";
+        let mut stream = client.stream(&SQLQuery(sql.to_string())).await.unwrap();
+        let mut batches = vec![];
+        loop {
+            let msg = stream.next().await.unwrap();
+            let Some(batch) = msg.batch else {
+                break;
+            };
+            let mut decoder = arrow::ipc::reader::StreamDecoder::new();
+            let mut buffer = arrow::buffer::Buffer::from(batch.into_owned());
+            if let Some(batch) = decoder.decode(&mut buffer).unwrap() {
+                batches.push(batch);
+            }
+        }
+        let arr = batches[0]
+            .column_by_name("v")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        let values = arr.values();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values[0], 0.0);
+        assert!(values.windows(2).all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    async fn test_sql_time_clock_domain() {
+        let (addr, _db) = setup_test_db().await.unwrap();
+        let mut client = Client::connect(addr).await.unwrap();
+
+        let component_id = ComponentId::new("sim_pos");
+        let mut metadata = SetComponentMetadata::new(component_id, "sim_pos");
+        metadata.0.set_clock_domain(ClockDomain::Sim);
+        client.send(&metadata).await.0.unwrap();
+        let vtable_id = 1u16.to_le_bytes();
+        client
+            .send(&VTableMsg {
+                id: vtable_id,
+                vtable: vtable([raw_field(
+                    0,
+                    8,
+                    schema(PrimType::F64, &[], component(component_id)),
+                )]),
+            })
+            .await
+            .0
+            .unwrap();
+        let mut pkt = LenPacket::table(vtable_id, 8);
+        pkt.extend_aligned(&[1.0f64]);
+        client.send(pkt).await.0.unwrap();
+        sleep(Duration::from_millis(100)).await;
+
+        let sql = "SELECT sim_pos.time FROM sim_pos";
+        let mut stream = client.stream(&SQLQuery(sql.to_string())).await.unwrap();
+        let msg = stream.next().await.unwrap();
+        let batch = msg.batch.unwrap();
+        let mut decoder = arrow::ipc::reader::StreamDecoder::new();
+        let mut buffer = arrow::buffer::Buffer::from(batch.into_owned());
+        let batch = decoder.decode(&mut buffer).unwrap().unwrap();
+        let field = batch.schema().field(0).clone();
+        assert_eq!(
+            field.metadata().get("clock_domain").map(String::as_str),
+            Some("sim")
+        );
+    }
+
     #[test]
     async fn test_get_time_series() {
         let (addr, _db) = setup_test_db().await.unwrap();
@@ -336,7 +765,7 @@ mod tests {
 
         let vtable2 = vtable([raw_field(
             0,
-            4,
+            16,
             schema(PrimType::F32, &[2, 2], component(component_id2)),
         )]);
 
@@ -1041,8 +1470,8 @@ mod tests {
     }
 
     #[test]
-    async fn test_invalid_table_schema() {
-        let (addr, _) = setup_test_db().await.unwrap();
+    async fn test_schema_change() {
+        let (addr, db) = setup_test_db().await.unwrap();
         let mut client = Client::connect(addr).await.unwrap();
 
         let component_id = ComponentId::new("test_component");
@@ -1067,7 +1496,7 @@ mod tests {
             client.send(pkt).await.0.unwrap();
         }
 
-        sleep(Duration::from_millis(10)).await;
+        sleep(Duration::from_millis(50)).await;
 
         let vtable_different_type = vtable([raw_field(
             0,
@@ -1075,23 +1504,183 @@ mod tests {
             schema(PrimType::F32, &[1], component(component_id)),
         )]);
 
-        let vtable_id = 2u16.to_le_bytes();
         client
-            .send(
-                VTableMsg {
-                    id: vtable_id,
-                    vtable: vtable_different_type,
-                }
-                .with_request_id(42),
-            )
+            .send(&VTableMsg {
+                id: 2u16.to_le_bytes(),
+                vtable: vtable_different_type,
+            })
             .await
             .0
             .unwrap();
 
-        let Err(metor_proto_stellar::Error::Response(err)) = client.recv::<()>(42).await else {
-            panic!("invalid response");
-        };
-        assert_eq!(metor_db::Error::SchemaMismatch.to_string(), err.description);
+        let mut pkt = LenPacket::table(2u16.to_le_bytes(), 4);
+        pkt.extend_aligned(&[7.0f32]);
+        client.send(pkt).await.0.unwrap();
+
+        sleep(Duration::from_millis(50)).await;
+
+        let metadata = client
+            .request(&GetComponentMetadata { component_id })
+            .await
+            .unwrap();
+        assert_eq!(metadata.schema_version(), 1);
+
+        db.with_state(|state| {
+            let component = state.get_component(component_id).unwrap();
+            assert_eq!(component.schema.prim_type, PrimType::F32);
+            assert_eq!(component.version, 1);
+            let latest = component.time_series.latest().unwrap();
+            assert_eq!(latest.data(), 7.0f32.as_bytes());
+
+            let [old] = &component.history[..] else {
+                panic!("expected one archived version");
+            };
+            assert_eq!(old.version, 0);
+            assert_eq!(old.schema.prim_type, PrimType::F64);
+            let latest = old.time_series.latest().unwrap();
+            assert_eq!(latest.data(), 42.0f64.as_bytes());
+        });
+
+        // the archived version can be fetched like any other component
+        let archived_name = format!("{component_id}_v0");
+        let archived_id = ComponentId::new(&archived_name);
+        let metadata = client
+            .request(&GetComponentMetadata {
+                component_id: archived_id,
+            })
+            .await
+            .unwrap();
+        assert_eq!(metadata.name, archived_name);
+        let time_series = client
+            .request(&GetTimeSeries {
+                id: 3u16.to_le_bytes(),
+                range: Timestamp(0)..Timestamp::now(),
+                component_id: archived_id,
+                limit: None,
+            })
+            .await
+            .unwrap();
+        let data = <[f64]>::ref_from_bytes(time_series.data().unwrap()).unwrap();
+        assert_eq!(data, &[42.0]);
+
+        // setting the metadata keeps the schema version
+        client
+            .send(&SetComponentMetadata::new(component_id, "Renamed"))
+            .await
+            .0
+            .unwrap();
+        let metadata = client
+            .request(&GetComponentMetadata { component_id })
+            .await
+            .unwrap();
+        assert_eq!(metadata.name, "Renamed");
+        assert_eq!(metadata.schema_version(), 1);
+
+        // going back to an archived schema, like after a firmware rollback, starts a new version
+        let vtable_old_type = vtable([raw_field(
+            0,
+            8,
+            schema(PrimType::F64, &[1], component(component_id)),
+        )]);
+        client
+            .send(&VTableMsg {
+                id: 4u16.to_le_bytes(),
+                vtable: vtable_old_type,
+            })
+            .await
+            .0
+            .unwrap();
+        let mut pkt = LenPacket::table(4u16.to_le_bytes(), 8);
+        pkt.extend_aligned(&[43.0f64]);
+        client.send(pkt).await.0.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        db.with_state(|state| {
+            let component = state.get_component(component_id).unwrap();
+            assert_eq!(component.version, 2);
+            assert_eq!(component.schema.prim_type, PrimType::F64);
+            let latest = component.time_series.latest().unwrap();
+            assert_eq!(latest.data(), 43.0f64.as_bytes());
+            let versions = component
+                .history
+                .iter()
+                .map(|c| (c.version, c.schema.prim_type))
+                .collect::<Vec<_>>();
+            assert_eq!(versions, [(0, PrimType::F64), (1, PrimType::F32)]);
+        });
+    }
+
+    #[test]
+    async fn test_schema_change_reopen() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("metor_db_schema_change_test_{}", fastrand::u64(..)));
+        let component_id = ComponentId::new("evolving");
+        let schemas = [
+            (1u16, 24, &[3][..], [1.0f64, 2.0, 3.0, 0.0]),
+            (2u16, 32, &[4][..], [4.0f64, 5.0, 6.0, 7.0]),
+        ];
+
+        for (id, len, shape, value) in schemas {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            // the db is reopened from disk before every schema change
+            let server = Server::from_listener(listener, temp_dir.clone()).unwrap();
+            let shutdown = CancelToken::new();
+            let stop = shutdown.clone();
+            let server = stellar(move || async move {
+                let runtime = Runtime::builder().workers(1).build()?;
+                server.run_until(runtime, stop.wait()).await
+            });
+            let mut client = Client::connect(addr).await.unwrap();
+
+            let vtable = vtable([raw_field(
+                0,
+                len,
+                schema(PrimType::F64, shape, component(component_id)),
+            )]);
+            client
+                .send(&VTableMsg {
+                    id: id.to_le_bytes(),
+                    vtable,
+                })
+                .await
+                .0
+                .unwrap();
+            let mut pkt = LenPacket::table(id.to_le_bytes(), len as usize);
+            pkt.extend_aligned(&value[..len as usize / 8]);
+            client.send(pkt).await.0.unwrap();
+            // packets on a connection are handled in order, so once this is answered the value
+            // has been written to the wal
+            client
+                .request(&GetComponentMetadata { component_id })
+                .await
+                .unwrap();
+
+            shutdown.cancel();
+            server.join().await.unwrap().unwrap().unwrap();
+        }
+
+        let db = metor_db::DB::open(temp_dir).unwrap();
+        db.with_state(|state| {
+            let metadata = state.get_component_metadata(component_id).unwrap();
+            assert_eq!(metadata.schema_version(), 1);
+
+            let component = state.get_component(component_id).unwrap();
+            assert_eq!(component.version, 1);
+            assert_eq!(&component.schema.dim[..], &[4]);
+            let latest = component.time_series.latest().unwrap();
+            assert_eq!(latest.data(), [4.0f64, 5.0, 6.0, 7.0].as_bytes());
+
+            let [old] = &component.history[..] else {
+                panic!("expected one archived version");
+            };
+            assert_eq!(&old.schema.dim[..], &[3]);
+            let latest = old.time_series.latest().unwrap();
+            assert_eq!(latest.data(), [1.0f64, 2.0, 3.0].as_bytes());
+        });
+
+        let ctx = db.as_session_context().unwrap();
+        assert!(ctx.table_exist("evolving").unwrap());
+        assert!(ctx.table_exist("evolving_v0").unwrap());
     }
 
     #[test]
@@ -1,22 +1,33 @@
 use metor_proto::buf::{Slice, deref};
-use metor_proto::types::{Msg, PacketId};
+use metor_proto::types::{LenPacket, Msg, PacketId, Timestamp, TimestampNs};
 use metor_proto_stellar::{Client, Error, SubStream};
-use metor_proto_wkt::{MsgMetadata, SetMsgMetadata, StreamReply, VTableMsg, VTableStream};
+use metor_proto_wkt::{
+    ClockModel, MsgMetadata, SetMsgMetadata, StreamReply, TimeSyncRequest, VTableMsg, VTableStream,
+};
 use std::future::Future;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use stellarator::io::AsyncWrite;
-use zerocopy::{Immutable, KnownLayout};
+use zerocopy::{Immutable, IntoBytes, KnownLayout};
 
 use crate::{AsVTable, Metadatatize};
 
 pub trait SinkExt {
     fn send_vtable<V: AsVTable>(&self, id: PacketId) -> impl Future<Output = Result<(), Error>>;
+    fn send_timestamped_vtable<V: AsVTable>(
+        &self,
+        id: PacketId,
+    ) -> impl Future<Output = Result<(), Error>>;
     fn send_metadata<V: Metadatatize>(&self) -> impl Future<Output = Result<(), Error>>;
     fn init_world<V: AsVTable + Metadatatize>(
         &self,
         vtable_id: PacketId,
     ) -> impl Future<Output = Result<(), Error>>;
+    /// Like [`SinkExt::init_world`], but for tables built with [`timestamped_table`]
+    fn init_timestamped_world<V: AsVTable + Metadatatize>(
+        &self,
+        vtable_id: PacketId,
+    ) -> impl Future<Output = Result<(), Error>>;
     fn init_msg<M: postcard_schema::Schema + Msg>(&self)
     -> impl Future<Output = Result<(), Error>>;
 }
@@ -28,6 +39,12 @@ impl<W: AsyncWrite> SinkExt for metor_proto_stellar::PacketSink<W> {
         Ok(())
     }
 
+    async fn send_timestamped_vtable<V: AsVTable>(&self, id: PacketId) -> Result<(), Error> {
+        let vtable = V::as_timestamped_vtable();
+        self.send(&(VTableMsg { id, vtable })).await.0?;
+        Ok(())
+    }
+
     async fn send_metadata<V: Metadatatize>(&self) -> Result<(), Error> {
         for metadata in V::metadata(()) {
             println!("{metadata:?}");
@@ -47,6 +64,15 @@ impl<W: AsyncWrite> SinkExt for metor_proto_stellar::PacketSink<W> {
         Ok(())
     }
 
+    async fn init_timestamped_world<V: AsVTable + Metadatatize>(
+        &self,
+        vtable_id: PacketId,
+    ) -> Result<(), Error> {
+        self.send_timestamped_vtable::<V>(vtable_id).await?;
+        self.send_metadata::<V>().await?;
+        Ok(())
+    }
+
     async fn init_msg<M: postcard_schema::Schema + Msg>(&self) -> Result<(), Error> {
         let schema = M::SCHEMA;
         let name = std::any::type_name::<M>();
@@ -70,6 +96,13 @@ impl SinkExt for Client {
         self.tx.send_vtable::<V>(id)
     }
 
+    fn send_timestamped_vtable<V: AsVTable>(
+        &self,
+        id: PacketId,
+    ) -> impl Future<Output = Result<(), Error>> {
+        self.tx.send_timestamped_vtable::<V>(id)
+    }
+
     fn send_metadata<V: Metadatatize>(&self) -> impl Future<Output = Result<(), Error>> {
         self.tx.send_metadata::<V>()
     }
@@ -81,6 +114,13 @@ impl SinkExt for Client {
         self.tx.init_world::<V>(vtable_id)
     }
 
+    fn init_timestamped_world<V: AsVTable + Metadatatize>(
+        &self,
+        vtable_id: PacketId,
+    ) -> impl Future<Output = Result<(), Error>> {
+        self.tx.init_timestamped_world::<V>(vtable_id)
+    }
+
     fn init_msg<M: postcard_schema::Schema + Msg>(
         &self,
     ) -> impl Future<Output = Result<(), Error>> {
@@ -88,6 +128,55 @@ impl SinkExt for Client {
     }
 }
 
+/// Builds a table for a vtable sent with [`SinkExt::send_timestamped_vtable`]
+pub fn timestamped_table<V: IntoBytes + Immutable + ?Sized>(
+    id: PacketId,
+    timestamp: Timestamp,
+    value: &V,
+) -> LenPacket {
+    let mut table = LenPacket::table(id, size_of::<Timestamp>() + size_of_val(value));
+    table.extend_from_slice(timestamp.as_bytes());
+    table.extend_from_slice(value.as_bytes());
+    table
+}
+
+/// Estimates the db's clock from the local clock, so tables can be timestamped in the db's
+/// time base instead of whatever this host's clock reads
+#[derive(Debug, Clone, Default)]
+pub struct DbClock {
+    model: ClockModel,
+}
+
+impl DbClock {
+    /// Measures the offset to the db's clock `rounds` times
+    ///
+    /// Syncing again every so often keeps the drift estimate fresh.
+    pub async fn sync(&mut self, client: &mut Client, rounds: usize) -> Result<(), Error> {
+        for _ in 0..rounds {
+            let req = TimeSyncRequest {
+                client_send: TimestampNs::now(),
+            };
+            let resp = client.request(&req).await?;
+            self.model.push(resp.sample(TimestampNs::now()));
+        }
+        Ok(())
+    }
+
+    pub fn model(&self) -> &ClockModel {
+        &self.model
+    }
+
+    /// The current time in the db's time base
+    pub fn now(&self) -> Timestamp {
+        self.to_db(TimestampNs::now())
+    }
+
+    /// Maps a local time into the db's time base
+    pub fn to_db(&self, local: TimestampNs) -> Timestamp {
+        self.model.to_reference(local).into()
+    }
+}
+
 pub trait StreamExt {
     fn subscribe<T>(&mut self) -> impl Future<Output = Result<Subscription<'_, T>, Error>>
     where
@@ -88,6 +88,10 @@ impl<T: FromBytes + IntoBytes> Decomponentize for VTableSink<'_, T> {
             );
             return Err(VTableSinkError::IncompatibleShape);
         }
+        // fixed size tables don't have room for variable length values
+        if value.prim_type().is_var_len() {
+            return Err(VTableSinkError::IncompatibleShape);
+        }
         let table = self.table.as_mut_bytes();
         let buf = value.as_bytes();
         let table_field = table
@@ -188,6 +192,17 @@ impl ComponentViewExt for ComponentView<'_> {
                 T::PRIM_TYPE,
             )
             .unwrap(),
+            PrimType::F16 | PrimType::I128 | PrimType::U128 | PrimType::C64 | PrimType::C128 => {
+                ComponentView::try_from_bytes_shape(
+                    tensor.inner().buf.as_buf().as_bytes(),
+                    D::shape_slice(&tensor.inner().buf),
+                    T::PRIM_TYPE,
+                )
+                .unwrap()
+            }
+            PrimType::Bytes | PrimType::Utf8 => {
+                unreachable!("tensors can't hold variable length values")
+            }
         }
     }
 }
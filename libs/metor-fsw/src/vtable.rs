@@ -1,6 +1,9 @@
-use metor_proto::vtable::{
-    VTable,
-    builder::{FieldBuilder, vtable},
+use metor_proto::{
+    types::Timestamp,
+    vtable::{
+        VTable,
+        builder::{FieldBuilder, raw_table, vtable},
+    },
 };
 
 use crate::path::ComponentPath;
@@ -10,6 +13,17 @@ pub trait AsVTable {
     fn as_vtable() -> VTable {
         vtable(Self::vtable_fields(()))
     }
+
+    /// A vtable for tables that start with the [`Timestamp`] the values were sampled at,
+    /// followed by `Self`
+    fn as_timestamped_vtable() -> VTable {
+        let len = size_of::<Timestamp>() as u32;
+        let timestamp = raw_table(0, len);
+        vtable(
+            Self::vtable_fields(())
+                .map(|field| field.offset_by(len).with_timestamp(timestamp.clone())),
+        )
+    }
 }
 
 impl<const N: usize, T: AsVTable> AsVTable for [T; N] {
@@ -761,6 +761,15 @@ impl ComponentValueExt for ComponentValue {
                     .indexed_iter_mut()
                     .map(|(i, x)| (i, ElementValueMut::F64(x))),
             ),
+            // variable length values don't have indexed elements to edit, and the remaining
+            // types don't have an `ElementValueMut` to edit them through
+            ComponentValue::Bytes(_)
+            | ComponentValue::Utf8(_)
+            | ComponentValue::F16(_)
+            | ComponentValue::I128(_)
+            | ComponentValue::U128(_)
+            | ComponentValue::C64(_)
+            | ComponentValue::C128(_) => Box::new(std::iter::empty()),
         }
     }
 }
@@ -0,0 +1,122 @@
+//! Table driven CRCs used for frame trailers.
+
+/// The checksum appended to each frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Crc {
+    /// CRC-16/CCITT-FALSE, for links where the 2 extra bytes per frame matter
+    Crc16,
+    /// CRC-32/ISO-HDLC, the CRC used by Ethernet and zlib
+    #[default]
+    Crc32,
+}
+
+impl Crc {
+    /// The length of the trailer in bytes
+    pub const fn trailer_len(self) -> usize {
+        match self {
+            Crc::Crc16 => 2,
+            Crc::Crc32 => 4,
+        }
+    }
+
+    pub fn checksum(self, data: &[u8]) -> u32 {
+        match self {
+            Crc::Crc16 => crc16(data) as u32,
+            Crc::Crc32 => crc32(data),
+        }
+    }
+
+    /// Writes the little-endian trailer for `data` into `out`, which must be [`Crc::trailer_len`] bytes
+    pub(crate) fn write_trailer(self, data: &[u8], out: &mut [u8]) {
+        let checksum = self.checksum(data).to_le_bytes();
+        out.copy_from_slice(&checksum[..self.trailer_len()]);
+    }
+
+    /// Checks a payload with its trailer still attached, returning the payload's length
+    pub(crate) fn verify(self, frame: &[u8]) -> Option<usize> {
+        let len = frame.len().checked_sub(self.trailer_len())?;
+        let (payload, trailer) = frame.split_at(len);
+        let mut checksum = [0u8; 4];
+        checksum[..self.trailer_len()].copy_from_slice(trailer);
+        (self.checksum(payload) == u32::from_le_bytes(checksum)).then_some(len)
+    }
+}
+
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xffff, no reflection
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, &b| {
+        (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ b) as usize]
+    })
+}
+
+/// CRC-32/ISO-HDLC: reflected polynomial 0xedb88320, initial value and final xor 0xffffffff
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &b| {
+        (crc >> 8) ^ CRC32_TABLE[(crc as u8 ^ b) as usize]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_values() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_verify() {
+        for crc in [Crc::Crc16, Crc::Crc32] {
+            let mut frame = b"hello".to_vec();
+            frame.resize(5 + crc.trailer_len(), 0);
+            let (payload, trailer) = frame.split_at_mut(5);
+            crc.write_trailer(payload, trailer);
+            assert_eq!(crc.verify(&frame), Some(5));
+            frame[1] ^= 0x10;
+            assert_eq!(crc.verify(&frame), None);
+            assert_eq!(crc.verify(&frame[..1]), None);
+        }
+    }
+}
@@ -1,82 +1,367 @@
+//! Framing for byte streams without packet boundaries, like UARTs.
+//!
+//! Each frame is a payload followed by a little-endian CRC trailer, COBS encoded, with a `0x00`
+//! delimiter on each side:
+//!
+//! ```text
+//! 0x00 | COBS(payload | crc) | 0x00
+//! ```
+//!
+//! COBS removes every zero from the encoded bytes, so the decoder can always resync on the next
+//! delimiter after a corrupted or dropped byte, and the CRC catches frames that were corrupted
+//! without breaking the encoding. The leading delimiter means a frame is never lost to garbage
+//! left over from the previous one.
 #![no_std]
+
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
 use metor_proto::buf::Buf;
 use metor_proto::error::Error;
 
-#[derive(Default)]
-pub struct FrameDecoder<B: Buf<u8> = alloc::vec::Vec<u8>> {
+mod crc;
+
+pub use crc::{Crc, crc16, crc32};
+
+/// The largest payload a [`FrameDecoder`] accepts unless configured otherwise
+pub const DEFAULT_MAX_FRAME_LEN: usize = 4096;
+
+/// Encodes payloads into frames, see the [crate docs](crate) for the format
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameEncoder {
+    crc: Crc,
+}
+
+impl FrameEncoder {
+    pub const fn new(crc: Crc) -> Self {
+        Self { crc }
+    }
+
+    /// Returns the most bytes [`FrameEncoder::encode`] can write for a payload of `payload_len`
+    pub const fn max_encoded_len(&self, payload_len: usize) -> usize {
+        cobs::max_encoding_length(payload_len + self.crc.trailer_len()) + 2
+    }
+
+    /// Encodes `payload` into `out`, returning the length of the frame
+    ///
+    /// `out` must be at least [`FrameEncoder::max_encoded_len`] bytes long to fit every payload.
+    pub fn encode(&self, payload: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+        let (start, body) = out.split_first_mut().ok_or(Error::BufferOverflow)?;
+        *start = 0;
+        let mut trailer = [0u8; 4];
+        let trailer = &mut trailer[..self.crc.trailer_len()];
+        self.crc.write_trailer(payload, trailer);
+        let mut encoder = cobs::CobsEncoder::new(body);
+        encoder.push(payload).map_err(|_| Error::BufferOverflow)?;
+        encoder.push(trailer).map_err(|_| Error::BufferOverflow)?;
+        let len = encoder.finalize();
+        *body.get_mut(len).ok_or(Error::BufferOverflow)? = 0;
+        Ok(len + 2)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn encode_vec(&self, payload: &[u8]) -> alloc::vec::Vec<u8> {
+        let mut out = alloc::vec![0; self.max_encoded_len(payload.len())];
+        let len = self
+            .encode(payload, &mut out)
+            .expect("buffer fits the max encoded len");
+        out.truncate(len);
+        out
+    }
+}
+
+/// Counters describing the health of a link
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames that were decoded and passed their CRC check
+    pub frames_ok: u64,
+    /// Frames that were dropped because their CRC didn't match
+    pub crc_failures: u64,
+    /// Times the decoder dropped bytes to find the start of the next frame, either because a
+    /// frame wasn't valid COBS, was too short to hold a CRC or too long, or because the stream
+    /// started in the middle of a frame
+    pub resyncs: u64,
+}
+
+/// Decodes frames from a byte stream, see the [crate docs](crate) for the format
+///
+/// Frames that fail to decode are dropped and counted in [`FrameDecoder::stats`].
+pub struct FrameDecoder<B: Buf<u8>> {
     frame: B,
+    crc: Crc,
+    max_frame_len: usize,
     state: FrameDecoderState,
+    stats: FrameStats,
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 enum FrameDecoderState {
-    #[default]
-    Finding,
+    /// Waiting for a delimiter, `skipped` is set once bytes have been dropped along the way
+    Finding {
+        skipped: bool,
+    },
     Building,
     Decoded,
 }
 
+impl<B: Buf<u8>> Default for FrameDecoder<B> {
+    fn default() -> Self {
+        Self::new(Crc::default())
+    }
+}
+
 impl<B: Buf<u8>> FrameDecoder<B> {
-    pub fn push<'a>(&'a mut self, data: &[u8]) -> Result<Option<&'a [u8]>, Error> {
+    pub fn new(crc: Crc) -> Self {
+        Self {
+            frame: B::default(),
+            crc,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            state: FrameDecoderState::Finding { skipped: false },
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Sets the largest payload the decoder accepts, longer frames are dropped
+    ///
+    /// Fixed capacity buffers drop frames that don't fit them regardless of this limit.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Pushes bytes into the decoder, stopping after the first complete frame
+    ///
+    /// Returns the number of bytes consumed along with the frame, if one was completed. Push the
+    /// rest of `data` afterwards, since it can contain more frames:
+    ///
+    /// ```
+    /// # use metor_proto_frame::{FrameDecoder, FrameEncoder};
+    /// let encoder = FrameEncoder::default();
+    /// let mut data = encoder.encode_vec(b"foo");
+    /// data.extend(encoder.encode_vec(b"bar"));
+    ///
+    /// let mut decoder = FrameDecoder::<Vec<u8>>::default();
+    /// let mut frames = vec![];
+    /// let mut data = &data[..];
+    /// while !data.is_empty() {
+    ///     let (n, frame) = decoder.push(data);
+    ///     if let Some(frame) = frame {
+    ///         frames.push(frame.to_vec());
+    ///     }
+    ///     data = &data[n..];
+    /// }
+    /// assert_eq!(frames, [b"foo", b"bar"]);
+    /// ```
+    pub fn push<'a>(&'a mut self, data: &[u8]) -> (usize, Option<&'a [u8]>) {
+        let max_encoded_len =
+            cobs::max_encoding_length(self.max_frame_len + self.crc.trailer_len());
         let mut n = 0;
         while let Some(data) = data.get(n..) {
             if data.is_empty() {
                 break;
             }
             match self.state {
-                FrameDecoderState::Finding => {
-                    let Some((frame_start, _)) = data.iter().enumerate().find(|(_, b)| **b == 0x00)
-                    else {
+                FrameDecoderState::Finding { skipped } => {
+                    let Some(frame_start) = data.iter().position(|b| *b == 0x00) else {
                         n += data.len();
+                        self.state = FrameDecoderState::Finding { skipped: true };
                         continue;
                     };
+                    if skipped || frame_start > 0 {
+                        self.stats.resyncs += 1;
+                    }
                     n += frame_start + 1;
                     self.state = FrameDecoderState::Building;
-                    continue;
                 }
                 FrameDecoderState::Building => {
-                    match data.iter().enumerate().find(|(_, b)| **b == 0x00) {
-                        Some((frame_end, _)) => {
-                            if frame_end >= 255 {
-                                n += frame_end + 1;
-                                self.clear();
-                                continue;
-                            }
-                            let _ = self.frame.extend_from_slice(&data[..frame_end]);
-                            n += frame_end.saturating_sub(1);
-                            let Ok(len) = cobs::decode_in_place(self.frame.as_mut_slice()) else {
-                                self.clear();
-                                continue;
-                            };
-                            if len == 0 {
-                                self.clear();
-                                continue;
-                            }
-                            self.state = FrameDecoderState::Decoded;
-                            return Ok(Some(&self.frame.as_slice()[..len]));
+                    let Some(frame_end) = data.iter().position(|b| *b == 0x00) else {
+                        n += data.len();
+                        if self.frame.len() + data.len() > max_encoded_len
+                            || self.frame.extend_from_slice(data).is_err()
+                        {
+                            self.resync();
+                        }
+                        continue;
+                    };
+                    // the delimiter that ends this frame also starts the next one
+                    n += frame_end + 1;
+                    if self.frame.len() + frame_end > max_encoded_len
+                        || self.frame.extend_from_slice(&data[..frame_end]).is_err()
+                    {
+                        self.stats.resyncs += 1;
+                        self.frame.clear();
+                        continue;
+                    }
+                    // back to back delimiters, e.g. between two frames
+                    if self.frame.is_empty() {
+                        continue;
+                    }
+                    let len = match cobs::decode_in_place(self.frame.as_mut_slice()) {
+                        Ok(len)
+                            if len >= self.crc.trailer_len()
+                                && len <= self.max_frame_len + self.crc.trailer_len() =>
+                        {
+                            len
                         }
-                        None => {
-                            n += data.len();
-                            if self.frame.extend_from_slice(data).is_err() {
-                                self.clear();
-                                break;
-                            }
+                        _ => {
+                            self.stats.resyncs += 1;
+                            self.frame.clear();
+                            continue;
                         }
                     };
+                    let Some(len) = self.crc.verify(&self.frame.as_slice()[..len]) else {
+                        self.stats.crc_failures += 1;
+                        self.frame.clear();
+                        continue;
+                    };
+                    self.stats.frames_ok += 1;
+                    self.state = FrameDecoderState::Decoded;
+                    return (n, Some(&self.frame.as_slice()[..len]));
                 }
                 FrameDecoderState::Decoded => {
-                    self.clear();
-                    continue;
+                    self.frame.clear();
+                    self.state = FrameDecoderState::Building;
                 }
             }
         }
-        Ok(None)
+        (n, None)
+    }
+
+    /// Drops the partial frame and waits for the next delimiter, which counts as a resync
+    fn resync(&mut self) {
+        self.frame.clear();
+        self.state = FrameDecoderState::Finding { skipped: true };
     }
 
     pub fn clear(&mut self) {
         self.frame.clear();
-        self.state = FrameDecoderState::Finding;
+        self.state = FrameDecoderState::Finding { skipped: false };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn decode_all(
+        decoder: &mut FrameDecoder<Vec<u8>>,
+        data: &[u8],
+        chunk_len: usize,
+    ) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for mut chunk in data.chunks(chunk_len) {
+            while !chunk.is_empty() {
+                let (n, frame) = decoder.push(chunk);
+                if let Some(frame) = frame {
+                    frames.push(frame.to_vec());
+                }
+                chunk = &chunk[n..];
+            }
+        }
+        frames
+    }
+
+    fn encode_all(encoder: &FrameEncoder, payloads: &[Vec<u8>]) -> Vec<u8> {
+        payloads
+            .iter()
+            .flat_map(|p| encoder.encode_vec(p))
+            .collect()
+    }
+
+    #[test]
+    fn test_large_frame() {
+        let payload = (0..2000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let encoder = FrameEncoder::new(Crc::Crc16);
+        let data = encoder.encode_vec(&payload);
+        let mut decoder = FrameDecoder::<Vec<u8>>::new(Crc::Crc16);
+        assert_eq!(decode_all(&mut decoder, &data, 64), [payload]);
+
+        let mut decoder = FrameDecoder::<Vec<u8>>::new(Crc::Crc16).with_max_frame_len(1000);
+        assert!(decode_all(&mut decoder, &data, 64).is_empty());
+        assert_eq!(decoder.stats().resyncs, 1);
+    }
+
+    #[test]
+    fn test_encode_into_small_buffer() {
+        let encoder = FrameEncoder::default();
+        let mut out = [0u8; 8];
+        assert!(matches!(
+            encoder.encode(b"foo", &mut out),
+            Err(Error::BufferOverflow)
+        ));
+        let mut out = [0u8; 16];
+        let len = encoder.encode(b"foo", &mut out).unwrap();
+        assert!(len <= encoder.max_encoded_len(3));
+        assert_eq!(out[0], 0);
+        assert_eq!(out[len - 1], 0);
+    }
+
+    #[test]
+    fn test_garbage_before_first_frame() {
+        let encoder = FrameEncoder::default();
+        let mut data = b"\x01\x02garbage".to_vec();
+        data.extend(encoder.encode_vec(b"hello"));
+        let mut decoder = FrameDecoder::<Vec<u8>>::default();
+        assert_eq!(decode_all(&mut decoder, &data, 3), [b"hello"]);
+        assert_eq!(decoder.stats().frames_ok, 1);
+        assert_eq!(decoder.stats().resyncs, 1);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            payloads in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..600), 1..8),
+            chunk_len in 1usize..300,
+            crc in prop_oneof![Just(Crc::Crc16), Just(Crc::Crc32)],
+        ) {
+            let data = encode_all(&FrameEncoder::new(crc), &payloads);
+            let mut decoder = FrameDecoder::<Vec<u8>>::new(crc);
+            prop_assert_eq!(decode_all(&mut decoder, &data, chunk_len), payloads.clone());
+            prop_assert_eq!(decoder.stats().frames_ok, payloads.len() as u64);
+            prop_assert_eq!(decoder.stats().crc_failures + decoder.stats().resyncs, 0);
+        }
+
+        #[test]
+        fn test_bit_errors(
+            payloads in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..600), 1..8),
+            chunk_len in 1usize..300,
+            bit in any::<prop::sample::Index>(),
+        ) {
+            let encoder = FrameEncoder::default();
+            let frames = payloads.iter().map(|p| encoder.encode_vec(p)).collect::<Vec<_>>();
+            let mut data = frames.concat();
+            let bit = bit.index(data.len() * 8);
+            data[bit / 8] ^= 1 << (bit % 8);
+            // a corrupted trailing delimiter leaves the last frame pending until the next one
+            data.push(0);
+
+            // find the frame the flipped bit landed in
+            let mut corrupted = 0;
+            let mut offset = frames[0].len();
+            while offset <= bit / 8 {
+                corrupted += 1;
+                offset += frames[corrupted].len();
+            }
+
+            let mut decoder = FrameDecoder::<Vec<u8>>::default();
+            let decoded = decode_all(&mut decoder, &data, chunk_len);
+            // a bit error never yields a wrong frame, and it costs at most the frame it hit
+            let mut expected = payloads.clone();
+            if decoded.len() < payloads.len() {
+                expected.remove(corrupted);
+                let stats = decoder.stats();
+                prop_assert!(stats.crc_failures + stats.resyncs > 0);
+            }
+            prop_assert_eq!(decoded, expected);
+        }
     }
 }
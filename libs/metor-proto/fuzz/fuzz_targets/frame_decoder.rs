@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use metor_proto_frame::{DEFAULT_MAX_FRAME_LEN, FrameDecoder};
+
+// the first byte picks the chunk size, so frames get split across pushes
+fuzz_target!(|data: &[u8]| {
+    let Some((chunk_size, data)) = data.split_first() else {
+        return;
+    };
+    let mut decoder = FrameDecoder::<Vec<u8>>::default();
+    for mut chunk in data.chunks(*chunk_size as usize + 1) {
+        while !chunk.is_empty() {
+            let (n, frame) = decoder.push(chunk);
+            assert!(n > 0 && n <= chunk.len());
+            if let Some(frame) = frame {
+                assert!(frame.len() <= DEFAULT_MAX_FRAME_LEN);
+            }
+            chunk = &chunk[n..];
+        }
+    }
+});
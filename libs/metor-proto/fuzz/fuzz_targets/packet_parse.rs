@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use metor_proto::types::OwnedPacket;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(packet) = OwnedPacket::parse(data.to_vec()) else {
+        return;
+    };
+    let _ = packet.req_id();
+    if let OwnedPacket::TimeSeries(time_series) = &packet {
+        let _ = time_series.timestamps();
+        let _ = time_series.data();
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use metor_proto::vtable::VTable;
+
+// the input is a postcard encoded vtable followed by a table
+fuzz_target!(|data: &[u8]| {
+    let Ok((vtable, table)) = postcard::take_from_bytes::<VTable>(data) else {
+        return;
+    };
+    if vtable.validate().is_err() {
+        return;
+    }
+    let _ = vtable.realize_fields(Some(table)).count();
+    let _ = vtable.realize_fields(None).count();
+});
@@ -225,6 +225,37 @@ impl UmbraBuf {
         }
     }
 
+    /// Creates an `UmbraBuf` for `buf`, storing it inline if it fits, or pointing to `offset` if not
+    pub fn new(buf: &[u8], offset: u32) -> Self {
+        let len = buf.len() as u32;
+        match buf.len() {
+            ..=12 => {
+                let mut inline = [0u8; 12];
+                inline[..buf.len()].copy_from_slice(buf);
+                Self::with_inline(len, inline)
+            }
+            _ => {
+                let prefix = buf[..4].try_into().expect("trivial cast failed");
+                Self::with_offset(len, prefix, offset)
+            }
+        }
+    }
+
+    /// Returns the contents of the `UmbraBuf` stored at the start of `buf`
+    ///
+    /// Inline contents are borrowed from `buf` itself, while longer contents are read from `data`.
+    pub fn resolve<'a>(buf: &'a [u8], data: &'a [u8]) -> Option<&'a [u8]> {
+        let umbra = Self::read_from_bytes(buf.get(..size_of::<Self>())?).ok()?;
+        let len = umbra.len as usize;
+        match len {
+            ..=12 => buf.get(size_of::<u32>()..size_of::<u32>() + len),
+            _ => {
+                let offset = unsafe { umbra.data.offset.offset } as usize;
+                data.get(offset..offset.checked_add(len)?)
+            }
+        }
+    }
+
     /// Returns the offset of the UmbraBuf if it is an offset buffer
     pub fn offset(&self) -> Option<u32> {
         if self.len >= 12 {
@@ -6,6 +6,7 @@
 
 use crate::{
     error::Error,
+    num::{C64, C128, F16, I128, U128},
     types::{ComponentId, ComponentView, Timestamp},
 };
 use core::{convert::Infallible, slice};
@@ -193,3 +194,20 @@ impl_component_view!(i8, I8);
 impl_component_view!(f64, F64);
 impl_component_view!(f32, F32);
 impl_component_view!(bool, Bool);
+impl_component_view!(F16, F16);
+impl_component_view!(I128, I128);
+impl_component_view!(U128, U128);
+impl_component_view!(C64, C64);
+impl_component_view!(C128, C128);
+
+impl AsComponentView for str {
+    fn as_component_view(&self) -> ComponentView<'_> {
+        ComponentView::Utf8(self)
+    }
+}
+
+impl AsComponentView for [u8] {
+    fn as_component_view(&self) -> ComponentView<'_> {
+        ComponentView::Bytes(self)
+    }
+}
@@ -42,6 +42,11 @@ impl_prim_type_element!(u16, U16);
 impl_prim_type_element!(u32, U32);
 impl_prim_type_element!(u64, U64);
 impl_prim_type_element!(bool, Bool);
+impl_prim_type_element!(crate::num::F16, F16);
+impl_prim_type_element!(crate::num::I128, I128);
+impl_prim_type_element!(crate::num::U128, U128);
+impl_prim_type_element!(crate::num::C64, C64);
+impl_prim_type_element!(crate::num::C128, C128);
 
 pub trait Asset: DeserializeOwned + Serialize {
     const NAME: &'static str;
@@ -86,6 +86,36 @@ pub enum Error {
         diagnostic(code(impeller::schema_not_found), help("schema not found"))
     )]
     SchemaNotFound,
+
+    #[error("cyclic vtable")]
+    #[cfg_attr(
+        feature = "std",
+        diagnostic(
+            code(impeller::cyclic_vtable),
+            help("vtable ops can only reference the ops before them")
+        )
+    )]
+    CyclicVTable,
+
+    #[error("table too large")]
+    #[cfg_attr(
+        feature = "std",
+        diagnostic(
+            code(impeller::table_too_large),
+            help("vtable describes a table longer than MAX_TABLE_LEN")
+        )
+    )]
+    TableTooLarge,
+
+    #[error("unknown clock domain")]
+    #[cfg_attr(
+        feature = "std",
+        diagnostic(
+            code(impeller::unknown_clock_domain),
+            help("expected one of utc, tai, gps, sim or monotonic")
+        )
+    )]
+    UnknownClockDomain,
 }
 
 impl<A, B: ?Sized> From<zerocopy::CastError<A, B>> for Error {
@@ -27,6 +27,10 @@
 //! The tables themselves are laid out into a series of fields. Each field is a tensor associated with an entity and component id. Each tensor is expected
 //! to be aligned. Conceptually this is similar to a repr(C) struct where the field names are entity, component id pairs.
 //!
+//! Fields with a `bytes` or `utf8` [`types::PrimType`] are variable length. Their slot in the table holds a 16 byte
+//! [`buf::UmbraBuf`], which stores values up to 12 bytes inline, and otherwise points to the value's bytes at an offset
+//! into the same table, usually after the fixed size fields.
+//!
 //! ## Msgs
 //!
 //! Sometimes you have data that does not cleanly fit into a fixed-size tensor (i.e a command that contains a string).
@@ -42,6 +46,7 @@ pub mod buf;
 pub mod com_de;
 pub mod component;
 pub mod error;
+pub mod num;
 pub mod registry;
 pub mod schema;
 pub mod types;
@@ -0,0 +1,229 @@
+//! Element types that don't have a Rust primitive with a fixed layout.
+//!
+//! [`F16`] holds the raw bits of a half precision float. [`I128`] and [`U128`] are stored with an
+//! alignment of 8, since tables only guarantee that much alignment, while the native 128-bit
+//! integers have an alignment of 16 on most targets. [`C64`] and [`C128`] are complex numbers
+//! laid out as their real part followed by their imaginary part.
+
+use core::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+/// An IEEE 754 half precision float
+#[derive(
+    Clone, Copy, Default, FromBytes, IntoBytes, Immutable, KnownLayout, Serialize, Deserialize,
+)]
+#[repr(transparent)]
+pub struct F16(u16);
+
+impl F16 {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Converts a f32 into the nearest f16, values out of range become infinity
+    pub fn from_f32(value: f32) -> Self {
+        Self(half::f16::from_f32(value).to_bits())
+    }
+
+    pub fn to_f32(self) -> f32 {
+        half::f16::from_bits(self.0).to_f32()
+    }
+}
+
+impl From<f32> for F16 {
+    fn from(value: f32) -> Self {
+        Self::from_f32(value)
+    }
+}
+
+impl From<F16> for f32 {
+    fn from(value: F16) -> Self {
+        value.to_f32()
+    }
+}
+
+impl PartialEq for F16 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_f32() == other.to_f32()
+    }
+}
+
+impl PartialOrd for F16 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.to_f32().partial_cmp(&other.to_f32())
+    }
+}
+
+impl fmt::Debug for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_f32(), f)
+    }
+}
+
+impl fmt::Display for F16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f32(), f)
+    }
+}
+
+macro_rules! impl_int128 {
+    ($name:ident, $prim:ty, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(
+            Clone, Copy, Default, PartialEq, Eq, Hash, FromBytes, IntoBytes, Immutable, KnownLayout,
+        )]
+        #[repr(C, align(8))]
+        pub struct $name([u8; 16]);
+
+        impl $name {
+            pub const fn new(value: $prim) -> Self {
+                Self(value.to_ne_bytes())
+            }
+
+            pub const fn get(self) -> $prim {
+                <$prim>::from_ne_bytes(self.0)
+            }
+        }
+
+        impl From<$prim> for $name {
+            fn from(value: $prim) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for $prim {
+            fn from(value: $name) -> Self {
+                value.get()
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.get().cmp(&other.get())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.get(), f)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.get(), f)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.get().serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$prim>::deserialize(deserializer).map(Self::new)
+            }
+        }
+    };
+}
+
+impl_int128!(
+    I128,
+    i128,
+    "A signed 128-bit integer with an alignment of 8"
+);
+impl_int128!(
+    U128,
+    u128,
+    "An unsigned 128-bit integer with an alignment of 8"
+);
+
+macro_rules! impl_complex {
+    ($name:ident, $elem:ty, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Default,
+            PartialEq,
+            FromBytes,
+            IntoBytes,
+            Immutable,
+            KnownLayout,
+            Serialize,
+            Deserialize,
+        )]
+        #[repr(C)]
+        pub struct $name {
+            pub re: $elem,
+            pub im: $elem,
+        }
+
+        impl $name {
+            pub const fn new(re: $elem, im: $elem) -> Self {
+                Self { re, im }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if self.im.is_sign_negative() {
+                    write!(f, "{}-{}i", self.re, -self.im)
+                } else {
+                    write!(f, "{}+{}i", self.re, self.im)
+                }
+            }
+        }
+    };
+}
+
+impl_complex!(C64, f32, "A complex number made of two f32s");
+impl_complex!(C128, f64, "A complex number made of two f64s");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16() {
+        assert_eq!(F16::from_f32(1.5).to_bits(), 0x3e00);
+        assert_eq!(F16::from_bits(0xc000).to_f32(), -2.0);
+        assert_eq!(F16::from_f32(1e6).to_f32(), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_int128_layout() {
+        assert_eq!(core::mem::align_of::<I128>(), 8);
+        assert_eq!(core::mem::size_of::<U128>(), 16);
+        let value = I128::new(i128::MIN + 1);
+        assert_eq!(value.as_bytes(), (i128::MIN + 1).to_ne_bytes());
+        assert_eq!(
+            I128::read_from_bytes(value.as_bytes()).unwrap().get(),
+            i128::MIN + 1
+        );
+        assert!(U128::new(u128::MAX) > U128::new(1));
+    }
+
+    #[test]
+    fn test_complex_display() {
+        assert_eq!(C64::new(1.0, -2.5).to_string(), "1-2.5i");
+        assert_eq!(C128::new(0.5, 3.0).to_string(), "0.5+3i");
+    }
+}
@@ -12,7 +12,11 @@ use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, TryFromBytes, Unali
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-use crate::error::Error;
+use crate::{
+    buf::UmbraBuf,
+    error::Error,
+    num::{C64, C128, F16, I128, U128},
+};
 use stellarator_buf::{IoBuf, Slice};
 
 #[derive(
@@ -128,6 +132,20 @@ pub enum PrimType {
     Bool,
     F32,
     F64,
+    /// Variable length bytes, stored in a table as an [`UmbraBuf`] that points into the table
+    Bytes,
+    /// A variable length UTF-8 string, stored the same way as [`PrimType::Bytes`]
+    Utf8,
+    /// A half precision float, see [`F16`]
+    F16,
+    /// A signed 128-bit integer, stored with an alignment of 8, see [`I128`]
+    I128,
+    /// An unsigned 128-bit integer, stored with an alignment of 8, see [`U128`]
+    U128,
+    /// A complex number made of two f32s, see [`C64`]
+    C64,
+    /// A complex number made of two f64s, see [`C128`]
+    C128,
 }
 
 impl PrimType {
@@ -150,6 +168,12 @@ impl PrimType {
             PrimType::Bool => mem::align_of::<bool>(),
             PrimType::F32 => mem::align_of::<f32>(),
             PrimType::F64 => mem::align_of::<f64>(),
+            PrimType::Bytes | PrimType::Utf8 => mem::align_of::<UmbraBuf>(),
+            PrimType::F16 => mem::align_of::<F16>(),
+            PrimType::I128 => mem::align_of::<I128>(),
+            PrimType::U128 => mem::align_of::<U128>(),
+            PrimType::C64 => mem::align_of::<C64>(),
+            PrimType::C128 => mem::align_of::<C128>(),
         }
     }
 
@@ -166,6 +190,12 @@ impl PrimType {
             PrimType::Bool => mem::size_of::<bool>(),
             PrimType::F32 => mem::size_of::<f32>(),
             PrimType::F64 => mem::size_of::<f64>(),
+            PrimType::Bytes | PrimType::Utf8 => mem::size_of::<UmbraBuf>(),
+            PrimType::F16 => mem::size_of::<F16>(),
+            PrimType::I128 => mem::size_of::<I128>(),
+            PrimType::U128 => mem::size_of::<U128>(),
+            PrimType::C64 => mem::size_of::<C64>(),
+            PrimType::C128 => mem::size_of::<C128>(),
         }
     }
 
@@ -182,8 +212,20 @@ impl PrimType {
             PrimType::Bool => "bool",
             PrimType::F32 => "f32",
             PrimType::F64 => "f64",
+            PrimType::Bytes => "bytes",
+            PrimType::Utf8 => "utf8",
+            PrimType::F16 => "f16",
+            PrimType::I128 => "i128",
+            PrimType::U128 => "u128",
+            PrimType::C64 => "c64",
+            PrimType::C128 => "c128",
         }
     }
+
+    /// Returns true for types whose values are variable length, and stored as an [`UmbraBuf`]
+    pub const fn is_var_len(&self) -> bool {
+        matches!(self, PrimType::Bytes | PrimType::Utf8)
+    }
 }
 
 impl core::fmt::Display for PrimType {
@@ -200,6 +242,13 @@ impl core::fmt::Display for PrimType {
             PrimType::Bool => "bool",
             PrimType::F32 => "f32",
             PrimType::F64 => "f64",
+            PrimType::Bytes => "bytes",
+            PrimType::Utf8 => "utf8",
+            PrimType::F16 => "f16",
+            PrimType::I128 => "i128",
+            PrimType::U128 => "u128",
+            PrimType::C64 => "c64",
+            PrimType::C128 => "c128",
         };
         core::fmt::Display::fmt(s, f)
     }
@@ -218,6 +267,13 @@ pub enum ComponentView<'a> {
     Bool(ArrayView<'a, bool>),
     F32(ArrayView<'a, f32>),
     F64(ArrayView<'a, f64>),
+    Bytes(&'a [u8]),
+    Utf8(&'a str),
+    F16(ArrayView<'a, F16>),
+    I128(ArrayView<'a, I128>),
+    U128(ArrayView<'a, U128>),
+    C64(ArrayView<'a, C64>),
+    C128(ArrayView<'a, C128>),
 }
 
 impl<'a> From<ComponentView<'a>> for i64 {
@@ -295,6 +351,28 @@ impl core::fmt::Display for ComponentView<'_> {
                 f.write_str("f64")?;
                 core::fmt::Display::fmt(array, f)
             }
+            ComponentView::Bytes(bytes) => write!(f, "bytes{bytes:?}"),
+            ComponentView::Utf8(s) => write!(f, "utf8{s:?}"),
+            ComponentView::F16(array) => {
+                f.write_str("f16")?;
+                core::fmt::Display::fmt(array, f)
+            }
+            ComponentView::I128(array) => {
+                f.write_str("i128")?;
+                core::fmt::Display::fmt(array, f)
+            }
+            ComponentView::U128(array) => {
+                f.write_str("u128")?;
+                core::fmt::Display::fmt(array, f)
+            }
+            ComponentView::C64(array) => {
+                f.write_str("c64")?;
+                core::fmt::Display::fmt(array, f)
+            }
+            ComponentView::C128(array) => {
+                f.write_str("c128")?;
+                core::fmt::Display::fmt(array, f)
+            }
         }
     }
 }
@@ -313,6 +391,12 @@ impl<'a> ComponentView<'a> {
             Self::Bool(ref view) => view.shape(),
             Self::F32(ref view) => view.shape(),
             Self::F64(ref view) => view.shape(),
+            Self::Bytes(_) | Self::Utf8(_) => &[],
+            Self::F16(ref view) => view.shape(),
+            Self::I128(ref view) => view.shape(),
+            Self::U128(ref view) => view.shape(),
+            Self::C64(ref view) => view.shape(),
+            Self::C128(ref view) => view.shape(),
         }
     }
 
@@ -329,9 +413,20 @@ impl<'a> ComponentView<'a> {
             Self::Bool(_) => PrimType::Bool,
             Self::F32(_) => PrimType::F32,
             Self::F64(_) => PrimType::F64,
+            Self::Bytes(_) => PrimType::Bytes,
+            Self::Utf8(_) => PrimType::Utf8,
+            Self::F16(_) => PrimType::F16,
+            Self::I128(_) => PrimType::I128,
+            Self::U128(_) => PrimType::U128,
+            Self::C64(_) => PrimType::C64,
+            Self::C128(_) => PrimType::C128,
         }
     }
 
+    /// Creates a view of `buf` with the passed in shape and type
+    ///
+    /// Variable length types are always scalars, and `buf` is the value itself rather than the
+    /// [`UmbraBuf`] that points to it.
     pub fn try_from_bytes_shape(
         buf: &'a [u8],
         shape: &'a [usize],
@@ -386,6 +481,30 @@ impl<'a> ComponentView<'a> {
                 let (buf, _) = <[f64]>::ref_from_prefix_with_elems(buf, len)?;
                 Ok(Self::F64(ArrayView::from_buf_shape_unchecked(buf, shape)))
             }
+            PrimType::Bytes => Ok(Self::Bytes(buf)),
+            PrimType::Utf8 => core::str::from_utf8(buf)
+                .map(Self::Utf8)
+                .map_err(|_| Error::InvalidComponentData),
+            PrimType::F16 => {
+                let (buf, _) = <[F16]>::ref_from_prefix_with_elems(buf, len)?;
+                Ok(Self::F16(ArrayView::from_buf_shape_unchecked(buf, shape)))
+            }
+            PrimType::I128 => {
+                let (buf, _) = <[I128]>::ref_from_prefix_with_elems(buf, len)?;
+                Ok(Self::I128(ArrayView::from_buf_shape_unchecked(buf, shape)))
+            }
+            PrimType::U128 => {
+                let (buf, _) = <[U128]>::ref_from_prefix_with_elems(buf, len)?;
+                Ok(Self::U128(ArrayView::from_buf_shape_unchecked(buf, shape)))
+            }
+            PrimType::C64 => {
+                let (buf, _) = <[C64]>::ref_from_prefix_with_elems(buf, len)?;
+                Ok(Self::C64(ArrayView::from_buf_shape_unchecked(buf, shape)))
+            }
+            PrimType::C128 => {
+                let (buf, _) = <[C128]>::ref_from_prefix_with_elems(buf, len)?;
+                Ok(Self::C128(ArrayView::from_buf_shape_unchecked(buf, shape)))
+            }
         }
     }
     pub fn as_bytes(&self) -> &[u8] {
@@ -401,6 +520,13 @@ impl<'a> ComponentView<'a> {
             Self::Bool(ref view) => view.as_bytes(),
             Self::F32(ref view) => view.as_bytes(),
             Self::F64(ref view) => view.as_bytes(),
+            Self::Bytes(bytes) => bytes,
+            Self::Utf8(s) => s.as_bytes(),
+            Self::F16(ref view) => view.as_bytes(),
+            Self::I128(ref view) => view.as_bytes(),
+            Self::U128(ref view) => view.as_bytes(),
+            Self::C64(ref view) => view.as_bytes(),
+            Self::C128(ref view) => view.as_bytes(),
         }
     }
 
@@ -420,6 +546,20 @@ impl<'a> ComponentView<'a> {
             }
             ComponentView::F32(f32) => Box::new(f32.buf().iter().map(|&x| ElementValue::F32(x))),
             ComponentView::F64(f64) => Box::new(f64.buf().iter().map(|&x| ElementValue::F64(x))),
+            ComponentView::Bytes(_) | ComponentView::Utf8(_) => {
+                Box::new(self.as_bytes().iter().map(|&x| ElementValue::U8(x)))
+            }
+            ComponentView::F16(f16) => Box::new(f16.buf().iter().map(|&x| ElementValue::F16(x))),
+            ComponentView::I128(i128) => {
+                Box::new(i128.buf().iter().map(|&x| ElementValue::I128(x.get())))
+            }
+            ComponentView::U128(u128) => {
+                Box::new(u128.buf().iter().map(|&x| ElementValue::U128(x.get())))
+            }
+            ComponentView::C64(c64) => Box::new(c64.buf().iter().map(|&x| ElementValue::C64(x))),
+            ComponentView::C128(c128) => {
+                Box::new(c128.buf().iter().map(|&x| ElementValue::C128(x)))
+            }
         }
     }
 
@@ -436,6 +576,12 @@ impl<'a> ComponentView<'a> {
             Self::Bool(x) => x.buf().get(i).map(|&x| ElementValue::Bool(x)),
             Self::F32(x) => x.buf().get(i).map(|&x| ElementValue::F32(x)),
             Self::F64(x) => x.buf().get(i).map(|&x| ElementValue::F64(x)),
+            Self::Bytes(_) | Self::Utf8(_) => self.as_bytes().get(i).map(|&x| ElementValue::U8(x)),
+            Self::F16(x) => x.buf().get(i).map(|&x| ElementValue::F16(x)),
+            Self::I128(x) => x.buf().get(i).map(|&x| ElementValue::I128(x.get())),
+            Self::U128(x) => x.buf().get(i).map(|&x| ElementValue::U128(x.get())),
+            Self::C64(x) => x.buf().get(i).map(|&x| ElementValue::C64(x)),
+            Self::C128(x) => x.buf().get(i).map(|&x| ElementValue::C128(x)),
         }
     }
 }
@@ -453,9 +599,15 @@ pub enum ElementValue {
     F64(f64),
     F32(f32),
     Bool(bool),
+    F16(F16),
+    I128(i128),
+    U128(u128),
+    C64(C64),
+    C128(C128),
 }
 
 impl ElementValue {
+    /// Converts the value to a f64, complex values are converted using their real part
     pub fn as_f64(&self) -> f64 {
         match *self {
             ElementValue::U8(x) => x as f64,
@@ -475,9 +627,15 @@ impl ElementValue {
                     0.0
                 }
             }
+            ElementValue::F16(x) => x.to_f32() as f64,
+            ElementValue::I128(x) => x as f64,
+            ElementValue::U128(x) => x as f64,
+            ElementValue::C64(x) => x.re as f64,
+            ElementValue::C128(x) => x.re,
         }
     }
 
+    /// Converts the value to a f32, complex values are converted using their real part
     pub fn as_f32(&self) -> f32 {
         match *self {
             ElementValue::U8(x) => x as f32,
@@ -497,9 +655,15 @@ impl ElementValue {
                     0.0
                 }
             }
+            ElementValue::F16(x) => x.to_f32(),
+            ElementValue::I128(x) => x as f32,
+            ElementValue::U128(x) => x as f32,
+            ElementValue::C64(x) => x.re,
+            ElementValue::C128(x) => x.re as f32,
         }
     }
 
+    /// Converts the value to a usize, complex values are converted using their real part
     pub fn as_usize(&self) -> usize {
         match *self {
             ElementValue::U8(x) => x as usize,
@@ -519,6 +683,11 @@ impl ElementValue {
                     0
                 }
             }
+            ElementValue::F16(x) => x.to_f32() as usize,
+            ElementValue::I128(x) => x as usize,
+            ElementValue::U128(x) => x as usize,
+            ElementValue::C64(x) => x.re as usize,
+            ElementValue::C128(x) => x.re as usize,
         }
     }
 }
@@ -532,6 +701,9 @@ pub enum PacketTy {
     Table = 1,
     TimeSeries = 2,
     MsgWithTimestamp = 3,
+    /// A compressed packet, the id holds the compression algorithm and the body holds the
+    /// uncompressed length followed by the compressed packet.
+    Compressed = 4,
 }
 
 pub type PacketId = [u8; 2];
@@ -578,10 +750,25 @@ impl<T: Serialize + postcard_schema::Schema> Msg for T {
     const ID: PacketId = const_fnv1a_hash::fnv1a_hash_str_16_xor(T::SCHEMA.name).to_le_bytes();
 }
 
+/// The first byte of the ids reserved for well-known messages
+pub const RESERVED_MSG_PREFIX: u8 = 224;
+
+/// The id of the well-known `NewConnection` message, which predates [`RESERVED_MSG_PREFIX`]
+const NEW_CONNECTION_MSG_ID: PacketId = [225, 1];
+
+/// Returns true if the id is reserved for well-known messages
+///
+/// Ids derived from a message's name can still land here, they're kept as is so the wire id of
+/// an existing message never changes, and a db only refuses them for new message logs.
+pub const fn is_reserved_msg_id(id: PacketId) -> bool {
+    id[0] == RESERVED_MSG_PREFIX
+        || (id[0] == NEW_CONNECTION_MSG_ID[0] && id[1] == NEW_CONNECTION_MSG_ID[1])
+}
+
 pub const fn msg_id(name: &str) -> PacketId {
     let bytes = const_fnv1a_hash::fnv1a_hash_str_16_xor(name).to_le_bytes();
-    if bytes[0] == 224 {
-        [223, bytes[1]]
+    if bytes[0] == RESERVED_MSG_PREFIX {
+        [RESERVED_MSG_PREFIX - 1, bytes[1]]
     } else {
         bytes
     }
@@ -799,6 +986,8 @@ impl<B: IoBuf> OwnedPacket<B> {
                     len,
                 })
             }
+            // compressed packets must be decompressed by the transport before parsing
+            PacketTy::Compressed => return Err(Error::InvalidPacket),
         })
     }
     pub fn parse(packet_buf: B) -> Result<Self, Error> {
@@ -912,23 +1101,59 @@ impl<B: IoBuf> OwnedPacket<B> {
 #[repr(transparent)]
 pub struct Timestamp(pub i64);
 
+/// Converts a UTC timestamp into an epoch, see [`Timestamp::to_epoch`] for other clock domains
 #[cfg(feature = "hifitime")]
 impl From<Timestamp> for hifitime::Epoch {
     fn from(val: Timestamp) -> Self {
-        hifitime::Epoch::from_unix_milliseconds((val.0 as f64) / 1000.0)
+        ClockDomain::Utc
+            .epoch_from_nanos(val.as_nanos())
+            .expect("utc is absolute")
     }
 }
 
 #[cfg(feature = "hifitime")]
 impl From<hifitime::Epoch> for Timestamp {
     fn from(epoch: hifitime::Epoch) -> Self {
-        Timestamp((epoch.to_unix_milliseconds() * 1000.0) as i64)
+        Timestamp::from_epoch(epoch, ClockDomain::Utc).expect("utc is absolute")
     }
 }
 
 impl Timestamp {
     pub const EPOCH: Timestamp = Timestamp(0);
 
+    /// Returns the number of nanoseconds since the epoch
+    pub const fn as_nanos(self) -> i128 {
+        self.0 as i128 * 1000
+    }
+
+    /// Converts the timestamp into an epoch in the time scale of `domain`
+    ///
+    /// Returns `None` for clock domains without an absolute date.
+    #[cfg(feature = "hifitime")]
+    pub fn to_epoch(self, domain: ClockDomain) -> Option<hifitime::Epoch> {
+        domain.epoch_from_nanos(self.as_nanos())
+    }
+
+    /// Converts an epoch into a timestamp in `domain`, rounding it down to the previous whole
+    /// microsecond like `From<TimestampNs>`
+    ///
+    /// Returns `None` for clock domains without an absolute date.
+    #[cfg(feature = "hifitime")]
+    pub fn from_epoch(epoch: hifitime::Epoch, domain: ClockDomain) -> Option<Self> {
+        let nanos = domain.nanos_from_epoch(epoch)?;
+        Some(Timestamp(nanos.div_euclid(1000) as i64))
+    }
+
+    /// Formats the timestamp as a date in the time scale of `domain`, or as an offset from the
+    /// start of the clock for domains without an absolute date
+    #[cfg(feature = "hifitime")]
+    pub fn display(self, domain: ClockDomain) -> TimestampDisplay {
+        TimestampDisplay {
+            nanos: self.as_nanos(),
+            domain,
+        }
+    }
+
     #[cfg(feature = "std")]
     pub fn now() -> Self {
         std::time::SystemTime::now().into()
@@ -1010,6 +1235,253 @@ impl From<std::time::SystemTime> for Timestamp {
     }
 }
 
+/// A timestamp with nanosecond resolution, for sources that can measure time more precisely
+/// than a [`Timestamp`]
+///
+/// It covers the years 1678 through 2261, and otherwise follows the same rules as a [`Timestamp`].
+/// Only messages like clock synchronization carry nanoseconds, the db stores, queries and
+/// plots component values with microsecond [`Timestamp`]s.
+#[derive(
+    Copy,
+    Clone,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    IntoBytes,
+    Immutable,
+    FromBytes,
+    KnownLayout,
+    Serialize,
+    Deserialize,
+    Default,
+    postcard_schema::Schema,
+)]
+#[repr(transparent)]
+pub struct TimestampNs(pub i64);
+
+impl TimestampNs {
+    pub const EPOCH: TimestampNs = TimestampNs(0);
+
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        std::time::SystemTime::now().into()
+    }
+
+    pub const fn as_nanos(self) -> i128 {
+        self.0 as i128
+    }
+
+    /// See [`Timestamp::to_epoch`]
+    #[cfg(feature = "hifitime")]
+    pub fn to_epoch(self, domain: ClockDomain) -> Option<hifitime::Epoch> {
+        domain.epoch_from_nanos(self.as_nanos())
+    }
+
+    /// See [`Timestamp::from_epoch`], the epoch is truncated to whole nanoseconds instead
+    #[cfg(feature = "hifitime")]
+    pub fn from_epoch(epoch: hifitime::Epoch, domain: ClockDomain) -> Option<Self> {
+        let nanos = domain.nanos_from_epoch(epoch)?;
+        Some(TimestampNs(nanos as i64))
+    }
+
+    /// See [`Timestamp::display`]
+    #[cfg(feature = "hifitime")]
+    pub fn display(self, domain: ClockDomain) -> TimestampDisplay {
+        TimestampDisplay {
+            nanos: self.as_nanos(),
+            domain,
+        }
+    }
+}
+
+impl From<Timestamp> for TimestampNs {
+    fn from(value: Timestamp) -> Self {
+        TimestampNs(value.0.saturating_mul(1000))
+    }
+}
+
+/// Rounds down to the previous whole microsecond
+impl From<TimestampNs> for Timestamp {
+    fn from(value: TimestampNs) -> Self {
+        Timestamp(value.0.div_euclid(1000))
+    }
+}
+
+impl Add<Duration> for TimestampNs {
+    type Output = TimestampNs;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        TimestampNs(self.0 + rhs.as_nanos() as i64)
+    }
+}
+
+impl Sub<Duration> for TimestampNs {
+    type Output = TimestampNs;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        TimestampNs(self.0 - rhs.as_nanos() as i64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::time::SystemTime> for TimestampNs {
+    fn from(value: std::time::SystemTime) -> Self {
+        match value.duration_since(std::time::SystemTime::UNIX_EPOCH) {
+            Ok(dur) => Self(dur.as_nanos() as i64),
+            Err(err) => Self(-(err.duration().as_nanos() as i64)),
+        }
+    }
+}
+
+#[cfg(feature = "hifitime")]
+impl From<TimestampNs> for hifitime::Epoch {
+    fn from(val: TimestampNs) -> Self {
+        ClockDomain::Utc
+            .epoch_from_nanos(val.as_nanos())
+            .expect("utc is absolute")
+    }
+}
+
+#[cfg(feature = "hifitime")]
+impl From<hifitime::Epoch> for TimestampNs {
+    fn from(epoch: hifitime::Epoch) -> Self {
+        TimestampNs::from_epoch(epoch, ClockDomain::Utc).expect("utc is absolute")
+    }
+}
+
+/// The clock a timestamp was read from
+///
+/// Timestamps count from 1970-01-01T00:00:00 in the time scale of their clock, so UTC timestamps
+/// are Unix time, while TAI and GPS timestamps run ahead of them by the leap seconds their scales
+/// don't skip. Sim and monotonic timestamps count from an arbitrary start, like the start of a
+/// simulation or the boot of a flight computer, and have no absolute date.
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    postcard_schema::Schema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClockDomain {
+    /// Wall clock time, usually from NTP or the system clock
+    #[default]
+    Utc,
+    /// International Atomic Time
+    Tai,
+    /// GPS time, which is always 19 seconds behind TAI
+    Gps,
+    /// Simulation time
+    Sim,
+    /// A monotonic clock, like the time since boot
+    Monotonic,
+}
+
+impl ClockDomain {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ClockDomain::Utc => "utc",
+            ClockDomain::Tai => "tai",
+            ClockDomain::Gps => "gps",
+            ClockDomain::Sim => "sim",
+            ClockDomain::Monotonic => "monotonic",
+        }
+    }
+
+    /// Whether timestamps in the domain refer to a date
+    pub const fn is_absolute(&self) -> bool {
+        matches!(self, ClockDomain::Utc | ClockDomain::Tai | ClockDomain::Gps)
+    }
+
+    /// The time scale of the domain, `None` for domains without an absolute date
+    #[cfg(feature = "hifitime")]
+    pub const fn time_scale(&self) -> Option<hifitime::TimeScale> {
+        match self {
+            ClockDomain::Utc => Some(hifitime::TimeScale::UTC),
+            ClockDomain::Tai => Some(hifitime::TimeScale::TAI),
+            ClockDomain::Gps => Some(hifitime::TimeScale::GPST),
+            ClockDomain::Sim | ClockDomain::Monotonic => None,
+        }
+    }
+
+    // unix time skips leap seconds, so utc goes through hifitime's unix conversions rather than
+    // counting the elapsed time since 1970
+    #[cfg(feature = "hifitime")]
+    fn epoch_from_nanos(&self, nanos: i128) -> Option<hifitime::Epoch> {
+        let duration = hifitime::Duration::from_total_nanoseconds(nanos);
+        match self {
+            ClockDomain::Utc => Some(hifitime::Epoch::from_unix_duration(duration)),
+            domain => {
+                let start =
+                    hifitime::Epoch::from_gregorian_at_midnight(1970, 1, 1, domain.time_scale()?);
+                Some(start + duration)
+            }
+        }
+    }
+
+    #[cfg(feature = "hifitime")]
+    fn nanos_from_epoch(&self, epoch: hifitime::Epoch) -> Option<i128> {
+        match self {
+            ClockDomain::Utc => Some(epoch.to_unix_duration().total_nanoseconds()),
+            domain => {
+                let start =
+                    hifitime::Epoch::from_gregorian_at_midnight(1970, 1, 1, domain.time_scale()?);
+                Some((epoch - start).total_nanoseconds())
+            }
+        }
+    }
+}
+
+impl Display for ClockDomain {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::str::FromStr for ClockDomain {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utc" => Ok(ClockDomain::Utc),
+            "tai" => Ok(ClockDomain::Tai),
+            "gps" => Ok(ClockDomain::Gps),
+            "sim" => Ok(ClockDomain::Sim),
+            "monotonic" => Ok(ClockDomain::Monotonic),
+            _ => Err(Error::UnknownClockDomain),
+        }
+    }
+}
+
+/// Formats a timestamp in its clock domain, see [`Timestamp::display`]
+#[cfg(feature = "hifitime")]
+pub struct TimestampDisplay {
+    nanos: i128,
+    domain: ClockDomain,
+}
+
+#[cfg(feature = "hifitime")]
+impl Display for TimestampDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.domain.epoch_from_nanos(self.nanos) {
+            Some(epoch) => write!(f, "{epoch}"),
+            None => {
+                let sign = if self.nanos < 0 { '-' } else { '+' };
+                let offset = hifitime::Duration::from_total_nanoseconds(self.nanos.abs());
+                write!(f, "T{sign}{offset}")
+            }
+        }
+    }
+}
+
 pub trait Request {
     type Reply<B: IoBuf + Clone>: TryFromPacket<B>;
 }
@@ -1079,4 +1551,71 @@ mod tests {
         assert_eq!(PrimType::U16.padding(12), 0);
         assert_eq!(PrimType::U16.padding(11), 1);
     }
+
+    #[test]
+    fn test_timestamp_ns() {
+        let ts = Timestamp(1_700_000_000_123_456);
+        assert_eq!(
+            TimestampNs::from(ts),
+            TimestampNs(1_700_000_000_123_456_000)
+        );
+        assert_eq!(Timestamp::from(TimestampNs(1_999)), Timestamp(1));
+        assert_eq!(Timestamp::from(TimestampNs(-1)), Timestamp(-1));
+        assert_eq!("gps".parse::<ClockDomain>().unwrap(), ClockDomain::Gps);
+        assert!("unix".parse::<ClockDomain>().is_err());
+    }
+
+    #[test]
+    fn test_derived_msg_ids_are_stable() {
+        #[derive(Serialize, postcard_schema::Schema)]
+        struct Telemetry233;
+
+        // both names hash into the reserved ids, which only moves `msg_id`'s 224 prefix as it
+        // always has, so the wire id of existing messages stays the same
+        assert_eq!(Telemetry233::ID, [224, 70]);
+        assert_eq!(msg_id("Telemetry233"), [223, 70]);
+        assert_eq!(msg_id("Telemetry26115"), [225, 1]);
+        assert_eq!(msg_id("Stream"), [7, 66]);
+        assert!(is_reserved_msg_id([224, 37]));
+        assert!(is_reserved_msg_id([225, 1]));
+        assert!(!is_reserved_msg_id([225, 2]));
+    }
+
+    #[cfg(feature = "hifitime")]
+    #[test]
+    fn test_epoch_conversions() {
+        let ts = TimestampNs(1_700_000_000_123_456_789);
+        let epoch = hifitime::Epoch::from(ts);
+        assert_eq!(TimestampNs::from(epoch), ts);
+        let ts = Timestamp(1_700_000_000_123_457);
+        let epoch = hifitime::Epoch::from(ts);
+        assert_eq!(Timestamp::from(epoch), ts);
+
+        // both scales skip no leap seconds, so GPS stays 19 seconds behind TAI
+        let tai = Timestamp::from_epoch(epoch, ClockDomain::Tai).unwrap();
+        assert!(tai.0 > ts.0);
+        assert_eq!(tai.to_epoch(ClockDomain::Tai), Some(epoch));
+        let gps = Timestamp::from_epoch(epoch, ClockDomain::Gps).unwrap();
+        assert_eq!(tai.0 - gps.0, 19_000_000);
+
+        assert_eq!(Timestamp::from_epoch(epoch, ClockDomain::Sim), None);
+        assert!(
+            Timestamp(90_000_000)
+                .display(ClockDomain::Sim)
+                .to_string()
+                .starts_with("T+")
+        );
+        assert!(
+            Timestamp(-1)
+                .display(ClockDomain::Monotonic)
+                .to_string()
+                .starts_with("T-")
+        );
+
+        // epochs before 1970 round down like timestamps do
+        let ts = TimestampNs(-1_500);
+        let epoch = hifitime::Epoch::from(ts);
+        assert_eq!(Timestamp::from(epoch), Timestamp(-2));
+        assert_eq!(Timestamp::from(epoch), Timestamp::from(ts));
+    }
 }
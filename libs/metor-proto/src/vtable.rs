@@ -52,7 +52,7 @@ use serde::{Deserialize, Serialize};
 use zerocopy::{FromBytes, IntoBytes, TryFromBytes};
 
 use crate::{
-    buf::Buf,
+    buf::{Buf, UmbraBuf},
     com_de::Decomponentize,
     error::Error,
     types::{ComponentId, ComponentView, PacketId, PrimType, Timestamp},
@@ -104,6 +104,19 @@ pub struct Field {
     pub arg: OpRef,
 }
 
+impl Op {
+    /// Returns the ops referenced by this op
+    fn op_refs(&self) -> [Option<OpRef>; 3] {
+        match *self {
+            Op::Data { .. } | Op::Table { .. } | Op::None => [None; 3],
+            Op::Component { component_id } => [Some(component_id), None, None],
+            Op::Schema { ty, dim, arg } => [Some(ty), Some(dim), Some(arg)],
+            Op::Timestamp { source, arg } => [Some(source), Some(arg), None],
+            Op::Ext { arg, data, .. } => [Some(arg), Some(data), None],
+        }
+    }
+}
+
 const _ASSERT_OP_SIZE: () = const {
     assert!(core::mem::size_of::<Op>() <= 64);
 };
@@ -142,6 +155,17 @@ impl Offset {
     }
 }
 
+/// The longest table a [`VTable`] can describe
+pub const MAX_TABLE_LEN: usize = 16 * 1024 * 1024;
+
+/// Returns the end of the range starting at `offset`
+fn range_end(offset: Offset, len: u32) -> Result<usize, Error> {
+    offset
+        .to_index()
+        .checked_add(len as usize)
+        .ok_or(Error::OffsetOverflow)
+}
+
 #[cfg(feature = "alloc")]
 type DefaultOps = alloc::vec::Vec<Op>;
 
@@ -293,12 +317,12 @@ impl<Ops: Buf<Op>, Data: Buf<u8>, Fields: Buf<Field>> VTable<Ops, Data, Fields>
             Op::Data { offset, len } => {
                 let data = self.data.as_slice();
                 let data = data
-                    .get(offset.to_index()..offset.to_index() + *len as usize)
+                    .get(offset.to_index()..range_end(*offset, *len)?)
                     .ok_or(Error::BufferOverflow)?;
                 Ok(RealizedOp::Data(data))
             }
             Op::Table { offset, len } => {
-                let range = offset.to_index()..offset.to_index() + *len as usize;
+                let range = offset.to_index()..range_end(*offset, *len)?;
                 let table = if let Some(table) = table {
                     Some(table.get(range.clone()).ok_or(Error::BufferUnderflow)?)
                 } else {
@@ -356,6 +380,90 @@ impl<Ops: Buf<Op>, Data: Buf<u8>, Fields: Buf<Field>> VTable<Ops, Data, Fields>
         }
     }
 
+    /// Returns the length of the tables described by this vtable, the end of its furthest field
+    /// or table op
+    pub fn table_len(&self) -> Result<usize, Error> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| range_end(field.offset, field.len));
+        let tables = self.ops.iter().filter_map(|op| match op {
+            Op::Table { offset, len } => Some(range_end(*offset, *len)),
+            _ => None,
+        });
+        fields
+            .chain(tables)
+            .try_fold(0, |table_len, end| Ok(table_len.max(end?)))
+    }
+
+    /// Checks that a vtable is well formed, this should be called on every vtable received from a peer
+    /// before it is realized
+    ///
+    /// Ops may only reference the ops before them, which is the order the builder emits them in, so a
+    /// vtable can't contain cycles. Data ops must be within the vtable's data, tables can be at most
+    /// [`MAX_TABLE_LEN`] long, and each field must be aligned to its [`PrimType`] and as long as
+    /// its shape. Variable length fields must be scalars.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (i, op) in self.ops.iter().enumerate() {
+            for op_ref in op.op_refs().into_iter().flatten() {
+                if op_ref.to_index() >= i {
+                    return Err(Error::CyclicVTable);
+                }
+            }
+            let Op::Data { offset, len } = op else {
+                continue;
+            };
+            if range_end(*offset, *len)? > self.data.as_slice().len() {
+                return Err(Error::BufferOverflow);
+            }
+        }
+        if self.table_len()? > MAX_TABLE_LEN {
+            return Err(Error::TableTooLarge);
+        }
+        for field in self.fields.iter() {
+            self.validate_field(field)?;
+        }
+        Ok(())
+    }
+
+    fn validate_field(&self, field: &Field) -> Result<(), Error> {
+        let mut op_ref = field.arg;
+        // every step moves to an earlier op, so this always terminates
+        loop {
+            match self.realize(op_ref, None)? {
+                RealizedOp::Component(_) => return Ok(()),
+                RealizedOp::Schema(schema) => {
+                    if field.offset.to_index() % schema.ty.alignment() != 0 {
+                        return Err(Error::Alignment);
+                    }
+                    if schema.ty.is_var_len() && !schema.dim.is_empty() {
+                        return Err(Error::InvalidOp);
+                    }
+                    let len = schema
+                        .dim
+                        .iter()
+                        .try_fold(schema.ty.size() as u64, |len, dim| len.checked_mul(*dim))
+                        .ok_or(Error::OffsetOverflow)?;
+                    if len != field.len as u64 {
+                        return Err(Error::InvalidComponentData);
+                    }
+                    op_ref = schema.arg;
+                }
+                RealizedOp::Timestamp(timestamp) => {
+                    if timestamp
+                        .range
+                        .is_some_and(|range| range.len() != size_of::<Timestamp>())
+                    {
+                        return Err(Error::InvalidOp);
+                    }
+                    op_ref = timestamp.arg;
+                }
+                RealizedOp::Ext(ext) => op_ref = ext.arg,
+                _ => return Err(Error::InvalidOp),
+            }
+        }
+    }
+
     /// Evaluated each `field`, returning a `RealizedField`
     ///
     /// `realized_fields` loops through each field, turning each [`Offset`] into a reference, and evaluating any [`Op`]
@@ -398,6 +506,11 @@ impl<Ops: Buf<Op>, Data: Buf<u8>, Fields: Buf<Field>> VTable<Ops, Data, Fields>
                                         field.len
                                     )
                                 })?;
+                            let data = if schema.ty.is_var_len() {
+                                UmbraBuf::resolve(data, table).ok_or(Error::BufferUnderflow)?
+                            } else {
+                                data
+                            };
                             Some(ComponentView::try_from_bytes_shape(data, shape, schema.ty)?)
                         } else {
                             None
@@ -509,6 +622,14 @@ pub mod builder {
                 arg: self.arg,
             }
         }
+
+        /// Timestamps the field with the value read from `source`, see [`timestamp`]
+        pub fn with_timestamp(self, source: Arc<OpBuilder>) -> Self {
+            Self {
+                arg: timestamp(source, self.arg),
+                ..self
+            }
+        }
     }
 
     /// Creates a data operation builder from the provided data
@@ -797,4 +918,130 @@ mod tests {
         assert_eq!(bar.buf.as_buf(), &[5.0]);
         assert_eq!(sink.timestamp, Some(foo.timestamp));
     }
+
+    #[test]
+    fn test_validate() {
+        use super::builder::*;
+        use super::*;
+        use crate::error::Error;
+
+        let time = raw_table(0, 8);
+        let mut v = vtable([
+            raw_field(
+                8,
+                16,
+                schema(PrimType::F64, &[2], timestamp(time, component("a"))),
+            ),
+            raw_field(24, 4, schema(PrimType::F32, &[], component("b"))),
+        ]);
+        v.validate().unwrap();
+        assert_eq!(v.table_len().unwrap(), 28);
+
+        let mut misaligned = v.clone();
+        misaligned.fields[1].offset = Offset(26);
+        assert!(matches!(misaligned.validate(), Err(Error::Alignment)));
+
+        let mut wrong_len = v.clone();
+        wrong_len.fields[1].len = 8;
+        assert!(matches!(
+            wrong_len.validate(),
+            Err(Error::InvalidComponentData)
+        ));
+
+        let mut too_large = v.clone();
+        too_large.ops.push(Op::Table {
+            offset: Offset(0),
+            len: MAX_TABLE_LEN as u32 + 1,
+        });
+        assert!(matches!(too_large.validate(), Err(Error::TableTooLarge)));
+
+        let mut out_of_data = v.clone();
+        out_of_data.ops.push(Op::Data {
+            offset: Offset(u32::MAX),
+            len: 8,
+        });
+        assert!(matches!(out_of_data.validate(), Err(Error::BufferOverflow)));
+
+        let cyclic = OpRef(v.ops.len() as u32);
+        v.ops.push(Op::Timestamp {
+            source: cyclic,
+            arg: cyclic,
+        });
+        v.fields[0].arg = cyclic;
+        assert!(matches!(v.validate(), Err(Error::CyclicVTable)));
+    }
+
+    #[test]
+    fn test_var_len() {
+        use super::builder::*;
+        use crate::buf::UmbraBuf;
+
+        let v = vtable([
+            raw_field(0, 16, schema(PrimType::Utf8, &[], component("name"))),
+            raw_field(16, 16, schema(PrimType::Bytes, &[], component("raw"))),
+        ]);
+        v.validate().unwrap();
+
+        let name = "a name longer than twelve bytes";
+        let mut table = vec![];
+        table.extend_from_slice(UmbraBuf::new(name.as_bytes(), 32).as_bytes());
+        table.extend_from_slice(UmbraBuf::new(&[1, 2, 3], 0).as_bytes());
+        table.extend_from_slice(name.as_bytes());
+
+        let views = v
+            .realize_fields(Some(&table))
+            .map(|field| field.unwrap().view.unwrap())
+            .collect::<Vec<_>>();
+        assert!(matches!(views[0], ComponentView::Utf8(s) if s == name));
+        assert!(matches!(views[1], ComponentView::Bytes(&[1, 2, 3])));
+
+        let truncated = &table[..40];
+        assert!(
+            v.realize_fields(Some(truncated))
+                .any(|field| field.is_err())
+        );
+
+        let array = vtable([raw_field(
+            0,
+            32,
+            schema(PrimType::Utf8, &[2], component("names")),
+        )]);
+        assert!(matches!(
+            array.validate(),
+            Err(crate::error::Error::InvalidOp)
+        ));
+    }
+
+    #[test]
+    fn test_wide_prim_types() {
+        use super::builder::*;
+        use crate::num::{C64, F16, I128};
+
+        let v = vtable([
+            raw_field(0, 4, schema(PrimType::F16, &[2], component("half"))),
+            raw_field(8, 16, schema(PrimType::I128, &[], component("wide"))),
+            raw_field(24, 8, schema(PrimType::C64, &[], component("iq"))),
+        ]);
+        v.validate().unwrap();
+
+        let mut table = vec![];
+        table.extend_from_slice([F16::from_f32(1.5), F16::from_f32(-2.0)].as_bytes());
+        table.extend_from_slice(&[0; 4]);
+        table.extend_from_slice(I128::new(i128::MIN).as_bytes());
+        table.extend_from_slice(C64::new(1.0, -1.0).as_bytes());
+
+        let views = v
+            .realize_fields(Some(&table))
+            .map(|field| field.unwrap().view.unwrap())
+            .collect::<Vec<_>>();
+        let ComponentView::F16(half) = views[0] else {
+            panic!("expected f16 view");
+        };
+        assert_eq!(half.buf(), &[F16::from_f32(1.5), F16::from_f32(-2.0)]);
+        assert!(matches!(views[1], ComponentView::I128(view) if view.buf()[0].get() == i128::MIN));
+        assert!(
+            matches!(views[2], ComponentView::C64(view) if view.buf()[0] == C64::new(1.0, -1.0))
+        );
+        assert_eq!(views[2].get(0).unwrap().as_f64(), 1.0);
+    }
 }
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use divan::{Bencher, black_box, counter::BytesCount};
+use metor_proto::types::LenPacket;
+use metor_proto_stellar::Compression;
+
+fn main() {
+    divan::main();
+}
+
+/// The capture the benches compress, `METOR_STREAM_CAPTURE` or `benches/fixtures/stream.bin`
+///
+/// Captures are written by `Client:capture_stream` in the db's Lua REPL, e.g.
+/// `connect("localhost:2240"):capture_stream({ behavior = "RealTime" }, "stream.bin")` while a
+/// simulation is streaming into the db.
+fn capture_path() -> PathBuf {
+    std::env::var_os("METOR_STREAM_CAPTURE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/fixtures/stream.bin")
+        })
+}
+
+/// Splits a captured stream back into the length prefixed packets it was sent as
+fn captured_stream() -> Vec<LenPacket> {
+    let path = capture_path();
+    let buf = std::fs::read(&path).unwrap_or_else(|err| {
+        panic!(
+            "failed to read stream capture at {}: {err}, capture one with `Client:capture_stream`",
+            path.display()
+        )
+    });
+    let mut packets = vec![];
+    let mut rest = &buf[..];
+    while let Some((len, _)) = rest.split_first_chunk::<4>() {
+        let len = 4 + u32::from_le_bytes(*len) as usize;
+        assert!(len <= rest.len(), "truncated packet in stream capture");
+        let (pkt, tail) = rest.split_at(len);
+        packets.push(LenPacket {
+            inner: pkt.to_vec(),
+        });
+        rest = tail;
+    }
+    assert!(rest.is_empty(), "trailing bytes in stream capture");
+    packets
+}
+
+#[divan::bench(args = [Compression::Lz4, Compression::Zstd])]
+fn compress(bencher: Bencher, compression: Compression) {
+    let stream = captured_stream();
+    let bytes = stream.iter().map(|pkt| pkt.inner.len()).sum::<usize>();
+    let compressed = stream
+        .iter()
+        .map(|pkt| {
+            compression
+                .compress(&pkt.inner)
+                .map_or(pkt.inner.len(), |c| c.len())
+        })
+        .sum::<usize>();
+    println!(
+        "{compression:?}: {bytes} -> {compressed} bytes ({:.1}%)",
+        compressed as f64 / bytes as f64 * 100.0
+    );
+    bencher.counter(BytesCount::new(bytes)).bench(|| {
+        for pkt in &stream {
+            black_box(compression.compress(black_box(&pkt.inner)));
+        }
+    });
+}
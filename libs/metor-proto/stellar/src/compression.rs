@@ -0,0 +1,221 @@
+use metor_proto::types::{PACKET_HEADER_LEN, PacketTy};
+use metor_proto_wkt::Capabilities;
+use stellarator::buf::{IoBuf, IoBufMut, Slice};
+
+use crate::{Error, LEN_PREFIX};
+
+/// Packets smaller than this are always sent uncompressed, since the compression header
+/// would eat most of the savings.
+pub const MIN_COMPRESSED_LEN: usize = 128;
+
+/// The largest packet a compressed packet may claim to decompress to, so a corrupt or hostile
+/// length can't make the receiver allocate an arbitrary amount of memory
+pub const MAX_DECOMPRESSED_LEN: usize = 64 * 1024 * 1024;
+
+/// The per-packet compression used by a [`crate::PacketSink`]
+///
+/// A compressed packet has the type [`PacketTy::Compressed`], the algorithm in the first byte
+/// of its id, and a body of the uncompressed packet length followed by the compressed packet,
+/// header included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Compression {
+    #[default]
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl Compression {
+    /// Picks the best compression supported by both sides of a connection, preferring zstd
+    /// for its better ratio on low-bandwidth links
+    pub fn negotiate(capabilities: Capabilities) -> Self {
+        if capabilities.contains(Capabilities::COMPRESSION_ZSTD) {
+            Compression::Zstd
+        } else if capabilities.contains(Capabilities::COMPRESSION_LZ4) {
+            Compression::Lz4
+        } else {
+            Compression::None
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            1 => Ok(Compression::Lz4),
+            2 => Ok(Compression::Zstd),
+            _ => Err(Error::InvalidPacketType),
+        }
+    }
+
+    /// Compresses a length-prefixed packet, returning `None` if the packet should be sent
+    /// as is.
+    pub fn compress(&self, len_packet: &[u8]) -> Option<Vec<u8>> {
+        let packet = len_packet.get(LEN_PREFIX..)?;
+        if *self == Compression::None || packet.len() < MIN_COMPRESSED_LEN {
+            return None;
+        }
+        let req_id = packet[PACKET_HEADER_LEN - 1];
+        let mut out = Vec::with_capacity(packet.len() / 2);
+        out.extend_from_slice(&[0; LEN_PREFIX]);
+        out.extend_from_slice(&[PacketTy::Compressed as u8, *self as u8, 0, req_id]);
+        out.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        match self {
+            Compression::None => unreachable!(),
+            Compression::Lz4 => {
+                let header_len = out.len();
+                out.resize(
+                    header_len + lz4_flex::block::get_maximum_output_size(packet.len()),
+                    0,
+                );
+                let len = lz4_flex::block::compress_into(packet, &mut out[header_len..]).ok()?;
+                out.truncate(header_len + len);
+            }
+            Compression::Zstd => {
+                zstd::stream::copy_encode(packet, &mut out, zstd::DEFAULT_COMPRESSION_LEVEL)
+                    .ok()?;
+            }
+        }
+        if out.len() >= len_packet.len() {
+            return None;
+        }
+        let len = (out.len() - LEN_PREFIX) as u32;
+        out[..LEN_PREFIX].copy_from_slice(&len.to_le_bytes());
+        Some(out)
+    }
+}
+
+/// Returns true if the packet, without its length prefix, is compressed
+pub fn is_compressed(packet: &[u8]) -> bool {
+    packet.first() == Some(&(PacketTy::Compressed as u8))
+}
+
+/// Returns the uncompressed length of a compressed packet, without its length prefix
+///
+/// Fails if the length is larger than [`MAX_DECOMPRESSED_LEN`].
+pub fn decompressed_len(packet: &[u8]) -> Result<usize, Error> {
+    let len = packet
+        .get(PACKET_HEADER_LEN..PACKET_HEADER_LEN + LEN_PREFIX)
+        .ok_or(Error::InvalidPacketType)?;
+    let len = u32::from_le_bytes(len.try_into().expect("len wrong size")) as usize;
+    if len > MAX_DECOMPRESSED_LEN {
+        return Err(Error::Impeller(metor_proto::error::Error::BufferOverflow));
+    }
+    Ok(len)
+}
+
+/// Decompresses a compressed packet into `out`, returning the length of the packet
+pub fn decompress(packet: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let len = decompressed_len(packet)?;
+    let compression = Compression::from_id(packet[1])?;
+    let out = out
+        .get_mut(..len)
+        .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))?;
+    let body = &packet[PACKET_HEADER_LEN + LEN_PREFIX..];
+    let written = match compression {
+        Compression::None => unreachable!(),
+        Compression::Lz4 => {
+            lz4_flex::block::decompress_into(body, out).map_err(|_| Error::Decompress)?
+        }
+        Compression::Zstd => {
+            zstd::bulk::decompress_to_buffer(body, out).map_err(|_| Error::Decompress)?
+        }
+    };
+    if written != len {
+        return Err(Error::Decompress);
+    }
+    Ok(len)
+}
+
+/// Decompresses a received packet in place if it was compressed by the peer's
+/// [`crate::PacketSink`], keeping the length prefix in front of the packet like
+/// [`stellarator::io::LengthDelReader`] does.
+pub(crate) fn decompress_slice<B: IoBufMut>(
+    scratch: &mut Vec<u8>,
+    packet_buf: Slice<B>,
+    grow: impl FnOnce(&mut B, usize),
+) -> Result<Slice<B>, Error> {
+    if !is_compressed(&packet_buf) {
+        return Ok(packet_buf);
+    }
+    let len = decompressed_len(&packet_buf)?;
+    scratch.clear();
+    scratch.extend_from_slice(&packet_buf);
+    let mut buf = packet_buf.into_inner();
+    grow(&mut buf, len + LEN_PREFIX);
+    let out = stellarator::buf::deref_mut(&mut buf);
+    if out.len() < len + LEN_PREFIX {
+        return Err(Error::Impeller(metor_proto::error::Error::BufferOverflow));
+    }
+    decompress(scratch, &mut out[LEN_PREFIX..])?;
+    out[..LEN_PREFIX].copy_from_slice(&(len as u32).to_le_bytes());
+    buf.try_slice(LEN_PREFIX..LEN_PREFIX + len)
+        .ok_or(Error::Impeller(metor_proto::error::Error::BufferOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use metor_proto::types::LenPacket;
+
+    use super::*;
+
+    fn table_packet() -> LenPacket {
+        let mut pkt = LenPacket::table([1, 2], 1024).with_request_id(7);
+        let data = (0..512)
+            .map(|i| (i as f64 * 0.01).sin())
+            .collect::<Vec<_>>();
+        pkt.extend_aligned(&data);
+        pkt
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            let pkt = table_packet();
+            let compressed = compression.compress(&pkt.inner).unwrap();
+            assert!(compressed.len() < pkt.inner.len());
+            let packet = &compressed[LEN_PREFIX..];
+            assert!(is_compressed(packet));
+            assert_eq!(packet[PACKET_HEADER_LEN - 1], 7);
+            let mut out = vec![0; decompressed_len(packet).unwrap()];
+            let len = decompress(packet, &mut out).unwrap();
+            assert_eq!(&out[..len], &pkt.inner[LEN_PREFIX..]);
+        }
+    }
+
+    #[test]
+    fn test_oversized_decompressed_len() {
+        let pkt = table_packet();
+        let mut compressed = Compression::Lz4.compress(&pkt.inner).unwrap();
+        let len_range = LEN_PREFIX + PACKET_HEADER_LEN..LEN_PREFIX + PACKET_HEADER_LEN + LEN_PREFIX;
+        compressed[len_range].copy_from_slice(&u32::MAX.to_le_bytes());
+        let packet = &compressed[LEN_PREFIX..];
+        assert!(decompressed_len(packet).is_err());
+
+        let slice = compressed.try_slice(LEN_PREFIX..).unwrap();
+        let mut grown = 0;
+        let res = decompress_slice(&mut vec![], slice, |_: &mut Vec<u8>, len| grown = len);
+        assert!(res.is_err());
+        assert_eq!(grown, 0);
+    }
+
+    #[test]
+    fn test_small_packets_are_not_compressed() {
+        let mut pkt = LenPacket::table([1, 2], 8);
+        pkt.extend_aligned(&[1.0f64]);
+        assert_eq!(Compression::Zstd.compress(&pkt.inner), None);
+        assert_eq!(Compression::None.compress(&table_packet().inner), None);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            Compression::negotiate(Capabilities::COMPRESSION_LZ4 | Capabilities::COMPRESSION_ZSTD),
+            Compression::Zstd
+        );
+        assert_eq!(
+            Compression::negotiate(Capabilities::COMPRESSION_LZ4),
+            Compression::Lz4
+        );
+        assert_eq!(Compression::negotiate(Capabilities::SQL), Compression::None);
+    }
+}
@@ -2,12 +2,13 @@ use std::{
     marker::PhantomData,
     net::SocketAddr,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
 use metor_proto::types::{
     IntoLenPacket, LenPacket, Msg, OwnedPacket, Request, RequestId, TryFromPacket,
 };
-use metor_proto_wkt::ErrorResponse;
+use metor_proto_wkt::{Capabilities, ErrorResponse, Hello, PROTOCOL_VERSION, ProtocolVersion};
 use stellarator::{
     BufResult,
     buf::{IoBufMut, Slice},
@@ -15,11 +16,23 @@ use stellarator::{
     net::TcpStream,
 };
 
+mod compression;
 #[cfg(feature = "queue")]
 pub mod queue;
 
+pub use compression::{Compression, MIN_COMPRESSED_LEN};
+
+const LEN_PREFIX: usize = size_of::<u32>();
+
+/// How long [`Client`] waits for the server's [`Hello`] when no request timeout is set
+///
+/// A db from before the handshake never replies to a [`Hello`], so connecting to one fails with
+/// [`stellarator::Error::TimedOut`] after this long instead of hanging.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct PacketStream<R: AsyncRead> {
     reader: LengthDelReader<R>,
+    scratch: Vec<u8>,
 }
 
 impl<R: AsyncRead> PacketStream<R> {
@@ -28,11 +41,15 @@ impl<R: AsyncRead> PacketStream<R> {
         Self::from_reader(reader)
     }
     pub fn from_reader(reader: LengthDelReader<R>) -> Self {
-        Self { reader }
+        Self {
+            reader,
+            scratch: vec![],
+        }
     }
 
     pub async fn next<B: IoBufMut>(&mut self, buf: B) -> Result<OwnedPacket<Slice<B>>, Error> {
         let packet_buf = self.reader.recv(buf).await?;
+        let packet_buf = compression::decompress_slice(&mut self.scratch, packet_buf, |_, _| {})?;
         OwnedPacket::parse(packet_buf).map_err(Error::from)
     }
 
@@ -41,21 +58,40 @@ impl<R: AsyncRead> PacketStream<R> {
         buf: B,
     ) -> Result<OwnedPacket<Slice<B>>, Error> {
         let packet_buf = self.reader.recv_growable(buf).await?;
+        let packet_buf = compression::decompress_slice(&mut self.scratch, packet_buf, B::grow)?;
         OwnedPacket::parse(packet_buf).map_err(Error::from)
     }
 }
 
 pub struct PacketSink<W: AsyncWrite> {
     writer: W,
+    compression: Compression,
 }
 
 impl<W: AsyncWrite> PacketSink<W> {
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            compression: Compression::None,
+        }
+    }
+
+    /// Sets the compression used for outgoing packets, usually from the capabilities
+    /// negotiated with a [`Hello`]
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
     }
 
     pub async fn send(&self, packet: impl IntoLenPacket) -> BufResult<(), LenPacket> {
         let packet = packet.into_len_packet();
+        if let Some(compressed) = self.compression.compress(&packet.inner) {
+            let (res, _) = self.writer.write_all(compressed).await;
+            return (res, packet);
+        }
         let (res, inner) = self.writer.write_all(packet.inner).await;
         (res, LenPacket { inner })
     }
@@ -66,20 +102,69 @@ pub struct Client {
     pub tx: PacketSink<OwnedWriter<TcpStream>>,
     pub rx: PacketStream<OwnedReader<TcpStream>>,
     next_req_id: u8,
+    capabilities: Capabilities,
+    request_timeout: Option<Duration>,
+    poisoned: bool,
 }
 
 impl Client {
+    /// Connects to the server with [`Capabilities::DEFAULT`], use
+    /// [`Client::connect_with_capabilities`] to opt into compression
+    ///
+    /// Fails after [`HANDSHAKE_TIMEOUT`] if the server doesn't answer the [`Hello`].
     pub async fn connect(addr: SocketAddr) -> Result<Self, Error> {
+        Self::connect_with_capabilities(addr, Capabilities::DEFAULT).await
+    }
+
+    pub async fn connect_with_capabilities(
+        addr: SocketAddr,
+        capabilities: Capabilities,
+    ) -> Result<Self, Error> {
         let stream = TcpStream::connect(addr).await?;
+        Self::handshake(stream, capabilities, None).await
+    }
+
+    /// Connects to the server, failing with [`stellarator::Error::TimedOut`] if connecting or any
+    /// later request takes longer than `timeout`
+    pub async fn connect_timeout(addr: SocketAddr, timeout: Duration) -> Result<Self, Error> {
+        let stream = TcpStream::connect_timeout(addr, timeout).await?;
+        Self::handshake(stream, Capabilities::DEFAULT, Some(timeout)).await
+    }
+
+    async fn handshake(
+        stream: TcpStream,
+        capabilities: Capabilities,
+        request_timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
         let (rx, tx) = stream.split();
         let tx = PacketSink::new(tx);
         let rx = PacketStream::new(rx);
-        Ok(Client {
+        let mut client = Client {
             tx,
             rx,
             next_req_id: 0,
             resp_buf: Some(vec![0u8; 256]),
-        })
+            capabilities: Capabilities::NONE,
+            request_timeout,
+            poisoned: false,
+        };
+        let timeout = request_timeout.unwrap_or(HANDSHAKE_TIMEOUT);
+        let hello = client
+            .request_with_timeout(&Hello::new(capabilities), timeout)
+            .await?;
+        if !PROTOCOL_VERSION.is_compatible(&hello.version) {
+            return Err(Error::IncompatibleVersion(hello.version));
+        }
+        client.capabilities = hello.capabilities & capabilities;
+        client
+            .tx
+            .set_compression(Compression::negotiate(client.capabilities));
+        Ok(client)
+    }
+
+    /// The capabilities negotiated with the server
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
     }
 
     pub async fn send(&mut self, packet: impl IntoLenPacket) -> BufResult<(), LenPacket> {
@@ -87,19 +172,70 @@ impl Client {
         self.tx.send(len_pkt).await
     }
 
+    /// The deadline applied to each [`Client::request`], if any
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Sets the deadline applied to each [`Client::request`]
+    ///
+    /// A request that times out may leave a partially read reply on the connection, so every
+    /// later request fails with [`Error::Poisoned`] and the client has to be reconnected.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
     pub async fn request<R: Request + IntoLenPacket>(
         &mut self,
         req: R,
     ) -> Result<R::Reply<Slice<Vec<u8>>>, Error> {
-        let req_id = self.next_req_id.wrapping_add(1);
+        match self.request_timeout {
+            Some(timeout) => self.request_with_timeout(req, timeout).await,
+            None => self.request_inner(req).await,
+        }
+    }
+
+    /// Sends a request, failing with [`stellarator::Error::TimedOut`] if the reply doesn't arrive
+    /// within `timeout`
+    ///
+    /// The client is poisoned after a timeout, see [`Client::set_request_timeout`].
+    pub async fn request_with_timeout<R: Request + IntoLenPacket>(
+        &mut self,
+        req: R,
+        timeout: Duration,
+    ) -> Result<R::Reply<Slice<Vec<u8>>>, Error> {
+        let res = stellarator::time::timeout(timeout, self.request_inner(req)).await;
+        if res.is_err() {
+            self.poisoned = true;
+        }
+        res.map_err(stellarator::Error::from)?
+    }
+
+    async fn request_inner<R: Request + IntoLenPacket>(
+        &mut self,
+        req: R,
+    ) -> Result<R::Reply<Slice<Vec<u8>>>, Error> {
+        let req_id = self.next_req_id()?;
         self.send(req.with_request_id(req_id)).await.0?;
         self.recv(req_id).await
     }
 
+    /// Picks the id for the next request, skipping 0 as that's the id of packets sent without one
+    fn next_req_id(&mut self) -> Result<RequestId, Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+        self.next_req_id = self.next_req_id.checked_add(1).unwrap_or(1);
+        Ok(self.next_req_id)
+    }
+
     pub async fn recv<O: TryFromPacket<Slice<Vec<u8>>>>(
         &mut self,
         req_id: RequestId,
     ) -> Result<O, Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
         loop {
             let buf = self.resp_buf.take().unwrap_or(vec![0u8; 256]);
             let pkt = self.rx.next_grow(buf).await?;
@@ -127,7 +263,7 @@ impl Client {
         &mut self,
         req: R,
     ) -> Result<SubStream<'_, R::Reply<Slice<Vec<u8>>>>, Error> {
-        let req_id = self.next_req_id.wrapping_add(1);
+        let req_id = self.next_req_id()?;
         self.send(req.with_request_id(req_id)).await.0?;
         Ok(SubStream {
             req_id,
@@ -181,6 +317,12 @@ pub enum Error {
     RxHandleClosed,
     #[error("{0}")]
     Response(ErrorResponse),
+    #[error("failed to decompress packet")]
+    Decompress,
+    #[error("incompatible protocol version {0}, expected {}", PROTOCOL_VERSION)]
+    IncompatibleVersion(ProtocolVersion),
+    #[error("a request timed out mid reply, the client has to be reconnected")]
+    Poisoned,
 }
 
 #[cfg(test)]
@@ -2,7 +2,7 @@ use bbq2::{queue::ArcBBQueue, traits::storage::BoxedSlice};
 use futures_concurrency::future::Race;
 use metor_proto::types::{LenPacket, Msg};
 use metor_proto_bbq::*;
-use metor_proto_wkt::{NewConnection, StreamId};
+use metor_proto_wkt::{Hello, NewConnection, StreamId};
 use miette::{IntoDiagnostic, miette};
 use std::net::SocketAddr;
 use stellarator::{
@@ -11,7 +11,7 @@ use stellarator::{
 };
 use thingbuf::mpsc;
 
-use crate::PacketStream;
+use crate::{PacketStream, compression};
 
 pub async fn tcp_connect<I>(
     addr: SocketAddr,
@@ -28,6 +28,7 @@ where
     let (rx, tx) = stream.split();
     let tx = crate::PacketSink::new(tx);
     let mut rx = LengthDelReader::<_, u32>::new(rx);
+    let mut scratch = vec![];
 
     let len_pkt = LenPacket::new(metor_proto::types::PacketTy::Msg, NewConnection::ID, 0);
     let grant = PacketGrantW::new(
@@ -37,6 +38,9 @@ where
     );
     grant.commit_len_pkt(len_pkt);
 
+    // compression is opt-in, so the default hello keeps the server sending uncompressed packets,
+    // though incoming packets are still decompressed below
+    tx.send(&Hello::default()).await.0?;
     for packet in new_connection_packets(stream_id) {
         tx.send(packet).await.0?;
     }
@@ -46,6 +50,8 @@ where
             let grant_r = incoming_packet_tx.wait_grant(512 * 1024).await;
             let grant_r = PacketGrantW::new(grant_r);
             let slice = rx.recv(grant_r).await.into_diagnostic()?;
+            let slice =
+                compression::decompress_slice(&mut scratch, slice, |_, _| {}).into_diagnostic()?;
             let len = slice.range().len();
             slice.into_inner().commit(len + 4);
         }
@@ -75,6 +81,10 @@ pub fn spawn_recv<R: AsyncRead + 'static>(
             let Ok(slice) = stream.reader.recv(grant_r).await else {
                 continue;
             };
+            let Ok(slice) = compression::decompress_slice(&mut stream.scratch, slice, |_, _| {})
+            else {
+                continue;
+            };
             let len = slice.range().len();
             slice.into_inner().commit(len + 4);
         }
@@ -3,7 +3,7 @@ use metor_proto::types::IntoLenPacket;
 use metor_proto::types::{Msg, PacketId};
 use postcard::experimental::max_size::MaxSize;
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 use stellarator::net::{TcpListener, TcpStream};
 
 #[derive(Serialize, Deserialize, MaxSize, PartialEq, Debug)]
@@ -34,3 +34,48 @@ async fn test_packet_echo() {
     let foo: Foo = m.parse().unwrap();
     assert_eq!(foo, Foo { bar: 0xBB });
 }
+
+#[stellarator::test]
+async fn test_request_timeout() {
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = listener.local_addr().unwrap();
+    stellarator::spawn(async move {
+        // accept the connection, but never reply to the handshake
+        let _stream = listener.accept().await.unwrap();
+        std::future::pending::<()>().await;
+    });
+    let res = Client::connect_timeout(addr, Duration::from_millis(50)).await;
+    assert!(matches!(
+        res,
+        Err(Error::Stellar(stellarator::Error::TimedOut))
+    ));
+}
+
+#[stellarator::test]
+async fn test_timeout_poisons_client() {
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = listener.local_addr().unwrap();
+    stellarator::spawn(async move {
+        let stream = listener.accept().await.unwrap();
+        let (rx, tx) = stream.split();
+        let mut rx = PacketStream::new(rx);
+        let tx = PacketSink::new(tx);
+        // answer the handshake, then swallow every later request
+        let hello = rx.next(vec![0; 256]).await.unwrap();
+        let reply = Hello::new(Capabilities::NONE).with_request_id(hello.req_id());
+        tx.send(reply).await.0.unwrap();
+        loop {
+            rx.next(vec![0; 256]).await.unwrap();
+        }
+    });
+    let mut client = Client::connect_timeout(addr, Duration::from_millis(50))
+        .await
+        .unwrap();
+    let res = client.request(&Hello::default()).await;
+    assert!(matches!(
+        res,
+        Err(Error::Stellar(stellarator::Error::TimedOut))
+    ));
+    let res = client.request(&Hello::default()).await;
+    assert!(matches!(res, Err(Error::Poisoned)));
+}
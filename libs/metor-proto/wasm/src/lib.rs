@@ -0,0 +1,299 @@
+//! WebAssembly bindings for talking to metor-db from a browser
+//!
+//! Browsers can't open TCP sockets, so dashboards connect to the WebSocket bridge served by
+//! `metor-db run --ws-addr`. Each binary WebSocket message carries exactly one packet, without
+//! the length prefix used over TCP.
+//!
+//! ```js
+//! import init, { Client } from "./metor_proto_wasm.js";
+//! await init();
+//! const client = new Client();
+//! const ws = new WebSocket("ws://localhost:2250");
+//! ws.binaryType = "arraybuffer";
+//! ws.onopen = () => ws.send(client.request("Stream", { behavior: "RealTime", id: 1n }));
+//! ws.onmessage = (e) => {
+//!   const event = client.receive(new Uint8Array(e.data));
+//!   if (event.kind === "table") console.log(event.fields);
+//! };
+//! ```
+
+use std::collections::HashMap;
+
+use js_sys::{
+    BigInt64Array, BigUint64Array, Float32Array, Float64Array, Int8Array, Int16Array, Int32Array,
+    Object, Reflect, Uint8Array, Uint16Array, Uint32Array,
+};
+use metor_proto::{
+    error::Error,
+    num::F16,
+    types::{ComponentId, IntoLenPacket, LenPacket, Msg, OwnedPacket, PacketId, PrimType},
+    vtable::{Field, Op, VTable},
+};
+use metor_proto_wkt::*;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+mod table;
+pub use table::*;
+
+const SERIALIZER: serde_wasm_bindgen::Serializer =
+    serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&SERIALIZER)
+        .map_err(|err| JsError::new(&err.to_string()))
+}
+
+fn set(obj: &Object, key: &str, value: impl Into<JsValue>) {
+    Reflect::set(obj, &key.into(), &value.into()).expect("setting a property on an object");
+}
+
+/// Strips the length prefix, returning the packet as it is sent over the bridge
+fn bridge_packet(packet: LenPacket) -> Vec<u8> {
+    let mut inner = packet.inner;
+    inner.drain(..size_of::<u32>());
+    inner
+}
+
+macro_rules! msgs {
+    ($($name:literal => $ty:ty),* $(,)?) => {
+        /// Decodes a well-known message, returning its name and value
+        fn decode_msg(id: PacketId, body: &[u8]) -> Result<Option<(&'static str, JsValue)>, JsError> {
+            $(
+                if id == <$ty>::ID {
+                    let msg: $ty = postcard::from_bytes(body).map_err(Error::from)?;
+                    return Ok(Some(($name, to_js(&msg)?)));
+                }
+            )*
+            Ok(None)
+        }
+    };
+}
+
+msgs! {
+    "VTableMsg" => VTableMsg,
+    "SchemaMsg" => SchemaMsg,
+    "ComponentMetadata" => ComponentMetadata,
+    "DumpMetadataResp" => DumpMetadataResp,
+    "LastUpdated" => LastUpdated,
+    "DbConfig" => DbConfig,
+    "EarliestTimestamp" => EarliestTimestamp,
+    "DumpSchemaResp" => DumpSchemaResp,
+    "StreamTimestamp" => StreamTimestamp,
+    "ArrowIPC" => ArrowIPC<'_>,
+    "ErrorResponse" => ErrorResponse,
+    "MsgMetadata" => MsgMetadata,
+    "MsgBatch" => MsgBatch,
+    "Hello" => Hello,
+}
+
+macro_rules! requests {
+    ($($name:literal => $ty:ty),* $(,)?) => {
+        /// The names of the messages accepted by [`Client::request`]
+        pub const REQUESTS: &[&str] = &[$($name),*];
+
+        fn encode_request(name: &str, value: JsValue) -> Result<LenPacket, JsError> {
+            match name {
+                $(
+                    $name => {
+                        let msg: $ty = serde_wasm_bindgen::from_value(value)
+                            .map_err(|err| JsError::new(&err.to_string()))?;
+                        Ok((&msg).into_len_packet())
+                    }
+                )*
+                _ => Err(JsError::new(&format!("unknown request {name}"))),
+            }
+        }
+    };
+}
+
+requests! {
+    "Stream" => Stream,
+    "VTableStream" => VTableStream,
+    "SetStreamState" => SetStreamState,
+    "GetTimeSeries" => GetTimeSeries,
+    "GetSchema" => GetSchema,
+    "GetComponentMetadata" => GetComponentMetadata,
+    "SetComponentMetadata" => SetComponentMetadata,
+    "DumpMetadata" => DumpMetadata,
+    "DumpSchema" => DumpSchema,
+    "SubscribeLastUpdated" => SubscribeLastUpdated,
+    "SetDbConfig" => SetDbConfig,
+    "GetDbSettings" => GetDbSettings,
+    "GetEarliestTimestamp" => GetEarliestTimestamp,
+    "SQLQuery" => SQLQuery,
+    "MsgStream" => MsgStream,
+    "FixedRateMsgStream" => FixedRateMsgStream,
+    "GetMsgs" => GetMsgs,
+    "VTableMsg" => VTableMsg,
+}
+
+/// Copies the values of a field into the typed array matching its type, `utf8` fields become a string
+///
+/// `f16` fields are widened into a `Float32Array`, 128-bit integers become an array of `BigInt`s,
+/// and complex fields interleave their real and imaginary parts.
+fn typed_array(ty: PrimType, data: &[u8]) -> JsValue {
+    match ty {
+        PrimType::U8 | PrimType::Bool => Uint8Array::from(data).into(),
+        PrimType::U16 => Uint16Array::from(&read_values(data, u16::from_le_bytes)[..]).into(),
+        PrimType::U32 => Uint32Array::from(&read_values(data, u32::from_le_bytes)[..]).into(),
+        PrimType::U64 => BigUint64Array::from(&read_values(data, u64::from_le_bytes)[..]).into(),
+        PrimType::I8 => Int8Array::from(&read_values(data, i8::from_le_bytes)[..]).into(),
+        PrimType::I16 => Int16Array::from(&read_values(data, i16::from_le_bytes)[..]).into(),
+        PrimType::I32 => Int32Array::from(&read_values(data, i32::from_le_bytes)[..]).into(),
+        PrimType::I64 => BigInt64Array::from(&read_values(data, i64::from_le_bytes)[..]).into(),
+        PrimType::F32 | PrimType::C64 => {
+            Float32Array::from(&read_values(data, f32::from_le_bytes)[..]).into()
+        }
+        PrimType::F64 | PrimType::C128 => {
+            Float64Array::from(&read_values(data, f64::from_le_bytes)[..]).into()
+        }
+        PrimType::F16 => {
+            let values = read_values(data, |bytes| {
+                F16::from_bits(u16::from_le_bytes(bytes)).to_f32()
+            });
+            Float32Array::from(&values[..]).into()
+        }
+        PrimType::I128 => read_values(data, i128::from_le_bytes)
+            .into_iter()
+            .map(JsValue::from)
+            .collect::<js_sys::Array>()
+            .into(),
+        PrimType::U128 => read_values(data, u128::from_le_bytes)
+            .into_iter()
+            .map(JsValue::from)
+            .collect::<js_sys::Array>()
+            .into(),
+        PrimType::Bytes => Uint8Array::from(data).into(),
+        PrimType::Utf8 => JsValue::from_str(&String::from_utf8_lossy(data)),
+    }
+}
+
+/// Returns the id of a component from its name
+#[wasm_bindgen(js_name = componentId)]
+pub fn component_id(name: &str) -> u64 {
+    ComponentId::new(name).0
+}
+
+/// A connection's state, the vtables the database has sent so far
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Client {
+    vtables: HashMap<PacketId, VTable<Vec<Op>, Vec<u8>, Vec<Field>>>,
+}
+
+#[wasm_bindgen]
+impl Client {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Client {
+        Client::default()
+    }
+
+    /// Decodes a packet received from the bridge into a plain object
+    ///
+    /// Every event has a `kind`, `id`, and `reqId`. Messages add the message `name` and
+    /// decoded `value`, tables add their `fields` each with a typed array of `values`, and
+    /// time series add `timestamps` and the raw `data`. A `VTableMsg` is also kept, so the
+    /// tables that follow it can be decoded.
+    pub fn receive(&mut self, packet: Vec<u8>) -> Result<JsValue, JsError> {
+        let packet = OwnedPacket::parse(packet)?;
+        let event = Object::new();
+        set(&event, "reqId", packet.req_id());
+        match &packet {
+            OwnedPacket::Msg(msg) => {
+                set(&event, "kind", "msg");
+                set(&event, "id", Uint8Array::from(&msg.id[..]));
+                if let Some(timestamp) = msg.timestamp {
+                    set(&event, "timestamp", timestamp.0);
+                }
+                if msg.id == VTableMsg::ID {
+                    let vtable = msg.parse::<VTableMsg>()?;
+                    self.vtables.insert(vtable.id, vtable.vtable);
+                }
+                match decode_msg(msg.id, &msg.buf)? {
+                    Some((name, value)) => {
+                        set(&event, "name", name);
+                        set(&event, "value", value);
+                    }
+                    None => set(&event, "data", Uint8Array::from(&msg.buf[..])),
+                }
+            }
+            OwnedPacket::Table(table) => {
+                set(&event, "kind", "table");
+                set(&event, "id", Uint8Array::from(&table.id[..]));
+                let vtable = self.vtables.get(&table.id).ok_or(Error::VTableNotFound)?;
+                let fields = js_sys::Array::new();
+                for field in decode_table(vtable, &table.buf)? {
+                    let obj = Object::new();
+                    set(&obj, "componentId", field.component_id.0);
+                    set(&obj, "ty", field.ty.as_str());
+                    let shape = field.shape.iter().map(|dim| JsValue::from(*dim as f64));
+                    set(&obj, "shape", shape.collect::<js_sys::Array>());
+                    if let Some(timestamp) = field.timestamp {
+                        set(&obj, "timestamp", timestamp.0);
+                    }
+                    set(&obj, "values", typed_array(field.ty, field.data));
+                    fields.push(&obj);
+                }
+                set(&event, "fields", fields);
+            }
+            OwnedPacket::TimeSeries(time_series) => {
+                set(&event, "kind", "time_series");
+                set(&event, "id", Uint8Array::from(&time_series.id[..]));
+                let timestamps = time_series_timestamps(&time_series.buf)?;
+                set(&event, "timestamps", BigInt64Array::from(&timestamps[..]));
+                set(&event, "data", Uint8Array::from(time_series.data()?));
+            }
+        }
+        Ok(event.into())
+    }
+
+    /// Encodes a request from a plain object, returning the packet to send over the bridge
+    ///
+    /// `name` is the name of the message, like `"Stream"` or `"GetTimeSeries"`, and `value`
+    /// is its serde representation. 64 bit integers are passed as `BigInt`s.
+    pub fn request(
+        &self,
+        name: &str,
+        value: JsValue,
+        req_id: Option<u8>,
+    ) -> Result<Vec<u8>, JsError> {
+        let packet = encode_request(name, value)?.with_request_id(req_id.unwrap_or_default());
+        Ok(bridge_packet(packet))
+    }
+
+    /// Encodes a SQL query, the reply is a series of `ArrowIPC` messages ending with an empty
+    /// batch
+    pub fn sql(&self, query: &str, req_id: Option<u8>) -> Vec<u8> {
+        let packet = (&SQLQuery(query.to_string())).with_request_id(req_id.unwrap_or_default());
+        bridge_packet(packet)
+    }
+
+    /// Encodes a table for a vtable previously sent with a `VTableMsg` request
+    pub fn table(&self, id: &[u8], data: &[u8]) -> Result<Vec<u8>, JsError> {
+        let id: PacketId = id
+            .try_into()
+            .map_err(|_| JsError::new("packet ids are two bytes"))?;
+        let mut packet = LenPacket::table(id, data.len());
+        packet.extend_from_slice(data);
+        Ok(bridge_packet(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_packet() {
+        let packet = bridge_packet((&VTableStream { id: [1, 2] }).into_len_packet());
+        assert_eq!(packet[0], metor_proto::types::PacketTy::Msg as u8);
+        assert_eq!(&packet[1..3], &VTableStream::ID);
+        let parsed = OwnedPacket::parse(packet).unwrap();
+        let OwnedPacket::Msg(msg) = parsed else {
+            panic!("expected msg");
+        };
+        assert_eq!(msg.parse::<VTableStream>().unwrap().id, [1, 2]);
+    }
+}
@@ -0,0 +1,158 @@
+use metor_proto::{
+    buf::UmbraBuf,
+    error::Error,
+    types::{ComponentId, PrimType, Timestamp},
+    vtable::{Field, Op, RealizedOp, VTable},
+};
+
+/// A field of a table, decoded without depending on the width of `usize`
+///
+/// [`VTable::realize_fields`] reads shapes as `usize`, which is 32 bits wide on
+/// `wasm32-unknown-unknown`, so this walks the ops itself and keeps the shape as `u64`.
+#[derive(Debug)]
+pub struct DecodedField<'a> {
+    pub component_id: ComponentId,
+    pub ty: PrimType,
+    pub shape: Vec<u64>,
+    pub timestamp: Option<Timestamp>,
+    /// The field's values, or the value itself for variable length types
+    pub data: &'a [u8],
+}
+
+/// Decodes every field of a table packet's body using its vtable
+pub fn decode_table<'a>(
+    vtable: &'a VTable<Vec<Op>, Vec<u8>, Vec<Field>>,
+    table: &'a [u8],
+) -> Result<Vec<DecodedField<'a>>, Error> {
+    vtable
+        .fields
+        .iter()
+        .map(|field| decode_field(vtable, field, table))
+        .collect()
+}
+
+fn decode_field<'a>(
+    vtable: &'a VTable<Vec<Op>, Vec<u8>, Vec<Field>>,
+    field: &Field,
+    table: &'a [u8],
+) -> Result<DecodedField<'a>, Error> {
+    let mut schema = None;
+    let mut timestamp = None;
+    let mut op_ref = field.arg;
+    // every op is visited at most once, so a cyclic vtable errors instead of looping forever
+    for _ in 0..=vtable.ops.len() {
+        match vtable.realize(op_ref, Some(table))? {
+            RealizedOp::Component(component) => {
+                let (ty, dim): (PrimType, &[u64]) = schema.ok_or(Error::SchemaNotFound)?;
+                let offset = field.offset.to_index();
+                let end = offset
+                    .checked_add(field.len as usize)
+                    .ok_or(Error::OffsetOverflow)?;
+                let data = table.get(offset..end).ok_or(Error::BufferUnderflow)?;
+                let len = dim
+                    .iter()
+                    .try_fold(ty.size() as u64, |len, d| len.checked_mul(*d));
+                if len != Some(data.len() as u64) {
+                    return Err(Error::InvalidComponentData);
+                }
+                let data = if ty.is_var_len() {
+                    UmbraBuf::resolve(data, table).ok_or(Error::BufferUnderflow)?
+                } else {
+                    data
+                };
+                return Ok(DecodedField {
+                    component_id: component.component_id,
+                    ty,
+                    shape: dim.to_vec(),
+                    timestamp,
+                    data,
+                });
+            }
+            RealizedOp::Schema(s) => {
+                schema.get_or_insert((s.ty, s.dim));
+                op_ref = s.arg;
+            }
+            RealizedOp::Timestamp(t) => {
+                timestamp = timestamp.or(t.timestamp);
+                op_ref = t.arg;
+            }
+            RealizedOp::Ext(e) => op_ref = e.arg,
+            _ => return Err(Error::InvalidOp),
+        }
+    }
+    Err(Error::InvalidOp)
+}
+
+/// Copies little endian values out of a possibly unaligned buffer
+pub fn read_values<T, const N: usize>(data: &[u8], from_le_bytes: fn([u8; N]) -> T) -> Vec<T> {
+    data.chunks_exact(N)
+        .map(|chunk| from_le_bytes(chunk.try_into().expect("chunk wrong size")))
+        .collect()
+}
+
+/// Reads the timestamps of a time series packet's body
+pub fn time_series_timestamps(body: &[u8]) -> Result<Vec<i64>, Error> {
+    let len = body.get(..8).ok_or(Error::BufferUnderflow)?;
+    let len = u64::from_le_bytes(len.try_into().expect("len wrong size")) as usize;
+    let end = len
+        .checked_mul(8)
+        .and_then(|n| n.checked_add(8))
+        .ok_or(Error::OffsetOverflow)?;
+    let timestamps = body.get(8..end).ok_or(Error::BufferUnderflow)?;
+    Ok(read_values(timestamps, i64::from_le_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use metor_proto::vtable::builder::{
+        component, raw_field, raw_table, schema, timestamp, vtable,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_decode_table() {
+        let time = raw_table(0, 8);
+        let vtable = vtable([
+            raw_field(
+                8,
+                24,
+                timestamp(time.clone(), schema(PrimType::F64, &[3], component("pos"))),
+            ),
+            raw_field(32, 2, schema(PrimType::U16, &[], component("mode"))),
+        ]);
+        let mut table = vec![];
+        table.extend_from_slice(&1234i64.to_le_bytes());
+        for v in [1.0f64, 2.0, 3.0] {
+            table.extend_from_slice(&v.to_le_bytes());
+        }
+        table.extend_from_slice(&7u16.to_le_bytes());
+
+        let fields = decode_table(&vtable, &table).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].component_id, ComponentId::new("pos"));
+        assert_eq!(fields[0].shape, vec![3]);
+        assert_eq!(fields[0].timestamp, Some(Timestamp(1234)));
+        assert_eq!(
+            read_values(fields[0].data, f64::from_le_bytes),
+            vec![1.0, 2.0, 3.0]
+        );
+        assert_eq!(fields[1].ty, PrimType::U16);
+        assert_eq!(fields[1].timestamp, None);
+        assert_eq!(read_values(fields[1].data, u16::from_le_bytes), vec![7]);
+
+        // a table that is too short for its vtable is rejected instead of read out of bounds
+        assert!(decode_table(&vtable, &table[..20]).is_err());
+    }
+
+    #[test]
+    fn test_cyclic_vtable() {
+        let mut vtable = vtable([raw_field(0, 8, schema(PrimType::F64, &[], component("a")))]);
+        let schema_ref = vtable.fields[0].arg;
+        // the schema is the last op visited, point it back at itself
+        if let Some(Op::Schema { arg, .. }) = vtable.ops.last_mut() {
+            *arg = schema_ref;
+        }
+        assert!(decode_table(&vtable, &[0; 8]).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Connection handshake and protocol compatibility.
+//!
+//! # Compatibility policy
+//!
+//! Every connection may start with a [`Hello`] exchange, where each side sends its
+//! [`ProtocolVersion`] and the [`Capabilities`] it supports. Two peers are compatible when
+//! their major versions are equal:
+//!
+//! - Minor versions may add new messages, new capabilities, and new optional behaviour.
+//!   Peers must ignore capability bits they don't understand.
+//! - A message id is never reused or reassigned, and an existing message's encoding is never
+//!   changed. Doing either requires a major version bump.
+//! - Ids whose first byte is `224`, along with `NewConnection`'s `[225, 1]`, are reserved for
+//!   well-known messages defined in this crate. A peer that receives a reserved id it doesn't
+//!   know must reply with an error instead of treating it as a user message, unless it already
+//!   logs messages under that id, since ids derived from a message's name predate the reserved
+//!   range and can't change without breaking existing peers.
+//! - A peer that sent a [`Hello`] may only use the capabilities negotiated for its connection.
+//!   One that never sent one predates the handshake, and may use everything but compression.
+//!
+//! The `test_msg_ids_are_stable` test pins the id of every well-known message, so changing
+//! one is caught before it ships.
+
+pub use metor_proto::types::{RESERVED_MSG_PREFIX, is_reserved_msg_id};
+use metor_proto::{
+    buf::IoBuf,
+    types::{Msg, PacketId, Request},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// The version of the protocol spoken by this build
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    pub fn is_compatible(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Optional protocol features, negotiated per connection
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+#[repr(transparent)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// Querying the database with [`SQLQuery`]
+    pub const SQL: Self = Self(1 << 0);
+    /// Storing and streaming arbitrary messages with [`MsgStream`] and [`GetMsgs`]
+    pub const MSG_LOG: Self = Self(1 << 1);
+    /// Streaming tables for a single vtable with [`VTableStream`]
+    pub const VTABLE_STREAM: Self = Self(1 << 2);
+    /// Decompressing packets compressed with LZ4
+    pub const COMPRESSION_LZ4: Self = Self(1 << 3);
+    /// Decompressing packets compressed with zstd
+    pub const COMPRESSION_ZSTD: Self = Self(1 << 4);
+    /// Answering clock synchronization requests with [`TimeSyncRequest`]
+    pub const TIME_SYNC: Self = Self(1 << 5);
+
+    /// Every compression algorithm
+    pub const COMPRESSION: Self = Self(Self::COMPRESSION_LZ4.0 | Self::COMPRESSION_ZSTD.0);
+
+    /// All capabilities supported by this build
+    pub const SUPPORTED: Self = Self(
+        Self::SQL.0
+            | Self::MSG_LOG.0
+            | Self::VTABLE_STREAM.0
+            | Self::COMPRESSION_LZ4.0
+            | Self::COMPRESSION_ZSTD.0
+            | Self::TIME_SYNC.0,
+    );
+
+    /// Every supported capability but compression, which a peer has to opt into
+    pub const DEFAULT: Self = Self(Self::SUPPORTED.0 & !Self::COMPRESSION.0);
+
+    /// The capability a peer has to negotiate before sending the message with `id`, or
+    /// [`Capabilities::NONE`] if the message is always available
+    pub fn required_by(id: PacketId) -> Self {
+        match id {
+            id if id == SQLQuery::ID => Self::SQL,
+            id if id == MsgStream::ID || id == FixedRateMsgStream::ID || id == GetMsgs::ID => {
+                Self::MSG_LOG
+            }
+            id if id == VTableStream::ID || id == UdpVTableStream::ID => Self::VTABLE_STREAM,
+            id if id == TimeSyncRequest::ID => Self::TIME_SYNC,
+            _ => Self::NONE,
+        }
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(&self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl std::ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitAnd for Capabilities {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.intersection(rhs)
+    }
+}
+
+/// The first message sent by each side of a connection.
+///
+/// The server replies to a [`Hello`] with its own version and the capabilities both sides
+/// support, or with an [`ErrorResponse`] if the versions are incompatible.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hello {
+    pub version: ProtocolVersion,
+    pub capabilities: Capabilities,
+}
+
+impl Hello {
+    pub fn new(capabilities: Capabilities) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities,
+        }
+    }
+
+    /// Returns the reply to a peer's hello, or `None` if the peer isn't compatible
+    pub fn negotiate(&self, peer: &Hello) -> Option<Hello> {
+        if !self.version.is_compatible(&peer.version) {
+            return None;
+        }
+        Some(Hello {
+            version: self.version,
+            capabilities: self.capabilities & peer.capabilities,
+        })
+    }
+}
+
+/// A hello that opts into [`Capabilities::DEFAULT`], so compression is only used when a peer
+/// asks for it
+impl Default for Hello {
+    fn default() -> Self {
+        Self::new(Capabilities::DEFAULT)
+    }
+}
+
+impl Msg for Hello {
+    const ID: PacketId = [224, 37];
+}
+
+impl Request for Hello {
+    type Reply<B: IoBuf + Clone> = Hello;
+}
+
+/// Returns true if the id belongs to a well-known message defined in this crate
+pub fn is_known_msg_id(id: PacketId) -> bool {
+    KNOWN_MSG_IDS.iter().any(|(_, known)| *known == id)
+}
+
+/// Every well-known message and its id
+pub const KNOWN_MSG_IDS: &[(&str, PacketId)] = &[
+    ("VTableMsg", VTableMsg::ID),
+    ("Stream", Stream::ID),
+    ("VTableStream", VTableStream::ID),
+    ("SetStreamState", SetStreamState::ID),
+    ("GetTimeSeries", GetTimeSeries::ID),
+    ("SchemaMsg", SchemaMsg::ID),
+    ("GetSchema", GetSchema::ID),
+    ("GetComponentMetadata", GetComponentMetadata::ID),
+    ("SetComponentMetadata", SetComponentMetadata::ID),
+    ("DumpMetadata", DumpMetadata::ID),
+    ("DumpMetadataResp", DumpMetadataResp::ID),
+    ("SubscribeLastUpdated", SubscribeLastUpdated::ID),
+    ("LastUpdated", LastUpdated::ID),
+    ("SetDbConfig", SetDbConfig::ID),
+    ("DbConfig", DbConfig::ID),
+    ("GetDbSettings", GetDbSettings::ID),
+    ("NewConnection", NewConnection::ID),
+    ("GetEarliestTimestamp", GetEarliestTimestamp::ID),
+    ("EarliestTimestamp", EarliestTimestamp::ID),
+    ("DumpSchema", DumpSchema::ID),
+    ("DumpSchemaResp", DumpSchemaResp::ID),
+    ("StreamTimestamp", StreamTimestamp::ID),
+    ("SQLQuery", SQLQuery::ID),
+    ("ArrowIPC", <ArrowIPC<'static>>::ID),
+    ("ErrorResponse", ErrorResponse::ID),
+    ("MsgMetadata", MsgMetadata::ID),
+    ("SetMsgMetadata", SetMsgMetadata::ID),
+    ("MsgStream", MsgStream::ID),
+    ("FixedRateMsgStream", FixedRateMsgStream::ID),
+    ("GetMsgMetadata", GetMsgMetadata::ID),
+    ("GetMsgs", GetMsgs::ID),
+    ("MsgBatch", MsgBatch::ID),
+    ("UdpUnicast", UdpUnicast::ID),
+    ("UdpVTableStream", UdpVTableStream::ID),
+    ("SaveArchive", SaveArchive::ID),
+    ("ArchiveSaved", ArchiveSaved::ID),
+    ("UpdateComponent", UpdateComponent::ID),
+    ("Hello", Hello::ID),
+    ("TimeSyncRequest", TimeSyncRequest::ID),
+    ("TimeSyncResponse", TimeSyncResponse::ID),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_version_compatibility() {
+        let v1_0 = ProtocolVersion { major: 1, minor: 0 };
+        let v1_3 = ProtocolVersion { major: 1, minor: 3 };
+        let v2_0 = ProtocolVersion { major: 2, minor: 0 };
+        assert!(v1_0.is_compatible(&v1_3));
+        assert!(v1_3.is_compatible(&v1_0));
+        assert!(!v1_3.is_compatible(&v2_0));
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let server = Hello::new(Capabilities::SQL | Capabilities::MSG_LOG);
+        let client = Hello {
+            version: ProtocolVersion {
+                major: PROTOCOL_VERSION.major,
+                minor: PROTOCOL_VERSION.minor + 1,
+            },
+            // unknown capability bits from a newer peer are ignored
+            capabilities: Capabilities::SQL | Capabilities(1 << 63),
+        };
+        let reply = server.negotiate(&client).unwrap();
+        assert_eq!(reply.version, PROTOCOL_VERSION);
+        assert_eq!(reply.capabilities, Capabilities::SQL);
+
+        let old_client = Hello {
+            version: ProtocolVersion { major: 0, minor: 9 },
+            capabilities: Capabilities::SUPPORTED,
+        };
+        assert_eq!(server.negotiate(&old_client), None);
+    }
+
+    #[test]
+    fn test_required_capabilities() {
+        assert_eq!(Capabilities::required_by(SQLQuery::ID), Capabilities::SQL);
+        assert_eq!(
+            Capabilities::required_by(GetMsgs::ID),
+            Capabilities::MSG_LOG
+        );
+        assert_eq!(
+            Capabilities::required_by(VTableStream::ID),
+            Capabilities::VTABLE_STREAM
+        );
+        assert_eq!(Capabilities::required_by(VTableMsg::ID), Capabilities::NONE);
+        assert!(Capabilities::DEFAULT.contains(Capabilities::SQL | Capabilities::TIME_SYNC));
+        assert!(!Capabilities::DEFAULT.contains(Capabilities::COMPRESSION_LZ4));
+    }
+
+    #[test]
+    fn test_msg_ids_are_unique() {
+        let mut seen = HashSet::new();
+        for (name, id) in KNOWN_MSG_IDS {
+            assert!(seen.insert(*id), "{name} reuses msg id {id:?}");
+        }
+    }
+
+    /// Pins the id of every well-known reserved message, see the compatibility policy in the
+    /// module docs before changing anything here.
+    #[test]
+    fn test_msg_ids_are_stable() {
+        let expected: &[(&str, PacketId)] = &[
+            ("SetStreamState", [224, 2]),
+            ("GetTimeSeries", [224, 3]),
+            ("SchemaMsg", [224, 4]),
+            ("GetSchema", [224, 5]),
+            ("GetComponentMetadata", [224, 6]),
+            ("DumpMetadata", [224, 14]),
+            ("DumpMetadataResp", [224, 15]),
+            ("SubscribeLastUpdated", [224, 17]),
+            ("LastUpdated", [224, 18]),
+            ("SetDbConfig", [224, 19]),
+            ("DbConfig", [224, 20]),
+            ("GetDbSettings", [224, 21]),
+            ("NewConnection", [225, 1]),
+            ("GetEarliestTimestamp", [224, 22]),
+            ("EarliestTimestamp", [224, 23]),
+            ("DumpSchema", [224, 24]),
+            ("DumpSchemaResp", [224, 25]),
+            ("StreamTimestamp", [224, 26]),
+            ("SQLQuery", [224, 27]),
+            ("ArrowIPC", [224, 28]),
+            ("ErrorResponse", [224, 29]),
+            ("MsgMetadata", [224, 30]),
+            ("SetMsgMetadata", [224, 31]),
+            ("GetMsgMetadata", [224, 33]),
+            ("GetMsgs", [224, 34]),
+            ("MsgBatch", [224, 35]),
+            ("UpdateComponent", [224, 36]),
+            ("Hello", [224, 37]),
+            ("TimeSyncRequest", [224, 38]),
+            ("TimeSyncResponse", [224, 39]),
+        ];
+        for (name, id) in expected {
+            let known = KNOWN_MSG_IDS
+                .iter()
+                .find(|(known, _)| known == name)
+                .map(|(_, id)| *id);
+            assert_eq!(known, Some(*id), "msg id of {name} changed");
+        }
+    }
+
+    #[test]
+    fn test_schema_msg_ids_are_not_reserved() {
+        // ids derived from a schema name must never collide with the reserved range
+        for (name, id) in [
+            ("VTableMsg", VTableMsg::ID),
+            ("Stream", Stream::ID),
+            ("VTableStream", VTableStream::ID),
+            ("SetComponentMetadata", SetComponentMetadata::ID),
+            ("MsgStream", MsgStream::ID),
+            ("FixedRateMsgStream", FixedRateMsgStream::ID),
+            ("UdpUnicast", UdpUnicast::ID),
+            ("UdpVTableStream", UdpVTableStream::ID),
+            ("SaveArchive", SaveArchive::ID),
+            ("ArchiveSaved", ArchiveSaved::ID),
+        ] {
+            assert!(!is_reserved_msg_id(id), "{name} has a reserved id {id:?}");
+        }
+        assert!(is_reserved_msg_id(NewConnection::ID));
+        assert!(is_known_msg_id(Hello::ID));
+        assert!(!is_known_msg_id([224, 255]));
+    }
+}
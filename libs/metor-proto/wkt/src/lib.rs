@@ -3,15 +3,19 @@ use metor_proto::component::Component;
 use metor_proto::types::Timestamp;
 use serde::{Deserialize, Serialize};
 
+mod handshake;
 mod metadata;
 mod msgs;
 mod path;
+mod time_sync;
 #[cfg(feature = "nox")]
 mod value;
 
+pub use handshake::*;
 pub use metadata::*;
 pub use msgs::*;
 pub use path::*;
+pub use time_sync::*;
 #[cfg(feature = "nox")]
 pub use value::*;
 
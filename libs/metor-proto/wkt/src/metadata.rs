@@ -1,4 +1,4 @@
-use metor_proto::types::{ComponentId, EntityId};
+use metor_proto::types::{ClockDomain, ComponentId, EntityId};
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -53,6 +53,27 @@ impl ComponentMetadata {
         self
     }
 
+    /// Sets the clock the component's timestamps are read from
+    pub fn with_clock_domain(mut self, domain: ClockDomain) -> Self {
+        self.set_clock_domain(domain);
+        self
+    }
+
+    /// The number of times the component's schema has changed, older versions of the component
+    /// remain readable from the db
+    pub fn schema_version(&self) -> u32 {
+        self.metadata
+            .get("schema_version")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.metadata
+            .insert("schema_version".to_string(), version.to_string());
+        self
+    }
+
     pub fn is_string(&self) -> bool {
         self.metadata
             .get("is_string")
@@ -92,6 +113,15 @@ pub trait MetadataExt {
     fn set_priority(&mut self, priority: i64) {
         self.set("priority", &priority.to_string());
     }
+    /// The clock timestamps are read from, defaults to UTC if unset or unknown
+    fn clock_domain(&self) -> ClockDomain {
+        self.get("clock_domain")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    }
+    fn set_clock_domain(&mut self, domain: ClockDomain) {
+        self.set("clock_domain", domain.as_str());
+    }
     fn set(&mut self, key: &str, value: &str) {
         self.metadata_mut()
             .insert(key.to_string(), value.to_string());
@@ -118,3 +148,21 @@ impl MetadataExt for EntityMetadata {
         &self.metadata
     }
 }
+
+impl MetadataExt for crate::MsgMetadata {
+    fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.metadata
+    }
+    fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+}
+
+impl MetadataExt for crate::DbConfig {
+    fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.metadata
+    }
+    fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+}
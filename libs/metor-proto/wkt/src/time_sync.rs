@@ -0,0 +1,430 @@
+//! NTP style clock synchronization between a client and metor-db.
+//!
+//! A client sends a [`TimeSyncRequest`] stamped with its own clock, and the db replies with a
+//! [`TimeSyncResponse`] carrying the times it received the request and sent the reply. Together
+//! with the time the reply arrived, those four timestamps give a [`TimeSyncSample`] of the offset
+//! between the two clocks. A [`ClockModel`] fits a line through a window of samples to track both
+//! the offset and the drift between the clocks.
+//!
+//! Devices that only have a free running counter, rather than a clock, can be mapped onto a
+//! reference clock with a [`CounterClock`].
+
+use std::collections::VecDeque;
+
+use metor_proto::{
+    buf::IoBuf,
+    types::{Msg, PacketId, Request, Timestamp, TimestampNs},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSyncRequest {
+    /// The client's clock when the request was sent
+    pub client_send: TimestampNs,
+}
+
+impl Msg for TimeSyncRequest {
+    const ID: PacketId = [224, 38];
+}
+
+impl Request for TimeSyncRequest {
+    type Reply<B: IoBuf + Clone> = TimeSyncResponse;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSyncResponse {
+    /// The client's clock when the request was sent, echoed back from the request
+    pub client_send: TimestampNs,
+    /// The server's clock when the request arrived
+    pub server_recv: TimestampNs,
+    /// The server's clock when the response was sent
+    pub server_send: TimestampNs,
+}
+
+impl TimeSyncResponse {
+    /// Measures the offset between the clocks, given the client's clock when the response arrived
+    ///
+    /// The measurement assumes the request and response took equally long in flight, so its
+    /// error is at most half of the round trip delay.
+    pub fn sample(&self, client_recv: TimestampNs) -> TimeSyncSample {
+        let t0 = self.client_send.as_nanos();
+        let t1 = self.server_recv.as_nanos();
+        let t2 = self.server_send.as_nanos();
+        let t3 = client_recv.as_nanos();
+        let offset = ((t1 - t0) + (t2 - t3)) / 2;
+        let delay = (t3 - t0) - (t2 - t1);
+        TimeSyncSample {
+            local: TimestampNs((t0 + (t3 - t0) / 2) as i64),
+            offset_ns: offset as i64,
+            delay_ns: delay.max(0) as i64,
+        }
+    }
+}
+
+impl Msg for TimeSyncResponse {
+    const ID: PacketId = [224, 39];
+}
+
+/// A single measurement of the offset between a local clock and a reference clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSyncSample {
+    /// The local clock when the sample was taken
+    pub local: TimestampNs,
+    /// The reference clock minus the local clock
+    pub offset_ns: i64,
+    /// The round trip delay of the measurement, zero if unknown
+    pub delay_ns: i64,
+}
+
+/// Tracks the offset and drift between a local clock and a reference clock
+///
+/// The model keeps the most recent samples, and fits a line through the ones with the shortest
+/// round trips, since those are the least affected by queueing on either side.
+#[derive(Debug, Clone)]
+pub struct ClockModel {
+    samples: VecDeque<TimeSyncSample>,
+    window: usize,
+    fit: Option<ClockFit>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ClockFit {
+    /// The local time the fit is centered on
+    local: i64,
+    /// The whole nanoseconds of the offset at `local`, kept out of the float so it stays precise
+    base_offset: i64,
+    /// The rest of the offset at `local`
+    offset: f64,
+    /// Nanoseconds of offset gained per nanosecond of local time
+    drift: f64,
+}
+
+impl Default for ClockModel {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+impl ClockModel {
+    pub const DEFAULT_WINDOW: usize = 32;
+
+    /// How far apart in local time the fitted samples must be before drift is estimated, since
+    /// the jitter of back-to-back samples swamps any drift between them
+    pub const MIN_DRIFT_SPAN_NS: i64 = 5_000_000_000;
+
+    /// The largest drift the model will estimate, in parts per million, well beyond the
+    /// tolerance of any crystal so only a bad fit is clamped
+    pub const MAX_DRIFT_PPM: f64 = 500.0;
+
+    /// Creates a model that fits the last `window` samples
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window must not be empty");
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window,
+            fit: None,
+        }
+    }
+
+    pub fn push(&mut self, sample: TimeSyncSample) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.fit = Self::fit(&self.samples);
+    }
+
+    /// Returns true once the model has at least one sample
+    pub fn is_synced(&self) -> bool {
+        self.fit.is_some()
+    }
+
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = &TimeSyncSample> {
+        self.samples.iter()
+    }
+
+    /// The estimated offset of the reference clock at the local time `local`
+    pub fn offset_ns(&self, local: TimestampNs) -> i64 {
+        let Some(fit) = self.fit else {
+            return 0;
+        };
+        let dt = local.0.saturating_sub(fit.local) as f64;
+        fit.base_offset
+            .saturating_add((fit.offset + fit.drift * dt).round() as i64)
+    }
+
+    /// How much faster the reference clock runs than the local clock, in parts per million
+    pub fn drift_ppm(&self) -> f64 {
+        self.fit.map(|fit| fit.drift * 1e6).unwrap_or(0.0)
+    }
+
+    /// Maps a local time to the reference clock
+    pub fn to_reference(&self, local: TimestampNs) -> TimestampNs {
+        TimestampNs(local.0.saturating_add(self.offset_ns(local)))
+    }
+
+    fn fit(samples: &VecDeque<TimeSyncSample>) -> Option<ClockFit> {
+        let min_delay = samples.iter().map(|s| s.delay_ns).min()?;
+        let max_delay = min_delay.saturating_mul(2);
+        let used = samples.iter().filter(|s| s.delay_ns <= max_delay);
+        let last = samples.iter().rev().find(|s| s.delay_ns <= max_delay)?;
+        let (local, base_offset) = (last.local.0, last.offset_ns);
+
+        let (mut n, mut sum_x, mut sum_y) = (0.0, 0.0, 0.0);
+        let mut first = local;
+        let points = used
+            .map(|s| {
+                first = first.min(s.local.0);
+                let x = s.local.0.saturating_sub(local) as f64;
+                let y = s.offset_ns.saturating_sub(base_offset) as f64;
+                n += 1.0;
+                sum_x += x;
+                sum_y += y;
+                (x, y)
+            })
+            .collect::<Vec<_>>();
+        let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+        let (mut sxx, mut sxy) = (0.0, 0.0);
+        for (x, y) in points {
+            sxx += (x - mean_x) * (x - mean_x);
+            sxy += (x - mean_x) * (y - mean_y);
+        }
+        let max_drift = Self::MAX_DRIFT_PPM / 1e6;
+        let drift = if sxx > 0.0 && local.saturating_sub(first) >= Self::MIN_DRIFT_SPAN_NS {
+            (sxy / sxx).clamp(-max_drift, max_drift)
+        } else {
+            0.0
+        };
+        Some(ClockFit {
+            local,
+            base_offset,
+            offset: mean_y - drift * mean_x,
+            drift,
+        })
+    }
+}
+
+/// Maps a device's free running counter onto a reference clock
+///
+/// Each time a reading arrives it is paired with the reference clock, and the pairs are fitted
+/// with a [`ClockModel`]. The time a reading spends in flight is folded into the offset, so the
+/// mapped timestamps lag the device by the average link latency.
+#[derive(Debug, Clone)]
+pub struct CounterClock {
+    ticks_per_second: u64,
+    bits: u32,
+    last: Option<u64>,
+    wraps: u64,
+    model: ClockModel,
+}
+
+impl CounterClock {
+    /// Creates a clock for a counter that ticks `ticks_per_second` times a second and wraps
+    /// around after `bits` bits
+    pub fn new(ticks_per_second: u64, bits: u32) -> Self {
+        assert!(ticks_per_second > 0, "counter must tick");
+        assert!(
+            (1..=64).contains(&bits),
+            "counter must be 1 to 64 bits wide"
+        );
+        Self {
+            ticks_per_second,
+            bits,
+            last: None,
+            wraps: 0,
+            model: ClockModel::default(),
+        }
+    }
+
+    /// A clock for a 32 bit millisecond counter, like the `ts` field of a blackbox record
+    pub fn millis_u32() -> Self {
+        Self::new(1000, 32)
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.model = ClockModel::new(window);
+        self
+    }
+
+    pub fn model(&self) -> &ClockModel {
+        &self.model
+    }
+
+    /// Records that the counter read `ticks` when the reference clock read `reference`, and
+    /// returns the reading mapped onto the reference clock
+    ///
+    /// Readings must be observed in order, any step backwards is treated as the counter wrapping.
+    pub fn observe(&mut self, ticks: u64, reference: Timestamp) -> Timestamp {
+        let ticks = ticks & self.mask();
+        let local = self.unwrap(ticks);
+        self.wraps = self.wraps_at(ticks);
+        self.last = Some(ticks);
+        self.model.push(TimeSyncSample {
+            local,
+            offset_ns: (reference.as_nanos() - local.as_nanos()) as i64,
+            delay_ns: 0,
+        });
+        self.model.to_reference(local).into()
+    }
+
+    /// Maps a counter reading onto the reference clock without adding a sample, returning `None`
+    /// until a reading has been observed
+    ///
+    /// The reading is taken to be at or after the last observed one, and doesn't change the wrap
+    /// arounds counted by [`CounterClock::observe`].
+    pub fn to_timestamp(&self, ticks: u64) -> Option<Timestamp> {
+        if !self.model.is_synced() {
+            return None;
+        }
+        let local = self.unwrap(ticks);
+        Some(self.model.to_reference(local).into())
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    /// The wrap arounds counted up to a reading that follows the last observed one
+    fn wraps_at(&self, ticks: u64) -> u64 {
+        if self.last.is_some_and(|last| ticks < last) {
+            self.wraps + 1
+        } else {
+            self.wraps
+        }
+    }
+
+    /// Extends a reading across wrap arounds, into nanoseconds since the counter first started
+    fn unwrap(&self, ticks: u64) -> TimestampNs {
+        let ticks = ticks & self.mask();
+        let total = ((self.wraps_at(ticks) as u128) << self.bits) + ticks as u128;
+        let nanos = total * 1_000_000_000 / self.ticks_per_second as u128;
+        TimestampNs(nanos.min(i64::MAX as u128) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(t0: i64, t1: i64, t2: i64, t3: i64) -> TimeSyncSample {
+        TimeSyncResponse {
+            client_send: TimestampNs(t0),
+            server_recv: TimestampNs(t1),
+            server_send: TimestampNs(t2),
+        }
+        .sample(TimestampNs(t3))
+    }
+
+    #[test]
+    fn test_sample() {
+        // the server is 1000ns ahead, each leg takes 100ns and the server takes 50ns to reply
+        let sample = exchange(0, 1100, 1150, 250);
+        assert_eq!(sample.offset_ns, 1000);
+        assert_eq!(sample.delay_ns, 200);
+        assert_eq!(sample.local, TimestampNs(125));
+    }
+
+    #[test]
+    fn test_clock_model_drift() {
+        let mut model = ClockModel::default();
+        assert!(!model.is_synced());
+        assert_eq!(model.to_reference(TimestampNs(5)), TimestampNs(5));
+
+        // the reference runs 50ppm fast and starts 1.7e18ns ahead
+        let base = 1_700_000_000_000_000_000i64;
+        for i in 0..20i64 {
+            let local = i * 1_000_000_000;
+            let delay = if i % 3 == 0 { 5_000_000 } else { 100_000 };
+            model.push(TimeSyncSample {
+                local: TimestampNs(local),
+                offset_ns: base + local / 20_000,
+                delay_ns: delay,
+            });
+        }
+        assert!((model.drift_ppm() - 50.0).abs() < 1e-6);
+        let local = 30_000_000_000;
+        assert_eq!(
+            model.to_reference(TimestampNs(local)),
+            TimestampNs(base + local + local / 20_000)
+        );
+    }
+
+    #[test]
+    fn test_clock_model_ignores_drift_over_short_spans() {
+        let mut model = ClockModel::default();
+        // back-to-back samples a millisecond apart, with a few microseconds of jitter
+        for (i, jitter) in [0, 4_000, -3_000, 5_000, -2_000].into_iter().enumerate() {
+            let local = i as i64 * 1_000_000;
+            model.push(TimeSyncSample {
+                local: TimestampNs(local),
+                offset_ns: 1000 + jitter,
+                delay_ns: 100_000,
+            });
+        }
+        assert_eq!(model.drift_ppm(), 0.0);
+        assert_eq!(model.offset_ns(TimestampNs(60_000_000_000)), 1800);
+
+        // samples far enough apart still can't estimate more drift than a crystal could have
+        model.push(TimeSyncSample {
+            local: TimestampNs(10_000_000_000),
+            offset_ns: 1000 + 100_000_000,
+            delay_ns: 100_000,
+        });
+        assert_eq!(model.drift_ppm(), ClockModel::MAX_DRIFT_PPM);
+    }
+
+    #[test]
+    fn test_clock_model_rejects_slow_round_trips() {
+        let mut model = ClockModel::default();
+        model.push(exchange(0, 1100, 1150, 250));
+        // a reply that was stuck in a queue on the way back skews the offset
+        model.push(exchange(1000, 2100, 2150, 9000));
+        assert_eq!(model.offset_ns(TimestampNs(1000)), 1000);
+    }
+
+    #[test]
+    fn test_clock_model_window() {
+        let mut model = ClockModel::new(2);
+        for offset in [10, 20, 30] {
+            model.push(TimeSyncSample {
+                local: TimestampNs(0),
+                offset_ns: offset,
+                delay_ns: 0,
+            });
+        }
+        assert_eq!(model.samples().len(), 2);
+        assert_eq!(model.offset_ns(TimestampNs(0)), 25);
+    }
+
+    #[test]
+    fn test_counter_clock_wraps() {
+        let mut clock = CounterClock::new(1000, 8);
+        assert_eq!(clock.to_timestamp(0), None);
+        let start = Timestamp(1_700_000_000_000_000);
+        assert_eq!(clock.observe(250, start), start);
+        // 250 -> 4 wraps the 8 bit counter, so 10 ticks have passed
+        assert_eq!(clock.to_timestamp(4), Some(Timestamp(start.0 + 10_000)));
+        assert_eq!(clock.to_timestamp(5), Some(Timestamp(start.0 + 11_000)));
+        // looking up readings doesn't count wrap arounds, only observing them does
+        assert_eq!(clock.to_timestamp(251), Some(Timestamp(start.0 + 1_000)));
+        assert_eq!(
+            clock.observe(4, Timestamp(start.0 + 10_000)),
+            Timestamp(start.0 + 10_000)
+        );
+        assert_eq!(clock.to_timestamp(251), Some(Timestamp(start.0 + 257_000)));
+    }
+
+    #[test]
+    fn test_counter_clock_averages_latency() {
+        let mut clock = CounterClock::millis_u32();
+        let start = 1_700_000_000_000_000;
+        for (i, latency) in [100, 300, 300, 100].into_iter().enumerate() {
+            let ticks = i as u64 * 10;
+            clock.observe(ticks, Timestamp(start + ticks as i64 * 1000 + latency));
+        }
+        assert_eq!(
+            clock.to_timestamp(50),
+            Some(Timestamp(start + 50_000 + 200))
+        );
+    }
+}
@@ -1,5 +1,8 @@
-use metor_proto::types::{ComponentView, ElementValue, PrimType};
-use nox::{Array, ArrayBuf, Dyn, array::ArrayViewExt};
+use metor_proto::{
+    num::{C64, C128, F16, I128, U128},
+    types::{ComponentView, ElementValue, PrimType},
+};
+use nox::{Array, ArrayBuf, ArrayView, Dyn, array::ArrayViewExt};
 use serde::{Deserialize, Serialize};
 use zerocopy::IntoBytes;
 
@@ -17,6 +20,13 @@ pub enum ComponentValue {
     Bool(Array<bool, Dyn>),
     F32(Array<f32, Dyn>),
     F64(Array<f64, Dyn>),
+    Bytes(Vec<u8>),
+    Utf8(String),
+    F16(Array<F16, Dyn>),
+    I128(Array<i128, Dyn>),
+    U128(Array<u128, Dyn>),
+    C64(Array<C64, Dyn>),
+    C128(Array<C128, Dyn>),
 }
 
 impl std::fmt::Display for ComponentValue {
@@ -33,6 +43,13 @@ impl std::fmt::Display for ComponentValue {
             Self::Bool(arr) => write!(f, "{}", arr.view()),
             Self::F32(arr) => write!(f, "{}", arr.view()),
             Self::F64(arr) => write!(f, "{}", arr.view()),
+            Self::Bytes(bytes) => write!(f, "{bytes:?}"),
+            Self::Utf8(s) => write!(f, "{s:?}"),
+            Self::F16(arr) => write!(f, "{}", arr.view()),
+            Self::I128(arr) => write!(f, "{}", arr.view()),
+            Self::U128(arr) => write!(f, "{}", arr.view()),
+            Self::C64(arr) => write!(f, "{}", arr.view()),
+            Self::C128(arr) => write!(f, "{}", arr.view()),
         }
     }
 }
@@ -51,6 +68,13 @@ impl ComponentValue {
             PrimType::Bool => Self::Bool(Array::zeroed(shape)),
             PrimType::F32 => Self::F32(Array::zeroed(shape)),
             PrimType::F64 => Self::F64(Array::zeroed(shape)),
+            PrimType::Bytes => Self::Bytes(Vec::new()),
+            PrimType::Utf8 => Self::Utf8(String::new()),
+            PrimType::F16 => Self::F16(Array::zeroed(shape)),
+            PrimType::I128 => Self::I128(Array::zeroed(shape)),
+            PrimType::U128 => Self::U128(Array::zeroed(shape)),
+            PrimType::C64 => Self::C64(Array::zeroed(shape)),
+            PrimType::C128 => Self::C128(Array::zeroed(shape)),
         }
     }
 
@@ -89,6 +113,23 @@ impl ComponentValue {
             Self::F64(a) => {
                 a.buf.as_mut_buf().fill(0.0);
             }
+            Self::Bytes(bytes) => bytes.clear(),
+            Self::Utf8(s) => s.clear(),
+            Self::F16(a) => {
+                a.buf.as_mut_buf().fill(F16::ZERO);
+            }
+            Self::I128(a) => {
+                a.buf.as_mut_buf().fill(0);
+            }
+            Self::U128(a) => {
+                a.buf.as_mut_buf().fill(0);
+            }
+            Self::C64(a) => {
+                a.buf.as_mut_buf().fill(C64::default());
+            }
+            Self::C128(a) => {
+                a.buf.as_mut_buf().fill(C128::default());
+            }
         }
     }
 
@@ -105,6 +146,12 @@ impl ComponentValue {
             Self::Bool(arr) => arr.shape(),
             Self::F32(arr) => arr.shape(),
             Self::F64(arr) => arr.shape(),
+            Self::Bytes(_) | Self::Utf8(_) => &[],
+            Self::F16(arr) => arr.shape(),
+            Self::I128(arr) => arr.shape(),
+            Self::U128(arr) => arr.shape(),
+            Self::C64(arr) => arr.shape(),
+            Self::C128(arr) => arr.shape(),
         }
     }
 
@@ -187,6 +234,43 @@ impl ComponentValue {
                     }
                 }
             }
+            (Self::F16(arr), ComponentView::F16(view)) => {
+                for (i, &val) in view.buf().iter().enumerate() {
+                    if let Some(r) = arr.buf.as_mut_buf().get_mut(i) {
+                        *r = F16::from_f32(r.to_f32() + val.to_f32());
+                    }
+                }
+            }
+            (Self::I128(arr), ComponentView::I128(view)) => {
+                for (i, &val) in view.buf().iter().enumerate() {
+                    if let Some(r) = arr.buf.as_mut_buf().get_mut(i) {
+                        *r = r.saturating_add(val.get());
+                    }
+                }
+            }
+            (Self::U128(arr), ComponentView::U128(view)) => {
+                for (i, &val) in view.buf().iter().enumerate() {
+                    if let Some(r) = arr.buf.as_mut_buf().get_mut(i) {
+                        *r = r.saturating_add(val.get());
+                    }
+                }
+            }
+            (Self::C64(arr), ComponentView::C64(view)) => {
+                for (i, &val) in view.buf().iter().enumerate() {
+                    if let Some(r) = arr.buf.as_mut_buf().get_mut(i) {
+                        r.re += val.re;
+                        r.im += val.im;
+                    }
+                }
+            }
+            (Self::C128(arr), ComponentView::C128(view)) => {
+                for (i, &val) in view.buf().iter().enumerate() {
+                    if let Some(r) = arr.buf.as_mut_buf().get_mut(i) {
+                        r.re += val.re;
+                        r.im += val.im;
+                    }
+                }
+            }
             _ => panic!("Cannot add values of different types"),
         }
     }
@@ -234,6 +318,7 @@ impl ComponentValue {
                 }
             }
             Self::Bool(_) => panic!("Cannot divide boolean values"),
+            Self::Bytes(_) | Self::Utf8(_) => panic!("Cannot divide variable length values"),
             Self::F32(a) => {
                 for r in a.buf.as_mut_buf().iter_mut() {
                     *r /= count as f32;
@@ -244,6 +329,33 @@ impl ComponentValue {
                     *r /= count;
                 }
             }
+            Self::F16(a) => {
+                for r in a.buf.as_mut_buf().iter_mut() {
+                    *r = F16::from_f32(r.to_f32() / count as f32);
+                }
+            }
+            Self::I128(a) => {
+                for r in a.buf.as_mut_buf().iter_mut() {
+                    *r = (*r as f64 / count) as i128;
+                }
+            }
+            Self::U128(a) => {
+                for r in a.buf.as_mut_buf().iter_mut() {
+                    *r = (*r as f64 / count) as u128;
+                }
+            }
+            Self::C64(a) => {
+                for r in a.buf.as_mut_buf().iter_mut() {
+                    r.re /= count as f32;
+                    r.im /= count as f32;
+                }
+            }
+            Self::C128(a) => {
+                for r in a.buf.as_mut_buf().iter_mut() {
+                    r.re /= count;
+                    r.im /= count;
+                }
+            }
         }
     }
     pub fn copy_from_view(&mut self, view: ComponentView<'_>) -> Option<()> {
@@ -281,6 +393,35 @@ impl ComponentValue {
             (Self::F64(arr), ComponentView::F64(view)) => {
                 arr.buf.as_mut_buf().copy_from_slice(view.buf());
             }
+            (Self::Bytes(bytes), ComponentView::Bytes(view)) => {
+                bytes.clear();
+                bytes.extend_from_slice(view);
+            }
+            (Self::Utf8(s), ComponentView::Utf8(view)) => {
+                s.clear();
+                s.push_str(view);
+            }
+            (Self::F16(arr), ComponentView::F16(view)) => {
+                arr.buf.as_mut_buf().copy_from_slice(view.buf());
+            }
+            (Self::I128(arr), ComponentView::I128(view)) => {
+                arr.buf
+                    .as_mut_buf()
+                    .as_mut_bytes()
+                    .copy_from_slice(view.as_bytes());
+            }
+            (Self::U128(arr), ComponentView::U128(view)) => {
+                arr.buf
+                    .as_mut_buf()
+                    .as_mut_bytes()
+                    .copy_from_slice(view.as_bytes());
+            }
+            (Self::C64(arr), ComponentView::C64(view)) => {
+                arr.buf.as_mut_buf().copy_from_slice(view.buf());
+            }
+            (Self::C128(arr), ComponentView::C128(view)) => {
+                arr.buf.as_mut_buf().copy_from_slice(view.buf());
+            }
             _ => {
                 return None;
             }
@@ -301,6 +442,25 @@ impl ComponentValue {
             ComponentView::Bool(view) => Self::Bool(view.to_dyn_owned()),
             ComponentView::F32(view) => Self::F32(view.to_dyn_owned()),
             ComponentView::F64(view) => Self::F64(view.to_dyn_owned()),
+            ComponentView::Bytes(view) => Self::Bytes(view.to_vec()),
+            ComponentView::Utf8(view) => Self::Utf8(view.to_string()),
+            ComponentView::F16(view) => Self::F16(view.to_dyn_owned()),
+            ComponentView::I128(view) => Self::I128(
+                Array::from_shape_vec(
+                    view.shape().into(),
+                    view.buf().iter().map(|x| x.get()).collect(),
+                )
+                .unwrap(),
+            ),
+            ComponentView::U128(view) => Self::U128(
+                Array::from_shape_vec(
+                    view.shape().into(),
+                    view.buf().iter().map(|x| x.get()).collect(),
+                )
+                .unwrap(),
+            ),
+            ComponentView::C64(view) => Self::C64(view.to_dyn_owned()),
+            ComponentView::C128(view) => Self::C128(view.to_dyn_owned()),
         }
     }
 
@@ -339,6 +499,24 @@ impl ComponentValue {
             ComponentValue::F64(f64) => {
                 Box::new(f64.buf.as_buf().iter().map(|&x| ElementValue::F64(x)))
             }
+            ComponentValue::Bytes(_) | ComponentValue::Utf8(_) => {
+                Box::new(self.as_bytes().iter().map(|&x| ElementValue::U8(x)))
+            }
+            ComponentValue::F16(f16) => {
+                Box::new(f16.buf.as_buf().iter().map(|&x| ElementValue::F16(x)))
+            }
+            ComponentValue::I128(i128) => {
+                Box::new(i128.buf.as_buf().iter().map(|&x| ElementValue::I128(x)))
+            }
+            ComponentValue::U128(u128) => {
+                Box::new(u128.buf.as_buf().iter().map(|&x| ElementValue::U128(x)))
+            }
+            ComponentValue::C64(c64) => {
+                Box::new(c64.buf.as_buf().iter().map(|&x| ElementValue::C64(x)))
+            }
+            ComponentValue::C128(c128) => {
+                Box::new(c128.buf.as_buf().iter().map(|&x| ElementValue::C128(x)))
+            }
         }
     }
 
@@ -355,6 +533,14 @@ impl ComponentValue {
             ComponentValue::Bool(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::Bool(x)),
             ComponentValue::F32(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::F32(x)),
             ComponentValue::F64(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::F64(x)),
+            ComponentValue::Bytes(_) | ComponentValue::Utf8(_) => {
+                self.as_bytes().get(i).map(|&x| ElementValue::U8(x))
+            }
+            ComponentValue::F16(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::F16(x)),
+            ComponentValue::I128(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::I128(x)),
+            ComponentValue::U128(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::U128(x)),
+            ComponentValue::C64(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::C64(x)),
+            ComponentValue::C128(x) => x.buf.as_buf().get(i).map(|&x| ElementValue::C128(x)),
         }
     }
 
@@ -371,6 +557,13 @@ impl ComponentValue {
             ComponentValue::Bool(_) => PrimType::Bool,
             ComponentValue::F32(_) => PrimType::F32,
             ComponentValue::F64(_) => PrimType::F64,
+            ComponentValue::Bytes(_) => PrimType::Bytes,
+            ComponentValue::Utf8(_) => PrimType::Utf8,
+            ComponentValue::F16(_) => PrimType::F16,
+            ComponentValue::I128(_) => PrimType::I128,
+            ComponentValue::U128(_) => PrimType::U128,
+            ComponentValue::C64(_) => PrimType::C64,
+            ComponentValue::C128(_) => PrimType::C128,
         }
     }
 
@@ -387,6 +580,13 @@ impl ComponentValue {
             ComponentValue::Bool(x) => x.buf.as_buf().as_bytes(),
             ComponentValue::F32(x) => x.buf.as_buf().as_bytes(),
             ComponentValue::F64(x) => x.buf.as_buf().as_bytes(),
+            ComponentValue::Bytes(x) => x,
+            ComponentValue::Utf8(x) => x.as_bytes(),
+            ComponentValue::F16(x) => x.buf.as_buf().as_bytes(),
+            ComponentValue::I128(x) => x.buf.as_buf().as_bytes(),
+            ComponentValue::U128(x) => x.buf.as_buf().as_bytes(),
+            ComponentValue::C64(x) => x.buf.as_buf().as_bytes(),
+            ComponentValue::C128(x) => x.buf.as_buf().as_bytes(),
         }
     }
 
@@ -403,15 +603,93 @@ impl ComponentValue {
             ComponentValue::Bool(x) => ComponentView::Bool(x.view()),
             ComponentValue::F32(x) => ComponentView::F32(x.view()),
             ComponentValue::F64(x) => ComponentView::F64(x.view()),
+            ComponentValue::Bytes(x) => ComponentView::Bytes(x),
+            ComponentValue::Utf8(x) => ComponentView::Utf8(x),
+            ComponentValue::F16(x) => ComponentView::F16(x.view()),
+            // the native 128-bit integers are at least as aligned as the views' element types
+            ComponentValue::I128(x) => ComponentView::I128(
+                ArrayView::from_bytes_shape_unchecked(x.buf.as_buf().as_bytes(), x.shape())
+                    .unwrap(),
+            ),
+            ComponentValue::U128(x) => ComponentView::U128(
+                ArrayView::from_bytes_shape_unchecked(x.buf.as_buf().as_bytes(), x.shape())
+                    .unwrap(),
+            ),
+            ComponentValue::C64(x) => ComponentView::C64(x.view()),
+            ComponentValue::C128(x) => ComponentView::C128(x.view()),
         }
     }
 
     /// Casts the ComponentValue to a different primitive type
+    ///
+    /// Variable length values can only be cast between each other, and into or out of `u8` arrays.
+    /// Invalid UTF-8 is replaced when casting to [`PrimType::Utf8`]. `f16` values are cast through
+    /// `f32`, and complex values are cast to real types using their real part.
     pub fn cast(&self, target_type: PrimType) -> Self {
         if self.prim_type() == target_type {
             return self.clone();
         }
 
+        match (self, target_type) {
+            (ComponentValue::Utf8(_), PrimType::Bytes) => {
+                return ComponentValue::Bytes(self.as_bytes().to_vec());
+            }
+            (_, PrimType::Utf8) if self.prim_type().is_var_len() => {
+                return ComponentValue::Utf8(String::from_utf8_lossy(self.as_bytes()).into_owned());
+            }
+            (_, PrimType::Bytes | PrimType::Utf8) => {
+                let ComponentValue::U8(bytes) = self.cast(PrimType::U8) else {
+                    unreachable!()
+                };
+                return ComponentValue::Bytes(bytes.buf.as_buf().to_vec()).cast(target_type);
+            }
+            (ComponentValue::Bytes(_) | ComponentValue::Utf8(_), _) => {
+                let bytes = self.as_bytes().to_vec();
+                return ComponentValue::U8(
+                    Array::from_shape_vec(smallvec::smallvec![bytes.len()], bytes).unwrap(),
+                )
+                .cast(target_type);
+            }
+            (ComponentValue::F16(array), _) => {
+                return ComponentValue::F32(map_elems(array, |x| x.to_f32())).cast(target_type);
+            }
+            (ComponentValue::C64(array), PrimType::C128) => {
+                return ComponentValue::C128(map_elems(array, |x| {
+                    C128::new(x.re as f64, x.im as f64)
+                }));
+            }
+            (ComponentValue::C128(array), PrimType::C64) => {
+                return ComponentValue::C64(map_elems(array, |x| {
+                    C64::new(x.re as f32, x.im as f32)
+                }));
+            }
+            (ComponentValue::C64(array), _) => {
+                return ComponentValue::F32(map_elems(array, |x| x.re)).cast(target_type);
+            }
+            (ComponentValue::C128(array), _) => {
+                return ComponentValue::F64(map_elems(array, |x| x.re)).cast(target_type);
+            }
+            (_, PrimType::F16) => {
+                let ComponentValue::F32(array) = self.cast(PrimType::F32) else {
+                    unreachable!()
+                };
+                return ComponentValue::F16(map_elems(&array, F16::from_f32));
+            }
+            (_, PrimType::C64) => {
+                let ComponentValue::F32(array) = self.cast(PrimType::F32) else {
+                    unreachable!()
+                };
+                return ComponentValue::C64(map_elems(&array, |x| C64::new(x, 0.0)));
+            }
+            (_, PrimType::C128) => {
+                let ComponentValue::F64(array) = self.cast(PrimType::F64) else {
+                    unreachable!()
+                };
+                return ComponentValue::C128(map_elems(&array, |x| C128::new(x, 0.0)));
+            }
+            _ => {}
+        }
+
         let shape = self.shape();
 
         macro_rules! cast_from {
@@ -457,7 +735,14 @@ impl ComponentValue {
                     PrimType::I64 => cast_from!($src_array, bool, I64),
                     PrimType::F32 => cast_from!($src_array, bool, F32),
                     PrimType::F64 => cast_from!($src_array, bool, F64),
-                    PrimType::Bool => unreachable!(),
+                    PrimType::I128 => cast_from!($src_array, bool, I128),
+                    PrimType::U128 => cast_from!($src_array, bool, U128),
+                    PrimType::Bool
+                    | PrimType::Bytes
+                    | PrimType::Utf8
+                    | PrimType::F16
+                    | PrimType::C64
+                    | PrimType::C128 => unreachable!(),
                 }
             };
             ($src_array:expr, $src_type:ty) => {
@@ -473,6 +758,13 @@ impl ComponentValue {
                     PrimType::F32 => cast_from!($src_array, $src_type, F32),
                     PrimType::F64 => cast_from!($src_array, $src_type, F64),
                     PrimType::Bool => cast_from!($src_array, $src_type, Bool),
+                    PrimType::I128 => cast_from!($src_array, $src_type, I128),
+                    PrimType::U128 => cast_from!($src_array, $src_type, U128),
+                    PrimType::Bytes
+                    | PrimType::Utf8
+                    | PrimType::F16
+                    | PrimType::C64
+                    | PrimType::C128 => unreachable!(),
                 }
             };
         }
@@ -489,12 +781,22 @@ impl ComponentValue {
             ComponentValue::Bool(array) => cast_to_all!(array, bool),
             ComponentValue::F32(array) => cast_to_all!(array, f32),
             ComponentValue::F64(array) => cast_to_all!(array, f64),
+            ComponentValue::I128(array) => cast_to_all!(array, i128),
+            ComponentValue::U128(array) => cast_to_all!(array, u128),
+            ComponentValue::Bytes(_)
+            | ComponentValue::Utf8(_)
+            | ComponentValue::F16(_)
+            | ComponentValue::C64(_)
+            | ComponentValue::C128(_) => unreachable!(),
         }
     }
 
     pub fn as_str(&self) -> Option<&str> {
-        let ComponentValue::U8(array) = self else {
-            return None;
+        let array = match self {
+            ComponentValue::U8(array) => array,
+            ComponentValue::Utf8(s) => return Some(s),
+            ComponentValue::Bytes(bytes) => return std::str::from_utf8(bytes).ok(),
+            _ => return None,
         };
         let buf = array.buf.as_buf();
         let len = buf.iter().position(|p| *p == 0).unwrap_or(buf.len());
@@ -503,6 +805,14 @@ impl ComponentValue {
     }
 }
 
+fn map_elems<T: nox::Elem, U: nox::Elem>(
+    array: &Array<T, Dyn>,
+    f: impl FnMut(T) -> U,
+) -> Array<U, Dyn> {
+    let values = array.buf.as_buf().iter().copied().map(f).collect();
+    Array::from_shape_vec(array.shape().into(), values).unwrap()
+}
+
 #[derive(Debug)]
 pub enum ElementValueMut<'a> {
     U8(&'a mut u8),
@@ -517,3 +827,49 @@ pub enum ElementValueMut<'a> {
     F32(&'a mut f32),
     Bool(&'a mut bool),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn i64_array(values: Vec<i64>) -> ComponentValue {
+        ComponentValue::I64(
+            Array::from_shape_vec(smallvec::smallvec![values.len()], values).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cast_wide_prim_types() {
+        let value = i64_array(vec![-3, 4]);
+
+        let ComponentValue::I128(wide) = value.cast(PrimType::I128) else {
+            panic!("expected i128 value");
+        };
+        assert_eq!(wide.buf.as_buf(), &[-3, 4]);
+
+        let half = value.cast(PrimType::F16);
+        assert_eq!(half.get(0), Some(ElementValue::F16(F16::from_f32(-3.0))));
+        let ComponentValue::I64(back) = half.cast(PrimType::I64) else {
+            panic!("expected i64 value");
+        };
+        assert_eq!(back.buf.as_buf(), &[-3, 4]);
+
+        let complex = value.cast(PrimType::C128);
+        assert_eq!(
+            complex.get(1),
+            Some(ElementValue::C128(C128::new(4.0, 0.0)))
+        );
+        let ComponentValue::F32(real) = complex.cast(PrimType::F32) else {
+            panic!("expected f32 value");
+        };
+        assert_eq!(real.buf.as_buf(), &[-3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_wide_view_round_trip() {
+        let value = i64_array(vec![i64::MIN, 1]).cast(PrimType::U128);
+        let copy = ComponentValue::from_view(value.as_view());
+        assert_eq!(copy.as_bytes(), value.as_bytes());
+        assert_eq!(copy.get(1), Some(ElementValue::U128(1)));
+    }
+}
@@ -32,7 +32,7 @@ use metor_proto_bevy::{
     CurrentStreamId, EntityMap, InvalidatedEqlCtx, PacketHandlerInput, PacketHandlers, PacketTx,
 };
 use metor_proto_wkt::{CurrentTimestamp, NewConnection, Object3D, SetStreamState, WorldPos};
-use metor_proto_wkt::{EarliestTimestamp, LastUpdated};
+use metor_proto_wkt::{EarliestTimestamp, LastUpdated, MetadataExt};
 use nox::Tensor;
 use object_3d::create_object_3d_entity;
 use plugins::navigation_gizmo::{NavigationGizmoPlugin, RenderLayerAlloc};
@@ -1018,6 +1018,7 @@ pub fn update_eql_context(
             let schema = component_schema_registry.0.get(id)?;
             let metadata = component_metadata_registry.0.get(id)?;
             let mut component = eql::Component::new(metadata.name.clone(), path.id, schema.clone());
+            component.clock_domain = metadata.clock_domain();
             if !metadata.element_names().is_empty() {
                 component.element_names = metadata
                     .element_names()
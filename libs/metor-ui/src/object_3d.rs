@@ -2,9 +2,9 @@ use bevy::prelude::Mesh;
 use bevy::prelude::*;
 use big_space::GridCell;
 use eql::Expr;
+use metor_proto::types::Timestamp;
 use metor_proto_bevy::EntityMap;
-use metor_proto_wkt::{ComponentValue, Object3D};
-use nox::Array;
+use metor_proto_wkt::{ComponentValue, CurrentTimestamp, Object3D};
 use smallvec::smallvec;
 
 use crate::BevyExt;
@@ -34,6 +34,7 @@ impl EditableEQL {
 type ExprFn = dyn for<'a, 'b> Fn(
         &'a EntityMap,
         &'a Query<'b, 'b, &'static ComponentValue>,
+        Timestamp,
     ) -> Result<ComponentValue, String>
     + Send
     + Sync;
@@ -49,6 +50,7 @@ impl CompiledExpr {
         F: for<'a, 'b> Fn(
                 &'a EntityMap,
                 &'a Query<'b, 'b, &'static ComponentValue>,
+                Timestamp,
             ) -> Result<ComponentValue, String>
             + Send
             + Sync
@@ -57,137 +59,55 @@ impl CompiledExpr {
         Self::Closure(Box::new(closure))
     }
 
-    /// Executes the compiled expression
+    /// Executes the compiled expression against the values streamed for `timestamp`
     pub fn execute<'a, 'b>(
         &'a self,
         entity_map: &'a EntityMap,
         values: &'a Query<'b, 'b, &'static ComponentValue>,
+        timestamp: Timestamp,
     ) -> Result<ComponentValue, String> {
         match self {
-            Self::Closure(c) => (c)(entity_map, values),
+            Self::Closure(c) => (c)(entity_map, values, timestamp),
             Self::Value(value) => Ok(value.clone()),
         }
     }
 }
 
-/// Compiles an EQL expression into a closure-based form
-pub fn compile_eql_expr(expression: eql::Expr) -> CompiledExpr {
-    match expression {
-        Expr::ComponentPart(component) => {
-            let component_id = component.id;
-            CompiledExpr::closure(move |entity_map, component_value| {
-                let entity_id = entity_map.get(&component_id).ok_or_else(|| {
-                    format!("component '{}' not found in entity map", component.name)
-                })?;
+/// Latest component values from the ECS, for evaluating EQL expressions in real time
+struct LiveValues<'a, 'b> {
+    entity_map: &'a EntityMap,
+    values: &'a Query<'b, 'b, &'static ComponentValue>,
+    /// The time the values were streamed for
+    timestamp: Timestamp,
+}
 
-                component_value
-                    .get(*entity_id)
-                    .map_err(|_| {
-                        format!(
-                            "no component value map found for component '{}'",
-                            component.name
-                        )
-                    })
-                    .cloned()
-            })
-        }
-        Expr::ArrayAccess(expr, index) => {
-            let compiled_expr = compile_eql_expr(*expr);
-            CompiledExpr::closure(move |entity_map, component_value_maps| {
-                let resolved_expr = compiled_expr.execute(entity_map, component_value_maps)?;
-                match resolved_expr {
-                    ComponentValue::F64(array) => {
-                        use nox::ArrayBuf;
-                        let data = array.buf.as_buf();
-                        if index < data.len() {
-                            let value = data[index];
-                            let value = nox::Array::<_, ()> { buf: value }.to_dyn();
-                            Ok(ComponentValue::F64(value))
-                        } else {
-                            Err(format!(
-                                "array index {} out of bounds (length: {})",
-                                index,
-                                data.len()
-                            ))
-                        }
-                    }
-                    ComponentValue::F32(array) => {
-                        use nox::ArrayBuf;
-                        let data = array.buf.as_buf();
-                        if index < data.len() {
-                            let value = data[index];
-                            let value = nox::Array::<_, ()> { buf: value }.to_dyn();
-                            Ok(ComponentValue::F32(value))
-                        } else {
-                            Err(format!(
-                                "array index {} out of bounds (length: {})",
-                                index,
-                                data.len()
-                            ))
-                        }
-                    }
-                    _ => Err("array access can only be applied to numeric arrays".to_string()),
-                }
-            })
-        }
-        Expr::Tuple(exprs) => {
-            let compiled_exprs: Vec<CompiledExpr> =
-                exprs.into_iter().map(compile_eql_expr).collect();
-            CompiledExpr::closure(move |entity_map, component_value_maps| {
-                use nox::ArrayBuf;
-                let mut values = Vec::new();
-                for compiled_expr in &compiled_exprs {
-                    let resolved = compiled_expr.execute(entity_map, component_value_maps)?;
-                    match resolved {
-                        ComponentValue::F64(array) => {
-                            values.extend_from_slice(array.buf.as_buf());
-                        }
-                        ComponentValue::F32(array) => {
-                            let f32_data = array.buf.as_buf();
-                            values.extend(f32_data.iter().map(|&x| x as f64));
-                        }
-                        _ => return Err("tuple elements must be numeric".to_string()),
-                    }
-                }
-                let tuple_array = Array::from_shape_vec(smallvec![values.len()], values).unwrap();
-                Ok(ComponentValue::F64(tuple_array))
-            })
-        }
-        Expr::BinaryOp(left, right, op) => {
-            let left_compiled = compile_eql_expr(*left);
-            let right_compiled = compile_eql_expr(*right);
-            CompiledExpr::closure(move |entity_map, component_value_maps| {
-                let left_val = left_compiled.execute(entity_map, component_value_maps)?;
-                let right_val = right_compiled.execute(entity_map, component_value_maps)?;
+impl eql::Source for LiveValues<'_, '_> {
+    fn latest(&self, component: &eql::Component) -> Option<ComponentValue> {
+        let entity = self.entity_map.get(&component.id)?;
+        self.values.get(*entity).ok().cloned()
+    }
 
-                match (left_val, right_val) {
-                    (ComponentValue::F64(left), ComponentValue::F64(right)) => {
-                        if !nox::array::can_broadcast(left.shape(), right.shape()) {
-                            return Err(
-                                "binary operation requires arrays be broadcastable".to_string()
-                            );
-                        }
-                        let result = match op {
-                            eql::BinaryOp::Add => left.add(&right),
-                            eql::BinaryOp::Sub => left.sub(&right),
-                            eql::BinaryOp::Mul => left.mul(&right),
-                            eql::BinaryOp::Div => left.div(&right),
-                        };
+    fn latest_timestamp(&self, component: &eql::Component) -> Option<Timestamp> {
+        let entity = self.entity_map.get(&component.id)?;
+        self.values.contains(*entity).then_some(self.timestamp)
+    }
+}
 
-                        Ok(ComponentValue::F64(result))
-                    }
-                    _ => Err("binary operations only supported for F64 arrays".to_string()),
-                }
-            })
-        }
+/// Compiles an EQL expression into a closure-based form
+pub fn compile_eql_expr(expression: eql::Expr) -> CompiledExpr {
+    match expression {
         Expr::FloatLiteral(f) => CompiledExpr::Value(ComponentValue::F64(nox::array!(f).to_dyn())),
         Expr::BoolLiteral(b) => CompiledExpr::Value(ComponentValue::Bool(
             nox::Array::from_shape_vec(smallvec![], vec![b]).unwrap(),
         )),
-        expr => {
-            let error = format!("{:?} can't be converted to a component value", expr);
-            CompiledExpr::closure(move |_, _| Err(error.clone()))
-        }
+        expr => CompiledExpr::closure(move |entity_map, values, timestamp| {
+            expr.eval(&LiveValues {
+                entity_map,
+                values,
+                timestamp,
+            })
+            .map_err(|err| err.to_string())
+        }),
     }
 }
 
@@ -196,12 +116,13 @@ pub fn update_object_3d_system(
     mut objects_query: Query<(Entity, &Object3DState, &mut metor_proto_wkt::WorldPos)>,
     entity_map: Res<EntityMap>,
     component_value_maps: Query<&'static ComponentValue>,
+    tick: Res<CurrentTimestamp>,
 ) {
     for (entity, object_3d, mut pos) in objects_query.iter_mut() {
         let Some(compiled_expr) = &object_3d.compiled_expr else {
             continue;
         };
-        match compiled_expr.execute(&entity_map, &component_value_maps) {
+        match compiled_expr.execute(&entity_map, &component_value_maps, tick.0) {
             Ok(component_value) => {
                 if let Some(world_pos) = component_value.as_world_pos() {
                     *pos = world_pos;
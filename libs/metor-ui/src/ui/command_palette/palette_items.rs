@@ -15,13 +15,13 @@ use bevy::{
 use bevy_infinite_grid::InfiniteGrid;
 use egui_tiles::TileId;
 use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
-use metor_proto::types::{ComponentId, PrimType, msg_id};
+use metor_proto::types::{ComponentId, PrimType, Timestamp, msg_id};
 use metor_proto_bevy::{
     ComponentMetadataRegistry, ComponentSchemaRegistry, CurrentStreamId, EntityMap, PacketTx,
 };
 use metor_proto_kdl::ToKdl;
 use metor_proto_wkt::{
-    ComponentValue, IsRecording, Material, Mesh, Object3D, SetDbConfig, SetStreamState,
+    ComponentValue, CurrentTimestamp, IsRecording, Material, Mesh, Object3D, SetDbConfig, SetStreamState,
     UpdateComponent,
 };
 use miette::IntoDiagnostic;
@@ -380,6 +380,7 @@ fn update_component_parts(
             let name = part.name.clone();
             let id = part.id;
             let is_bool = schema.prim_type() == PrimType::Bool && schema.size() == 1;
+            let is_utf8 = schema.prim_type() == PrimType::Utf8;
             PaletteItem::new(
                 part.name.clone(),
                 "Component",
@@ -441,7 +442,7 @@ fn update_component_parts(
                                         .collect(),
                                 ),
                             };
-                        } else if metadata.is_string() {
+                        } else if metadata.is_string() || is_utf8 {
                             return PaletteEvent::NextPage {
                                 prev_page_label: Some(name.clone()),
                                 next_page: PalettePage::new(vec![
@@ -452,6 +453,11 @@ fn update_component_parts(
                                              let Some(schema) = schema.get(&id) else {
                                                  return PaletteEvent::Exit;
                                              };
+                                             if schema.prim_type() == PrimType::Utf8 {
+                                                 let value = ComponentValue::Utf8(string.0);
+                                                 tx.send_msg(UpdateComponent { id, value });
+                                                 return PaletteEvent::Exit;
+                                             }
                                              if string.len() > schema.size() {
                                                  return PaletteEvent::Error(format!("This string is longer than the max length of {:?}", schema.size()));
                                              }
@@ -487,12 +493,13 @@ fn update_component_eql(component_id: ComponentId) -> Vec<PaletteItem> {
                   ctx: Res<EqlContext>,
                   entity_map: Res<EntityMap>,
                   values: Query<&'static ComponentValue>,
+                  tick: Res<CurrentTimestamp>,
                   tx: Res<PacketTx>,
                   schema: Res<ComponentSchemaRegistry>| {
                 match ctx.0.parse_str(&eql.0) {
                     Ok(eql) => {
                         let eql = compile_eql_expr(eql);
-                        let value = match dbg!(eql.execute(&entity_map, &values)) {
+                        let value = match dbg!(eql.execute(&entity_map, &values, tick.0)) {
                             Ok(result) => result,
                             Err(err) => return PaletteEvent::Error(err.to_string()),
                         };
@@ -923,13 +930,19 @@ fn create_object_3d_with_color(eql: String, expr: eql::Expr, mesh: Mesh) -> Pale
                   eql_ctx: Res<EqlContext>,
                   entity_map: Res<EntityMap>,
                   component_value_maps: Query<&'static ComponentValue>,
+                  tick: Res<CurrentTimestamp>,
                   mut material_assets: ResMut<Assets<StandardMaterial>>,
                   mut mesh_assets: ResMut<Assets<bevy::prelude::Mesh>>,
                   assets: Res<AssetServer>| {
                 let color_str = color_str.trim();
-                let (r, g, b) =
-                    parse_color(color_str, &eql_ctx.0, &entity_map, component_value_maps)
-                        .unwrap_or((0.8, 0.8, 0.8));
+                let (r, g, b) = parse_color(
+                    color_str,
+                    &eql_ctx.0,
+                    &entity_map,
+                    component_value_maps,
+                    tick.0,
+                )
+                .unwrap_or((0.8, 0.8, 0.8));
 
                 let mesh_source = metor_proto_wkt::Object3DMesh::Mesh {
                     mesh: mesh.clone(),
@@ -963,10 +976,11 @@ fn parse_color(
     ctx: &eql::Context,
     entity_map: &EntityMap,
     component_value_maps: Query<&'static ComponentValue>,
+    timestamp: Timestamp,
 ) -> Option<(f32, f32, f32)> {
     let expr = ctx.parse_str(expr).ok()?;
     let expr = crate::object_3d::compile_eql_expr(expr);
-    let val = expr.execute(entity_map, &component_value_maps).ok()?;
+    let val = expr.execute(entity_map, &component_value_maps, timestamp).ok()?;
 
     let ComponentValue::F64(array) = val else {
         return None;
@@ -8,8 +8,9 @@ use bevy::ui::{
 };
 use bevy::window::PrimaryWindow;
 use eql::FmtExpr;
+use metor_proto::types::Timestamp;
 use metor_proto_bevy::EntityMap;
-use metor_proto_wkt::{ComponentValue, DashboardNode};
+use metor_proto_wkt::{ComponentValue, CurrentTimestamp, DashboardNode};
 use nox::ArrayBuf;
 use smallvec::{SmallVec, smallvec};
 
@@ -65,6 +66,7 @@ impl WidgetSystem for DashboardWidget<'_, '_> {
 pub struct NodeUpdaterParams<'w, 's> {
     entity_map: Res<'w, EntityMap>,
     values: Query<'w, 's, &'static ComponentValue>,
+    tick: Res<'w, CurrentTimestamp>,
 }
 
 type NodeFn = dyn for<'a, 'b> Fn(
@@ -121,31 +123,32 @@ impl CompiledVal {
         &self,
         entity_map: &EntityMap,
         values: &Query<&'static ComponentValue>,
+        timestamp: Timestamp,
     ) -> Result<Val, String> {
         let val = match self {
             CompiledVal::Auto => Val::Auto,
             CompiledVal::Px(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::Px(val.as_f32().ok_or("invalid value")?)
             }
             CompiledVal::Percent(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::Percent(val.as_f32().ok_or("invalid value")?)
             }
             CompiledVal::Vw(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::Vw(val.as_f32().ok_or("invalid value")?)
             }
             CompiledVal::Vh(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::Vh(val.as_f32().ok_or("invalid value")?)
             }
             CompiledVal::VMin(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::VMin(val.as_f32().ok_or("invalid value")?)
             }
             CompiledVal::VMax(expr) => {
-                let val = expr.execute(entity_map, values)?;
+                let val = expr.execute(entity_map, values, timestamp)?;
                 Val::VMax(val.as_f32().ok_or("invalid value")?)
             }
         };
@@ -373,38 +376,40 @@ pub fn spawn_node<T>(
         let NodeUpdaterParams {
             entity_map: e,
             values: q,
+            tick,
         } = params;
+        let t = tick.0;
         let mut node = updater_node.clone();
-        node.left = left.execute(e, q)?;
-        node.right = right.execute(e, q)?;
-        node.top = top.execute(e, q)?;
-        node.bottom = bottom.execute(e, q)?;
+        node.left = left.execute(e, q, t)?;
+        node.right = right.execute(e, q, t)?;
+        node.top = top.execute(e, q, t)?;
+        node.bottom = bottom.execute(e, q, t)?;
 
-        node.width = width.execute(e, q)?;
-        node.height = height.execute(e, q)?;
+        node.width = width.execute(e, q, t)?;
+        node.height = height.execute(e, q, t)?;
 
-        node.min_width = min_width.execute(e, q)?;
-        node.min_height = min_height.execute(e, q)?;
-        node.max_width = max_width.execute(e, q)?;
-        node.max_height = max_height.execute(e, q)?;
-        node.margin.left = margin_left.execute(e, q)?;
-        node.margin.right = margin_right.execute(e, q)?;
-        node.margin.top = margin_top.execute(e, q)?;
-        node.margin.bottom = margin_bottom.execute(e, q)?;
-        node.padding.left = padding_left.execute(e, q)?;
-        node.padding.right = padding_right.execute(e, q)?;
-        node.padding.top = padding_top.execute(e, q)?;
-        node.padding.bottom = padding_bottom.execute(e, q)?;
-        node.border.left = border_left.execute(e, q)?;
-        node.border.right = border_right.execute(e, q)?;
-        node.border.top = border_top.execute(e, q)?;
-        node.border.bottom = border_bottom.execute(e, q)?;
-        node.flex_basis = flex_basis.execute(e, q)?;
-        node.row_gap = row_gap.execute(e, q)?;
-        node.column_gap = column_gap.execute(e, q)?;
+        node.min_width = min_width.execute(e, q, t)?;
+        node.min_height = min_height.execute(e, q, t)?;
+        node.max_width = max_width.execute(e, q, t)?;
+        node.max_height = max_height.execute(e, q, t)?;
+        node.margin.left = margin_left.execute(e, q, t)?;
+        node.margin.right = margin_right.execute(e, q, t)?;
+        node.margin.top = margin_top.execute(e, q, t)?;
+        node.margin.bottom = margin_bottom.execute(e, q, t)?;
+        node.padding.left = padding_left.execute(e, q, t)?;
+        node.padding.right = padding_right.execute(e, q, t)?;
+        node.padding.top = padding_top.execute(e, q, t)?;
+        node.padding.bottom = padding_bottom.execute(e, q, t)?;
+        node.border.left = border_left.execute(e, q, t)?;
+        node.border.right = border_right.execute(e, q, t)?;
+        node.border.top = border_top.execute(e, q, t)?;
+        node.border.bottom = border_bottom.execute(e, q, t)?;
+        node.flex_basis = flex_basis.execute(e, q, t)?;
+        node.row_gap = row_gap.execute(e, q, t)?;
+        node.column_gap = column_gap.execute(e, q, t)?;
         let text = text
             .as_ref()
-            .and_then(|text| text.execute(e, q).ok())
+            .and_then(|text| text.execute(e, q, t).ok())
             .map(Text);
         let text_font = text.as_ref().map(|_| TextFont {
             font_size,
@@ -506,6 +511,7 @@ pub fn spawn_node<T>(
 type FmtExprFn = dyn for<'a, 'b> Fn(
         &'a EntityMap,
         &'a Query<'b, 'b, &'static ComponentValue>,
+        Timestamp,
     ) -> Result<String, String>
     + Send
     + Sync;
@@ -520,10 +526,11 @@ impl CompiledFmtExpr {
         &'a self,
         entity_map: &'a EntityMap,
         values: &'a Query<'b, 'b, &'static ComponentValue>,
+        timestamp: Timestamp,
     ) -> Result<String, String> {
         match self {
             CompiledFmtExpr::String(str) => Ok(str.clone()),
-            CompiledFmtExpr::Closure(c) => (c)(entity_map, values),
+            CompiledFmtExpr::Closure(c) => (c)(entity_map, values, timestamp),
         }
     }
 }
@@ -533,8 +540,8 @@ fn compile_fmt_expr(expr: FmtExpr) -> CompiledFmtExpr {
         FmtExpr::String(str) => CompiledFmtExpr::String(str),
         FmtExpr::Expr(expr) => {
             let expr = compile_eql_expr(expr);
-            CompiledFmtExpr::Closure(Box::new(move |e, q| {
-                expr.execute(e, q).map(|v| v.to_string())
+            CompiledFmtExpr::Closure(Box::new(move |e, q, t| {
+                expr.execute(e, q, t).map(|v| v.to_string())
             }))
         }
     }
@@ -542,9 +549,9 @@ fn compile_fmt_expr(expr: FmtExpr) -> CompiledFmtExpr {
 
 pub fn compile_fmt_string(expr: Vec<FmtExpr>) -> CompiledFmtExpr {
     let exprs: Vec<_> = expr.into_iter().map(compile_fmt_expr).collect();
-    CompiledFmtExpr::Closure(Box::new(move |e, q| {
+    CompiledFmtExpr::Closure(Box::new(move |e, q, t| {
         exprs.iter().try_fold(String::new(), |mut acc, expr| {
-            acc.push_str(&expr.execute(e, q)?);
+            acc.push_str(&expr.execute(e, q, t)?);
             Ok(acc)
         })
     }))
@@ -568,6 +575,12 @@ impl ComponentValueExt for ComponentValue {
             ComponentValue::Bool(a) => a.buf.as_buf().first().map(|&v| if v { 1.0 } else { 0.0 }),
             ComponentValue::F32(array) => array.buf.as_buf().first().copied(),
             ComponentValue::F64(array) => array.buf.as_buf().first().map(|&v| v as f32),
+            ComponentValue::F16(a) => a.buf.as_buf().first().map(|v| v.to_f32()),
+            ComponentValue::I128(a) => a.buf.as_buf().first().map(|&v| v as f32),
+            ComponentValue::U128(a) => a.buf.as_buf().first().map(|&v| v as f32),
+            ComponentValue::C64(a) => a.buf.as_buf().first().map(|v| v.re),
+            ComponentValue::C128(a) => a.buf.as_buf().first().map(|v| v.re as f32),
+            ComponentValue::Bytes(_) | ComponentValue::Utf8(_) => None,
         }
     }
 }
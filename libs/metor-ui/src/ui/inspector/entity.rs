@@ -163,7 +163,10 @@ impl WidgetSystem for InspectorEntity<'_, '_> {
                     }
                 }
                 create_graph = graph_clicked;
-            } else if metadata.is_string() {
+            } else if metadata.is_string()
+                || component_value.prim_type().is_var_len()
+                || is_read_only(&component_value)
+            {
                 let [graph_clicked] = label::label_with_buttons(
                     ui,
                     [icon_chart],
@@ -171,7 +174,11 @@ impl WidgetSystem for InspectorEntity<'_, '_> {
                     get_scheme().text_primary,
                     egui::Margin::symmetric(0, 4).bottom(12.0),
                 );
-                if let Some(s) = component_value.as_str() {
+                if let ComponentValue::Bytes(bytes) = &*component_value {
+                    ui.label(format!("{bytes:02x?}"));
+                } else if is_read_only(&component_value) {
+                    ui.label(component_value.to_string());
+                } else if let Some(s) = component_value.as_str() {
                     ui.label(s);
                 } else {
                     ui.label(format!("invalid str {:?}", component_value));
@@ -209,6 +216,18 @@ impl WidgetSystem for InspectorEntity<'_, '_> {
 #[derive(Resource, Default)]
 pub struct ComponentFilter(pub String);
 
+/// Whether the value's elements have no `ElementValueMut` to edit them through
+fn is_read_only(value: &ComponentValue) -> bool {
+    matches!(
+        value,
+        ComponentValue::F16(_)
+            | ComponentValue::I128(_)
+            | ComponentValue::U128(_)
+            | ComponentValue::C64(_)
+            | ComponentValue::C128(_)
+    )
+}
+
 fn inspector_item_value_ui(
     ui: &mut egui::Ui,
     label: &str,
@@ -18,7 +18,7 @@ use crate::{
         SettingModal, SettingModalState,
         button::{EButton, ECheckboxButton},
         colors::get_scheme,
-        inspector::{color_popup, eql_autocomplete, query},
+        inspector::{color_popup, eql_autocomplete, eql_check_label, eql_query, query},
         label::{self, label_with_buttons},
         plot::GraphState,
         query_plot::QueryPlotData,
@@ -185,7 +185,12 @@ impl WidgetSystem for InspectorGraph<'_, '_> {
                     ui.label(egui::RichText::new("Query").color(get_scheme().text_secondary));
                     configure_input_with_border(ui.style_mut());
                     let query_type = query_plot.data.query_type;
-                    let query_res = ui.add(query(&mut query_plot.data.query, query_type));
+                    let query_res = match query_type {
+                        QueryType::EQL => {
+                            ui.add(eql_query(&mut query_plot.data.query, &eql_context.0))
+                        }
+                        QueryType::SQL => ui.add(query(&mut query_plot.data.query, query_type)),
+                    };
                     if query_type == QueryType::EQL {
                         eql_autocomplete(
                             ui,
@@ -193,6 +198,7 @@ impl WidgetSystem for InspectorGraph<'_, '_> {
                             &query_res,
                             &mut query_plot.data.query,
                         );
+                        eql_check_label(ui, &eql_context.0, &query_plot.data.query);
                     }
                     let enter_key = query_res.lost_focus()
                         && ui.ctx().input(|i| i.key_pressed(egui::Key::Enter));
@@ -7,7 +7,7 @@ use bevy::{
 use bevy_egui::egui::{self, Align};
 use bevy_infinite_grid::InfiniteGrid;
 use metor_proto_bevy::EntityMap;
-use metor_proto_wkt::{ComponentValue, QueryType, WorldPos};
+use metor_proto_wkt::{ComponentValue, CurrentTimestamp, WorldPos};
 
 use crate::EqlContext;
 use crate::object_3d::{ComponentArrayExt, EditableEQL, compile_eql_expr};
@@ -21,7 +21,7 @@ use crate::{
     ui::{label::ELabel, theme, utils::MarginSides},
 };
 
-use super::{empty_inspector, eql_autocomplete, query};
+use super::{empty_inspector, eql_autocomplete, eql_check_label, eql_query};
 
 #[derive(Component)]
 pub struct Viewport {
@@ -155,8 +155,9 @@ fn eql_input(ui: &mut egui::Ui, editable_expr: &mut EditableEQL, ctx: &eql::Cont
     ui.scope(|ui| {
         ui.spacing_mut().item_spacing.y = 0.0;
         configure_input_with_border(ui.style_mut());
-        let query_res = ui.add(query(&mut editable_expr.eql, QueryType::EQL));
+        let query_res = ui.add(eql_query(&mut editable_expr.eql, ctx));
         eql_autocomplete(ui, ctx, &query_res, &mut editable_expr.eql);
+        eql_check_label(ui, ctx, &editable_expr.eql);
         if query_res.changed() {
             if editable_expr.eql.is_empty() {
                 editable_expr.compiled_expr = None;
@@ -179,13 +180,14 @@ pub fn set_viewport_pos(
     mut pos: Query<&mut WorldPos>,
     entity_map: Res<EntityMap>,
     values: Query<&'static ComponentValue>,
+    tick: Res<CurrentTimestamp>,
 ) {
     for viewport in viewports.iter() {
         let Ok(mut pos) = pos.get_mut(viewport.parent_entity) else {
             continue;
         };
         if let Some(compiled_expr) = &viewport.pos.compiled_expr {
-            if let Ok(val) = compiled_expr.execute(&entity_map, &values) {
+            if let Ok(val) = compiled_expr.execute(&entity_map, &values, tick.0) {
                 if let Some(world_pos) = val.as_world_pos() {
                     //*pos = world_pos;
                     *pos = WorldPos {
@@ -196,7 +198,7 @@ pub fn set_viewport_pos(
             }
             if let Some(compiled_expr) = &viewport.look_at.compiled_expr {
                 if let Ok(val) = compiled_expr
-                    .execute(&entity_map, &values)
+                    .execute(&entity_map, &values, tick.0)
                     .inspect_err(|err| {
                         println!("look at invalid {:?}", err);
                     })
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, sync::Arc};
+
 use bevy_egui::egui;
 use egui::color_picker::{Alpha, color_picker_color32};
 use metor_proto_wkt::QueryType;
@@ -19,41 +21,171 @@ pub fn query(query: &mut String, ty: QueryType) -> impl egui::Widget {
         ))
     }
 }
+
+/// Like [`query`] for EQL, but underlines the spans that fail [`eql::Context::check`]
+/// and shows the type of every subexpression on hover.
+pub fn eql_query<'a>(query: &'a mut String, eql_ctx: &'a eql::Context) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| {
+        let check = cached_check(ui, eql_ctx, query);
+        let mut layouter = |ui: &egui::Ui, text: &dyn egui::TextBuffer, _wrap_width: f32| {
+            let text = text.as_str();
+            let check = cached_check(ui, eql_ctx, text);
+            let font_id = inspector_font(ui);
+            let job = eql_layout_job(text, &check, font_id, ui.visuals().text_color());
+            ui.fonts(|f| f.layout_job(job))
+        };
+        let hover_text = check
+            .types
+            .iter()
+            .filter_map(|hint| Some(format!("{}: {}", query.get(hint.span.clone())?, hint.ty)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let res = inspector_text_edit(
+            ui,
+            egui::TextEdit::singleline(query)
+                .hint_text("EQL Query (i.e a.world_pos.x)")
+                .layouter(&mut layouter),
+        );
+        if hover_text.is_empty() {
+            res
+        } else {
+            res.on_hover_text(hover_text)
+        }
+    }
+}
+
+/// The checks of the EQL inputs shown this pass, by input
+#[derive(Clone, Default)]
+struct EqlChecks {
+    pass: u64,
+    checks: HashMap<String, Arc<eql::Check>>,
+}
+
+/// Checks `eql` at most once per pass, as the text field, its layouter and the label
+/// beneath it all show the result. Checks aren't kept across passes, so they never go
+/// stale when the context changes.
+fn cached_check(ui: &egui::Ui, eql_ctx: &eql::Context, eql: &str) -> Arc<eql::Check> {
+    let pass = ui.ctx().cumulative_pass_nr();
+    ui.ctx().data_mut(|data| {
+        let checks = data.get_temp_mut_or_default::<EqlChecks>(egui::Id::new("eql_checks"));
+        if checks.pass != pass {
+            checks.pass = pass;
+            checks.checks.clear();
+        }
+        checks
+            .checks
+            .entry(eql.to_string())
+            .or_insert_with(|| Arc::new(eql_ctx.check(eql)))
+            .clone()
+    })
+}
+
+fn eql_layout_job(
+    text: &str,
+    check: &eql::Check,
+    font_id: egui::FontId,
+    color: egui::Color32,
+) -> egui::text::LayoutJob {
+    let mut bounds = vec![0, text.len()];
+    for diagnostic in &check.diagnostics {
+        bounds.push(diagnostic.span.start.min(text.len()));
+        bounds.push(diagnostic.span.end.min(text.len()));
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut job = egui::text::LayoutJob::default();
+    for range in bounds.windows(2) {
+        let (start, end) = (range[0], range[1]);
+        let Some(segment) = text.get(start..end) else {
+            continue;
+        };
+        let has_error = check
+            .diagnostics
+            .iter()
+            .any(|d| d.span.start <= start && end <= d.span.end);
+        let mut format = egui::TextFormat::simple(font_id.clone(), color);
+        if has_error {
+            format.underline = egui::Stroke::new(1.5, get_scheme().error);
+        }
+        job.append(segment, 0.0, format);
+    }
+    // parse errors at the end of the input have an empty span, so mark them
+    // with a trailing underlined space
+    if check.diagnostics.iter().any(|d| d.span.start >= text.len()) {
+        let mut format = egui::TextFormat::simple(font_id, color);
+        format.underline = egui::Stroke::new(1.5, get_scheme().error);
+        job.append(" ", 0.0, format);
+    }
+    job
+}
+
+/// Shows the first error, or the result type of `eql`, beneath an EQL text field.
+pub fn eql_check_label(ui: &mut egui::Ui, eql_ctx: &eql::Context, eql: &str) {
+    if eql.trim().is_empty() {
+        return;
+    }
+    let check = cached_check(ui, eql_ctx, eql);
+    let mut font_id = egui::TextStyle::Small.resolve(ui.style());
+    font_id.size = 11.0;
+    let text = if let Some(diagnostic) = check.diagnostics.first() {
+        egui::RichText::new(&diagnostic.message).color(get_scheme().error)
+    } else if let Some(ty) = &check.ty {
+        egui::RichText::new(ty.to_string()).color(get_scheme().text_tertiary)
+    } else {
+        return;
+    };
+    ui.add_space(4.0);
+    ui.label(text.font(font_id));
+}
+
+fn inspector_font(ui: &egui::Ui) -> egui::FontId {
+    let mut font_id = egui::TextStyle::Button.resolve(ui.style());
+    font_id.size = 12.0;
+    font_id
+}
+
 pub fn inspector_text_field(query: &mut String, hint_text: &str) -> impl egui::Widget {
     move |ui: &mut egui::Ui| {
-        let scheme = get_scheme();
-        ui.scope(|ui| {
-            ui.style_mut().visuals.widgets.inactive = egui::style::WidgetVisuals {
-                bg_fill: scheme.bg_primary,
-                weak_bg_fill: scheme.bg_primary,
-                bg_stroke: egui::Stroke::NONE,
-                corner_radius: theme::corner_radius_xs(),
-                fg_stroke: egui::Stroke::new(1.0, scheme.text_primary),
-                expansion: 0.0,
-            };
-            ui.style_mut().visuals.widgets.active = egui::style::WidgetVisuals {
-                bg_stroke: egui::Stroke::new(1.0, scheme.highlight),
-                ..ui.style_mut().visuals.widgets.inactive
-            };
-            ui.style_mut().visuals.widgets.hovered = egui::style::WidgetVisuals {
-                bg_stroke: egui::Stroke::new(1.0, scheme.highlight.opacity(0.5)),
-                ..ui.style_mut().visuals.widgets.inactive
-            };
-            let mut font_id = egui::TextStyle::Button.resolve(ui.style());
-            font_id.size = 12.0;
-            ui.add(
-                egui::TextEdit::singleline(query)
-                    .font(font_id)
-                    .lock_focus(true)
-                    .hint_text(hint_text)
-                    .desired_width(ui.available_width() - 16.0)
-                    .margin(8.0),
-            )
-        })
-        .inner
+        let font_id = inspector_font(ui);
+        inspector_text_edit(
+            ui,
+            egui::TextEdit::singleline(query)
+                .font(font_id)
+                .hint_text(hint_text),
+        )
     }
 }
 
+fn inspector_text_edit(ui: &mut egui::Ui, text_edit: egui::TextEdit<'_>) -> egui::Response {
+    let scheme = get_scheme();
+    ui.scope(|ui| {
+        ui.style_mut().visuals.widgets.inactive = egui::style::WidgetVisuals {
+            bg_fill: scheme.bg_primary,
+            weak_bg_fill: scheme.bg_primary,
+            bg_stroke: egui::Stroke::NONE,
+            corner_radius: theme::corner_radius_xs(),
+            fg_stroke: egui::Stroke::new(1.0, scheme.text_primary),
+            expansion: 0.0,
+        };
+        ui.style_mut().visuals.widgets.active = egui::style::WidgetVisuals {
+            bg_stroke: egui::Stroke::new(1.0, scheme.highlight),
+            ..ui.style_mut().visuals.widgets.inactive
+        };
+        ui.style_mut().visuals.widgets.hovered = egui::style::WidgetVisuals {
+            bg_stroke: egui::Stroke::new(1.0, scheme.highlight.opacity(0.5)),
+            ..ui.style_mut().visuals.widgets.inactive
+        };
+        ui.add(
+            text_edit
+                .lock_focus(true)
+                .desired_width(ui.available_width() - 16.0)
+                .margin(8.0),
+        )
+    })
+    .inner
+}
+
 pub fn eql_autocomplete(
     ui: &mut egui::Ui,
     eql_context: &eql::Context,
@@ -256,8 +388,11 @@ pub fn eql_textfield(
 ) -> egui::Response {
     ui.vertical(|ui| {
         ui.spacing_mut().item_spacing.y = 0.0;
-        let eql_res = ui.add_enabled(enabled, query(eql, metor_proto_wkt::QueryType::EQL));
+        let eql_res = ui.add_enabled(enabled, eql_query(eql, eql_ctx));
         eql_autocomplete(ui, eql_ctx, &eql_res, eql);
+        if enabled {
+            eql_check_label(ui, eql_ctx, eql);
+        }
         eql_res
     })
     .inner
@@ -4,7 +4,7 @@ use bevy::{
 };
 use bevy_egui::egui::Ui;
 use metor_proto_bevy::EntityMap;
-use metor_proto_wkt::ComponentValue;
+use metor_proto_wkt::{ComponentValue, CurrentTimestamp};
 use walkers::{
     HttpOptions, HttpTiles, Map, MapMemory, Position,
     extras::{Place, Places},
@@ -47,6 +47,7 @@ pub struct MapTileWidget<'w, 's> {
     map_states: Query<'w, 's, &'static mut MapTileState>,
     entity_map: Res<'w, EntityMap>,
     component_values: Query<'w, 's, &'static ComponentValue>,
+    tick: Res<'w, CurrentTimestamp>,
     _selected_object: ResMut<'w, SelectedObject>,
 }
 
@@ -81,7 +82,7 @@ impl WidgetSystem for MapTileWidget<'_, '_> {
             return;
         };
 
-        let pos = match compiled_expr.execute(&state.entity_map, &state.component_values) {
+        let pos = match compiled_expr.execute(&state.entity_map, &state.component_values, state.tick.0) {
             Ok(component_value) => extract_positions(&component_value),
             Err(e) => {
                 ui.label(format!("Error evaluating EQL: {}", e));
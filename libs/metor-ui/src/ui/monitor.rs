@@ -62,6 +62,22 @@ impl WidgetSystem for MonitorWidget<'_, '_> {
                 let label = RichText::new(&metadata.name).monospace().size(25.);
                 ui.label(label);
                 ui.add_space(20.0);
+                // variable length values are shown whole, rather than element by element, as are
+                // the types that can't be edited in place
+                let text = match &*value {
+                    ComponentValue::Utf8(s) => Some(s.clone()),
+                    ComponentValue::Bytes(bytes) => Some(format!("{bytes:02x?}")),
+                    ComponentValue::F16(_)
+                    | ComponentValue::I128(_)
+                    | ComponentValue::U128(_)
+                    | ComponentValue::C64(_)
+                    | ComponentValue::C128(_) => Some(value.to_string()),
+                    _ => None,
+                };
+                if let Some(text) = text {
+                    ui.label(RichText::new(text).monospace().size(18.));
+                    return;
+                }
                 let width = ui.max_rect().width();
                 ui.horizontal_wrapped(|ui| {
                     ui.set_width(width);
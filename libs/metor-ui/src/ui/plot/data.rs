@@ -10,6 +10,7 @@ use bevy::{
 use bevy_render::render_resource::{Buffer, BufferDescriptor, BufferSlice, BufferUsages};
 use bevy_render::renderer::{RenderDevice, RenderQueue};
 use itertools::{Itertools, MinMaxResult};
+use metor_proto::num::{C64, C128, F16, I128, U128};
 use metor_proto::schema::Schema;
 use metor_proto::types::{
     ComponentId, ComponentView, IntoLenPacket, LenPacket, OwnedPacket, PrimType, Timestamp,
@@ -76,6 +77,9 @@ impl PlotDataComponent {
         timestamp: Timestamp,
         earliest_timestamp: Timestamp,
     ) {
+        if component_view.prim_type().is_var_len() {
+            return;
+        }
         let element_names = self
             .element_names
             .iter()
@@ -341,6 +345,48 @@ pub fn handle_time_series(
                     &mut lines,
                     earliest_timestamp.0,
                 ),
+                PrimType::F16 => process_time_series::<F16>(
+                    buf,
+                    timestamps,
+                    len,
+                    plot_data,
+                    &mut lines,
+                    earliest_timestamp.0,
+                ),
+                PrimType::I128 => process_time_series::<I128>(
+                    buf,
+                    timestamps,
+                    len,
+                    plot_data,
+                    &mut lines,
+                    earliest_timestamp.0,
+                ),
+                PrimType::U128 => process_time_series::<U128>(
+                    buf,
+                    timestamps,
+                    len,
+                    plot_data,
+                    &mut lines,
+                    earliest_timestamp.0,
+                ),
+                PrimType::C64 => process_time_series::<C64>(
+                    buf,
+                    timestamps,
+                    len,
+                    plot_data,
+                    &mut lines,
+                    earliest_timestamp.0,
+                ),
+                PrimType::C128 => process_time_series::<C128>(
+                    buf,
+                    timestamps,
+                    len,
+                    plot_data,
+                    &mut lines,
+                    earliest_timestamp.0,
+                ),
+                // variable length values can't be plotted
+                PrimType::Bytes | PrimType::Utf8 => {}
             }
             let Some(last_timestamp) = timestamps.last() else {
                 return;
@@ -684,6 +730,37 @@ impl AsF32 for f32 {
     }
 }
 
+impl AsF32 for F16 {
+    fn as_f32(&self) -> f32 {
+        self.to_f32()
+    }
+}
+
+impl AsF32 for I128 {
+    fn as_f32(&self) -> f32 {
+        self.get() as f32
+    }
+}
+
+impl AsF32 for U128 {
+    fn as_f32(&self) -> f32 {
+        self.get() as f32
+    }
+}
+
+// complex values are plotted by their real part
+impl AsF32 for C64 {
+    fn as_f32(&self) -> f32 {
+        self.re
+    }
+}
+
+impl AsF32 for C128 {
+    fn as_f32(&self) -> f32 {
+        self.re as f32
+    }
+}
+
 impl AsF32 for bool {
     fn as_f32(&self) -> f32 {
         if *self { 1.0 } else { 0.0 }
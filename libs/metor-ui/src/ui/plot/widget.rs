@@ -25,9 +25,9 @@ use bevy::{
 };
 use bevy_egui::egui::{self, Align, Layout};
 use egui::{CornerRadius, Frame, Margin, RichText, Stroke};
-use metor_proto::types::Timestamp;
+use metor_proto::types::{ClockDomain, Timestamp};
 use metor_proto_bevy::{ComponentMetadataRegistry, ComponentPath};
-use metor_proto_wkt::{CurrentTimestamp, EarliestTimestamp};
+use metor_proto_wkt::{CurrentTimestamp, EarliestTimestamp, MetadataExt};
 use std::time::{Duration, Instant};
 use std::{
     fmt::Debug,
@@ -43,7 +43,7 @@ use crate::{
             CollectedGraphData, GraphState, Line,
             gpu::{LineBundle, LineConfig, LineUniform},
         },
-        time_label::{PrettyDuration, time_label},
+        time_label::{PrettyDuration, timestamp_label},
         timeline::DurationExt,
         utils::format_num,
         widgets::WidgetSystem,
@@ -64,6 +64,7 @@ pub struct PlotWidget<'w, 's> {
     selected_time_range: Res<'w, SelectedTimeRange>,
     earliest_timestamp: Res<'w, EarliestTimestamp>,
     current_timestamp: Res<'w, CurrentTimestamp>,
+    metadata_store: Res<'w, ComponentMetadataRegistry>,
     time_range_behavior: ResMut<'w, TimeRangeBehavior>,
     line_query: Query<'w, 's, &'static LineHandle>,
 }
@@ -87,6 +88,7 @@ impl WidgetSystem for PlotWidget<'_, '_> {
             selected_time_range,
             earliest_timestamp,
             current_timestamp,
+            metadata_store,
             mut time_range_behavior,
             line_query,
         } = state.get_mut(world);
@@ -117,6 +119,7 @@ impl WidgetSystem for PlotWidget<'_, '_> {
             selected_time_range.0.clone(),
             earliest_timestamp.0,
             current_timestamp.0,
+            graph_clock_domain(&graph_state, &metadata_store),
         )
         .render(
             ui,
@@ -130,11 +133,32 @@ impl WidgetSystem for PlotWidget<'_, '_> {
     }
 }
 
+/// The clock domain shared by a graph's components, or UTC if they don't agree
+fn graph_clock_domain(
+    graph_state: &GraphState,
+    metadata_store: &ComponentMetadataRegistry,
+) -> ClockDomain {
+    let mut domains = graph_state
+        .components
+        .keys()
+        .filter_map(|path| metadata_store.get_metadata(&path.id))
+        .map(|metadata| metadata.clock_domain());
+    let Some(domain) = domains.next() else {
+        return ClockDomain::default();
+    };
+    if domains.all(|other| other == domain) {
+        domain
+    } else {
+        ClockDomain::default()
+    }
+}
+
 #[derive(Debug)]
 pub struct TimeseriesPlot {
     selected_range: Range<Timestamp>,
     current_timestamp: Timestamp,
     earliest_timestamp: Timestamp,
+    clock_domain: ClockDomain,
     bounds: PlotBounds,
     rect: egui::Rect,
     inner_rect: egui::Rect,
@@ -178,6 +202,7 @@ impl TimeseriesPlot {
         mut selected_range: Range<Timestamp>,
         earliest_timestamp: Timestamp,
         current_timestamp: Timestamp,
+        clock_domain: ClockDomain,
     ) -> Self {
         let inner_rect = get_inner_rect(rect);
 
@@ -195,6 +220,7 @@ impl TimeseriesPlot {
             selected_range,
             current_timestamp,
             earliest_timestamp,
+            clock_domain,
 
             bounds,
             rect,
@@ -302,9 +328,7 @@ impl TimeseriesPlot {
                     }),
             )
             .show(ui.ctx(), |ui| {
-                let time: hifitime::Epoch = timestamp.into();
-
-                ui.add(time_label(time));
+                ui.add(timestamp_label(timestamp, self.clock_domain));
                 let offset = hifitime::Duration::from_microseconds(
                     (timestamp.0 - self.selected_range.start.0) as f64,
                 );
@@ -1,4 +1,7 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use arrow::{
     array::{
@@ -13,16 +16,19 @@ use bevy::{
     asset::{Assets, Handle},
     ecs::{hierarchy::ChildOf, system::SystemParam},
     math::DVec2,
-    prelude::{Commands, Component, Entity, In, Query, Res, ResMut},
+    prelude::{Commands, Component, Entity, In, InRef, Query, Res, ResMut},
     render::camera::Projection,
 };
 use egui::RichText;
-use metor_proto_bevy::CommandsExt;
-use metor_proto_wkt::{ArrowIPC, ErrorResponse, QueryPlot, QueryType, SQLQuery};
+use metor_proto::types::{ComponentId, Msg, OwnedPacket, PacketId, Timestamp};
+use metor_proto_bevy::{CommandsExt, PacketGrantR};
+use metor_proto_wkt::{
+    ArrowIPC, ComponentValue, ErrorResponse, GetTimeSeries, QueryPlot, QueryType, SQLQuery,
+};
 use itertools::Itertools;
 
 use crate::{
-    EqlContext,
+    EqlContext, SelectedTimeRange,
     ui::{
         colors::{ColorExt, EColor, get_scheme},
         plot::{
@@ -53,6 +59,7 @@ pub struct QueryPlotData {
     pub x_offset: f64,
     pub y_offset: f64,
     pub last_refresh: Option<Instant>,
+    pub eql_fetch: Option<EqlFetch>,
 }
 
 impl Default for QueryPlotData {
@@ -73,6 +80,7 @@ impl Default for QueryPlotData {
             x_offset: Default::default(),
             y_offset: Default::default(),
             last_refresh: Some(Instant::now()),
+            eql_fetch: None,
         }
     }
 }
@@ -86,6 +94,135 @@ pub enum QueryPlotState {
     Error(ErrorResponse),
 }
 
+/// The time series an EQL plot is evaluated over with [`eql::Expr::eval_series`], fetched from
+/// the db one component at a time
+pub struct EqlFetch {
+    expr: eql::Expr,
+    /// The packet id each component's time series was requested with
+    requests: HashMap<ComponentId, PacketId>,
+    fetched: HashMap<ComponentId, (Vec<Timestamp>, Vec<u8>)>,
+}
+
+impl eql::Source for EqlFetch {
+    fn latest(&self, component: &eql::Component) -> Option<ComponentValue> {
+        let (_, data) = self.fetched.get(&component.id)?;
+        let size = component.schema.size();
+        let view = metor_proto::types::ComponentView::try_from_bytes_shape(
+            data.get(data.len().checked_sub(size)?..)?,
+            component.schema.shape(),
+            component.schema.prim_type(),
+        )
+        .ok()?;
+        Some(ComponentValue::from_view(view))
+    }
+
+    fn latest_timestamp(&self, component: &eql::Component) -> Option<Timestamp> {
+        let (timestamps, _) = self.fetched.get(&component.id)?;
+        timestamps.last().copied()
+    }
+
+    fn time_series(&self, component: &eql::Component) -> Option<eql::TimeSeriesRef<'_>> {
+        let (timestamps, data) = self.fetched.get(&component.id)?;
+        Some(eql::TimeSeriesRef { timestamps, data })
+    }
+}
+
+impl EqlFetch {
+    /// Requests the time series of every component `expr` reads over `range`
+    fn send(
+        expr: eql::Expr,
+        entity: Entity,
+        range: std::ops::Range<Timestamp>,
+        commands: &mut Commands,
+    ) -> Self {
+        let mut requests = HashMap::new();
+        for component in expr.components() {
+            let component_id = component.id;
+            let packet_id = fastrand::u16(..).to_le_bytes();
+            requests.insert(component_id, packet_id);
+            let msg = GetTimeSeries {
+                id: packet_id,
+                range: range.clone(),
+                component_id,
+                limit: None,
+            };
+            commands.send_req_with_handler(
+                msg,
+                packet_id,
+                move |pkt: InRef<OwnedPacket<PacketGrantR>>,
+                      states: Query<&mut QueryPlotData>,
+                      xy_lines: ResMut<Assets<XYLine>>| {
+                    handle_eql_time_series(pkt, states, xy_lines, entity, component_id, packet_id);
+                },
+            );
+        }
+        Self {
+            expr,
+            requests,
+            fetched: HashMap::new(),
+        }
+    }
+}
+
+fn handle_eql_time_series(
+    InRef(pkt): InRef<OwnedPacket<PacketGrantR>>,
+    mut states: Query<&mut QueryPlotData>,
+    mut xy_lines: ResMut<Assets<XYLine>>,
+    entity: Entity,
+    component_id: ComponentId,
+    packet_id: PacketId,
+) {
+    let Ok(mut plot) = states.get_mut(entity) else {
+        return;
+    };
+    let plot = &mut *plot;
+    let Some(fetch) = &mut plot.eql_fetch else {
+        return;
+    };
+    // the plot has been refreshed since this was requested
+    if fetch.requests.get(&component_id) != Some(&packet_id) {
+        return;
+    }
+    match pkt {
+        OwnedPacket::TimeSeries(time_series) => {
+            let (Ok(timestamps), Ok(data)) = (time_series.timestamps(), time_series.data()) else {
+                return;
+            };
+            fetch
+                .fetched
+                .insert(component_id, (timestamps.to_vec(), data.to_vec()));
+        }
+        OwnedPacket::Msg(m) if m.id == ErrorResponse::ID => {
+            let err = m
+                .parse::<ErrorResponse>()
+                .unwrap_or_else(|_| ErrorResponse {
+                    description: "parse failed".to_string(),
+                });
+            plot.eql_fetch = None;
+            plot.state = QueryPlotState::Error(err);
+            return;
+        }
+        _ => return,
+    }
+    if fetch.fetched.len() < fetch.requests.len() {
+        return;
+    }
+    let Some(fetch) = plot.eql_fetch.take() else {
+        return;
+    };
+    match fetch.expr.eval_series(&fetch) {
+        Ok(series) => {
+            plot.process_series(series, &mut xy_lines);
+            plot.state = QueryPlotState::Results;
+        }
+        Err(err) => {
+            plot.state = QueryPlotState::Error(ErrorResponse {
+                description: err.to_string(),
+            });
+        }
+    }
+}
+
 impl QueryPlotData {
     fn process_record_batch(&mut self, batch: RecordBatch, xy_lines: &mut Assets<XYLine>) {
         if batch.num_columns() < 2 || batch.num_rows() == 0 {
@@ -94,9 +231,31 @@ impl QueryPlotData {
 
         let x_col = batch.column(0);
         let y_col = batch.column(1);
+        self.set_line(
+            array_iter(x_col).collect(),
+            array_iter(y_col).collect(),
+            xy_lines,
+        );
+    }
+
+    /// Plots the first element of each value against its timestamp, like the time and value
+    /// columns of the SQL an EQL query compiles to
+    fn process_series(&mut self, series: eql::Series, xy_lines: &mut Assets<XYLine>) {
+        if series.values.is_empty() {
+            return;
+        }
+        let x = series.timestamps.iter().map(|ts| ts.0 as f64).collect();
+        let y = series
+            .values
+            .iter()
+            .map(|value| value.get(0).map(|e| e.as_f64()).unwrap_or_default())
+            .collect();
+        self.set_line(x, y, xy_lines);
+    }
 
-        self.x_offset = array_iter(x_col).fold(f64::INFINITY, f64::min);
-        self.y_offset = array_iter(y_col).fold(f64::INFINITY, f64::min);
+    fn set_line(&mut self, x: Vec<f64>, y: Vec<f64>, xy_lines: &mut Assets<XYLine>) {
+        self.x_offset = x.iter().copied().fold(f64::INFINITY, f64::min);
+        self.y_offset = y.iter().copied().fold(f64::INFINITY, f64::min);
 
         if !self.x_offset.is_finite() {
             self.x_offset = 0.0;
@@ -113,11 +272,11 @@ impl QueryPlotData {
             y_values: vec![],
         };
 
-        for value in array_iter(x_col) {
+        for value in x {
             xy_line.push_x_value((value - self.x_offset) as f32);
         }
 
-        for value in array_iter(y_col) {
+        for value in y {
             xy_line.push_y_value((value - self.y_offset) as f32);
         }
 
@@ -152,6 +311,7 @@ pub struct QueryPlotWidget<'w, 's> {
     states: Query<'w, 's, &'static mut QueryPlotData>,
     graphs_state: Query<'w, 's, &'static mut GraphState>,
     eql_context: Res<'w, EqlContext>,
+    selected_range: Res<'w, SelectedTimeRange>,
     commands: Commands<'w, 's>,
 }
 
@@ -190,9 +350,25 @@ impl WidgetSystem for QueryPlotWidget<'_, '_> {
                 plot.state = QueryPlotState::Requested(Instant::now());
                 plot.last_refresh = Some(Instant::now());
                 let query = match plot.data.query_type {
-                    QueryType::SQL => plot.data.query.to_string(),
-                    QueryType::EQL => match state.eql_context.0.sql(&plot.data.query) {
-                        Ok(sql) => sql,
+                    QueryType::SQL => Some(plot.data.query.to_string()),
+                    // EQL is evaluated here over the time series of the components it reads,
+                    // which supports more of the language than compiling it to SQL
+                    QueryType::EQL => match state.eql_context.0.parse_str(&plot.data.query) {
+                        Ok(expr) if expr.components().is_empty() => {
+                            plot.state = QueryPlotState::Error(ErrorResponse {
+                                description: "the query doesn't read any component".to_string(),
+                            });
+                            return;
+                        }
+                        Ok(expr) => {
+                            plot.eql_fetch = Some(EqlFetch::send(
+                                expr,
+                                entity,
+                                state.selected_range.0.clone(),
+                                &mut state.commands,
+                            ));
+                            None
+                        }
                         Err(err) => {
                             plot.state = QueryPlotState::Error(ErrorResponse {
                                 description: err.to_string(),
@@ -201,36 +377,38 @@ impl WidgetSystem for QueryPlotWidget<'_, '_> {
                         }
                     },
                 };
-                state.commands.send_req_reply(
-                    SQLQuery(query),
-                    move |In(res): In<Result<ArrowIPC<'static>, ErrorResponse>>,
-                          mut states: Query<&mut QueryPlotData>,
-                          mut xy_lines: ResMut<Assets<XYLine>>| {
-                        let Ok(mut plot) = states.get_mut(entity) else {
-                            return true;
-                        };
-                        match res {
-                            Ok(ipc) => {
-                                if let Some(batch) = ipc.batch {
-                                    let mut decoder = arrow::ipc::reader::StreamDecoder::new();
-                                    let mut buffer =
-                                        arrow::buffer::Buffer::from(batch.into_owned());
-                                    if let Some(batch) =
-                                        decoder.decode(&mut buffer).ok().and_then(|b| b)
-                                    {
-                                        plot.process_record_batch(batch, &mut xy_lines);
-                                        plot.state = QueryPlotState::Results;
-                                        return false;
+                if let Some(query) = query {
+                    state.commands.send_req_reply(
+                        SQLQuery(query),
+                        move |In(res): In<Result<ArrowIPC<'static>, ErrorResponse>>,
+                              mut states: Query<&mut QueryPlotData>,
+                              mut xy_lines: ResMut<Assets<XYLine>>| {
+                            let Ok(mut plot) = states.get_mut(entity) else {
+                                return true;
+                            };
+                            match res {
+                                Ok(ipc) => {
+                                    if let Some(batch) = ipc.batch {
+                                        let mut decoder = arrow::ipc::reader::StreamDecoder::new();
+                                        let mut buffer =
+                                            arrow::buffer::Buffer::from(batch.into_owned());
+                                        if let Some(batch) =
+                                            decoder.decode(&mut buffer).ok().and_then(|b| b)
+                                        {
+                                            plot.process_record_batch(batch, &mut xy_lines);
+                                            plot.state = QueryPlotState::Results;
+                                            return false;
+                                        }
                                     }
                                 }
+                                Err(err) => {
+                                    plot.state = QueryPlotState::Error(err);
+                                }
                             }
-                            Err(err) => {
-                                plot.state = QueryPlotState::Error(err);
-                            }
-                        }
-                        true
-                    },
-                );
+                            true
+                        },
+                    );
+                }
             }
 
             if let Some(xy_line_handle) = &plot.xy_line_handle {
@@ -1,6 +1,8 @@
 use std::time::Instant;
 
 use arrow::{
+    array::{Array, AsArray},
+    datatypes::{DataType, TimeUnit, TimestampMicrosecondType},
     record_batch::RecordBatch,
     util::display::{ArrayFormatter, FormatOptions},
 };
@@ -9,6 +11,7 @@ use bevy::{
     prelude::{Commands, Component, Entity, In, Query, Res},
 };
 use egui::{RichText, Stroke};
+use metor_proto::types::{ClockDomain, Timestamp};
 use metor_proto_bevy::CommandsExt;
 use metor_proto_wkt::{ArrowIPC, ErrorResponse, QueryTable, QueryType, SQLQuery};
 
@@ -57,6 +60,7 @@ impl QueryTableState {
 pub struct QueryTableResults<'a> {
     batches: &'a [RecordBatch],
     formatters: Vec<Vec<ArrayFormatter<'a>>>,
+    clock_domains: Vec<ClockDomain>,
 }
 
 impl<'a> QueryTableResults<'a> {
@@ -71,9 +75,28 @@ impl<'a> QueryTableResults<'a> {
                 formatters[i].push(fmt);
             }
         }
+        // the db tags each component's time column with the clock domain from its metadata
+        let clock_domains = batches
+            .first()
+            .map(|batch| {
+                batch
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|field| {
+                        field
+                            .metadata()
+                            .get("clock_domain")
+                            .and_then(|domain| domain.parse().ok())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
         QueryTableResults {
             batches,
             formatters,
+            clock_domains,
         }
     }
 }
@@ -121,7 +144,23 @@ impl egui_table::TableDelegate for QueryTableResults<'_> {
             );
         }
 
-        let label = formatter.value(offset).to_string();
+        let column = self.batches[batch_i].column(cell.col_nr);
+        let clock_domain = self
+            .clock_domains
+            .get(cell.col_nr)
+            .copied()
+            .unwrap_or_default();
+        let label = match column.data_type() {
+            DataType::Timestamp(TimeUnit::Microsecond, _)
+                if clock_domain != ClockDomain::Utc && column.is_valid(offset) =>
+            {
+                let micros = column
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .value(offset);
+                Timestamp(micros).display(clock_domain).to_string()
+            }
+            _ => formatter.value(offset).to_string(),
+        };
         egui::Frame::NONE
             .inner_margin(egui::Margin::symmetric(8, 0))
             .show(ui, |ui| {
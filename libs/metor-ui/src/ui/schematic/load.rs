@@ -139,6 +139,7 @@ impl LoadSchematicParams<'_, '_> {
     }
 
     pub fn spawn_object_3d(&mut self, object_3d: Object3D) {
+        check_schematic_eql(&self.eql.0, "object_3d", &object_3d.eql);
         let Ok(expr) = self.eql.0.parse_str(&object_3d.eql) else {
             return;
         };
@@ -159,6 +160,9 @@ impl LoadSchematicParams<'_, '_> {
     pub fn spawn_panel(&mut self, panel: &Panel, parent_id: Option<TileId>) -> Option<TileId> {
         match panel {
             Panel::Viewport(viewport) => {
+                for eql in [&viewport.pos, &viewport.look_at].into_iter().flatten() {
+                    check_schematic_eql(&self.eql.0, "viewport", eql);
+                }
                 let label = viewport_label(viewport);
                 let pane = ViewportPane::spawn(
                     &mut self.commands,
@@ -225,6 +229,7 @@ impl LoadSchematicParams<'_, '_> {
                 tile_id
             }
             Panel::Graph(graph) => {
+                check_schematic_eql(&self.eql.0, "graph", &graph.eql);
                 let components_tree =
                     eql_to_component_tree(&self.eql, &self.schema_reg, &graph.eql).ok()?;
 
@@ -332,6 +337,7 @@ impl LoadSchematicParams<'_, '_> {
                 )
             }
             Panel::Map(map) => {
+                check_schematic_eql(&self.eql.0, "map", &map.eql);
                 let compiled_expr = self.eql.0.parse_str(&map.eql).ok().map(compile_eql_expr);
                 let entity = self
                     .commands
@@ -360,9 +366,7 @@ pub fn eql_to_component_tree(
     schema_reg: &ComponentSchemaRegistry,
     eql: &str,
 ) -> Result<BTreeMap<ComponentPath, Vec<(bool, Color32)>>, eql::Error> {
-    let eql = ctx.0.parse_str(eql).inspect_err(|err| {
-        warn!(?err, "error parsing graph eql");
-    })?;
+    let eql = ctx.0.parse_str(eql)?;
     let mut component_vec = eql.to_graph_components();
     component_vec.sort();
     let mut components_tree: BTreeMap<ComponentPath, Vec<(bool, Color32)>> = BTreeMap::new();
@@ -384,6 +388,26 @@ pub fn eql_to_component_tree(
     Ok(components_tree)
 }
 
+/// Type-checks an EQL expression from a schematic, logging every diagnostic with
+/// its span underlined.
+fn check_schematic_eql(ctx: &eql::Context, kind: &str, eql: &str) {
+    let check = ctx.check(eql);
+    for diagnostic in &check.diagnostics {
+        let start = eql[..diagnostic.span.start.min(eql.len())].chars().count();
+        let len = eql
+            .get(diagnostic.span.clone())
+            .map(|s| s.chars().count())
+            .unwrap_or(0)
+            .max(1);
+        warn!(
+            "invalid {kind} eql: {}\n  {eql}\n  {}{}",
+            diagnostic.message,
+            " ".repeat(start),
+            "^".repeat(len)
+        );
+    }
+}
+
 pub fn viewport_label(viewport: &Viewport) -> String {
     viewport
         .name
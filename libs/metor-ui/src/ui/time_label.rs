@@ -3,6 +3,7 @@ use std::str::FromStr;
 
 use egui::{Response, Ui};
 use hifitime::prelude::*;
+use metor_proto::types::{ClockDomain, Timestamp};
 
 use crate::ui::colors::get_scheme;
 
@@ -38,6 +39,22 @@ pub fn time_label(time: Epoch) -> impl for<'a> FnOnce(&'a mut Ui) -> Response {
     }
 }
 
+/// Shows `timestamp` in its clock domain: UTC as a [`time_label`], other absolute
+/// domains with their time scale, and relative domains as an offset like `T+1 min 30 s`.
+pub fn timestamp_label(
+    timestamp: Timestamp,
+    domain: ClockDomain,
+) -> impl for<'a> FnOnce(&'a mut Ui) -> Response {
+    move |ui| match timestamp.to_epoch(domain) {
+        Some(time) if domain == ClockDomain::Utc => time_label(time)(ui),
+        _ => {
+            let text = egui::RichText::new(timestamp.display(domain).to_string())
+                .color(get_scheme().text_primary);
+            ui.add(egui::Label::new(text).selectable(false))
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct PrettyDuration(pub hifitime::Duration);
 
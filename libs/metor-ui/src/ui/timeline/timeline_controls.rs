@@ -7,7 +7,8 @@ use egui::{Ui, load::SizedTexture};
 use metor_proto::types::Timestamp;
 use metor_proto_bevy::{CurrentStreamId, PacketTx};
 use metor_proto_wkt::{
-    CurrentTimestamp, EarliestTimestamp, LastUpdated, SetStreamState, SimulationTimeStep,
+    CurrentTimestamp, DbConfig, EarliestTimestamp, LastUpdated, MetadataExt, SetStreamState,
+    SimulationTimeStep,
 };
 
 use crate::{
@@ -17,7 +18,7 @@ use crate::{
         button::EImageButton,
         colors::{ColorExt, get_scheme},
         theme::configure_combo_box,
-        time_label::time_label,
+        time_label::timestamp_label,
         utils::MarginSides,
         widgets::WidgetSystem,
     },
@@ -35,6 +36,7 @@ pub struct TimelineControls<'w> {
     stream_id: Res<'w, CurrentStreamId>,
     earliest_timestamp: Res<'w, EarliestTimestamp>,
     behavior: ResMut<'w, TimeRangeBehavior>,
+    db_config: Res<'w, DbConfig>,
 }
 
 impl WidgetSystem for TimelineControls<'_> {
@@ -57,6 +59,7 @@ impl WidgetSystem for TimelineControls<'_> {
             stream_id,
             earliest_timestamp,
             mut behavior,
+            db_config,
         } = state.get_mut(world);
 
         let mut tick_changed = false;
@@ -161,8 +164,7 @@ impl WidgetSystem for TimelineControls<'_> {
 
                                     // TIME
 
-                                    let time: hifitime::Epoch = tick.0.into();
-                                    ui.add(time_label(time));
+                                    ui.add(timestamp_label(tick.0, db_config.clock_domain()));
 
                                     let time_label = egui::RichText::new("TIME")
                                         .color(get_scheme().text_secondary);
@@ -2,16 +2,21 @@ use convert_case::Casing;
 use miette::IntoDiagnostic;
 use minijinja::{Environment, value::ViaDeserialize};
 use postcard_schema::schema::owned::{
-    OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType, OwnedNamedValue,
+    OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType, OwnedNamedValue, OwnedNamedVariant,
 };
 
 static CPP_STRUCT_TMPL: &str = include_str!("./struct.cpp.jinja");
 static CPP_ENUM_TMPL: &str = include_str!("./enum.cpp.jinja");
 static CPP_NEW_TYPE_STRUCT_TMPL: &str = include_str!("./new_type_struct.cpp.jinja");
 static HEADER_TMPL: &str = include_str!("./header.hpp.jinja");
+static C_STRUCT_TMPL: &str = include_str!("./struct.c.jinja");
+static C_ENUM_TMPL: &str = include_str!("./enum.c.jinja");
+static C_NEW_TYPE_STRUCT_TMPL: &str = include_str!("./new_type_struct.c.jinja");
+static C_HEADER_TMPL: &str = include_str!("./header.h.jinja");
 
 pub trait SchemaExt {
     fn to_cpp() -> miette::Result<String>;
+    fn to_c() -> miette::Result<String>;
 }
 
 impl<S: postcard_schema::Schema> SchemaExt for S {
@@ -19,6 +24,11 @@ impl<S: postcard_schema::Schema> SchemaExt for S {
         let owned_ty: OwnedNamedType = S::SCHEMA.into();
         generate_cpp(&owned_ty)
     }
+
+    fn to_c() -> miette::Result<String> {
+        let owned_ty: OwnedNamedType = S::SCHEMA.into();
+        generate_c(&owned_ty)
+    }
 }
 
 pub fn hpp_header(
@@ -42,6 +52,28 @@ pub fn hpp_header(
     .into_diagnostic()
 }
 
+pub fn h_header(
+    name: impl ToString,
+    types: impl IntoIterator<Item = String>,
+) -> miette::Result<String> {
+    let types = types.into_iter().collect::<Vec<String>>();
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct HeaderData {
+        name: String,
+        types: Vec<String>,
+    }
+    let mut env = Environment::new();
+    env.add_template("header", C_HEADER_TMPL)
+        .into_diagnostic()?;
+
+    let tmpl = env.get_template("header").expect("template missing");
+    tmpl.render(HeaderData {
+        name: name.to_string(),
+        types,
+    })
+    .into_diagnostic()
+}
+
 pub fn generate_cpp(ty: &OwnedNamedType) -> miette::Result<String> {
     let mut env = Environment::new();
     env.add_template("struct", CPP_STRUCT_TMPL)
@@ -180,8 +212,8 @@ pub fn to_cpp_ty(named_ty: OwnedNamedType) -> String {
             to_cpp_ty(*val)
         ),
         OwnedDataModelType::Struct(_) => named_ty.name,
-        OwnedDataModelType::I128 => "int128_t".to_string(),
-        OwnedDataModelType::U128 => "uint128_t".to_string(),
+        OwnedDataModelType::I128 => "postcard_i128_t".to_string(),
+        OwnedDataModelType::U128 => "postcard_u128_t".to_string(),
         OwnedDataModelType::Usize => "size_t".to_string(),
         OwnedDataModelType::Isize => "ssize_t".to_string(),
         OwnedDataModelType::Char => "char".to_string(),
@@ -211,7 +243,9 @@ pub fn cpp_size(ty: &OwnedNamedValue) -> String {
         OwnedDataModelType::I16 => format!("size += postcard_size_i16({});", ty.name),
         OwnedDataModelType::I32 => format!("size += postcard_size_i32({});", ty.name),
         OwnedDataModelType::I64 => format!("size += postcard_size_i64({});", ty.name),
-        OwnedDataModelType::F32 => "size += postcard_size_f32({});".to_string(),
+        OwnedDataModelType::I128 => format!("size += postcard_size_i128({});", ty.name),
+        OwnedDataModelType::U128 => format!("size += postcard_size_u128({});", ty.name),
+        OwnedDataModelType::F32 => "size += postcard_size_f32();".to_string(),
         OwnedDataModelType::F64 => "size += postcard_size_f64();".to_string(),
         OwnedDataModelType::Bool => "size += postcard_size_bool();".to_string(),
         OwnedDataModelType::String => {
@@ -682,3 +716,702 @@ pub fn cpp_decode_variant(value_name: &str, variant: &OwnedDataModelVariant) ->
 pub fn variant_is_unit(variant: &OwnedDataModelVariant) -> bool {
     matches!(variant, OwnedDataModelVariant::UnitVariant)
 }
+
+pub fn generate_c(ty: &OwnedNamedType) -> miette::Result<String> {
+    let mut env = Environment::new();
+    env.add_template("struct", C_STRUCT_TMPL)
+        .into_diagnostic()?;
+    env.add_template("enum", C_ENUM_TMPL).into_diagnostic()?;
+    env.add_template("new_type_struct", C_NEW_TYPE_STRUCT_TMPL)
+        .into_diagnostic()?;
+
+    env.add_function("c_ty", |ty: ViaDeserialize<OwnedNamedType>| to_c_ty(&ty.0));
+
+    env.add_function(
+        "c_size",
+        |expr: &str, ty: ViaDeserialize<OwnedNamedType>| c_size(expr, &ty.0, 0),
+    );
+
+    env.add_function(
+        "c_encode",
+        |expr: &str, ty: ViaDeserialize<OwnedNamedType>| c_encode(expr, &ty.0, 0),
+    );
+
+    env.add_function(
+        "c_decode",
+        |expr: &str, ty: ViaDeserialize<OwnedNamedType>| c_decode(expr, &ty.0, 0),
+    );
+
+    env.add_function("c_tag", |enum_name: &str, variant_name: &str| {
+        c_tag(enum_name, variant_name)
+    });
+
+    env.add_function(
+        "c_variant_ty",
+        |enum_name: &str, variant: ViaDeserialize<OwnedNamedVariant>| {
+            c_variant_ty(enum_name, &variant.0)
+                .map(|ty| to_c_ty(&ty))
+                .unwrap_or_default()
+        },
+    );
+
+    env.add_function(
+        "c_size_variant",
+        |enum_name: &str, variant: ViaDeserialize<OwnedNamedVariant>| {
+            c_variant_code(enum_name, &variant.0, c_size)
+        },
+    );
+
+    env.add_function(
+        "c_encode_variant",
+        |enum_name: &str, variant: ViaDeserialize<OwnedNamedVariant>| {
+            c_variant_code(enum_name, &variant.0, c_encode)
+        },
+    );
+
+    env.add_function(
+        "c_decode_variant",
+        |enum_name: &str, variant: ViaDeserialize<OwnedNamedVariant>| {
+            c_variant_code(enum_name, &variant.0, c_decode)
+        },
+    );
+
+    env.add_function(
+        "variant_is_unit",
+        |variant: ViaDeserialize<OwnedDataModelVariant>| variant_is_unit(&variant.0),
+    );
+    env.add_filter("c_member", |value: &str| c_member(value));
+    env.add_filter("c_ident", |value: &str| c_ident(value));
+
+    let mut result = c_containers(ty);
+    match &ty.ty {
+        OwnedDataModelType::Struct(_) => {
+            let tmpl = env.get_template("struct").expect("template missing");
+            result.push_str(&tmpl.render(ty).into_diagnostic()?);
+            Ok(result)
+        }
+        OwnedDataModelType::Enum(variants) => {
+            let mut structs = String::new();
+            for variant in variants {
+                if let OwnedDataModelVariant::StructVariant(fields) = &variant.ty {
+                    let struct_type = OwnedNamedType {
+                        name: format!("{}{}", ty.name, variant.name),
+                        ty: OwnedDataModelType::Struct(fields.clone()),
+                    };
+                    structs.push_str(&generate_c(&struct_type)?);
+                    structs.push_str("\n\n");
+                }
+            }
+            env.add_global("unit_only", variants.iter().all(|v| variant_is_unit(&v.ty)));
+            let tmpl = env.get_template("enum").expect("template missing");
+            result.push_str(&tmpl.render(ty).into_diagnostic()?);
+            Ok(structs + &result)
+        }
+        OwnedDataModelType::NewtypeStruct(_) => {
+            let tmpl = env
+                .get_template("new_type_struct")
+                .expect("template missing");
+            result.push_str(&tmpl.render(ty).into_diagnostic()?);
+            Ok(result)
+        }
+        _ => Err(miette::miette!("unsupported data ty")),
+    }
+}
+
+/// Returns the C type used to store a value of the passed in type.
+///
+/// Strings and byte arrays are views into the decoded buffer, while sequences, maps, options
+/// and tuples get a generated container type, see [`c_containers`]. 128-bit integers need a
+/// compiler that supports `__int128`.
+pub fn to_c_ty(named_ty: &OwnedNamedType) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => "uint8_t".to_string(),
+        OwnedDataModelType::U16 => "uint16_t".to_string(),
+        OwnedDataModelType::U32 => "uint32_t".to_string(),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => "uint64_t".to_string(),
+        OwnedDataModelType::I8 => "int8_t".to_string(),
+        OwnedDataModelType::I16 => "int16_t".to_string(),
+        OwnedDataModelType::I32 => "int32_t".to_string(),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => "int64_t".to_string(),
+        OwnedDataModelType::U128 => "postcard_u128_t".to_string(),
+        OwnedDataModelType::I128 => "postcard_i128_t".to_string(),
+        OwnedDataModelType::F32 => "float".to_string(),
+        OwnedDataModelType::F64 => "double".to_string(),
+        OwnedDataModelType::Bool => "bool".to_string(),
+        OwnedDataModelType::String => "postcard_str_t".to_string(),
+        OwnedDataModelType::ByteArray => "postcard_bytes_t".to_string(),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            "postcard_bytes_t".to_string()
+        }
+        OwnedDataModelType::Seq(_)
+        | OwnedDataModelType::Map { .. }
+        | OwnedDataModelType::Option(_)
+        | OwnedDataModelType::Tuple(_) => format!("{}_t", c_mangle(named_ty)),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => named_ty.name.clone(),
+        OwnedDataModelType::NewtypeStruct(inner) => to_c_ty(inner),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Returns the name used for a type inside generated container type names
+fn c_mangle(named_ty: &OwnedNamedType) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => "u8".to_string(),
+        OwnedDataModelType::U16 => "u16".to_string(),
+        OwnedDataModelType::U32 => "u32".to_string(),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => "u64".to_string(),
+        OwnedDataModelType::I8 => "i8".to_string(),
+        OwnedDataModelType::I16 => "i16".to_string(),
+        OwnedDataModelType::I32 => "i32".to_string(),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => "i64".to_string(),
+        OwnedDataModelType::U128 => "u128".to_string(),
+        OwnedDataModelType::I128 => "i128".to_string(),
+        OwnedDataModelType::F32 => "f32".to_string(),
+        OwnedDataModelType::F64 => "f64".to_string(),
+        OwnedDataModelType::Bool => "bool".to_string(),
+        OwnedDataModelType::String => "str".to_string(),
+        OwnedDataModelType::ByteArray => "bytes".to_string(),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            "bytes".to_string()
+        }
+        OwnedDataModelType::Seq(inner) => format!("{}_seq", c_mangle(inner)),
+        OwnedDataModelType::Map { key, val } => {
+            format!("{}_{}_map", c_mangle(key), c_mangle(val))
+        }
+        OwnedDataModelType::Option(inner) => format!("{}_option", c_mangle(inner)),
+        OwnedDataModelType::Tuple(tys) => {
+            let tys = tys.iter().map(c_mangle).collect::<Vec<_>>().join("_");
+            format!("{}_tuple", tys)
+        }
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => named_ty.name.clone(),
+        OwnedDataModelType::NewtypeStruct(inner) => c_mangle(inner),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Returns the typedefs for every container type used by the passed in type.
+///
+/// Each typedef is wrapped in an include guard, since the same container is often used by
+/// several generated types in one header.
+pub fn c_containers(ty: &OwnedNamedType) -> String {
+    let mut defs = vec![];
+    match &ty.ty {
+        OwnedDataModelType::Struct(fields) => {
+            for field in fields {
+                c_container_defs(&field.ty, &mut defs);
+            }
+        }
+        OwnedDataModelType::Enum(variants) => {
+            for variant in variants {
+                match &variant.ty {
+                    OwnedDataModelVariant::NewtypeVariant(inner) => {
+                        c_container_defs(inner, &mut defs)
+                    }
+                    OwnedDataModelVariant::TupleVariant(tys) => c_container_defs(
+                        &OwnedNamedType {
+                            name: String::new(),
+                            ty: OwnedDataModelType::Tuple(tys.clone()),
+                        },
+                        &mut defs,
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => c_container_defs(inner, &mut defs),
+        _ => {}
+    }
+    defs.into_iter()
+        .map(|(_, def)| def + "\n\n")
+        .collect::<String>()
+}
+
+fn c_container_defs(named_ty: &OwnedNamedType, defs: &mut Vec<(String, String)>) {
+    let c_ty = to_c_ty(named_ty);
+    let def = match &named_ty.ty {
+        OwnedDataModelType::Seq(inner) if !matches!(inner.ty, OwnedDataModelType::U8) => {
+            c_container_defs(inner, defs);
+            format!(
+                "typedef struct {{\n  {}* data;\n  size_t len;\n}} {c_ty};",
+                to_c_ty(inner)
+            )
+        }
+        OwnedDataModelType::Map { key, val } => {
+            c_container_defs(key, defs);
+            c_container_defs(val, defs);
+            let entry = format!("{}_entry_t", c_mangle(named_ty));
+            format!(
+                "typedef struct {{\n  {} key;\n  {} value;\n}} {entry};\n\n\
+                 typedef struct {{\n  {entry}* data;\n  size_t len;\n}} {c_ty};",
+                to_c_ty(key),
+                to_c_ty(val),
+            )
+        }
+        OwnedDataModelType::Option(inner) => {
+            c_container_defs(inner, defs);
+            format!(
+                "typedef struct {{\n  bool is_some;\n  {} value;\n}} {c_ty};",
+                to_c_ty(inner)
+            )
+        }
+        OwnedDataModelType::Tuple(tys) => {
+            let mut fields = String::new();
+            for (i, ty) in tys.iter().enumerate() {
+                c_container_defs(ty, defs);
+                fields += &format!("  {} _{i};\n", to_c_ty(ty));
+            }
+            format!("typedef struct {{\n{fields}}} {c_ty};")
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => return c_container_defs(inner, defs),
+        _ => return,
+    };
+    if defs.iter().any(|(name, _)| *name == c_ty) {
+        return;
+    }
+    let guard = format!("POSTCARD_{}_DEFINED", c_ty.to_uppercase());
+    defs.push((
+        c_ty,
+        format!("#ifndef {guard}\n#define {guard}\n{def}\n#endif"),
+    ));
+}
+
+/// Wraps a call returning a `postcard_error_t`, returning early on failure
+fn c_try(call: String) -> String {
+    format!("result = {call};\nif (result != POSTCARD_SUCCESS) return result;")
+}
+
+fn c_indent(code: String) -> String {
+    code.replace('\n', "\n  ")
+}
+
+/// Generates code adding the encoded size of the value at `expr` to `size`
+///
+/// `depth` is the nesting level of sequences and maps, and keeps loop variables unique.
+pub fn c_size(expr: &str, named_ty: &OwnedNamedType, depth: usize) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => "size += postcard_size_u8();".to_string(),
+        OwnedDataModelType::U16 => format!("size += postcard_size_u16({expr});"),
+        OwnedDataModelType::U32 => format!("size += postcard_size_u32({expr});"),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => {
+            format!("size += postcard_size_u64({expr});")
+        }
+        OwnedDataModelType::I8 => "size += postcard_size_i8();".to_string(),
+        OwnedDataModelType::I16 => format!("size += postcard_size_i16({expr});"),
+        OwnedDataModelType::I32 => format!("size += postcard_size_i32({expr});"),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => {
+            format!("size += postcard_size_i64({expr});")
+        }
+        OwnedDataModelType::U128 => format!("size += postcard_size_u128({expr});"),
+        OwnedDataModelType::I128 => format!("size += postcard_size_i128({expr});"),
+        OwnedDataModelType::F32 => "size += postcard_size_f32();".to_string(),
+        OwnedDataModelType::F64 => "size += postcard_size_f64();".to_string(),
+        OwnedDataModelType::Bool => "size += postcard_size_bool();".to_string(),
+        OwnedDataModelType::String => format!("size += postcard_size_string({expr}.len);"),
+        OwnedDataModelType::ByteArray => {
+            format!("size += postcard_size_byte_array({expr}.len);")
+        }
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            format!("size += postcard_size_byte_array({expr}.len);")
+        }
+        OwnedDataModelType::Seq(inner) => {
+            let i = format!("i{depth}");
+            format!(
+                "size += postcard_size_seq({expr}.len);\n\
+                 for (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n}}",
+                c_indent(c_size(&format!("{expr}.data[{i}]"), inner, depth + 1))
+            )
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let i = format!("i{depth}");
+            format!(
+                "size += postcard_size_map({expr}.len);\n\
+                 for (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n  {}\n}}",
+                c_indent(c_size(&format!("{expr}.data[{i}].key"), key, depth + 1)),
+                c_indent(c_size(&format!("{expr}.data[{i}].value"), val, depth + 1))
+            )
+        }
+        OwnedDataModelType::Option(inner) => format!(
+            "if ({expr}.is_some) {{\n  size += postcard_size_option_some(0);\n  {}\n\
+             }} else {{\n  size += postcard_size_option_none();\n}}",
+            c_indent(c_size(&format!("{expr}.value"), inner, depth))
+        ),
+        OwnedDataModelType::Tuple(tys) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| c_size(&format!("{expr}._{i}"), ty, depth))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => {
+            format!("size += {}_encoded_size(&{expr});", named_ty.name)
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => c_size(expr, inner, depth),
+        ty => panic!("unsupported type for size {:?}", ty),
+    }
+}
+
+/// Generates code encoding the value at `expr` into `slice`
+pub fn c_encode(expr: &str, named_ty: &OwnedNamedType, depth: usize) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => c_try(format!("postcard_encode_u8(slice, {expr})")),
+        OwnedDataModelType::U16 => c_try(format!("postcard_encode_u16(slice, {expr})")),
+        OwnedDataModelType::U32 => c_try(format!("postcard_encode_u32(slice, {expr})")),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => {
+            c_try(format!("postcard_encode_u64(slice, {expr})"))
+        }
+        OwnedDataModelType::I8 => c_try(format!("postcard_encode_i8(slice, {expr})")),
+        OwnedDataModelType::I16 => c_try(format!("postcard_encode_i16(slice, {expr})")),
+        OwnedDataModelType::I32 => c_try(format!("postcard_encode_i32(slice, {expr})")),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => {
+            c_try(format!("postcard_encode_i64(slice, {expr})"))
+        }
+        OwnedDataModelType::U128 => c_try(format!("postcard_encode_u128(slice, {expr})")),
+        OwnedDataModelType::I128 => c_try(format!("postcard_encode_i128(slice, {expr})")),
+        OwnedDataModelType::F32 => c_try(format!("postcard_encode_f32(slice, {expr})")),
+        OwnedDataModelType::F64 => c_try(format!("postcard_encode_f64(slice, {expr})")),
+        OwnedDataModelType::Bool => c_try(format!("postcard_encode_bool(slice, {expr})")),
+        OwnedDataModelType::String => c_try(format!(
+            "postcard_encode_string(slice, {expr}.data, {expr}.len)"
+        )),
+        OwnedDataModelType::ByteArray => c_try(format!(
+            "postcard_encode_byte_array(slice, {expr}.data, {expr}.len)"
+        )),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => c_try(
+            format!("postcard_encode_byte_array(slice, {expr}.data, {expr}.len)"),
+        ),
+        OwnedDataModelType::Seq(inner) => {
+            let i = format!("i{depth}");
+            format!(
+                "{}\nfor (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n}}",
+                c_try(format!("postcard_start_seq(slice, {expr}.len)")),
+                c_indent(c_encode(&format!("{expr}.data[{i}]"), inner, depth + 1))
+            )
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let i = format!("i{depth}");
+            format!(
+                "{}\nfor (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n  {}\n}}",
+                c_try(format!("postcard_start_map(slice, {expr}.len)")),
+                c_indent(c_encode(&format!("{expr}.data[{i}].key"), key, depth + 1)),
+                c_indent(c_encode(&format!("{expr}.data[{i}].value"), val, depth + 1))
+            )
+        }
+        OwnedDataModelType::Option(inner) => format!(
+            "if ({expr}.is_some) {{\n  {}\n  {}\n}} else {{\n  {}\n}}",
+            c_indent(c_try("postcard_encode_option_some(slice)".to_string())),
+            c_indent(c_encode(&format!("{expr}.value"), inner, depth)),
+            c_indent(c_try("postcard_encode_option_none(slice)".to_string())),
+        ),
+        OwnedDataModelType::Tuple(tys) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| c_encode(&format!("{expr}._{i}"), ty, depth))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => {
+            c_try(format!("{}_encode_raw(&{expr}, slice)", named_ty.name))
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => c_encode(expr, inner, depth),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Generates code decoding the value at `expr` from `slice`
+///
+/// Strings and byte arrays point into the slice's buffer, and the elements of sequences and
+/// maps are allocated from `arena`.
+pub fn c_decode(expr: &str, named_ty: &OwnedNamedType, depth: usize) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => c_try(format!("postcard_decode_u8(slice, &{expr})")),
+        OwnedDataModelType::U16 => c_try(format!("postcard_decode_u16(slice, &{expr})")),
+        OwnedDataModelType::U32 => c_try(format!("postcard_decode_u32(slice, &{expr})")),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => {
+            c_try(format!("postcard_decode_u64(slice, &{expr})"))
+        }
+        OwnedDataModelType::I8 => c_try(format!("postcard_decode_i8(slice, &{expr})")),
+        OwnedDataModelType::I16 => c_try(format!("postcard_decode_i16(slice, &{expr})")),
+        OwnedDataModelType::I32 => c_try(format!("postcard_decode_i32(slice, &{expr})")),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => {
+            c_try(format!("postcard_decode_i64(slice, &{expr})"))
+        }
+        OwnedDataModelType::U128 => c_try(format!("postcard_decode_u128(slice, &{expr})")),
+        OwnedDataModelType::I128 => c_try(format!("postcard_decode_i128(slice, &{expr})")),
+        OwnedDataModelType::F32 => c_try(format!("postcard_decode_f32(slice, &{expr})")),
+        OwnedDataModelType::F64 => c_try(format!("postcard_decode_f64(slice, &{expr})")),
+        OwnedDataModelType::Bool => c_try(format!("postcard_decode_bool(slice, &{expr})")),
+        OwnedDataModelType::String => c_try(format!(
+            "postcard_decode_string_ref(slice, &{expr}.data, &{expr}.len)"
+        )),
+        OwnedDataModelType::ByteArray => c_try(format!(
+            "postcard_decode_byte_array_ref(slice, &{expr}.data, &{expr}.len)"
+        )),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => c_try(
+            format!("postcard_decode_byte_array_ref(slice, &{expr}.data, &{expr}.len)"),
+        ),
+        OwnedDataModelType::Seq(inner) => {
+            let i = format!("i{depth}");
+            let elem_ty = to_c_ty(inner);
+            format!(
+                "{}\n{expr}.data = ({elem_ty}*)postcard_arena_alloc(arena, {expr}.len, sizeof({elem_ty}));\n\
+                 if (!{expr}.data) return POSTCARD_ERROR_BUFFER_TOO_SMALL;\n\
+                 for (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n}}",
+                c_try(format!("postcard_decode_seq_len(slice, &{expr}.len)")),
+                c_indent(c_decode(&format!("{expr}.data[{i}]"), inner, depth + 1))
+            )
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let i = format!("i{depth}");
+            let entry_ty = format!("{}_entry_t", c_mangle(named_ty));
+            format!(
+                "{}\n{expr}.data = ({entry_ty}*)postcard_arena_alloc(arena, {expr}.len, sizeof({entry_ty}));\n\
+                 if (!{expr}.data) return POSTCARD_ERROR_BUFFER_TOO_SMALL;\n\
+                 for (size_t {i} = 0; {i} < {expr}.len; {i}++) {{\n  {}\n  {}\n}}",
+                c_try(format!("postcard_decode_map_len(slice, &{expr}.len)")),
+                c_indent(c_decode(&format!("{expr}.data[{i}].key"), key, depth + 1)),
+                c_indent(c_decode(&format!("{expr}.data[{i}].value"), val, depth + 1))
+            )
+        }
+        OwnedDataModelType::Option(inner) => format!(
+            "{}\nif ({expr}.is_some) {{\n  {}\n}} else {{\n  memset(&{expr}.value, 0, sizeof({expr}.value));\n}}",
+            c_try(format!(
+                "postcard_decode_option_tag(slice, &{expr}.is_some)"
+            )),
+            c_indent(c_decode(&format!("{expr}.value"), inner, depth)),
+        ),
+        OwnedDataModelType::Tuple(tys) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| c_decode(&format!("{expr}._{i}"), ty, depth))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => c_try(format!(
+            "{}_decode_raw(&{expr}, slice, arena)",
+            named_ty.name
+        )),
+        OwnedDataModelType::NewtypeStruct(inner) => c_decode(expr, inner, depth),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Returns the type of the data carried by an enum variant, or `None` for unit variants
+///
+/// Struct variants are generated as standalone structs named after the enum and the variant,
+/// the same way the C++ generator does.
+pub fn c_variant_ty(enum_name: &str, variant: &OwnedNamedVariant) -> Option<OwnedNamedType> {
+    match &variant.ty {
+        OwnedDataModelVariant::UnitVariant => None,
+        OwnedDataModelVariant::NewtypeVariant(ty) => Some(*ty.clone()),
+        OwnedDataModelVariant::TupleVariant(tys) => Some(OwnedNamedType {
+            name: String::new(),
+            ty: OwnedDataModelType::Tuple(tys.clone()),
+        }),
+        OwnedDataModelVariant::StructVariant(fields) => Some(OwnedNamedType {
+            name: format!("{}{}", enum_name, variant.name),
+            ty: OwnedDataModelType::Struct(fields.clone()),
+        }),
+    }
+}
+
+/// Generates the size, encode, or decode code for the data of an enum variant, stored in
+/// the `value` union of a tagged enum
+fn c_variant_code(
+    enum_name: &str,
+    variant: &OwnedNamedVariant,
+    code: fn(&str, &OwnedNamedType, usize) -> String,
+) -> String {
+    match c_variant_ty(enum_name, variant) {
+        Some(ty) => code(&format!("value->value.{}", c_member(&variant.name)), &ty, 0),
+        None => String::new(),
+    }
+}
+
+/// Returns the name of the enum constant used as the tag of a variant
+pub fn c_tag(enum_name: &str, variant_name: &str) -> String {
+    format!(
+        "{}_{}",
+        enum_name
+            .from_case(convert_case::Case::Pascal)
+            .to_case(convert_case::Case::UpperSnake),
+        variant_name
+            .from_case(convert_case::Case::Pascal)
+            .to_case(convert_case::Case::UpperSnake)
+    )
+}
+
+/// Returns the union member used to store the data of a variant
+pub fn c_member(variant_name: &str) -> String {
+    c_ident(
+        &variant_name
+            .from_case(convert_case::Case::Pascal)
+            .to_case(convert_case::Case::Snake),
+    )
+}
+
+/// Escapes identifiers that are reserved in C
+pub fn c_ident(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double",
+        "else", "enum", "extern", "false", "float", "for", "goto", "if", "inline", "int", "long",
+        "register", "restrict", "return", "short", "signed", "sizeof", "static", "struct",
+        "switch", "true", "typedef", "union", "unsigned", "void", "volatile", "while",
+    ];
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, io::Write, process::Command};
+
+    use postcard_schema::Schema;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Schema)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Calibration {
+        offset: f32,
+        gain: f64,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    enum Mode {
+        Idle,
+        Raw(u8),
+        Pair(i16, bool),
+        Calibrated { offset: f32, scale: f64 },
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct SensorId(u16);
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Reading {
+        id: SensorId,
+        seq: u64,
+        delta: i32,
+        temp: f32,
+        pressure: f64,
+        ok: bool,
+        name: String,
+        payload: Vec<u8>,
+        samples: Vec<i64>,
+        total: u128,
+        offset: i128,
+        nested: Vec<Vec<u32>>,
+        level: Level,
+        mode: Mode,
+        modes: Vec<Mode>,
+        calibration: Option<Calibration>,
+        missing: Option<u32>,
+        labels: BTreeMap<String, u64>,
+    }
+
+    const MAIN_C: &str = r#"
+#include <stdio.h>
+#include "reading.h"
+
+int main(void) {
+  static uint8_t arena_buf[4096];
+  static uint8_t out[4096];
+  postcard_arena_t arena;
+  postcard_init_arena(&arena, arena_buf, sizeof(arena_buf));
+  Reading reading;
+  if (Reading_decode(&reading, INPUT, sizeof(INPUT), &arena) != POSTCARD_SUCCESS) return 1;
+  if (reading.id != 513 || reading.level != LEVEL_HIGH || reading.mode.tag != MODE_CALIBRATED) return 2;
+  if (reading.name.len != 5 || memcmp(reading.name.data, "probe", 5) != 0) return 3;
+  size_t len;
+  if (Reading_encode(&reading, out, sizeof(out), &len) != POSTCARD_SUCCESS) return 4;
+  if (Reading_encoded_size(&reading) != len) return 5;
+  for (size_t i = 0; i < len; i++) printf("%02x", out[i]);
+  return 0;
+}
+"#;
+
+    #[test]
+    #[ignore = "needs a C compiler, set CC to pick one"]
+    fn test_c_round_trip() {
+        let reading = Reading {
+            id: SensorId(513),
+            seq: u64::MAX - 7,
+            delta: -40_000,
+            temp: 21.5,
+            pressure: 101_325.25,
+            ok: true,
+            name: "probe".to_string(),
+            payload: vec![0, 1, 2, 254, 255],
+            samples: vec![i64::MIN, -1, 0, 1, i64::MAX],
+            total: u128::MAX - 1,
+            offset: i128::MIN + 3,
+            nested: vec![vec![], vec![1, 300, 70_000]],
+            level: Level::High,
+            mode: Mode::Calibrated {
+                offset: -0.5,
+                scale: 2.0,
+            },
+            modes: vec![Mode::Idle, Mode::Raw(200), Mode::Pair(-300, true)],
+            calibration: Some(Calibration {
+                offset: 1.25,
+                gain: -3.5,
+            }),
+            missing: None,
+            labels: BTreeMap::from([("a".to_string(), 1), ("unit".to_string(), 1 << 40)]),
+        };
+        let encoded = postcard::to_allocvec(&reading).unwrap();
+        let input = encoded
+            .iter()
+            .map(|b| format!("0x{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let header = h_header(
+            "READING",
+            [
+                include_str!("../../postcard.h").to_string(),
+                Level::to_c().unwrap(),
+                Calibration::to_c().unwrap(),
+                Mode::to_c().unwrap(),
+                SensorId::to_c().unwrap(),
+                Reading::to_c().unwrap(),
+                format!("static const uint8_t INPUT[] = {{ {input} }};"),
+            ],
+        )
+        .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("postcard-c-codegen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("reading.h"), header).unwrap();
+        std::fs::write(dir.join("main.c"), MAIN_C).unwrap();
+
+        let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let output = Command::new(&cc)
+            .args(["-std=c99", "-Wall", "-Wextra", "-pedantic", "-Werror"])
+            .arg(dir.join("main.c"))
+            .arg("-o")
+            .arg(dir.join("main"))
+            .output()
+            .unwrap_or_else(|err| panic!("failed to run {cc}: {err}"));
+        std::io::stderr().write_all(&output.stderr).unwrap();
+        assert!(output.status.success(), "generated c failed to compile");
+
+        let output = Command::new(dir.join("main")).output().unwrap();
+        assert!(
+            output.status.success(),
+            "c round trip failed {:?}",
+            output.status
+        );
+        let hex = encoded
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), hex);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
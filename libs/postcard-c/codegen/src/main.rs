@@ -7,11 +7,17 @@ use postcard_schema::schema::owned::OwnedNamedType;
 #[derive(Parser)]
 struct Args {
     path: PathBuf,
+    #[clap(long, help = "Generate C99 instead of C++")]
+    c: bool,
 }
 fn main() -> miette::Result<()> {
     let args = Args::parse();
     let contents = std::fs::read_to_string(args.path).into_diagnostic()?;
     let named_ty: OwnedNamedType = ron::from_str(&contents).into_diagnostic()?;
-    println!("{}", postcard_c_codegen::generate_cpp(&named_ty)?);
+    if args.c {
+        println!("{}", postcard_c_codegen::generate_c(&named_ty)?);
+    } else {
+        println!("{}", postcard_c_codegen::generate_cpp(&named_ty)?);
+    }
     Ok(())
 }
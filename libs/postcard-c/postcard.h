@@ -403,6 +403,57 @@ postcard_error_t postcard_decode_seq_len(postcard_slice_t* slice, size_t* count)
 /// If decoding was successful, slice.len will be incremented by the number of decoded bytes
 postcard_error_t postcard_decode_map_len(postcard_slice_t* slice, size_t* count);
 
+/// Decodes a byte array from the slice without copying it
+///
+/// *Arguments*
+/// - slice - a pointer to an initialized `postcard_slice_t`
+/// - (out) bytes - a pointer set to the start of the byte array in the slice's buffer
+/// - (out) length - a pointer to a size_t to store the length of the byte array
+/// *Safety / Lifetimes*
+/// `bytes` points into the slice's buffer, so the user must ensure it does not outlive that buffer
+/// *Side Effects / Returns*
+/// If the slice is shorter than the encoded length `postcard_error_t` will return a non-zero value
+/// If decoding was successful, slice.len will be incremented by the number of decoded bytes
+postcard_error_t postcard_decode_byte_array_ref(postcard_slice_t* slice, const uint8_t** bytes, size_t* length);
+
+/// Decodes a string from the slice without copying it
+///
+/// This function is a wrapper around `postcard_decode_byte_array_ref`. The string is not null-terminated.
+postcard_error_t postcard_decode_string_ref(postcard_slice_t* slice, const char** string, size_t* length);
+
+#ifndef POSTCARD_ARENA_ALIGN
+/// The alignment of every allocation made from a `postcard_arena_t`
+#define POSTCARD_ARENA_ALIGN 8
+#endif
+
+/// A bump allocator over a user provided buffer
+///
+/// Generated C decoders allocate the elements of sequences and maps from an arena, so decoding never
+/// calls malloc. Reset `len` to 0 to reuse the arena once the decoded values are no longer needed.
+typedef struct {
+    /// Pointer to the underlying buffer
+    uint8_t* data;
+    /// The number of bytes allocated so far
+    size_t len;
+    /// The total size of the underlying buffer
+    size_t capacity;
+} postcard_arena_t;
+
+/// Initializes a postcard_arena_t over a buffer
+///
+/// *Arguments*
+///
+/// - arena - a pointer to an uninitialized `postcard_arena_t`
+/// - buffer - a pointer to the underlying buffer
+/// - capacity - the total size of the underlying buffer
+void postcard_init_arena(postcard_arena_t* arena, uint8_t* buffer, size_t capacity);
+
+/// Allocates space for `count` elements of `size` bytes from the arena
+///
+/// *Side Effects / Returns*
+/// Returns a pointer aligned to `POSTCARD_ARENA_ALIGN`, or NULL if the arena does not have enough room left
+void* postcard_arena_alloc(postcard_arena_t* arena, size_t count, size_t size);
+
 /// Returns the encoded size of a bool
 size_t postcard_size_bool();
 /// Returns the encoded size of a uint8_t
@@ -421,10 +472,10 @@ size_t postcard_size_i32(int32_t value);
 size_t postcard_size_u64(uint64_t value);
 /// Returns the encoded size of a int64_t based on the value
 size_t postcard_size_i64(int64_t value);
-/// Returns the encoded size of a float based on the value
-size_t postcard_size_f32(float value);
-/// Returns the encoded size of a double based on the value
-size_t postcard_size_f64(double value);
+/// Returns the encoded size of a float
+size_t postcard_size_f32();
+/// Returns the encoded size of a double
+size_t postcard_size_f64();
 /// Returns the encoded size of a string based on the length
 size_t postcard_size_string(size_t length);
 /// Returns the encoded size of a byte array based on the length
@@ -436,9 +487,9 @@ size_t postcard_size_option_some(size_t inner_size);
 /// Returns the encoded size of a variant based on the discriminant
 size_t postcard_size_variant(uint32_t discriminant);
 /// Returns the encoded size of a sequence's length
-size_t postcard_size_seq_len(size_t count);
+size_t postcard_size_seq(size_t count);
 /// Returns the encoded size of a maps's length
-size_t postcard_size_map_len(size_t count);
+size_t postcard_size_map(size_t count);
 /// Returns the size of an unsigned varint based on the value
 size_t postcard_size_unsigned_varint(uint64_t value);
 /// Returns the size of an signed varint based on the value
@@ -1049,4 +1100,185 @@ inline size_t postcard_size_map(size_t count)
     return postcard_size_unsigned_varint(count);
 }
 
+inline postcard_error_t postcard_decode_byte_array_ref(postcard_slice_t* slice, const uint8_t** bytes,
+    size_t* length)
+{
+    if (!slice || !slice->data || !bytes || !length)
+        return POSTCARD_ERROR_INVALID_INPUT;
+
+    size_t len;
+    postcard_error_t err = postcard_decode_byte_array_len(slice, &len);
+    if (err != POSTCARD_SUCCESS)
+        return err;
+
+    if (len > slice->capacity - slice->len)
+        return POSTCARD_ERROR_INCOMPLETE_DATA;
+
+    *bytes = slice->data + slice->len;
+    *length = len;
+    slice->len += len;
+    return POSTCARD_SUCCESS;
+}
+
+inline postcard_error_t postcard_decode_string_ref(postcard_slice_t* slice, const char** string, size_t* length)
+{
+    return postcard_decode_byte_array_ref(slice, (const uint8_t**)string, length);
+}
+
+inline void postcard_init_arena(postcard_arena_t* arena, uint8_t* buffer, size_t capacity)
+{
+    arena->data = buffer;
+    arena->len = 0;
+    arena->capacity = capacity;
+}
+
+inline void* postcard_arena_alloc(postcard_arena_t* arena, size_t count, size_t size)
+{
+    if (!arena || !arena->data)
+        return NULL;
+    if (size != 0 && count > SIZE_MAX / size)
+        return NULL;
+
+    uintptr_t addr = (uintptr_t)(arena->data + arena->len);
+    size_t padding = (POSTCARD_ARENA_ALIGN - addr % POSTCARD_ARENA_ALIGN) % POSTCARD_ARENA_ALIGN;
+    size_t total = count * size;
+    if (padding > arena->capacity - arena->len || total > arena->capacity - arena->len - padding)
+        return NULL;
+
+    void* ptr = arena->data + arena->len + padding;
+    arena->len += padding + total;
+    return ptr;
+}
+
+#ifdef __SIZEOF_INT128__
+/// An unsigned 128-bit integer, only available on compilers that support `__int128`
+__extension__ typedef unsigned __int128 postcard_u128_t;
+/// A signed 128-bit integer, only available on compilers that support `__int128`
+__extension__ typedef __int128 postcard_i128_t;
+
+/// Encodes a u128 to the passed in slice.
+///
+/// *Arguments*
+/// - slice - a pointer to an initialized `postcard_slice_t`
+/// - value - the value to encode
+/// *Side Effects / Return*
+/// If there is not enough room in the buffer `postcard_error_t` will return a non-zero value
+/// If encoding was successful, slice.len will be incremented by the number of encoded bytes
+postcard_error_t postcard_encode_u128(postcard_slice_t* slice, postcard_u128_t value);
+/// Encodes a i128 to the passed in slice.
+///
+/// *Arguments*
+/// - slice - a pointer to an initialized `postcard_slice_t`
+/// - value - the value to encode
+/// *Side Effects / Return*
+/// If there is not enough room in the buffer `postcard_error_t` will return a non-zero value
+/// If encoding was successful, slice.len will be incremented by the number of encoded bytes
+postcard_error_t postcard_encode_i128(postcard_slice_t* slice, postcard_i128_t value);
+/// Decodes a postcard_u128_t from the slice
+///
+/// *Arguments*
+/// - slice - a pointer to an initialized `postcard_slice_t`
+/// - (out) value - an out pointer to the unsigned 128-bit integer to be decoded
+/// *Side Effects / Returns*
+/// If there is not enough room in the buffer `postcard_error_t` will return a non-zero value
+/// If decoding was successful, slice.len will be incremented by the number of decoded bytes
+postcard_error_t postcard_decode_u128(postcard_slice_t* slice, postcard_u128_t* value);
+/// Decodes a postcard_i128_t from the slice
+///
+/// *Arguments*
+/// - slice - a pointer to an initialized `postcard_slice_t`
+/// - (out) value - an out pointer to the signed 128-bit integer to be decoded
+/// *Side Effects / Returns*
+/// If there is not enough room in the buffer `postcard_error_t` will return a non-zero value
+/// If decoding was successful, slice.len will be incremented by the number of decoded bytes
+postcard_error_t postcard_decode_i128(postcard_slice_t* slice, postcard_i128_t* value);
+/// Returns the encoded size of a postcard_u128_t based on the value
+size_t postcard_size_u128(postcard_u128_t value);
+/// Returns the encoded size of a postcard_i128_t based on the value
+size_t postcard_size_i128(postcard_i128_t value);
+
+inline postcard_error_t postcard_encode_u128(postcard_slice_t* slice, postcard_u128_t value)
+{
+    if (!slice || !slice->data)
+        return POSTCARD_ERROR_INVALID_INPUT;
+
+    size_t i = 0;
+    while (value >= 0x80) {
+        if (slice->len + i >= slice->capacity)
+            return POSTCARD_ERROR_BUFFER_TOO_SMALL;
+
+        slice->data[slice->len + i] = (value & 0x7f) | 0x80;
+        value >>= 7;
+        i++;
+    }
+
+    if (slice->len + i >= slice->capacity)
+        return POSTCARD_ERROR_BUFFER_TOO_SMALL;
+    slice->data[slice->len + i] = value & 0x7f;
+    slice->len += i + 1;
+
+    return POSTCARD_SUCCESS;
+}
+
+inline postcard_error_t postcard_encode_i128(postcard_slice_t* slice, postcard_i128_t value)
+{
+    // Zigzag encoding: (n << 1) ^ (n >> 127)
+    postcard_u128_t zigzag = ((postcard_u128_t)value << 1) ^ (postcard_u128_t)(value >> 127);
+    return postcard_encode_u128(slice, zigzag);
+}
+
+inline postcard_error_t postcard_decode_u128(postcard_slice_t* slice, postcard_u128_t* value)
+{
+    if (!slice || !slice->data || !value)
+        return POSTCARD_ERROR_INVALID_INPUT;
+
+    *value = 0;
+    unsigned shift = 0;
+
+    // a u128 takes at most 19 bytes as a varint
+    for (size_t i = 0; i < 19; i++) {
+        if (slice->len >= slice->capacity)
+            return POSTCARD_ERROR_INCOMPLETE_DATA;
+
+        uint8_t byte = slice->data[slice->len++];
+        *value |= ((postcard_u128_t)(byte & 0x7F)) << shift;
+        if (!(byte & 0x80))
+            return POSTCARD_SUCCESS;
+
+        shift += 7;
+    }
+
+    return POSTCARD_ERROR_OVERFLOW;
+}
+
+inline postcard_error_t postcard_decode_i128(postcard_slice_t* slice, postcard_i128_t* value)
+{
+    postcard_u128_t zigzag;
+    postcard_error_t err = postcard_decode_u128(slice, &zigzag);
+    if (err != POSTCARD_SUCCESS)
+        return err;
+
+    // Zigzag decoding: (n >> 1) ^ (-(n & 1))
+    *value = (postcard_i128_t)((zigzag >> 1) ^ (-(zigzag & 1)));
+    return POSTCARD_SUCCESS;
+}
+
+inline size_t postcard_size_u128(postcard_u128_t value)
+{
+    size_t size = 1;
+    while (value >= 0x80) {
+        value >>= 7;
+        size++;
+    }
+    return size;
+}
+
+inline size_t postcard_size_i128(postcard_i128_t value)
+{
+    // Zigzag encoding: (n << 1) ^ (n >> 127)
+    postcard_u128_t zigzag = ((postcard_u128_t)value << 1) ^ (postcard_u128_t)(value >> 127);
+    return postcard_size_u128(zigzag);
+}
+#endif // __SIZEOF_INT128__
+
 #endif // POSTCARD_H
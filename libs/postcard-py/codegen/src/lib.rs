@@ -0,0 +1,441 @@
+use miette::IntoDiagnostic;
+use minijinja::{Environment, value::ViaDeserialize};
+use postcard_schema::schema::owned::{
+    OwnedDataModelType, OwnedDataModelVariant, OwnedNamedType, OwnedNamedValue,
+};
+
+static PY_STRUCT_TMPL: &str = include_str!("./struct.py.jinja");
+static PY_ENUM_TMPL: &str = include_str!("./enum.py.jinja");
+static PY_NEW_TYPE_STRUCT_TMPL: &str = include_str!("./new_type_struct.py.jinja");
+static PY_MODULE_TMPL: &str = include_str!("./module.py.jinja");
+
+pub trait SchemaExt {
+    fn to_py() -> miette::Result<String>;
+}
+
+impl<S: postcard_schema::Schema> SchemaExt for S {
+    fn to_py() -> miette::Result<String> {
+        let owned_ty: OwnedNamedType = S::SCHEMA.into();
+        generate_py(&owned_ty)
+    }
+}
+
+pub fn py_module(
+    name: impl ToString,
+    types: impl IntoIterator<Item = String>,
+) -> miette::Result<String> {
+    let types = types.into_iter().collect::<Vec<String>>();
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct ModuleData {
+        name: String,
+        types: Vec<String>,
+    }
+    let mut env = Environment::new();
+    env.add_template("module", PY_MODULE_TMPL)
+        .into_diagnostic()?;
+
+    let tmpl = env.get_template("module").expect("template missing");
+    tmpl.render(ModuleData {
+        name: name.to_string(),
+        types,
+    })
+    .into_diagnostic()
+}
+
+/// Returns the statement assigning a packet id to a generated class
+pub fn py_msg_id(name: &str, id: [u8; 2]) -> String {
+    format!("{name}.ID = bytes([{}, {}])", id[0], id[1])
+}
+
+pub fn generate_py(ty: &OwnedNamedType) -> miette::Result<String> {
+    let mut env = Environment::new();
+    env.add_template("struct", PY_STRUCT_TMPL)
+        .into_diagnostic()?;
+    env.add_template("enum", PY_ENUM_TMPL).into_diagnostic()?;
+    env.add_template("new_type_struct", PY_NEW_TYPE_STRUCT_TMPL)
+        .into_diagnostic()?;
+
+    env.add_function("py_ty", |ty: ViaDeserialize<OwnedNamedType>| {
+        to_py_ty(&ty.0)
+    });
+
+    env.add_function(
+        "py_encode",
+        |expr: &str, ty: ViaDeserialize<OwnedNamedType>| py_encode(expr, &ty.0, 0),
+    );
+
+    env.add_function("py_decode", |ty: ViaDeserialize<OwnedNamedType>| {
+        py_decode(&ty.0)
+    });
+
+    env.add_function(
+        "py_variant_fields",
+        |variant: ViaDeserialize<OwnedDataModelVariant>| {
+            minijinja::Value::from_serialize(py_variant_fields(&variant.0))
+        },
+    );
+    env.add_filter("py_ident", |value: &str| py_ident(value));
+
+    match &ty.ty {
+        OwnedDataModelType::Struct(_) => {
+            let tmpl = env.get_template("struct").expect("template missing");
+            tmpl.render(ty).into_diagnostic()
+        }
+        OwnedDataModelType::Enum(variants) => {
+            env.add_global(
+                "unit_only",
+                variants
+                    .iter()
+                    .all(|v| matches!(v.ty, OwnedDataModelVariant::UnitVariant)),
+            );
+            let tmpl = env.get_template("enum").expect("template missing");
+            tmpl.render(ty).into_diagnostic()
+        }
+        OwnedDataModelType::NewtypeStruct(_) => {
+            let tmpl = env
+                .get_template("new_type_struct")
+                .expect("template missing");
+            tmpl.render(ty).into_diagnostic()
+        }
+        _ => Err(miette::miette!("unsupported data ty")),
+    }
+}
+
+/// Returns the type annotation used for a type
+///
+/// Newtypes are unwrapped to their inner type, the same way the C generator does.
+pub fn to_py_ty(named_ty: &OwnedNamedType) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8
+        | OwnedDataModelType::U16
+        | OwnedDataModelType::U32
+        | OwnedDataModelType::U64
+        | OwnedDataModelType::U128
+        | OwnedDataModelType::Usize
+        | OwnedDataModelType::I8
+        | OwnedDataModelType::I16
+        | OwnedDataModelType::I32
+        | OwnedDataModelType::I64
+        | OwnedDataModelType::I128
+        | OwnedDataModelType::Isize => "int".to_string(),
+        OwnedDataModelType::F32 | OwnedDataModelType::F64 => "float".to_string(),
+        OwnedDataModelType::Bool => "bool".to_string(),
+        OwnedDataModelType::Unit => "None".to_string(),
+        OwnedDataModelType::String => "str".to_string(),
+        OwnedDataModelType::ByteArray => "bytes".to_string(),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            "bytes".to_string()
+        }
+        OwnedDataModelType::Seq(inner) => format!("list[{}]", to_py_ty(inner)),
+        OwnedDataModelType::Map { key, val } => {
+            format!("dict[{}, {}]", to_py_ty(key), to_py_ty(val))
+        }
+        OwnedDataModelType::Option(inner) => format!("Optional[{}]", to_py_ty(inner)),
+        OwnedDataModelType::Tuple(tys) => format!(
+            "tuple[{}]",
+            tys.iter().map(to_py_ty).collect::<Vec<_>>().join(", ")
+        ),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => named_ty.name.clone(),
+        OwnedDataModelType::NewtypeStruct(inner) => to_py_ty(inner),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+fn py_indent(code: String) -> String {
+    code.replace('\n', "\n    ")
+}
+
+/// Generates statements writing the value of `expr` to the writer `w`
+pub fn py_encode(expr: &str, named_ty: &OwnedNamedType, depth: usize) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => format!("w.u8({expr})"),
+        OwnedDataModelType::U16 => format!("w.u16({expr})"),
+        OwnedDataModelType::U32 => format!("w.u32({expr})"),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => format!("w.u64({expr})"),
+        OwnedDataModelType::U128 => format!("w.u128({expr})"),
+        OwnedDataModelType::I8 => format!("w.i8({expr})"),
+        OwnedDataModelType::I16 => format!("w.i16({expr})"),
+        OwnedDataModelType::I32 => format!("w.i32({expr})"),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => format!("w.i64({expr})"),
+        OwnedDataModelType::I128 => format!("w.i128({expr})"),
+        OwnedDataModelType::F32 => format!("w.f32({expr})"),
+        OwnedDataModelType::F64 => format!("w.f64({expr})"),
+        OwnedDataModelType::Bool => format!("w.bool({expr})"),
+        OwnedDataModelType::Unit => "pass".to_string(),
+        OwnedDataModelType::String => format!("w.str({expr})"),
+        OwnedDataModelType::ByteArray => format!("w.bytes({expr})"),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            format!("w.bytes({expr})")
+        }
+        OwnedDataModelType::Seq(inner) => {
+            let v = format!("v{depth}");
+            format!(
+                "w.varint(len({expr}))\nfor {v} in {expr}:\n    {}",
+                py_indent(py_encode(&v, inner, depth + 1))
+            )
+        }
+        OwnedDataModelType::Map { key, val } => {
+            let k = format!("k{depth}");
+            let v = format!("v{depth}");
+            format!(
+                "w.varint(len({expr}))\nfor {k}, {v} in {expr}.items():\n    {}\n    {}",
+                py_indent(py_encode(&k, key, depth + 1)),
+                py_indent(py_encode(&v, val, depth + 1))
+            )
+        }
+        OwnedDataModelType::Option(inner) => format!(
+            "w.option({expr} is not None)\nif {expr} is not None:\n    {}",
+            py_indent(py_encode(expr, inner, depth))
+        ),
+        OwnedDataModelType::Tuple(tys) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| py_encode(&format!("{expr}[{i}]"), ty, depth))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => {
+            format!("{expr}.encode_into(w)")
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => py_encode(expr, inner, depth),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Generates an expression reading a value from the reader `r`
+///
+/// Python evaluates call arguments, comprehensions, and tuple displays from left to right,
+/// so the generated expressions read fields in the order they were encoded.
+pub fn py_decode(named_ty: &OwnedNamedType) -> String {
+    match &named_ty.ty {
+        OwnedDataModelType::U8 => "r.u8()".to_string(),
+        OwnedDataModelType::U16 => "r.u16()".to_string(),
+        OwnedDataModelType::U32 => "r.u32()".to_string(),
+        OwnedDataModelType::U64 | OwnedDataModelType::Usize => "r.u64()".to_string(),
+        OwnedDataModelType::U128 => "r.u128()".to_string(),
+        OwnedDataModelType::I8 => "r.i8()".to_string(),
+        OwnedDataModelType::I16 => "r.i16()".to_string(),
+        OwnedDataModelType::I32 => "r.i32()".to_string(),
+        OwnedDataModelType::I64 | OwnedDataModelType::Isize => "r.i64()".to_string(),
+        OwnedDataModelType::I128 => "r.i128()".to_string(),
+        OwnedDataModelType::F32 => "r.f32()".to_string(),
+        OwnedDataModelType::F64 => "r.f64()".to_string(),
+        OwnedDataModelType::Bool => "r.bool()".to_string(),
+        OwnedDataModelType::Unit => "None".to_string(),
+        OwnedDataModelType::String => "r.str()".to_string(),
+        OwnedDataModelType::ByteArray => "r.bytes()".to_string(),
+        OwnedDataModelType::Seq(inner) if matches!(inner.ty, OwnedDataModelType::U8) => {
+            "r.bytes()".to_string()
+        }
+        OwnedDataModelType::Seq(inner) => {
+            format!("[{} for _ in range(r.varint())]", py_decode(inner))
+        }
+        OwnedDataModelType::Map { key, val } => format!(
+            "{{{}: {} for _ in range(r.varint())}}",
+            py_decode(key),
+            py_decode(val)
+        ),
+        OwnedDataModelType::Option(inner) => {
+            format!("({} if r.option() else None)", py_decode(inner))
+        }
+        OwnedDataModelType::Tuple(tys) if tys.len() == 1 => format!("({},)", py_decode(&tys[0])),
+        OwnedDataModelType::Tuple(tys) => format!(
+            "({})",
+            tys.iter().map(py_decode).collect::<Vec<_>>().join(", ")
+        ),
+        OwnedDataModelType::Struct(_) | OwnedDataModelType::Enum(_) => {
+            format!("{}.decode_from(r)", named_ty.name)
+        }
+        OwnedDataModelType::NewtypeStruct(inner) => py_decode(inner),
+        ty => panic!("unsupported type {:?}", ty),
+    }
+}
+
+/// Returns the fields of the class generated for an enum variant
+///
+/// Newtype variants store their data in `value`, and tuple variants in `_0`, `_1`, and so on.
+pub fn py_variant_fields(variant: &OwnedDataModelVariant) -> Vec<OwnedNamedValue> {
+    match variant {
+        OwnedDataModelVariant::UnitVariant => vec![],
+        OwnedDataModelVariant::NewtypeVariant(ty) => vec![OwnedNamedValue {
+            name: "value".to_string(),
+            ty: *ty.clone(),
+        }],
+        OwnedDataModelVariant::TupleVariant(tys) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| OwnedNamedValue {
+                name: format!("_{i}"),
+                ty: ty.clone(),
+            })
+            .collect(),
+        OwnedDataModelVariant::StructVariant(fields) => fields.clone(),
+    }
+}
+
+/// Escapes identifiers that are reserved in Python
+pub fn py_ident(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "try", "while", "with", "yield",
+    ];
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, io::Write, process::Command};
+
+    use postcard_schema::Schema;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Schema)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Calibration {
+        offset: f32,
+        gain: f64,
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    enum Mode {
+        Idle,
+        Raw(u8),
+        Pair(i16, bool),
+        Calibrated { offset: f32, scale: f64 },
+    }
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct SensorId(u16);
+
+    #[derive(Serialize, Deserialize, Schema)]
+    struct Reading {
+        id: SensorId,
+        seq: u64,
+        delta: i32,
+        temp: f32,
+        pressure: f64,
+        ok: bool,
+        name: String,
+        payload: Vec<u8>,
+        samples: Vec<i64>,
+        nested: Vec<Vec<u32>>,
+        pair: (u8, i8),
+        level: Level,
+        mode: Mode,
+        modes: Vec<Mode>,
+        calibration: Option<Calibration>,
+        missing: Option<u32>,
+        labels: BTreeMap<String, u64>,
+        from: u128,
+    }
+
+    const MAIN_PY: &str = r#"
+import sys
+from reading import *
+
+data = bytes.fromhex(sys.argv[1])
+reading = Reading.decode(data)
+assert reading.id == 513
+assert reading.level == Level.High
+assert reading.mode == ModeCalibrated(offset=-0.5, scale=2.0)
+assert reading.modes == [ModeIdle(), ModeRaw(value=200), ModePair(_0=-300, _1=True)]
+assert reading.name == "probe"
+assert reading.pair == (255, -128)
+assert reading.labels == {"a": 1, "unit": 1 << 40}
+assert reading.missing is None
+assert Reading.ID == bytes([1, 2])
+try:
+    Reading.decode(data[:-1])
+    sys.exit(1)
+except DecodeError:
+    pass
+print(reading.encode().hex(), end="")
+"#;
+
+    #[test]
+    fn test_py_round_trip() {
+        let reading = Reading {
+            id: SensorId(513),
+            seq: u64::MAX - 7,
+            delta: -40_000,
+            temp: 21.5,
+            pressure: 101_325.25,
+            ok: true,
+            name: "probe".to_string(),
+            payload: vec![0, 1, 2, 254, 255],
+            samples: vec![i64::MIN, -1, 0, 1, i64::MAX],
+            nested: vec![vec![], vec![1, 300, 70_000]],
+            pair: (255, -128),
+            level: Level::High,
+            mode: Mode::Calibrated {
+                offset: -0.5,
+                scale: 2.0,
+            },
+            modes: vec![Mode::Idle, Mode::Raw(200), Mode::Pair(-300, true)],
+            calibration: Some(Calibration {
+                offset: 1.25,
+                gain: -3.5,
+            }),
+            missing: None,
+            labels: BTreeMap::from([("a".to_string(), 1), ("unit".to_string(), 1 << 40)]),
+            from: u128::MAX,
+        };
+        let encoded = postcard::to_allocvec(&reading).unwrap();
+        let module = py_module(
+            "reading",
+            [
+                include_str!("../../postcard.py").to_string(),
+                Level::to_py().unwrap(),
+                Calibration::to_py().unwrap(),
+                Mode::to_py().unwrap(),
+                SensorId::to_py().unwrap(),
+                Reading::to_py().unwrap(),
+                py_msg_id("Reading", [1, 2]),
+            ],
+        )
+        .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("postcard-py-codegen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("reading.py"), module).unwrap();
+        std::fs::write(dir.join("main.py"), MAIN_PY).unwrap();
+
+        let hex = encoded
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+        let output = Command::new(&python)
+            .arg(dir.join("main.py"))
+            .arg(&hex)
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("skipping python round trip test, {python} not found");
+                return;
+            }
+            Err(err) => panic!("failed to run {python}: {err}"),
+        };
+        std::io::stderr().write_all(&output.stderr).unwrap();
+        assert!(
+            output.status.success(),
+            "python round trip failed {:?}",
+            output.status
+        );
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), hex);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
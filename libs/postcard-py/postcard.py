@@ -0,0 +1,209 @@
+# Pure Python postcard encoding and decoding
+#
+# Modules generated by postcard-py-codegen include this runtime, so they have no dependencies
+# outside of the standard library.
+
+import enum
+import struct
+from dataclasses import dataclass
+from typing import ClassVar, Optional
+
+
+class DecodeError(Exception):
+    """Raised when a buffer isn't a valid postcard encoding of the requested type"""
+
+
+class Writer:
+    """Appends postcard encoded values to a growable buffer"""
+
+    def __init__(self) -> None:
+        self.buf = bytearray()
+
+    def varint(self, value: int) -> None:
+        if value < 0:
+            raise ValueError(f"varint must be positive, got {value}")
+        while value >= 0x80:
+            self.buf.append((value & 0x7F) | 0x80)
+            value >>= 7
+        self.buf.append(value)
+
+    def _unsigned(self, value: int, bits: int) -> None:
+        if not 0 <= value < (1 << bits):
+            raise ValueError(f"{value} does not fit in u{bits}")
+        self.varint(value)
+
+    def _signed(self, value: int, bits: int) -> None:
+        if not -(1 << (bits - 1)) <= value < (1 << (bits - 1)):
+            raise ValueError(f"{value} does not fit in i{bits}")
+        self.varint((value << 1) ^ (value >> (bits - 1)))
+
+    def u8(self, value: int) -> None:
+        if not 0 <= value <= 0xFF:
+            raise ValueError(f"{value} does not fit in u8")
+        self.buf.append(value)
+
+    def i8(self, value: int) -> None:
+        self.buf += struct.pack("<b", value)
+
+    def u16(self, value: int) -> None:
+        self._unsigned(value, 16)
+
+    def u32(self, value: int) -> None:
+        self._unsigned(value, 32)
+
+    def u64(self, value: int) -> None:
+        self._unsigned(value, 64)
+
+    def u128(self, value: int) -> None:
+        self._unsigned(value, 128)
+
+    def i16(self, value: int) -> None:
+        self._signed(value, 16)
+
+    def i32(self, value: int) -> None:
+        self._signed(value, 32)
+
+    def i64(self, value: int) -> None:
+        self._signed(value, 64)
+
+    def i128(self, value: int) -> None:
+        self._signed(value, 128)
+
+    def f32(self, value: float) -> None:
+        self.buf += struct.pack("<f", value)
+
+    def f64(self, value: float) -> None:
+        self.buf += struct.pack("<d", value)
+
+    def bool(self, value: bool) -> None:
+        self.buf.append(1 if value else 0)
+
+    def bytes(self, value: bytes) -> None:
+        self.varint(len(value))
+        self.buf += value
+
+    def str(self, value: str) -> None:
+        self.bytes(value.encode("utf-8"))
+
+    def option(self, is_some: bool) -> None:
+        self.bool(is_some)
+
+    def variant(self, tag: int) -> None:
+        self.u32(tag)
+
+
+class Reader:
+    """Reads postcard encoded values from the front of a buffer"""
+
+    def __init__(self, data: bytes) -> None:
+        self.data = bytes(data)
+        self.pos = 0
+
+    def take(self, n: int) -> bytes:
+        if self.pos + n > len(self.data):
+            raise DecodeError("unexpected end of input")
+        out = self.data[self.pos : self.pos + n]
+        self.pos += n
+        return out
+
+    def remaining(self) -> bytes:
+        return self.data[self.pos :]
+
+    def varint(self, bits: int = 64) -> int:
+        value = 0
+        shift = 0
+        while True:
+            byte = self.take(1)[0]
+            value |= (byte & 0x7F) << shift
+            if byte & 0x80 == 0:
+                break
+            shift += 7
+            if shift >= bits + 7:
+                raise DecodeError("varint is too long")
+        if value >= (1 << bits):
+            raise DecodeError(f"varint overflows u{bits}")
+        return value
+
+    def _signed(self, bits: int) -> int:
+        value = self.varint(bits)
+        return (value >> 1) ^ -(value & 1)
+
+    def u8(self) -> int:
+        return self.take(1)[0]
+
+    def i8(self) -> int:
+        return struct.unpack("<b", self.take(1))[0]
+
+    def u16(self) -> int:
+        return self.varint(16)
+
+    def u32(self) -> int:
+        return self.varint(32)
+
+    def u64(self) -> int:
+        return self.varint(64)
+
+    def u128(self) -> int:
+        return self.varint(128)
+
+    def i16(self) -> int:
+        return self._signed(16)
+
+    def i32(self) -> int:
+        return self._signed(32)
+
+    def i64(self) -> int:
+        return self._signed(64)
+
+    def i128(self) -> int:
+        return self._signed(128)
+
+    def f32(self) -> float:
+        return struct.unpack("<f", self.take(4))[0]
+
+    def f64(self) -> float:
+        return struct.unpack("<d", self.take(8))[0]
+
+    def bool(self) -> bool:
+        value = self.u8()
+        if value > 1:
+            raise DecodeError(f"invalid bool {value}")
+        return value == 1
+
+    def bytes(self) -> bytes:
+        return self.take(self.varint())
+
+    def str(self) -> str:
+        try:
+            return self.bytes().decode("utf-8")
+        except UnicodeDecodeError as err:
+            raise DecodeError(str(err)) from err
+
+    def option(self) -> bool:
+        return self.bool()
+
+    def variant(self) -> int:
+        return self.u32()
+
+
+class Message:
+    """Base class of every generated type"""
+
+    # The packet id of the message, set for types that are sent as messages
+    ID: ClassVar[Optional[bytes]] = None
+
+    def encode_into(self, w: Writer) -> None:
+        raise NotImplementedError
+
+    def encode(self) -> bytes:
+        w = Writer()
+        self.encode_into(w)
+        return bytes(w.buf)
+
+    @classmethod
+    def decode_from(cls, r: Reader):
+        raise NotImplementedError
+
+    @classmethod
+    def decode(cls, data: bytes):
+        return cls.decode_from(Reader(data))
@@ -0,0 +1,138 @@
+//! Compares the opt-in io_uring paths of the sockets with the default ones
+//!
+//! Every case runs on its own thread, as an executor can only be run once per thread. The paths
+//! being compared are io_uring only, so there's nothing to run on other platforms.
+//!
+//! The kernel copies zero copy sends over loopback anyway, so `send_zc` only shows its overhead here.
+
+fn main() {
+    #[cfg(target_os = "linux")]
+    linux::main();
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::future::Future;
+    use std::net::SocketAddr;
+    use std::time::{Duration, Instant};
+
+    use stellarator::net::{TcpListener, TcpStream, UdpSocket};
+    use stellarator::uring::BufRing;
+
+    const DATAGRAMS: usize = 100_000;
+    const DATAGRAM_LEN: usize = 256;
+    const STREAM_LEN: usize = 256 * 1024 * 1024;
+    const CHUNK_LEN: usize = 64 * 1024;
+
+    static PAYLOAD: [u8; DATAGRAM_LEN] = [1; DATAGRAM_LEN];
+
+    pub fn main() {
+        println!("udp recv, {DATAGRAMS} x {DATAGRAM_LEN}B datagrams");
+        bench("owned buffer", DATAGRAMS, || async {
+            let (a, b) = udp_pair(false);
+            let mut buf = vec![0u8; DATAGRAM_LEN];
+            let start = Instant::now();
+            for _ in 0..DATAGRAMS {
+                a.send(&PAYLOAD[..]).await.0.unwrap();
+                let (n, out) = b.recv(buf).await;
+                assert_eq!(n.unwrap(), DATAGRAM_LEN);
+                buf = out;
+            }
+            start.elapsed()
+        });
+        bench("fixed file", DATAGRAMS, || async {
+            let (a, b) = udp_pair(true);
+            let mut buf = vec![0u8; DATAGRAM_LEN];
+            let start = Instant::now();
+            for _ in 0..DATAGRAMS {
+                a.send(&PAYLOAD[..]).await.0.unwrap();
+                let (n, out) = b.recv(buf).await;
+                assert_eq!(n.unwrap(), DATAGRAM_LEN);
+                buf = out;
+            }
+            start.elapsed()
+        });
+        bench("multishot, fixed file", DATAGRAMS, || async {
+            let (a, b) = udp_pair(true);
+            let ring = BufRing::new(64, DATAGRAM_LEN).unwrap();
+            let mut stream = b.recv_multishot(&ring);
+            let start = Instant::now();
+            for _ in 0..DATAGRAMS {
+                a.send(&PAYLOAD[..]).await.0.unwrap();
+                assert_eq!(stream.recv().await.unwrap().len(), DATAGRAM_LEN);
+            }
+            start.elapsed()
+        });
+
+        println!(
+            "tcp send, {}MiB in {}KiB chunks",
+            STREAM_LEN >> 20,
+            CHUNK_LEN >> 10
+        );
+        bench("write", STREAM_LEN / CHUNK_LEN, || async {
+            let stream = tcp_pair().await;
+            let mut buf = vec![1u8; CHUNK_LEN];
+            let start = Instant::now();
+            let mut sent = 0;
+            while sent < STREAM_LEN {
+                let (n, out) = stream.write(buf).await;
+                sent += n.unwrap();
+                buf = out;
+            }
+            start.elapsed()
+        });
+        bench("send_zc", STREAM_LEN / CHUNK_LEN, || async {
+            let stream = tcp_pair().await;
+            let mut buf = vec![1u8; CHUNK_LEN];
+            let start = Instant::now();
+            let mut sent = 0;
+            while sent < STREAM_LEN {
+                let (n, out) = stream.send_zc(buf).await;
+                sent += n.unwrap();
+                buf = out;
+            }
+            start.elapsed()
+        });
+    }
+
+    fn bench<F: Future<Output = Duration> + 'static>(
+        name: &str,
+        ops: usize,
+        case: impl FnOnce() -> F + Send + 'static,
+    ) {
+        let elapsed = std::thread::spawn(move || stellarator::run(case))
+            .join()
+            .unwrap();
+        let per_op = elapsed.as_nanos() as f64 / ops as f64;
+        println!("  {name:<24} {per_op:>10.0} ns/op {elapsed:>12.2?}");
+    }
+
+    fn udp_pair(fixed: bool) -> (UdpSocket, UdpSocket) {
+        let mut a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let mut b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        a.connect(b.local_addr().unwrap());
+        if fixed {
+            a.register_fixed().unwrap();
+            b.register_fixed().unwrap();
+        }
+        (a, b)
+    }
+
+    /// Connects to a listener whose end of the connection drains everything written to it
+    async fn tcp_pair() -> TcpStream {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr().unwrap();
+        stellarator::spawn(async move {
+            let stream = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; CHUNK_LEN];
+            loop {
+                let (n, out) = stream.read(buf).await;
+                if !matches!(n, Ok(n) if n > 0) {
+                    break;
+                }
+                buf = out;
+            }
+        });
+        TcpStream::connect(addr).await.unwrap()
+    }
+}
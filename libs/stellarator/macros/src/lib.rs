@@ -2,7 +2,8 @@ use proc_macro::TokenStream;
 use proc_macro_crate::{FoundCrate, crate_name};
 use proc_macro2::Span;
 use quote::quote;
-use syn::Ident;
+use syn::punctuated::Punctuated;
+use syn::{Ident, Meta, Token};
 use syn::{ItemFn, parse_macro_input};
 
 /// Attribute macro for stellarator main functions that wraps an async function with stellarator::run
@@ -39,10 +40,30 @@ pub fn main(_args: TokenStream, input: TokenStream) -> TokenStream {
 }
 
 /// Attribute macro for stellarator tests that wraps an async function with stellarator::run
+///
+/// `#[stellarator::test(sim)]` runs the test as a simulation with stellarator::sim::run instead,
+/// the scheduling order can be seeded with `#[stellarator::test(sim, seed = 42)]`
 #[proc_macro_attribute]
-pub fn test(_args: TokenStream, input: TokenStream) -> TokenStream {
+pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<Meta, Token![,]>::parse_terminated);
     let input_fn = parse_macro_input!(input as ItemFn);
 
+    let mut sim = false;
+    let mut seed = None;
+    for arg in args {
+        match arg {
+            Meta::Path(path) if path.is_ident("sim") => sim = true,
+            Meta::NameValue(name_value) if name_value.path.is_ident("seed") => {
+                seed = Some(name_value.value)
+            }
+            arg => {
+                return syn::Error::new_spanned(arg, "expected `sim` or `seed = <u64>`")
+                    .to_compile_error()
+                    .into();
+            }
+        }
+    }
+
     if input_fn.sig.asyncness.is_none() {
         return syn::Error::new_spanned(
             &input_fn.sig,
@@ -59,11 +80,18 @@ pub fn test(_args: TokenStream, input: TokenStream) -> TokenStream {
     let fn_generics = &input_fn.sig.generics;
     let stellar = stellar_crate_name();
 
+    let run = if sim || seed.is_some() {
+        let seed = seed.map(|seed| quote!(#seed)).unwrap_or(quote!(0));
+        quote!(#stellar::sim::run(#seed, move || async move #fn_body))
+    } else {
+        quote!(#stellar::run(move || async move #fn_body))
+    };
+
     let result = quote! {
         #[::core::prelude::v1::test]
         #(#fn_attrs)*
         #fn_vis fn #fn_name #fn_generics() {
-            #stellar::run(move || async move #fn_body)
+            #run
         }
     };
 
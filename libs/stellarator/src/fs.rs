@@ -1,11 +1,13 @@
 use crate::io::{AsyncRead, AsyncWrite};
+use crate::os::OwnedHandle;
 use crate::reactor::Completion;
 use crate::{BufResult, Error};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::{io, os};
 
 pub struct File {
-    handle: crate::os::OwnedHandle,
+    handle: OwnedHandle,
 }
 
 impl File {
@@ -75,6 +77,60 @@ impl File {
             handle: self.handle.try_clone()?,
         })
     }
+
+    /// Flushes the file's data and metadata to disk
+    pub async fn sync_all(&self) -> Result<(), Error> {
+        Completion::run(crate::reactor::ops::Fsync::new(
+            self.handle.as_handle(),
+            false,
+        ))
+        .await
+    }
+
+    /// Flushes the file's data to disk, skipping metadata that isn't needed to read it back
+    pub async fn sync_data(&self) -> Result<(), Error> {
+        Completion::run(crate::reactor::ops::Fsync::new(
+            self.handle.as_handle(),
+            true,
+        ))
+        .await
+    }
+
+    /// Reserves disk space for `len` bytes starting at `offset`, extending the file if needed
+    pub async fn allocate(&self, offset: u64, len: u64) -> Result<(), Error> {
+        Completion::run(crate::reactor::ops::Fallocate::new(
+            self.handle.as_handle(),
+            offset,
+            len,
+        ))
+        .await
+    }
+
+    /// Truncates or extends the file to `len` bytes
+    pub async fn set_len(&self, len: u64) -> Result<(), Error> {
+        let res = Completion::run(crate::reactor::ops::Ftruncate::new(
+            self.handle.as_handle(),
+            len,
+        ))
+        .await;
+        // kernels before 6.9 don't know the ftruncate opcode and reject it with EINVAL, so it
+        // falls back to the blocking thread pool there
+        #[cfg(target_os = "linux")]
+        if let Err(Error::Io(err)) = &res {
+            if err.raw_os_error() == Some(libc::EINVAL) {
+                let handle = self.handle.try_clone()?;
+                return blocking::unblock(move || {
+                    Ok(rustix::fs::ftruncate(&handle, len).map_err(io::Error::from)?)
+                })
+                .await;
+            }
+        }
+        res
+    }
+
+    pub async fn metadata(&self) -> Result<Metadata, Error> {
+        Completion::run(crate::reactor::ops::Statx::new(self.handle.as_handle())).await
+    }
 }
 
 /// Options and flags which can be used to configure how a file is opened
@@ -203,6 +259,193 @@ impl OpenOptions {
     }
 }
 
+/// Metadata about a file, see [`File::metadata`] and [`metadata`]
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    len: u64,
+    is_dir: bool,
+    is_file: bool,
+    modified: SystemTime,
+}
+
+impl Metadata {
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.is_file
+    }
+
+    /// The last time the file's contents were modified
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn from_statx(statx: &libc::statx) -> Self {
+        let file_type = statx.stx_mode as libc::mode_t & libc::S_IFMT;
+        let modified = std::time::Duration::new(
+            statx.stx_mtime.tv_sec.max(0) as u64,
+            statx.stx_mtime.tv_nsec,
+        );
+        Self {
+            len: statx.stx_size,
+            is_dir: file_type == libc::S_IFDIR,
+            is_file: file_type == libc::S_IFREG,
+            modified: SystemTime::UNIX_EPOCH + modified,
+        }
+    }
+}
+
+impl From<std::fs::Metadata> for Metadata {
+    fn from(metadata: std::fs::Metadata) -> Self {
+        Self {
+            len: metadata.len(),
+            is_dir: metadata.is_dir(),
+            is_file: metadata.is_file(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        }
+    }
+}
+
+/// Reads the metadata of the file at `path`, following symlinks
+pub async fn metadata(path: impl AsRef<Path>) -> Result<Metadata, Error> {
+    Completion::run(crate::reactor::ops::Statx::path(
+        path.as_ref().to_path_buf(),
+    )?)
+    .await
+}
+
+/// Renames `from` to `to`, replacing `to` if it already exists
+///
+/// The rename is atomic, so writing to a temporary file and renaming it over the original
+/// never leaves a partially written file behind.
+pub async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Error> {
+    Completion::run(crate::reactor::ops::Rename::new(
+        from.as_ref().to_path_buf(),
+        to.as_ref().to_path_buf(),
+    )?)
+    .await
+}
+
+pub async fn remove_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    Completion::run(crate::reactor::ops::Unlink::new(
+        path.as_ref().to_path_buf(),
+        false,
+    )?)
+    .await
+}
+
+/// Removes an empty directory
+pub async fn remove_dir(path: impl AsRef<Path>) -> Result<(), Error> {
+    Completion::run(crate::reactor::ops::Unlink::new(
+        path.as_ref().to_path_buf(),
+        true,
+    )?)
+    .await
+}
+
+pub async fn create_dir(path: impl AsRef<Path>) -> Result<(), Error> {
+    Completion::run(crate::reactor::ops::MkDir::new(
+        path.as_ref().to_path_buf(),
+        0o777,
+    )?)
+    .await
+}
+
+/// Creates a directory and all of its missing parents
+pub async fn create_dir_all(path: impl AsRef<Path>) -> Result<(), Error> {
+    let mut missing = vec![];
+    let mut dir = path.as_ref();
+    loop {
+        match create_dir(dir).await {
+            Ok(()) => break,
+            Err(err) if io_kind(&err) == Some(io::ErrorKind::NotFound) => {
+                missing.push(dir);
+                match dir.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => dir = parent,
+                    _ => return Err(err),
+                }
+            }
+            Err(err) => return exists_as_dir(dir, err).await,
+        }
+    }
+    for dir in missing.into_iter().rev() {
+        if let Err(err) = create_dir(dir).await {
+            exists_as_dir(dir, err).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Treats a failure to create `dir` as success if another directory is already there
+async fn exists_as_dir(dir: &Path, err: Error) -> Result<(), Error> {
+    if io_kind(&err) == Some(io::ErrorKind::AlreadyExists)
+        && metadata(dir).await.is_ok_and(|m| m.is_dir())
+    {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+fn io_kind(err: &Error) -> Option<io::ErrorKind> {
+    match err {
+        Error::Io(err) => Some(err.kind()),
+        _ => None,
+    }
+}
+
+/// An entry returned by [`read_dir`]
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    path: PathBuf,
+    file_type: std::fs::FileType,
+}
+
+impl DirEntry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file_name(&self) -> &std::ffi::OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    pub fn file_type(&self) -> std::fs::FileType {
+        self.file_type
+    }
+}
+
+/// Lists the entries of the directory at `path`
+///
+/// io_uring has no opcode for reading directories, so this runs on the blocking thread pool on
+/// every platform.
+pub async fn read_dir(path: impl AsRef<Path>) -> Result<Vec<DirEntry>, Error> {
+    let path = path.as_ref().to_path_buf();
+    blocking::unblock(move || {
+        std::fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry {
+                    path: entry.path(),
+                    file_type: entry.file_type()?,
+                })
+            })
+            .collect()
+    })
+    .await
+}
+
 impl AsyncRead for File {
     fn read<B: crate::buf::IoBufMut>(
         &self,
@@ -255,4 +498,49 @@ mod tests {
         assert_eq!(n, 4);
         assert_eq!(&out_buf, buf);
     }
+
+    #[test]
+    async fn test_sync_and_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test");
+        let file = File::create(&path).await.unwrap();
+        file.write_all(&b"hello"[..]).await.0.unwrap();
+        file.sync_data().await.unwrap();
+        file.sync_all().await.unwrap();
+        let meta = file.metadata().await.unwrap();
+        assert_eq!(meta.len(), 5);
+        assert!(meta.is_file());
+        assert!(!meta.is_dir());
+
+        file.allocate(0, 4096).await.unwrap();
+        assert_eq!(metadata(&path).await.unwrap().len(), 4096);
+        file.set_len(2).await.unwrap();
+        assert_eq!(metadata(&path).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    async fn test_dir_ops() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b/c");
+        create_dir_all(&nested).await.unwrap();
+        // already existing directories aren't an error
+        create_dir_all(&nested).await.unwrap();
+        assert!(metadata(&nested).await.unwrap().is_dir());
+        assert!(create_dir(&nested).await.is_err());
+
+        let from = nested.join("from");
+        let to = nested.join("to");
+        File::create(&from).await.unwrap();
+        rename(&from, &to).await.unwrap();
+        assert!(metadata(&from).await.is_err());
+
+        let entries = read_dir(&nested).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].file_name(), "to");
+        assert!(entries[0].file_type().is_file());
+
+        remove_file(&to).await.unwrap();
+        remove_dir(&nested).await.unwrap();
+        assert!(read_dir(dir.path().join("a/b")).await.unwrap().is_empty());
+    }
 }
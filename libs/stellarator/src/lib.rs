@@ -22,8 +22,15 @@ pub mod io;
 pub mod net;
 pub mod os;
 #[cfg(not(target_os = "windows"))]
+pub mod process;
+pub mod rt;
+#[cfg(not(target_os = "windows"))]
 pub mod serial;
+#[cfg(not(target_os = "windows"))]
+pub mod signal;
+pub mod sim;
 pub mod struc_con;
+pub mod time;
 pub mod util;
 
 mod noop_waker;
@@ -76,6 +83,8 @@ pub enum Error {
 
     #[error("join failed")]
     JoinFailed,
+    #[error("timed out")]
+    TimedOut,
 }
 
 impl From<std::io::Error> for Error {
@@ -129,9 +138,12 @@ impl<R: Reactor> Executor<R> {
             }
             let turn = self.timer.try_turn();
             if !tick.has_remaining && turn.as_ref().map(|t| t.expired == 0).unwrap_or(true) {
-                self.reactor
-                    .borrow_mut()
-                    .wait_for_io(turn.and_then(|turn| turn.time_to_next_deadline()))?;
+                let timeout = turn.and_then(|turn| turn.time_to_next_deadline());
+                match timeout {
+                    // every task is idle, so a simulation can skip straight to the next deadline
+                    Some(timeout) if sim::is_active() => sim::advance(timeout),
+                    _ => self.reactor.borrow_mut().wait_for_io(timeout)?,
+                }
             }
         };
         unsafe {
@@ -200,7 +212,13 @@ where
     F: Future + 'static,
     F::Output: Send + 'static,
 {
-    JoinHandle(Executor::with(|exec| exec.scheduler.spawn(f)))
+    JoinHandle(Executor::with(|exec| {
+        if sim::is_active() {
+            exec.scheduler.spawn(sim::Shuffled::new(f))
+        } else {
+            exec.scheduler.spawn(f)
+        }
+    }))
 }
 
 pub fn sleep(duration: Duration) -> maitake::time::Sleep<'static> {
@@ -256,6 +274,41 @@ macro_rules! rent_read {
     };
 }
 
+/// Waits on several futures at once, running the branch of the first one to complete
+///
+/// Branches are polled in order, so earlier branches win ties. The futures of the other branches
+/// are dropped, which cancels any io they have in flight. Patterns must be irrefutable.
+///
+/// ```ignore
+/// select! {
+///     res = stream.read(buf) => handle(res),
+///     _ = stellarator::sleep(Duration::from_secs(1)) => println!("idle"),
+/// }
+/// ```
+#[macro_export]
+macro_rules! select {
+    (@future $pat:pat = $fut:expr => $body:expr $(,)?) => {
+        $fut
+    };
+    (@future $pat:pat = $fut:expr => $body:expr, $($rest:tt)+) => {
+        $crate::util::select($fut, $crate::select!(@future $($rest)+))
+    };
+    (@match $out:expr; $pat:pat = $fut:expr => $body:expr $(,)?) => {
+        match $out {
+            $pat => $body,
+        }
+    };
+    (@match $out:expr; $pat:pat = $fut:expr => $body:expr, $($rest:tt)+) => {
+        match $out {
+            $crate::util::Either::Left($pat) => $body,
+            $crate::util::Either::Right(out) => $crate::select!(@match out; $($rest)+),
+        }
+    };
+    ($($branches:tt)+) => {
+        $crate::select!(@match $crate::select!(@future $($branches)+).await; $($branches)+)
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
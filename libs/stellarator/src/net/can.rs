@@ -0,0 +1,469 @@
+use crate::Error;
+use crate::os::BorrowedHandle;
+use crate::reactor::{Completion, ops};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::ffi::CString;
+use std::io;
+use std::os::fd::AsRawFd;
+
+/// The size of a classic CAN frame on the wire, `struct can_frame`
+const CAN_MTU: usize = 16;
+/// The size of a CAN FD frame on the wire, `struct canfd_frame`
+const CANFD_MTU: usize = 72;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_SFF_MASK: u32 = 0x0000_07ff;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+const CAN_INV_FILTER: u32 = 0x2000_0000;
+
+const CANFD_BRS: u8 = 0x01;
+const CANFD_ESI: u8 = 0x02;
+
+const SOL_CAN_RAW: i32 = 100 + CAN_RAW;
+const CAN_RAW: i32 = 1;
+const CAN_RAW_FILTER: i32 = 1;
+const CAN_RAW_ERR_FILTER: i32 = 2;
+const CAN_RAW_LOOPBACK: i32 = 3;
+const CAN_RAW_RECV_OWN_MSGS: i32 = 4;
+const CAN_RAW_FD_FRAMES: i32 = 5;
+
+/// A CAN identifier, either 11 bit standard or 29 bit extended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CanId {
+    Standard(u16),
+    Extended(u32),
+}
+
+impl CanId {
+    /// Returns `None` if the id doesn't fit in 11 bits
+    pub fn standard(id: u16) -> Option<Self> {
+        (id as u32 <= CAN_SFF_MASK).then_some(CanId::Standard(id))
+    }
+
+    /// Returns `None` if the id doesn't fit in 29 bits
+    pub fn extended(id: u32) -> Option<Self> {
+        (id <= CAN_EFF_MASK).then_some(CanId::Extended(id))
+    }
+
+    /// The id without the extended flag
+    pub fn as_raw(&self) -> u32 {
+        match *self {
+            CanId::Standard(id) => id as u32,
+            CanId::Extended(id) => id,
+        }
+    }
+
+    fn to_bits(self) -> u32 {
+        match self {
+            CanId::Standard(id) => id as u32 & CAN_SFF_MASK,
+            CanId::Extended(id) => (id & CAN_EFF_MASK) | CAN_EFF_FLAG,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        if bits & CAN_EFF_FLAG != 0 {
+            CanId::Extended(bits & CAN_EFF_MASK)
+        } else {
+            CanId::Standard((bits & CAN_SFF_MASK) as u16)
+        }
+    }
+}
+
+/// A classic CAN or CAN FD frame
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CanFrame {
+    /// The id, including the `EFF`, `RTR` and `ERR` flags
+    id: u32,
+    len: u8,
+    fd_flags: u8,
+    fd: bool,
+    data: [u8; 64],
+}
+
+impl CanFrame {
+    /// A classic data frame, returns `None` if `data` is longer than 8 bytes
+    pub fn new(id: CanId, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        Some(Self::with_data(id.to_bits(), data, 0, false))
+    }
+
+    /// A CAN FD data frame, returns `None` if `data` is longer than 64 bytes
+    ///
+    /// `bit_rate_switch` sends the data phase at the interface's faster data bit rate.
+    pub fn new_fd(id: CanId, data: &[u8], bit_rate_switch: bool) -> Option<Self> {
+        if data.len() > 64 {
+            return None;
+        }
+        let flags = if bit_rate_switch { CANFD_BRS } else { 0 };
+        Some(Self::with_data(id.to_bits(), data, flags, true))
+    }
+
+    /// A classic remote frame, requesting `len` bytes from whichever node owns `id`
+    pub fn remote(id: CanId, len: u8) -> Option<Self> {
+        if len > 8 {
+            return None;
+        }
+        let mut frame = Self::with_data(id.to_bits() | CAN_RTR_FLAG, &[], 0, false);
+        frame.len = len;
+        Some(frame)
+    }
+
+    fn with_data(id: u32, data: &[u8], fd_flags: u8, fd: bool) -> Self {
+        let mut buf = [0; 64];
+        buf[..data.len()].copy_from_slice(data);
+        CanFrame {
+            id,
+            len: data.len() as u8,
+            fd_flags,
+            fd,
+            data: buf,
+        }
+    }
+
+    pub fn id(&self) -> CanId {
+        CanId::from_bits(self.id)
+    }
+
+    /// The id with the kernel's `EFF`, `RTR` and `ERR` flags in the top three bits
+    pub fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    /// The payload, which is empty for remote frames
+    pub fn data(&self) -> &[u8] {
+        if self.is_remote() {
+            return &[];
+        }
+        &self.data[..self.len as usize]
+    }
+
+    /// The data length code, which for remote frames is the number of bytes requested
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_fd(&self) -> bool {
+        self.fd
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.id & CAN_EFF_FLAG != 0
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.id & CAN_RTR_FLAG != 0
+    }
+
+    /// Whether this is an error frame generated by the driver, see [`CanSocket::set_error_filter`]
+    ///
+    /// The error class is in [`CanFrame::error_class`], and the details are in the payload as laid
+    /// out in `linux/can/error.h`.
+    pub fn is_error(&self) -> bool {
+        self.id & CAN_ERR_FLAG != 0
+    }
+
+    /// The `CAN_ERR_*` class bits of an error frame
+    pub fn error_class(&self) -> u32 {
+        self.id & CAN_EFF_MASK
+    }
+
+    pub fn bit_rate_switch(&self) -> bool {
+        self.fd_flags & CANFD_BRS != 0
+    }
+
+    /// Whether the sender was error passive, only set on received CAN FD frames
+    pub fn error_state_indicator(&self) -> bool {
+        self.fd_flags & CANFD_ESI != 0
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; if self.fd { CANFD_MTU } else { CAN_MTU }];
+        buf[..4].copy_from_slice(&self.id.to_ne_bytes());
+        buf[4] = self.len;
+        if self.fd {
+            buf[5] = self.fd_flags;
+        }
+        let data_len = buf.len() - 8;
+        buf[8..].copy_from_slice(&self.data[..data_len]);
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let fd = match buf.len() {
+            CAN_MTU => false,
+            CANFD_MTU => true,
+            _ => return Err(io::Error::from(io::ErrorKind::InvalidData).into()),
+        };
+        let mut data = [0; 64];
+        data[..buf.len() - 8].copy_from_slice(&buf[8..]);
+        Ok(CanFrame {
+            id: u32::from_ne_bytes(buf[..4].try_into().unwrap()),
+            len: buf[4].min(if fd { 64 } else { 8 }),
+            fd_flags: if fd { buf[5] } else { 0 },
+            fd,
+            data,
+        })
+    }
+}
+
+impl std::fmt::Debug for CanFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CanFrame")
+            .field("id", &self.id())
+            .field("fd", &self.fd)
+            .field("remote", &self.is_remote())
+            .field("error", &self.is_error())
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// An acceptance filter, a frame is received if `frame_id & mask == id & mask`
+///
+/// Laid out as the kernel's `struct can_filter`, the ids include the `EFF` and `RTR` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct CanFilter {
+    pub id: u32,
+    pub mask: u32,
+}
+
+impl CanFilter {
+    pub fn new(id: u32, mask: u32) -> Self {
+        CanFilter { id, mask }
+    }
+
+    /// Matches data frames with exactly `id`
+    pub fn exact(id: CanId) -> Self {
+        let mask = match id {
+            CanId::Standard(_) => CAN_SFF_MASK,
+            CanId::Extended(_) => CAN_EFF_MASK,
+        };
+        CanFilter {
+            id: id.to_bits(),
+            mask: mask | CAN_EFF_FLAG | CAN_RTR_FLAG,
+        }
+    }
+
+    /// Matches the frames this filter would otherwise reject
+    pub fn inverted(mut self) -> Self {
+        self.id |= CAN_INV_FILTER;
+        self
+    }
+}
+
+/// A raw SocketCAN socket bound to a single interface
+pub struct CanSocket {
+    socket: Socket,
+    fd_frames: bool,
+}
+
+impl CanSocket {
+    /// Opens a socket on `interface`, e.g. `can0` or `vcan0`
+    ///
+    /// The socket starts out receiving every frame but error frames, and only classic frames
+    /// until [`CanSocket::set_fd_frames`] is called.
+    pub fn open(interface: &str) -> io::Result<CanSocket> {
+        let name = CString::new(interface)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+        // safety: name is a valid, nul terminated string
+        let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let socket = Socket::new(
+            Domain::from(libc::AF_CAN),
+            Type::RAW,
+            Some(Protocol::from(CAN_RAW)),
+        )?;
+        socket.set_nonblocking(!cfg!(target_os = "linux"))?;
+
+        // safety: sockaddr_can is plain old data, so all zeros is a valid value
+        let mut addr: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+        addr.can_family = libc::AF_CAN as libc::sa_family_t;
+        addr.can_ifindex = ifindex as i32;
+        // safety: addr is a valid sockaddr_can and the length matches it
+        let res = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(CanSocket {
+            socket,
+            fd_frames: false,
+        })
+    }
+
+    /// Enables sending and receiving CAN FD frames, along with classic frames
+    pub fn set_fd_frames(&mut self, enabled: bool) -> io::Result<()> {
+        self.set_opt(CAN_RAW_FD_FRAMES, &(enabled as libc::c_int))?;
+        self.fd_frames = enabled;
+        Ok(())
+    }
+
+    /// Replaces the acceptance filters, an empty list rejects every data frame
+    pub fn set_filters(&self, filters: &[CanFilter]) -> io::Result<()> {
+        self.set_opt(CAN_RAW_FILTER, filters)
+    }
+
+    /// Receives error frames for the `CAN_ERR_*` classes in `mask`, which is zero by default
+    pub fn set_error_filter(&self, mask: u32) -> io::Result<()> {
+        self.set_opt(CAN_RAW_ERR_FILTER, &mask)
+    }
+
+    /// Whether frames sent on this socket are echoed to other sockets on the same host, which is
+    /// on by default
+    pub fn set_loopback(&self, enabled: bool) -> io::Result<()> {
+        self.set_opt(CAN_RAW_LOOPBACK, &(enabled as libc::c_int))
+    }
+
+    /// Whether this socket receives the frames it sent itself, which is off by default
+    pub fn set_recv_own_msgs(&self, enabled: bool) -> io::Result<()> {
+        self.set_opt(CAN_RAW_RECV_OWN_MSGS, &(enabled as libc::c_int))
+    }
+
+    fn set_opt<T: ?Sized>(&self, name: i32, value: &T) -> io::Result<()> {
+        // safety: value is valid for reads of its own size
+        let res = unsafe {
+            libc::setsockopt(
+                self.socket.as_raw_fd(),
+                SOL_CAN_RAW,
+                name,
+                value as *const T as *const libc::c_void,
+                size_of_val(value) as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Waits for the next frame that passes the filters
+    pub async fn recv(&self) -> Result<CanFrame, Error> {
+        let buf = vec![0u8; if self.fd_frames { CANFD_MTU } else { CAN_MTU }];
+        let (res, buf) = Completion::run(ops::Read::new(self.as_handle(), buf, None)).await;
+        let n = res?;
+        CanFrame::decode(&buf[..n])
+    }
+
+    /// Queues `frame` for transmission
+    ///
+    /// CAN FD frames need [`CanSocket::set_fd_frames`], and an interface with an FD capable MTU.
+    pub async fn send(&self, frame: &CanFrame) -> Result<(), Error> {
+        if frame.fd && !self.fd_frames {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "CAN FD frames aren't enabled on this socket",
+            )
+            .into());
+        }
+        let buf = frame.encode();
+        let len = buf.len();
+        let (res, _) = Completion::run(ops::Write::new(self.as_handle(), buf, None)).await;
+        if res? != len {
+            return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+        }
+        Ok(())
+    }
+
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        BorrowedHandle::Socket(&self.socket)
+    }
+}
+
+impl AsRawFd for CanSocket {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    /// Opens a pair of sockets on the virtual CAN interface in `STELLARATOR_VCAN`, or `vcan0`
+    ///
+    /// The tests using it are ignored by default, run them with `cargo test -- --ignored` once
+    /// the interface exists, it can be created with
+    /// `ip link add dev vcan0 type vcan && ip link set up vcan0`
+    fn vcan_pair() -> (CanSocket, CanSocket) {
+        let interface = std::env::var("STELLARATOR_VCAN").unwrap_or_else(|_| "vcan0".to_string());
+        let open = || {
+            CanSocket::open(&interface)
+                .unwrap_or_else(|err| panic!("{interface} isn't available: {err}"))
+        };
+        (open(), open())
+    }
+
+    #[test]
+    async fn test_frame_encoding() {
+        let id = CanId::extended(0x1234_5678).unwrap();
+        let frame = CanFrame::new_fd(id, &[1, 2, 3, 4, 5, 6, 7, 8, 9], true).unwrap();
+        let decoded = CanFrame::decode(&frame.encode()).unwrap();
+        assert_eq!(decoded, frame);
+        assert_eq!(decoded.id(), id);
+        assert!(decoded.bit_rate_switch());
+
+        let frame = CanFrame::remote(CanId::standard(0x7ff).unwrap(), 4).unwrap();
+        let decoded = CanFrame::decode(&frame.encode()).unwrap();
+        assert!(decoded.is_remote());
+        assert_eq!(decoded.len(), 4);
+        assert_eq!(decoded.data(), &[]);
+
+        assert!(CanId::standard(0x800).is_none());
+        assert!(CanFrame::new(CanId::Standard(1), &[0; 9]).is_none());
+    }
+
+    #[test]
+    #[ignore = "needs a vcan interface"]
+    async fn test_send_recv() {
+        let (a, b) = vcan_pair();
+        let frame = CanFrame::new(CanId::Standard(0x123), &[0xde, 0xad, 0xbe, 0xef]).unwrap();
+        a.send(&frame).await.unwrap();
+        assert_eq!(b.recv().await.unwrap(), frame);
+    }
+
+    #[test]
+    #[ignore = "needs a vcan interface"]
+    async fn test_fd_frames() {
+        let (mut a, mut b) = vcan_pair();
+        a.set_fd_frames(true).unwrap();
+        b.set_fd_frames(true).unwrap();
+        let frame = CanFrame::new_fd(CanId::Extended(0x1abc_def0), &[7; 48], true).unwrap();
+        a.send(&frame).await.unwrap();
+        let recv = b.recv().await.unwrap();
+        assert!(recv.is_fd());
+        assert_eq!(recv.id(), CanId::Extended(0x1abc_def0));
+        assert_eq!(recv.data(), &[7; 48]);
+    }
+
+    #[test]
+    #[ignore = "needs a vcan interface"]
+    async fn test_filters() {
+        let (a, b) = vcan_pair();
+        b.set_filters(&[CanFilter::exact(CanId::Standard(0x42))])
+            .unwrap();
+        let skipped = CanFrame::new(CanId::Standard(0x41), &[1]).unwrap();
+        let matched = CanFrame::new(CanId::Standard(0x42), &[2]).unwrap();
+        a.send(&skipped).await.unwrap();
+        a.send(&matched).await.unwrap();
+        assert_eq!(b.recv().await.unwrap(), matched);
+    }
+}
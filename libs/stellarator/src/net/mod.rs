@@ -6,6 +6,14 @@ mod tcp;
 pub use tcp::*;
 mod udp;
 pub use udp::*;
+#[cfg(target_os = "linux")]
+mod can;
+#[cfg(target_os = "linux")]
+pub use can::*;
+#[cfg(target_os = "linux")]
+mod multishot;
+#[cfg(target_os = "linux")]
+pub use multishot::*;
 
 #[cfg(target_os = "windows")]
 type SockAddrStorage = windows_sys::Win32::Networking::WinSock::SOCKADDR_STORAGE;
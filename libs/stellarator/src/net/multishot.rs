@@ -0,0 +1,90 @@
+use socket2::Socket;
+
+use super::TcpStream;
+use crate::Error;
+use crate::reactor::ops::{self, Target};
+use crate::uring::{BufRing, Multishot, ProvidedBuf};
+
+/// Data received by a multishot recv, see [`super::UdpSocket::recv_multishot`] and
+/// [`TcpStream::recv_multishot`]
+pub struct RecvStream<'a> {
+    target: Target,
+    ring: BufRing,
+    multishot: Option<Multishot<ops::RecvMulti>>,
+    _socket: std::marker::PhantomData<&'a Socket>,
+}
+
+impl RecvStream<'_> {
+    pub(crate) fn new(target: Target, ring: BufRing) -> Self {
+        RecvStream {
+            target,
+            ring,
+            multishot: None,
+            _socket: std::marker::PhantomData,
+        }
+    }
+
+    /// Waits for the next buffer of data, re-arming the recv if the kernel stopped it
+    ///
+    /// The kernel stops the recv with `ENOBUFS` when it runs out of buffers. The recv is re-armed
+    /// if some have been dropped since, so `ENOBUFS` is only returned while every buffer of the
+    /// ring is held.
+    pub async fn recv(&mut self) -> Result<ProvidedBuf, Error> {
+        loop {
+            let multishot = match &mut self.multishot {
+                Some(multishot) => multishot,
+                None => {
+                    let op = ops::RecvMulti::new(self.target, self.ring.clone());
+                    self.multishot
+                        .insert(Multishot::submit(op).map_err(|(_, err)| err)?)
+                }
+            };
+            match multishot.next().await {
+                Some(Err(Error::Io(err)))
+                    if err.raw_os_error() == Some(libc::ENOBUFS) && self.ring.has_free() =>
+                {
+                    self.multishot = None
+                }
+                Some(res) => return res,
+                None => self.multishot = None,
+            }
+        }
+    }
+}
+
+/// Connections accepted by a multishot accept, see [`super::TcpListener::accept_multishot`]
+pub struct AcceptStream<'a> {
+    socket: &'a Socket,
+    multishot: Option<Multishot<ops::AcceptMulti>>,
+}
+
+impl<'a> AcceptStream<'a> {
+    pub(crate) fn new(socket: &'a Socket) -> Self {
+        AcceptStream {
+            socket,
+            multishot: None,
+        }
+    }
+
+    /// Waits for the next connection, re-arming the accept if the kernel stopped it
+    pub async fn accept(&mut self) -> Result<TcpStream, Error> {
+        loop {
+            let multishot = match &mut self.multishot {
+                Some(multishot) => multishot,
+                None => {
+                    let op = ops::AcceptMulti::new(self.socket);
+                    self.multishot
+                        .insert(Multishot::submit(op).map_err(|(_, err)| err)?)
+                }
+            };
+            match multishot.next().await {
+                Some(res) => {
+                    let socket = res?;
+                    socket.set_cloexec(true)?;
+                    return Ok(TcpStream::from_socket(socket));
+                }
+                None => self.multishot = None,
+            }
+        }
+    }
+}
@@ -6,11 +6,20 @@ use crate::{BufResult, Error};
 use socket2::{SockAddr, Socket};
 use std::io::{self};
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
 
 use super::SockAddrRaw;
+#[cfg(target_os = "linux")]
+use super::{AcceptStream, RecvStream};
+#[cfg(target_os = "linux")]
+use crate::uring::{BufRing, FixedFile};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsFd, AsRawFd};
 
 pub struct TcpStream {
     socket: Socket,
+    #[cfg(target_os = "linux")]
+    fixed: Option<FixedFile>,
 }
 
 impl TcpStream {
@@ -26,15 +35,60 @@ impl TcpStream {
         let addr: SockAddr = addr.into();
         Completion::run(ops::Connect::new(&socket, Box::new(addr.into()))?).await?;
 
-        Ok(TcpStream { socket })
+        Ok(TcpStream::from_socket(socket))
+    }
+
+    pub(crate) fn from_socket(socket: Socket) -> Self {
+        TcpStream {
+            socket,
+            #[cfg(target_os = "linux")]
+            fixed: None,
+        }
+    }
+
+    /// Connects to `addr`, failing with [`Error::TimedOut`] if the connection isn't established
+    /// within `timeout`
+    pub async fn connect_timeout(addr: SocketAddr, timeout: Duration) -> Result<TcpStream, Error> {
+        crate::time::timeout(timeout, Self::connect(addr)).await?
     }
 
     pub async fn read<B: IoBufMut>(&self, buf: B) -> BufResult<usize, B> {
-        Completion::run(ops::Read::new(self.as_handle(), buf, None)).await
+        let op = ops::Read::new(self.as_handle(), buf, None);
+        #[cfg(target_os = "linux")]
+        let op = op.fixed(self.fixed_index());
+        Completion::run(op).await
     }
 
     pub async fn write<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
-        Completion::run(ops::Write::new(self.as_handle(), buf, None)).await
+        let op = ops::Write::new(self.as_handle(), buf, None);
+        #[cfg(target_os = "linux")]
+        let op = op.fixed(self.fixed_index());
+        Completion::run(op).await
+    }
+
+    /// Reads into `buf`, failing with [`Error::TimedOut`] if no data arrives within `timeout`
+    ///
+    /// The timeout is linked to the read in the io_uring, so unlike wrapping the read in
+    /// [`crate::time::timeout`], the buffer is handed back when it expires.
+    #[cfg(target_os = "linux")]
+    pub async fn read_with_timeout<B: IoBufMut>(
+        &self,
+        buf: B,
+        timeout: Duration,
+    ) -> BufResult<usize, B> {
+        let read = ops::Read::new(self.as_handle(), buf, None).fixed(self.fixed_index());
+        Completion::run(ops::WithTimeout::new(read, timeout)).await
+    }
+
+    /// Writes `buf`, failing with [`Error::TimedOut`] if it can't be written within `timeout`
+    #[cfg(target_os = "linux")]
+    pub async fn write_with_timeout<B: IoBuf>(
+        &self,
+        buf: B,
+        timeout: Duration,
+    ) -> BufResult<usize, B> {
+        let write = ops::Write::new(self.as_handle(), buf, None).fixed(self.fixed_index());
+        Completion::run(ops::WithTimeout::new(write, timeout)).await
     }
 
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
@@ -48,6 +102,44 @@ impl TcpStream {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl TcpStream {
+    /// Registers the stream as a fixed file with the current thread's io_uring, so reads and
+    /// writes issued from this thread skip the kernel's file descriptor lookup
+    ///
+    /// Ops issued from other threads keep using the stream's descriptor.
+    pub fn register_fixed(&mut self) -> Result<(), Error> {
+        self.fixed = Some(FixedFile::register(self.socket.as_fd())?);
+        Ok(())
+    }
+
+    /// Reads into buffers picked from `ring` by a multishot recv, which stays armed between calls
+    /// to [`RecvStream::recv`]
+    ///
+    /// Like [`TcpStream::read`], an empty buffer means the peer closed the stream. Requires
+    /// Linux 6.0.
+    pub fn recv_multishot(&self, ring: &BufRing) -> RecvStream<'_> {
+        RecvStream::new(self.target(), ring.clone())
+    }
+
+    /// Writes `buf` without copying it into the kernel, handing it back once the kernel is done
+    /// with it
+    ///
+    /// Pinning the pages costs more than copying small buffers, so this only pays off for writes
+    /// of tens of kilobytes or more. Requires Linux 6.0.
+    pub async fn send_zc<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
+        ops::SendZc::new(self.target(), buf, None).run().await
+    }
+
+    fn fixed_index(&self) -> Option<u32> {
+        self.fixed.as_ref().and_then(FixedFile::index)
+    }
+
+    fn target(&self) -> ops::Target {
+        ops::Target::new(self.socket.as_raw_fd(), self.fixed_index())
+    }
+}
+
 impl AsyncRead for TcpStream {
     fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
         self.read(buf)
@@ -93,7 +185,16 @@ impl TcpListener {
         let socket = Completion::run(op).await.0?;
         #[cfg(not(target_os = "windows"))]
         socket.set_cloexec(true)?;
-        Ok(TcpStream { socket })
+        Ok(TcpStream::from_socket(socket))
+    }
+
+    /// Accepts connections with a multishot accept, which stays armed between calls to
+    /// [`AcceptStream::accept`]
+    ///
+    /// Requires Linux 5.19.
+    #[cfg(target_os = "linux")]
+    pub fn accept_multishot(&self) -> AcceptStream<'_> {
+        AcceptStream::new(&self.socket)
     }
 
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
@@ -126,4 +227,57 @@ mod tests {
         assert_eq!(&buf[..n], b"foo");
         handle.await.unwrap();
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_accept_multishot() {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = crate::spawn(async move {
+            let mut accept = listener.accept_multishot();
+            for _ in 0..3 {
+                let stream = accept.accept().await.unwrap();
+                let mut buf = vec![0u8; 128];
+                let n = rent!(stream.read(buf).await, buf).unwrap();
+                buf.truncate(n);
+                stream.write(buf).await.0.unwrap();
+            }
+        });
+        for msg in [&b"foo"[..], b"bar", b"baz"] {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            stream.write(msg).await.0.unwrap();
+            let mut buf = vec![0; 128];
+            let n = rent!(stream.read(buf).await, buf).unwrap();
+            assert_eq!(&buf[..n], msg);
+        }
+        handle.await.unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_recv_multishot() {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = crate::spawn(async move {
+            let mut stream = listener.accept().await.unwrap();
+            stream.register_fixed().unwrap();
+            let ring = BufRing::new(4, 16).unwrap();
+            let mut recv = stream.recv_multishot(&ring);
+            let mut out = vec![];
+            loop {
+                let buf = recv.recv().await.unwrap();
+                if buf.is_empty() {
+                    break;
+                }
+                out.extend_from_slice(&buf);
+            }
+            out
+        });
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let msg = (0..100u8).collect::<Vec<_>>();
+        let (n, _) = stream.send_zc(msg.clone()).await;
+        assert_eq!(n.unwrap(), msg.len());
+        drop(stream);
+        assert_eq!(handle.await.unwrap(), msg);
+    }
 }
@@ -7,9 +7,21 @@ use socket2::Socket;
 use std::io;
 use std::net::SocketAddr;
 
+#[cfg(target_os = "linux")]
+use super::RecvStream;
+#[cfg(target_os = "linux")]
+use crate::{
+    Error,
+    uring::{BufRing, FixedFile},
+};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsFd, AsRawFd};
+
 pub struct UdpSocket {
     socket: Socket,
     connected_addr: Option<SocketAddr>,
+    #[cfg(target_os = "linux")]
+    fixed: Option<FixedFile>,
 }
 
 impl UdpSocket {
@@ -34,6 +46,8 @@ impl UdpSocket {
         Ok(UdpSocket {
             socket,
             connected_addr: None,
+            #[cfg(target_os = "linux")]
+            fixed: None,
         })
     }
 
@@ -42,19 +56,28 @@ impl UdpSocket {
     }
 
     pub async fn recv<B: IoBufMut>(&self, buf: B) -> BufResult<usize, B> {
-        Completion::run(ops::Read::new(self.as_handle(), buf, None)).await
+        let op = ops::Read::new(self.as_handle(), buf, None);
+        #[cfg(target_os = "linux")]
+        let op = op.fixed(self.fixed_index());
+        Completion::run(op).await
     }
 
     pub async fn send<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
         if let Some(addr) = &self.connected_addr {
             self.send_to(buf, *addr).await
         } else {
-            Completion::run(ops::Write::new(self.as_handle(), buf, None)).await
+            let op = ops::Write::new(self.as_handle(), buf, None);
+            #[cfg(target_os = "linux")]
+            let op = op.fixed(self.fixed_index());
+            Completion::run(op).await
         }
     }
 
     pub async fn send_to<B: IoBuf>(&self, buf: B, target: SocketAddr) -> BufResult<usize, B> {
-        Completion::run(ops::SendTo::new(self.as_handle(), buf, target)).await
+        let op = ops::SendTo::new(self.as_handle(), buf, target);
+        #[cfg(target_os = "linux")]
+        let op = op.fixed(self.fixed_index());
+        Completion::run(op).await
     }
 
     pub fn connect(&mut self, addr: SocketAddr) {
@@ -72,6 +95,53 @@ impl UdpSocket {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl UdpSocket {
+    /// Registers the socket as a fixed file with the current thread's io_uring, so sends and
+    /// receives issued from this thread skip the kernel's file descriptor lookup
+    ///
+    /// Ops issued from other threads keep using the socket's descriptor.
+    pub fn register_fixed(&mut self) -> Result<(), Error> {
+        self.fixed = Some(FixedFile::register(self.socket.as_fd())?);
+        Ok(())
+    }
+
+    /// Receives datagrams into buffers picked from `ring` by a multishot recv, which stays armed
+    /// between calls to [`RecvStream::recv`]
+    ///
+    /// Datagrams longer than the ring's buffers are truncated, and the sender's address isn't
+    /// reported. Requires Linux 6.0.
+    pub fn recv_multishot(&self, ring: &BufRing) -> RecvStream<'_> {
+        RecvStream::new(self.target(), ring.clone())
+    }
+
+    /// Sends `buf` to the connected address without copying it into the kernel, handing it back
+    /// once the kernel is done with it
+    ///
+    /// Pinning the pages costs more than copying small buffers, so this only pays off for large
+    /// datagrams. Requires Linux 6.0.
+    pub async fn send_zc<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
+        ops::SendZc::new(self.target(), buf, self.connected_addr)
+            .run()
+            .await
+    }
+
+    /// Sends `buf` to `target` without copying it into the kernel, see [`UdpSocket::send_zc`]
+    pub async fn send_to_zc<B: IoBuf>(&self, buf: B, target: SocketAddr) -> BufResult<usize, B> {
+        ops::SendZc::new(self.target(), buf, Some(target))
+            .run()
+            .await
+    }
+
+    fn fixed_index(&self) -> Option<u32> {
+        self.fixed.as_ref().and_then(FixedFile::index)
+    }
+
+    fn target(&self) -> ops::Target {
+        ops::Target::new(self.socket.as_raw_fd(), self.fixed_index())
+    }
+}
+
 impl AsyncRead for UdpSocket {
     fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
         self.recv(buf)
@@ -143,4 +213,93 @@ mod tests {
         recv_buf = buf;
         assert_eq!(&recv_buf[..received_len], b"response from b");
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_recv_multishot() {
+        let a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b_addr = b.local_addr().unwrap();
+        let ring = BufRing::new(4, 64).unwrap();
+        let mut stream = b.recv_multishot(&ring);
+        for msg in [&b"foo"[..], b"bar", b"baz"] {
+            a.send_to(msg, b_addr).await.0.unwrap();
+            assert_eq!(&*stream.recv().await.unwrap(), msg);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_recv_multishot_exhausted_ring() {
+        let a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b_addr = b.local_addr().unwrap();
+        let ring = BufRing::new(2, 64).unwrap();
+        let mut stream = b.recv_multishot(&ring);
+        for i in 0..3u8 {
+            a.send_to(vec![i; 8], b_addr).await.0.unwrap();
+        }
+        let first = stream.recv().await.unwrap();
+        let second = stream.recv().await.unwrap();
+        let Err(Error::Io(err)) = stream.recv().await else {
+            panic!("expected ENOBUFS with every buffer held");
+        };
+        assert_eq!(err.raw_os_error(), Some(libc::ENOBUFS));
+        assert_eq!((first[0], second[0]), (0, 1));
+        drop(first);
+        // the datagram stays queued on the socket until a buffer is free
+        assert_eq!(&*stream.recv().await.unwrap(), &[2; 8]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_recv_multishot_drop() {
+        let a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b_addr = b.local_addr().unwrap();
+        let ring = BufRing::new(2, 64).unwrap();
+        let mut stream = b.recv_multishot(&ring);
+        // dropping the timed out recv leaves the multishot armed, it's cancelled with the stream
+        crate::time::timeout(std::time::Duration::from_millis(10), stream.recv())
+            .await
+            .unwrap_err();
+        drop(stream);
+        let mut stream = b.recv_multishot(&ring);
+        a.send_to(b"foo", b_addr).await.0.unwrap();
+        assert_eq!(&*stream.recv().await.unwrap(), b"foo");
+        assert!(ring.has_free());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_fixed_send_recv() {
+        let mut a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let mut b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        a.connect(b.local_addr().unwrap());
+        a.register_fixed().unwrap();
+        b.register_fixed().unwrap();
+        assert!(a.fixed_index().is_some());
+        a.send(b"foo").await.0.unwrap();
+        let out_buf = vec![0u8; 64];
+        let (n, out_buf) = b.recv(out_buf).await;
+        assert_eq!(&out_buf[..n.unwrap()], b"foo");
+        let ring = BufRing::new(2, 64).unwrap();
+        let mut stream = b.recv_multishot(&ring);
+        a.send(b"bar").await.0.unwrap();
+        assert_eq!(&*stream.recv().await.unwrap(), b"bar");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_send_zc() {
+        let mut a = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let b = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        a.connect(b.local_addr().unwrap());
+        let (n, buf) = a.send_zc(vec![7u8; 1024]).await;
+        assert_eq!(n.unwrap(), 1024);
+        assert_eq!(buf.len(), 1024);
+        let out_buf = vec![0u8; 2048];
+        let (n, out_buf) = b.recv(out_buf).await;
+        assert_eq!(&out_buf[..n.unwrap()], &[7u8; 1024]);
+    }
 }
@@ -212,14 +212,25 @@ pub fn pwrite<T: AsRawHandle>(
 pub fn os_clock() -> Clock {
     use std::time::Duration;
 
+    Clock::new(Duration::new(0, 1), || monotonic_now().as_nanos() as u64)
+}
+
+/// Returns the current reading of `CLOCK_MONOTONIC`, the clock io_uring measures absolute
+/// timeouts against
+#[cfg(not(target_os = "windows"))]
+pub fn monotonic_now() -> std::time::Duration {
     use rustix::time::ClockId;
 
-    Clock::new(Duration::new(0, 1), || {
-        let timespec = rustix::time::clock_gettime(ClockId::Monotonic);
-        let sec = timespec.tv_sec as u64;
-        let nano = timespec.tv_nsec as u64;
-        sec * 1_000_000_000 + nano
-    })
+    let timespec = rustix::time::clock_gettime(ClockId::Monotonic);
+    std::time::Duration::new(timespec.tv_sec as u64, timespec.tv_nsec as u32)
+}
+
+/// Returns the time elapsed since the first call, as Windows has no monotonic clock with a fixed
+/// epoch we can read cheaply
+#[cfg(target_os = "windows")]
+pub fn monotonic_now() -> std::time::Duration {
+    static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    EPOCH.get_or_init(std::time::Instant::now).elapsed()
 }
 
 #[cfg(target_os = "windows")]
@@ -1,4 +1,4 @@
-use maitake::time::Timer;
+use maitake::time::{Clock, Timer};
 use pin_project::{pin_project, pinned_drop};
 use polling::Poller;
 use slab::Slab;
@@ -157,6 +157,31 @@ impl PollingReactor {
             }
         }
     }
+
+    /// Forgets an op whose completion was dropped before it finished, so its interest in the fd
+    /// doesn't outlive it
+    pub fn drop_completion<O: OpCode>(&mut self, completion: Pin<&mut Completion<O>>) {
+        let id = completion.id;
+        if self.states.try_remove(id.0).is_none() {
+            return;
+        }
+        let Some(event) = completion.op_code.event() else {
+            return;
+        };
+        let fd = event.key as RawFd;
+        let Some(ids) = self.fds.get_mut(&fd) else {
+            return;
+        };
+        ids.retain(|other| other.0 != id.0);
+        if ids.is_empty() {
+            self.fds.remove(&fd);
+            #[cfg(not(target_os = "windows"))]
+            let source = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+            #[cfg(target_os = "windows")]
+            let source = unsafe { std::os::windows::io::BorrowedSocket::borrow_raw(fd as _) };
+            let _ = self.poller.delete(source);
+        }
+    }
 }
 
 pub trait OpCode {
@@ -205,8 +230,7 @@ impl<O: OpCode> Future for Completion<O> {
 #[pinned_drop]
 impl<O: OpCode> PinnedDrop for Completion<O> {
     fn drop(self: Pin<&mut Self>) {
-        // TODO
-        //Executor::with_reactor(|r| todo!())
+        Executor::with_reactor(|r| r.drop_completion(self))
     }
 }
 
@@ -218,6 +242,11 @@ impl Default for Executor<PollingReactor> {
 
 impl Executor<PollingReactor> {
     pub fn try_new() -> Result<Self, Error> {
+        Self::with_clock(crate::os::os_clock())
+    }
+
+    /// Creates an executor whose timers are driven by `clock`
+    pub fn with_clock(clock: Clock) -> Result<Self, Error> {
         let reactor = PollingReactor {
             poller: Arc::new(Poller::new()?),
             states: Slab::with_capacity(256),
@@ -229,7 +258,7 @@ impl Executor<PollingReactor> {
         Ok(Executor {
             reactor: RefCell::new(reactor),
             scheduler,
-            timer: Timer::new(crate::os::os_clock()),
+            timer: Timer::new(clock),
         })
     }
 }
@@ -288,6 +288,136 @@ impl OpCode for Open {
     }
 }
 
+/// Declares an op that runs a blocking call on the thread pool
+macro_rules! blocking_op {
+    ($name:ident -> $output:ty) => {
+        #[pin_project]
+        pub struct $name(#[pin] blocking::Task<Result<$output, Error>>);
+
+        impl OpCode for $name {
+            type Output = Result<$output, Error>;
+
+            fn poll(
+                self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Self::Output> {
+                self.project().0.poll(cx)
+            }
+        }
+    };
+}
+
+blocking_op!(Fsync -> ());
+blocking_op!(Fallocate -> ());
+blocking_op!(Ftruncate -> ());
+blocking_op!(Statx -> crate::fs::Metadata);
+blocking_op!(Rename -> ());
+blocking_op!(Unlink -> ());
+blocking_op!(MkDir -> ());
+
+/// Borrows `fd` as a `std::fs::File` on the thread pool, without closing it afterwards
+///
+/// The caller must keep `fd` open until the task it's used in completes, like the other
+/// blocking ops.
+fn with_file<T, F>(fd: BorrowedHandle<'_>, f: F) -> Task<Result<T, Error>>
+where
+    F: FnOnce(&std::fs::File) -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(not(target_os = "windows"))]
+    let raw = fd.as_raw_fd();
+    #[cfg(target_os = "windows")]
+    let raw = fd.as_raw_os_handle() as usize;
+    unblock(move || {
+        #[cfg(not(target_os = "windows"))]
+        let file = unsafe { std::fs::File::from_raw_fd(raw) };
+        #[cfg(target_os = "windows")]
+        let file = unsafe {
+            use std::os::windows::io::FromRawHandle;
+            std::fs::File::from_raw_handle(raw as _)
+        };
+        let file = std::mem::ManuallyDrop::new(file);
+        Ok(f(&file)?)
+    })
+}
+
+impl Fsync {
+    /// Flushes the file's data and metadata, or only its data if `datasync` is set
+    pub fn new(fd: BorrowedHandle<'_>, datasync: bool) -> Self {
+        Fsync(with_file(fd, move |file| {
+            if datasync {
+                file.sync_data()
+            } else {
+                file.sync_all()
+            }
+        }))
+    }
+}
+
+impl Fallocate {
+    /// Extends the file to cover `offset + len` bytes, there is no portable way to reserve the
+    /// blocks themselves
+    pub fn new(fd: BorrowedHandle<'_>, offset: u64, len: u64) -> Self {
+        Fallocate(with_file(fd, move |file| {
+            let end = offset.checked_add(len).ok_or(io::ErrorKind::InvalidInput)?;
+            if file.metadata()?.len() < end {
+                file.set_len(end)?;
+            }
+            Ok(())
+        }))
+    }
+}
+
+impl Ftruncate {
+    pub fn new(fd: BorrowedHandle<'_>, len: u64) -> Self {
+        Ftruncate(with_file(fd, move |file| file.set_len(len)))
+    }
+}
+
+impl Statx {
+    /// Reads the metadata of an open file
+    pub fn new(fd: BorrowedHandle<'_>) -> Self {
+        Statx(with_file(fd, |file| file.metadata().map(Into::into)))
+    }
+
+    /// Reads the metadata of the file at `path`, following symlinks
+    pub fn path(path: PathBuf) -> Result<Self, Error> {
+        Ok(Statx(unblock(move || Ok(std::fs::metadata(path)?.into()))))
+    }
+}
+
+impl Rename {
+    pub fn new(from: PathBuf, to: PathBuf) -> Result<Self, Error> {
+        Ok(Rename(unblock(move || Ok(std::fs::rename(from, to)?))))
+    }
+}
+
+impl Unlink {
+    /// Removes the file at `path`, or the empty directory at `path` if `dir` is set
+    pub fn new(path: PathBuf, dir: bool) -> Result<Self, Error> {
+        Ok(Unlink(unblock(move || {
+            if dir {
+                Ok(std::fs::remove_dir(path)?)
+            } else {
+                Ok(std::fs::remove_file(path)?)
+            }
+        })))
+    }
+}
+
+impl MkDir {
+    pub fn new(path: PathBuf, mode: u32) -> Result<Self, Error> {
+        Ok(MkDir(unblock(move || {
+            let mut builder = std::fs::DirBuilder::new();
+            #[cfg(not(target_os = "windows"))]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, mode);
+            #[cfg(target_os = "windows")]
+            let _ = mode;
+            Ok(builder.create(path)?)
+        })))
+    }
+}
+
 #[cfg(not(target_os = "windows"))]
 const DEFAULT_OPEN_FLAGS: i32 = libc::O_CLOEXEC;
 
@@ -0,0 +1,418 @@
+//! Async child processes
+//!
+//! [`Command`] mirrors [`std::process::Command`], but the piped stdio of the spawned [`Child`]
+//! implements [`AsyncRead`] and [`AsyncWrite`], and waiting for the child to exit doesn't block
+//! the executor. On Linux the exit is observed by polling a pidfd, elsewhere the child is checked
+//! on a short timer.
+use std::{
+    ffi::OsStr,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    path::Path,
+    process::{ExitStatus, Output, Stdio},
+    time::Duration,
+};
+
+use crate::{
+    BufResult, Error,
+    buf::{IoBuf, IoBufMut},
+    io::{AsyncRead, AsyncWrite},
+    os::OwnedHandle,
+    reactor::{Completion, ops},
+    signal::SignalKind,
+};
+
+/// How often the exit of a child is checked for when it can't be polled through a pidfd
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A builder for spawning child processes
+pub struct Command {
+    inner: std::process::Command,
+    /// Whether stdin was configured, so [`Command::output`] leaves it alone
+    stdin_set: bool,
+}
+
+impl Command {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            inner: std::process::Command::new(program),
+            stdin_set: false,
+        }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    pub fn env(&mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.env(key, val);
+        self
+    }
+
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.env_remove(key);
+        self
+    }
+
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.inner.env_clear();
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Configures the child's stdin, pass [`Stdio::piped`] to write to it through [`Child::stdin`]
+    pub fn stdin(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stdin(cfg);
+        self.stdin_set = true;
+        self
+    }
+
+    /// Configures the child's stdout, pass [`Stdio::piped`] to read it through [`Child::stdout`]
+    pub fn stdout(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    /// Configures the child's stderr, pass [`Stdio::piped`] to read it through [`Child::stderr`]
+    pub fn stderr(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Spawns the command as a child process, inheriting any stdio that wasn't configured
+    pub fn spawn(&mut self) -> Result<Child, Error> {
+        let mut inner = self.inner.spawn()?;
+        let stdin = inner
+            .stdin
+            .take()
+            .map(|fd| ChildStdin(OwnedHandle::Fd(fd.into())));
+        let stdout = inner
+            .stdout
+            .take()
+            .map(|fd| ChildStdout(OwnedHandle::Fd(fd.into())));
+        let stderr = inner
+            .stderr
+            .take()
+            .map(|fd| ChildStderr(OwnedHandle::Fd(fd.into())));
+        Ok(Child {
+            stdin,
+            stdout,
+            stderr,
+            #[cfg(target_os = "linux")]
+            pidfd: pidfd_open(inner.id()),
+            inner,
+        })
+    }
+
+    /// Runs the command to completion, inheriting its stdio
+    pub async fn status(&mut self) -> Result<ExitStatus, Error> {
+        self.spawn()?.wait().await
+    }
+
+    /// Runs the command to completion, collecting its stdout and stderr
+    ///
+    /// Like [`std::process::Command::output`], stdin is connected to `/dev/null` unless it was
+    /// configured otherwise.
+    pub async fn output(&mut self) -> Result<Output, Error> {
+        if !self.stdin_set {
+            self.inner.stdin(Stdio::null());
+        }
+        self.inner.stdout(Stdio::piped());
+        self.inner.stderr(Stdio::piped());
+        self.spawn()?.wait_with_output().await
+    }
+}
+
+/// A spawned child process
+///
+/// Dropping a `Child` doesn't kill the process, see [`Child::kill`].
+pub struct Child {
+    pub stdin: Option<ChildStdin>,
+    pub stdout: Option<ChildStdout>,
+    pub stderr: Option<ChildStderr>,
+    inner: std::process::Child,
+    #[cfg(target_os = "linux")]
+    pidfd: Option<OwnedFd>,
+}
+
+impl Child {
+    /// The OS-assigned process id of the child
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Returns the exit status of the child if it has exited, without waiting
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>, Error> {
+        Ok(self.inner.try_wait()?)
+    }
+
+    /// Waits for the child to exit
+    ///
+    /// The child's stdin is closed first, so a child that reads until EOF doesn't wait forever.
+    pub async fn wait(&mut self) -> Result<ExitStatus, Error> {
+        drop(self.stdin.take());
+        loop {
+            if let Some(status) = self.inner.try_wait()? {
+                return Ok(status);
+            }
+            self.exited().await?;
+        }
+    }
+
+    /// Waits for the child to exit, collecting whatever is left of its stdout and stderr
+    pub async fn wait_with_output(mut self) -> Result<Output, Error> {
+        drop(self.stdin.take());
+        let stdout = self.stdout.take();
+        let stderr = self.stderr.take();
+        let (stdout, stderr) =
+            futures_lite::future::zip(read_to_end(stdout), read_to_end(stderr)).await;
+        let status = self.wait().await?;
+        Ok(Output {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    }
+
+    /// Sends `SIGKILL` to the child without waiting for it to exit
+    pub fn start_kill(&mut self) -> Result<(), Error> {
+        Ok(self.inner.kill()?)
+    }
+
+    /// Sends `signal` to the child, unless it has already been reaped
+    pub fn signal(&mut self, signal: SignalKind) -> Result<(), Error> {
+        if self.inner.try_wait()?.is_some() {
+            return Ok(());
+        }
+        // safety: kill only reads its arguments, and the pid can't have been reused since the
+        // child hasn't been reaped
+        if unsafe { libc::kill(self.inner.id() as libc::pid_t, signal.as_raw()) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Kills the child and waits for it to exit
+    pub async fn kill(&mut self) -> Result<(), Error> {
+        self.start_kill()?;
+        self.wait().await?;
+        Ok(())
+    }
+
+    /// Resolves once the child might have exited
+    async fn exited(&self) -> Result<(), Error> {
+        #[cfg(target_os = "linux")]
+        if let Some(pidfd) = &self.pidfd {
+            let pidfd = crate::os::BorrowedHandle::Fd(pidfd.as_fd());
+            Completion::run(ops::PollAdd::readable(pidfd)).await?;
+            return Ok(());
+        }
+        crate::sleep(EXIT_POLL_INTERVAL).await;
+        Ok(())
+    }
+}
+
+async fn read_to_end(reader: Option<impl AsyncRead>) -> Result<Vec<u8>, Error> {
+    let mut out = vec![];
+    let Some(reader) = reader else {
+        return Ok(out);
+    };
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let (res, b) = reader.read(buf).await;
+        buf = b;
+        match res? {
+            0 => return Ok(out),
+            n => out.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+/// The write end of a child's stdin pipe
+pub struct ChildStdin(OwnedHandle);
+
+/// The read end of a child's stdout pipe
+pub struct ChildStdout(OwnedHandle);
+
+/// The read end of a child's stderr pipe
+pub struct ChildStderr(OwnedHandle);
+
+impl ChildStdin {
+    pub async fn write<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
+        Completion::run(ops::Write::new(self.0.as_handle(), buf, None)).await
+    }
+}
+
+impl ChildStdout {
+    pub async fn read<B: IoBufMut>(&self, buf: B) -> BufResult<usize, B> {
+        Completion::run(ops::Read::new(self.0.as_handle(), buf, None)).await
+    }
+}
+
+impl ChildStderr {
+    pub async fn read<B: IoBufMut>(&self, buf: B) -> BufResult<usize, B> {
+        Completion::run(ops::Read::new(self.0.as_handle(), buf, None)).await
+    }
+}
+
+impl AsyncWrite for ChildStdin {
+    fn write<B: IoBuf>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
+        self.write(buf)
+    }
+}
+
+impl AsyncRead for ChildStdout {
+    fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
+        self.read(buf)
+    }
+}
+
+impl AsyncRead for ChildStderr {
+    fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
+        self.read(buf)
+    }
+}
+
+impl AsFd for ChildStdin {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsFd for ChildStdout {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsFd for ChildStderr {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for ChildStdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+impl AsRawFd for ChildStdout {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+impl AsRawFd for ChildStderr {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+/// Opens a pidfd for `pid`, which becomes readable once the process exits
+///
+/// Returns `None` on kernels older than 5.3, where the child is polled on a timer instead.
+#[cfg(target_os = "linux")]
+fn pidfd_open(pid: u32) -> Option<OwnedFd> {
+    use std::os::fd::FromRawFd;
+    // safety: pidfd_open only reads its arguments
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    // safety: the kernel just handed us this fd, so nothing else owns it
+    (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd as _) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::*;
+    use crate::test;
+
+    #[test]
+    async fn test_cat() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        stdin.write_all(&b"hello"[..]).await.0.unwrap();
+        let (res, buf) = stdout.read_exact(vec![0u8; 5]).await;
+        res.unwrap();
+        assert_eq!(&buf[..], b"hello");
+        drop(stdin);
+        assert_eq!(stdout.read(vec![0u8; 5]).await.0.unwrap(), 0);
+        assert!(child.wait().await.unwrap().success());
+    }
+
+    #[test]
+    async fn test_sh_output() {
+        let output = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2; exit 3"])
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    async fn test_output_stdin() {
+        let script = "if [ -p /dev/stdin ]; then echo pipe; else echo other; fi";
+        let output = Command::new("sh")
+            .args(["-c", script])
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, b"other\n");
+        let output = Command::new("sh")
+            .args(["-c", script])
+            .stdin(Stdio::piped())
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, b"pipe\n");
+    }
+
+    #[test]
+    async fn test_signal() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        child.signal(SignalKind::terminate()).unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+
+    #[test]
+    async fn test_kill() {
+        let mut child = Command::new("sh").args(["-c", "sleep 10"]).spawn().unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().await.unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+}
@@ -0,0 +1,481 @@
+//! A thread-per-core runtime
+//!
+//! [`Runtime`] starts a pool of worker threads, each running its own executor and reactor. Tasks
+//! never migrate between workers: [`Runtime::spawn_on`] hands a future to a specific worker, and
+//! [`Runtime::accept`] spreads incoming tcp connections across the least loaded workers.
+//!
+//! Handing work to a worker wakes its executor through the reactor's external waker, so an idle
+//! worker stays parked in the kernel until there is something for it to do.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use maitake::sync::WaitQueue;
+
+use crate::{
+    Error,
+    net::{TcpListener, TcpStream},
+    util::{OneshotRx, OneshotTx, oneshot},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Configures and starts a [`Runtime`]
+pub struct Builder {
+    workers: Option<usize>,
+    pin_threads: bool,
+    thread_name: String,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            workers: None,
+            pin_threads: false,
+            thread_name: "stellarator-worker".to_string(),
+        }
+    }
+}
+
+impl Builder {
+    /// Sets the number of worker threads, defaults to the available parallelism
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Pins each worker to its own cpu, only supported on Linux. Disabled by default, as pinning
+    /// only pays off when the runtime has the machine to itself
+    pub fn pin_threads(mut self, pin_threads: bool) -> Self {
+        self.pin_threads = pin_threads;
+        self
+    }
+
+    /// Sets the name prefix of the worker threads, the worker's index is appended to it
+    pub fn thread_name(mut self, name: impl Into<String>) -> Self {
+        self.thread_name = name.into();
+        self
+    }
+
+    pub fn build(self) -> Result<Runtime, Error> {
+        let workers = match self.workers {
+            Some(workers) => workers,
+            None => std::thread::available_parallelism()?.get(),
+        };
+        if workers == 0 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a runtime needs at least one worker",
+            )));
+        }
+        let cpus = if self.pin_threads {
+            os::allowed_cpus()?
+        } else {
+            vec![]
+        };
+
+        let mut runtime = Runtime {
+            workers: vec![],
+            threads: vec![],
+            exited: vec![],
+            next: AtomicUsize::new(0),
+        };
+        for index in 0..workers {
+            let worker = Arc::new(Worker::new());
+            let cpu = (!cpus.is_empty()).then(|| cpus[index % cpus.len()]);
+            let (tx, rx) = std::sync::mpsc::channel();
+            let (exit_tx, exit_rx) = oneshot();
+            let thread_worker = worker.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("{}-{}", self.thread_name, index))
+                .spawn(move || {
+                    let _exited = Exited(Some(exit_tx));
+                    if let Some(cpu) = cpu {
+                        if let Err(err) = os::pin_to_cpu(cpu) {
+                            let _ = tx.send(Err(err));
+                            return;
+                        }
+                    }
+                    let _ = tx.send(Ok(()));
+                    crate::run(|| thread_worker.run());
+                })?;
+            runtime.workers.push(worker);
+            runtime.threads.push(thread);
+            runtime.exited.push(exit_rx);
+            // dropping the partially started runtime stops the workers that did start
+            rx.recv().map_err(|_| Error::JoinFailed)??;
+        }
+        Ok(runtime)
+    }
+}
+
+/// A pool of executor threads
+///
+/// Dropping the runtime stops every worker, cancelling the tasks still running on them, and
+/// blocks until the worker threads exit. Async code should use [`Runtime::shutdown`] instead.
+pub struct Runtime {
+    workers: Vec<Arc<Worker>>,
+    threads: Vec<std::thread::JoinHandle<()>>,
+    exited: Vec<OneshotRx<()>>,
+    next: AtomicUsize,
+}
+
+impl Runtime {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Starts a runtime with the default configuration
+    pub fn new() -> Result<Self, Error> {
+        Builder::default().build()
+    }
+
+    /// Stops every worker, waiting for the worker threads to exit without blocking the calling
+    /// thread
+    pub async fn shutdown(mut self) {
+        self.stop();
+        for exited in self.exited.drain(..) {
+            exited.wait().await;
+        }
+    }
+
+    fn stop(&self) {
+        for worker in &self.workers {
+            worker.wait.close();
+        }
+    }
+
+    /// The number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// The number of tasks handed to the worker at `index` that haven't finished yet
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds
+    pub fn load(&self, index: usize) -> usize {
+        self.workers[index].load.load(Ordering::Acquire)
+    }
+
+    /// Spawns `future` on the worker at `index`
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds
+    pub fn spawn_on<F>(&self, index: usize, future: F) -> RemoteJoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawn_with(index, move || future)
+    }
+
+    /// Accepts connections from `listener` forever, handing each one to the least loaded worker
+    ///
+    /// `handler` is called on the worker the connection was handed to, so the future it returns
+    /// doesn't need to be `Send`.
+    pub async fn accept<H, Fut>(&self, listener: &TcpListener, handler: H) -> Result<(), Error>
+    where
+        H: Fn(TcpStream) -> Fut + Send + Sync + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        loop {
+            let stream = listener.accept().await?;
+            let handler = handler.clone();
+            self.spawn_with(self.least_loaded(), move || handler(stream));
+        }
+    }
+
+    /// Picks the worker with the fewest running tasks, rotating through workers with equal load
+    fn least_loaded(&self) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.workers.len())
+            .map(|offset| (start + offset) % self.workers.len())
+            .min_by_key(|&index| self.load(index))
+            .expect("runtime has no workers")
+    }
+
+    fn spawn_with<T, Fut>(
+        &self,
+        index: usize,
+        func: impl FnOnce() -> Fut + Send + 'static,
+    ) -> RemoteJoinHandle<T>
+    where
+        Fut: Future<Output = T> + 'static,
+        T: Send + 'static,
+    {
+        let worker = &self.workers[index];
+        let slot = Arc::new(Slot {
+            value: Mutex::new(None),
+            done: AtomicBool::new(false),
+            wait: WaitQueue::new(),
+        });
+        worker.load.fetch_add(1, Ordering::AcqRel);
+        let completer = Completer {
+            slot: slot.clone(),
+            load: worker.load.clone(),
+        };
+        worker.push(Box::new(move || {
+            crate::spawn(async move {
+                completer.finish(func().await);
+            });
+        }));
+        RemoteJoinHandle(slot)
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.stop();
+        // after `shutdown` the threads are only left to exit, so this doesn't block for long
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        for worker in &self.workers {
+            worker.jobs.lock().unwrap().clear();
+        }
+    }
+}
+
+struct Worker {
+    jobs: Mutex<VecDeque<Job>>,
+    wait: WaitQueue,
+    load: Arc<AtomicUsize>,
+}
+
+impl Worker {
+    fn new() -> Self {
+        Self {
+            jobs: Mutex::default(),
+            wait: WaitQueue::new(),
+            load: Arc::default(),
+        }
+    }
+
+    fn push(&self, job: Job) {
+        self.jobs.lock().unwrap().push_back(job);
+        self.wait.wake();
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            let res = self
+                .wait
+                .wait_for(|| !self.jobs.lock().unwrap().is_empty())
+                .await;
+            if res.is_err() {
+                return;
+            }
+            let jobs = std::mem::take(&mut *self.jobs.lock().unwrap());
+            for job in jobs {
+                job();
+            }
+        }
+    }
+}
+
+/// Tells [`Runtime::shutdown`] a worker thread is done with its executor, even if it panicked
+struct Exited(Option<OneshotTx<()>>);
+
+impl Drop for Exited {
+    fn drop(&mut self) {
+        if let Some(tx) = self.0.take() {
+            tx.send(());
+        }
+    }
+}
+
+struct Slot<T> {
+    value: Mutex<Option<T>>,
+    done: AtomicBool,
+    wait: WaitQueue,
+}
+
+/// Marks a task as finished when dropped, whether it ran to completion or was cancelled
+struct Completer<T> {
+    slot: Arc<Slot<T>>,
+    load: Arc<AtomicUsize>,
+}
+
+impl<T> Completer<T> {
+    fn finish(self, value: T) {
+        *self.slot.value.lock().unwrap() = Some(value);
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.load.fetch_sub(1, Ordering::AcqRel);
+        self.slot.done.store(true, Ordering::Release);
+        self.slot.wait.wake_all();
+    }
+}
+
+/// A handle to a task running on another worker, see [`Runtime::spawn_on`]
+pub struct RemoteJoinHandle<T>(Arc<Slot<T>>);
+
+impl<T> RemoteJoinHandle<T> {
+    /// Waits for the task to finish, returning [`Error::JoinFailed`] if it was cancelled
+    pub async fn join(self) -> Result<T, Error> {
+        let _ = self
+            .0
+            .wait
+            .wait_for(|| self.0.done.load(Ordering::Acquire))
+            .await;
+        self.0.value.lock().unwrap().take().ok_or(Error::JoinFailed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.done.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use rustix::process::{CpuSet, sched_getaffinity, sched_setaffinity};
+
+    use crate::Error;
+
+    /// The cpus the current thread is allowed to run on
+    pub fn allowed_cpus() -> Result<Vec<usize>, Error> {
+        let set = sched_getaffinity(None)?;
+        Ok((0..CpuSet::MAX_CPU)
+            .filter(|&cpu| set.is_set(cpu))
+            .collect())
+    }
+
+    pub fn pin_to_cpu(cpu: usize) -> Result<(), Error> {
+        let mut set = CpuSet::new();
+        set.set(cpu);
+        sched_setaffinity(None, &set)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod os {
+    use crate::Error;
+
+    pub fn allowed_cpus() -> Result<Vec<usize>, Error> {
+        Ok(vec![])
+    }
+
+    pub fn pin_to_cpu(_cpu: usize) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::{
+        io::{AsyncRead, AsyncWrite},
+        test,
+    };
+
+    fn thread_name() -> String {
+        std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    async fn test_spawn_on() {
+        let rt = Runtime::builder()
+            .workers(2)
+            .pin_threads(false)
+            .thread_name("test-rt")
+            .build()
+            .unwrap();
+        let a = rt.spawn_on(0, async { thread_name() });
+        let b = rt.spawn_on(1, async {
+            crate::yield_now().await;
+            thread_name()
+        });
+        assert_eq!(a.join().await.unwrap(), "test-rt-0");
+        assert_eq!(b.join().await.unwrap(), "test-rt-1");
+        assert_eq!(rt.load(0), 0);
+        assert_eq!(rt.load(1), 0);
+    }
+
+    #[test]
+    async fn test_join_cancelled() {
+        let rt = Runtime::builder()
+            .workers(1)
+            .pin_threads(false)
+            .build()
+            .unwrap();
+        let handle = rt.spawn_on(0, std::future::pending::<()>());
+        drop(rt);
+        assert!(handle.is_finished());
+        assert!(handle.join().await.is_err());
+    }
+
+    #[test]
+    async fn test_shutdown() {
+        let rt = Runtime::builder().workers(2).build().unwrap();
+        let pending = rt.spawn_on(1, std::future::pending::<()>());
+        let done = rt.spawn_on(0, async { 1 });
+        assert_eq!(done.join().await.unwrap(), 1);
+        rt.shutdown().await;
+        assert!(pending.is_finished());
+        assert!(pending.join().await.is_err());
+    }
+
+    #[test]
+    async fn test_accept_balances_connections() {
+        let rt = Arc::new(
+            Runtime::builder()
+                .workers(2)
+                .pin_threads(false)
+                .thread_name("accept-rt")
+                .build()
+                .unwrap(),
+        );
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor_rt = rt.clone();
+        crate::spawn(async move {
+            acceptor_rt
+                .accept(&listener, |stream| async move {
+                    stream
+                        .write_all(thread_name().into_bytes())
+                        .await
+                        .0
+                        .unwrap();
+                    // hold the connection until the client hangs up
+                    let _ = stream.read(vec![0u8; 1]).await;
+                })
+                .await
+                .unwrap();
+        });
+
+        let mut clients = vec![];
+        let mut names = vec![];
+        for _ in 0..4 {
+            let client = TcpStream::connect(addr).await.unwrap();
+            let (res, name) = client.read_exact(vec![0u8; "accept-rt-0".len()]).await;
+            res.unwrap();
+            names.push(String::from_utf8(name).unwrap());
+            clients.push(client);
+        }
+        names.sort();
+        assert_eq!(
+            names,
+            ["accept-rt-0", "accept-rt-0", "accept-rt-1", "accept-rt-1"]
+        );
+        assert_eq!(rt.load(0) + rt.load(1), 4);
+    }
+}
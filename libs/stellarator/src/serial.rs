@@ -1,5 +1,5 @@
 //! Async serial ports
-use std::{io, path::Path};
+use std::{io, os::fd::AsRawFd, path::Path, time::Duration};
 
 use crate::{
     BufResult, Error,
@@ -7,11 +7,12 @@ use crate::{
     fs::{File, OpenOptions},
     io::{AsyncRead, AsyncWrite},
 };
-use rustix::termios::{self, Termios};
+use rustix::termios::{self, ControlModes, InputModes, SpecialCodeIndex, Termios};
 
 /// Serial Port Baud Rate
 ///
 /// This enum contains a set of serial port baud rates, specified in bits per second
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Baud {
     /// 921600 Baud
     #[cfg(target_os = "linux")]
@@ -24,6 +25,9 @@ pub enum Baud {
     /// 9600 Baud
     B9600,
     /// A custom baud rate
+    ///
+    /// On Linux any rate the driver can generate is accepted, as the speed is set through
+    /// termios2 with `BOTHER`. Other platforms only accept the standard rates.
     Other(u64),
 }
 
@@ -56,6 +60,217 @@ impl Baud {
     }
 }
 
+impl From<u32> for Baud {
+    fn from(baud: u32) -> Self {
+        Baud::Other(baud as u64)
+    }
+}
+
+/// The number of data bits in each character
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    #[default]
+    Eight,
+}
+
+/// The parity bit appended to each character
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+/// The number of stop bits after each character
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StopBits {
+    #[default]
+    One,
+    Two,
+}
+
+/// How the two ends of the line pace each other
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlowControl {
+    #[default]
+    None,
+    /// XON/XOFF characters sent in band
+    Software,
+    /// The RTS and CTS lines
+    Hardware,
+}
+
+/// RS-485 half duplex settings, where the driver toggles RTS to switch the transceiver between
+/// sending and receiving
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rs485Config {
+    /// The level of RTS while sending, the opposite level is used while receiving
+    pub rts_on_send: bool,
+    /// How long RTS is asserted before the first byte is sent
+    pub delay_before_send: Duration,
+    /// How long RTS stays asserted after the last byte is sent
+    pub delay_after_send: Duration,
+    /// Keeps the receiver enabled while sending, so the port hears its own writes
+    pub rx_during_tx: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Default for Rs485Config {
+    fn default() -> Self {
+        Self {
+            rts_on_send: true,
+            delay_before_send: Duration::ZERO,
+            delay_after_send: Duration::ZERO,
+            rx_during_tx: false,
+        }
+    }
+}
+
+/// The line settings for a [`SerialPort`]
+///
+/// Defaults to 115200 baud, 8N1, without flow control, with reads returning as soon as a single
+/// byte is available.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    baud: Baud,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    read_min: u8,
+    read_timeout: Duration,
+    #[cfg(target_os = "linux")]
+    rs485: Option<Rs485Config>,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self::new(Baud::B115200)
+    }
+}
+
+impl SerialConfig {
+    pub fn new(baud: impl Into<Baud>) -> Self {
+        Self {
+            baud: baud.into(),
+            data_bits: DataBits::default(),
+            parity: Parity::default(),
+            stop_bits: StopBits::default(),
+            flow_control: FlowControl::default(),
+            read_min: 1,
+            read_timeout: Duration::ZERO,
+            #[cfg(target_os = "linux")]
+            rs485: None,
+        }
+    }
+
+    pub fn baud(mut self, baud: impl Into<Baud>) -> Self {
+        self.baud = baud.into();
+        self
+    }
+
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Sets the number of bytes a read waits for (`VMIN`)
+    pub fn read_min(mut self, read_min: u8) -> Self {
+        self.read_min = read_min;
+        self
+    }
+
+    /// Sets how long a read waits between bytes before returning what it has (`VTIME`)
+    ///
+    /// The timeout is rounded down to tenths of a second, and saturates at 25.5 seconds. A zero
+    /// timeout waits for [`SerialConfig::read_min`] bytes indefinitely.
+    pub fn read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Enables RS-485 direction control, which needs a driver that supports `TIOCSRS485`
+    ///
+    /// Configs without it turn RS-485 mode off on ports that support it.
+    #[cfg(target_os = "linux")]
+    pub fn rs485(mut self, rs485: Rs485Config) -> Self {
+        self.rs485 = Some(rs485);
+        self
+    }
+
+    fn apply(&self, termios: &mut Termios) -> io::Result<()> {
+        termios.make_raw();
+        termios.control_modes |= ControlModes::CLOCAL | ControlModes::CREAD;
+
+        termios.control_modes.remove(ControlModes::CSIZE);
+        termios.control_modes.insert(match self.data_bits {
+            DataBits::Five => ControlModes::CS5,
+            DataBits::Six => ControlModes::CS6,
+            DataBits::Seven => ControlModes::CS7,
+            DataBits::Eight => ControlModes::CS8,
+        });
+
+        termios
+            .control_modes
+            .remove(ControlModes::PARENB | ControlModes::PARODD);
+        match self.parity {
+            Parity::None => termios.input_modes.remove(InputModes::INPCK),
+            Parity::Odd => {
+                termios
+                    .control_modes
+                    .insert(ControlModes::PARENB | ControlModes::PARODD);
+                termios.input_modes.insert(InputModes::INPCK);
+            }
+            Parity::Even => {
+                termios.control_modes.insert(ControlModes::PARENB);
+                termios.input_modes.insert(InputModes::INPCK);
+            }
+        }
+        // characters with parity errors are dropped rather than passed through
+        termios.input_modes.insert(InputModes::IGNPAR);
+
+        termios
+            .control_modes
+            .set(ControlModes::CSTOPB, self.stop_bits == StopBits::Two);
+
+        termios.control_modes.set(
+            ControlModes::CRTSCTS,
+            self.flow_control == FlowControl::Hardware,
+        );
+        termios.input_modes.set(
+            InputModes::IXON | InputModes::IXOFF,
+            self.flow_control == FlowControl::Software,
+        );
+
+        let deciseconds = (self.read_timeout.as_millis() / 100).min(u8::MAX as u128) as u8;
+        termios.special_codes[SpecialCodeIndex::VMIN] = self.read_min;
+        termios.special_codes[SpecialCodeIndex::VTIME] = deciseconds;
+
+        termios.set_speed(self.baud.termios_baud())?;
+        Ok(())
+    }
+}
+
 /// A handle to an opened serial port
 pub struct SerialPort {
     termios: Termios,
@@ -63,44 +278,44 @@ pub struct SerialPort {
 }
 
 impl SerialPort {
-    /// Open a new serial port located at `path`
+    /// Open a new serial port located at `path`, with the default [`SerialConfig`] but leaving the
+    /// baud rate untouched
     pub async fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let mut options = OpenOptions::default();
-        options.write(true).read(true).custom_flags(libc::O_NOCTTY);
-
-        let file = File::open_with(path, &options).await?;
+        let file = Self::open_file(path).await?;
+        let mut termios = termios::tcgetattr(&file)?;
+        let baud = Baud::Other(termios.output_speed() as u64);
+        SerialConfig::new(baud).apply(&mut termios)?;
+        termios::tcsetattr(&file, termios::OptionalActions::Now, &termios)?;
+        Ok(SerialPort { termios, file })
+    }
 
-        let termios = {
-            let mut termios = termios::tcgetattr(&file)?;
-            if cfg!(target_os = "linux") {
-                termios.control_modes |=
-                    termios::ControlModes::CLOCAL | termios::ControlModes::CREAD;
-            }
+    /// Open a new serial port located at `path`, configured with `config`
+    pub async fn open_with(path: impl AsRef<Path>, config: &SerialConfig) -> Result<Self, Error> {
+        let file = Self::open_file(path).await?;
+        let termios = termios::tcgetattr(&file)?;
+        let mut port = SerialPort { termios, file };
+        port.configure(config)?;
+        Ok(port)
+    }
 
-            // Make raw
-            termios.make_raw();
-
-            // Configure serial port settings using rustix::termios structs
-            termios.control_modes.remove(termios::ControlModes::CSTOPB);
-            termios.control_modes.remove(termios::ControlModes::CRTSCTS);
-            termios.local_modes.remove(termios::LocalModes::ICANON);
-            termios
-                .input_modes
-                .remove(termios::InputModes::IXON | termios::InputModes::IXOFF);
-            termios
-                .control_modes
-                .remove(termios::ControlModes::PARENB | termios::ControlModes::PARODD);
-            termios.input_modes.remove(termios::InputModes::INPCK);
-            termios.input_modes.insert(termios::InputModes::IGNPAR);
-            termios.control_modes.remove(termios::ControlModes::CSIZE);
-            termios.control_modes.insert(termios::ControlModes::CS8);
-
-            termios::tcsetattr(&file, termios::OptionalActions::Now, &termios)?;
-
-            termios
-        };
+    async fn open_file(path: impl AsRef<Path>) -> Result<File, Error> {
+        let mut options = OpenOptions::default();
+        options.write(true).read(true).custom_flags(libc::O_NOCTTY);
+        File::open_with(path, &options).await
+    }
 
-        Ok(SerialPort { termios, file })
+    /// Applies `config` to the port
+    pub fn configure(&mut self, config: &SerialConfig) -> Result<(), Error> {
+        let mut termios = self.termios.clone();
+        config.apply(&mut termios)?;
+        termios::tcsetattr(&self.file, termios::OptionalActions::Now, &termios)?;
+        self.termios = termios;
+        #[cfg(target_os = "linux")]
+        match &config.rs485 {
+            Some(rs485) => self.set_rs485(rs485)?,
+            None => self.clear_rs485()?,
+        }
+        Ok(())
     }
 
     /// Set the baud rate for the serial port
@@ -113,6 +328,71 @@ impl SerialPort {
         Ok(())
     }
 
+    /// The port's current terminal settings
+    pub fn termios(&self) -> &Termios {
+        &self.termios
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_rs485(&self, config: &Rs485Config) -> io::Result<()> {
+        let mut flags = SER_RS485_ENABLED;
+        flags |= if config.rts_on_send {
+            SER_RS485_RTS_ON_SEND
+        } else {
+            SER_RS485_RTS_AFTER_SEND
+        };
+        if config.rx_during_tx {
+            flags |= SER_RS485_RX_DURING_TX;
+        }
+        let mut rs485 = SerialRs485 {
+            flags,
+            delay_rts_before_send: config.delay_before_send.as_millis() as u32,
+            delay_rts_after_send: config.delay_after_send.as_millis() as u32,
+            padding: [0; 5],
+        };
+        self.rs485_ioctl(libc::TIOCSRS485, &mut rs485)
+    }
+
+    /// Turns RS-485 mode off if it's on, leaving ports whose driver doesn't support RS-485 alone
+    #[cfg(target_os = "linux")]
+    fn clear_rs485(&self) -> io::Result<()> {
+        let mut rs485 = SerialRs485::default();
+        match self.rs485_ioctl(libc::TIOCGRS485, &mut rs485) {
+            Ok(()) => {}
+            Err(err) if err.raw_os_error() == Some(libc::ENOTTY) => return Ok(()),
+            Err(err) => return Err(err),
+        }
+        if rs485.flags & SER_RS485_ENABLED == 0 {
+            return Ok(());
+        }
+        rs485.flags &= !SER_RS485_ENABLED;
+        self.rs485_ioctl(libc::TIOCSRS485, &mut rs485)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn rs485_ioctl(&self, request: libc::Ioctl, rs485: &mut SerialRs485) -> io::Result<()> {
+        // safety: rs485 matches the kernel's `struct serial_rs485`, and outlives the call
+        let res = unsafe { libc::ioctl(self.file.as_raw_fd(), request, rs485 as *mut SerialRs485) };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Holds the line in the break condition for `duration`
+    pub async fn send_break(&self, duration: Duration) -> Result<(), Error> {
+        // safety: TIOCSBRK takes no argument
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCSBRK) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        crate::sleep(duration).await;
+        // safety: TIOCCBRK takes no argument
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCCBRK) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
     /// Write data to the serial port
     pub async fn write<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
         self.file.write(buf).await
@@ -124,6 +404,26 @@ impl SerialPort {
     }
 }
 
+/// `struct serial_rs485` from `linux/serial.h`
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+#[repr(C)]
+struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+#[cfg(target_os = "linux")]
+const SER_RS485_ENABLED: u32 = 1 << 0;
+#[cfg(target_os = "linux")]
+const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+#[cfg(target_os = "linux")]
+const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+#[cfg(target_os = "linux")]
+const SER_RS485_RX_DURING_TX: u32 = 1 << 4;
+
 impl AsyncRead for SerialPort {
     fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
         self.read(buf)
@@ -141,3 +441,123 @@ impl std::os::fd::AsRawFd for SerialPort {
         self.file.as_raw_fd()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::CStr,
+        io::{Read, Write},
+        os::fd::{FromRawFd, OwnedFd},
+        path::PathBuf,
+    };
+
+    use super::*;
+    use crate::test;
+
+    /// Opens a pseudo-terminal pair, returning the controlling side, the terminal side and the
+    /// terminal's path
+    fn openpty() -> (std::fs::File, OwnedFd, PathBuf) {
+        let (mut controller, mut terminal) = (0, 0);
+        let mut name = [0 as libc::c_char; 1024];
+        // safety: both fds and the name buffer are valid for writes, and the rest are null
+        let res = unsafe {
+            libc::openpty(
+                &mut controller,
+                &mut terminal,
+                name.as_mut_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(res, 0, "openpty failed: {}", io::Error::last_os_error());
+        // safety: openpty just handed us both fds
+        let (controller, terminal) = unsafe {
+            (
+                OwnedFd::from_raw_fd(controller),
+                OwnedFd::from_raw_fd(terminal),
+            )
+        };
+        // safety: openpty wrote a nul terminated path into name
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) };
+        (controller.into(), terminal, path.to_str().unwrap().into())
+    }
+
+    #[test]
+    async fn test_configure() {
+        let (_controller, _terminal, path) = openpty();
+        let config = SerialConfig::new(Baud::B9600)
+            .data_bits(DataBits::Seven)
+            .parity(Parity::Even)
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::Hardware)
+            .read_min(0)
+            .read_timeout(Duration::from_millis(500));
+        let port = SerialPort::open_with(&path, &config).await.unwrap();
+
+        // ptys always report 8 data bits without parity, so those are covered by `test_apply`
+        let termios = termios::tcgetattr(&port.file).unwrap();
+        let control = termios.control_modes;
+        assert!(control.contains(ControlModes::CSTOPB | ControlModes::CRTSCTS));
+        assert_eq!(termios.special_codes[SpecialCodeIndex::VMIN], 0);
+        assert_eq!(termios.special_codes[SpecialCodeIndex::VTIME], 5);
+        assert_eq!(termios.output_speed(), 9600);
+    }
+
+    #[test]
+    async fn test_apply() {
+        let (_controller, terminal, _path) = openpty();
+        let mut termios = termios::tcgetattr(&terminal).unwrap();
+        let control = |termios: &Termios| termios.control_modes;
+
+        SerialConfig::default()
+            .parity(Parity::Odd)
+            .apply(&mut termios)
+            .unwrap();
+        assert!(control(&termios).contains(ControlModes::PARENB | ControlModes::PARODD));
+
+        SerialConfig::default()
+            .data_bits(DataBits::Seven)
+            .parity(Parity::Even)
+            .apply(&mut termios)
+            .unwrap();
+        assert_eq!(control(&termios) & ControlModes::CSIZE, ControlModes::CS7);
+        assert!(control(&termios).contains(ControlModes::PARENB));
+        assert!(!control(&termios).contains(ControlModes::PARODD));
+        assert!(termios.input_modes.contains(InputModes::INPCK));
+
+        SerialConfig::default().apply(&mut termios).unwrap();
+        assert_eq!(control(&termios) & ControlModes::CSIZE, ControlModes::CS8);
+        assert!(!control(&termios).contains(ControlModes::PARENB));
+        assert!(!termios.input_modes.contains(InputModes::INPCK));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    async fn test_custom_baud() {
+        let (_controller, _terminal, path) = openpty();
+        let port = SerialPort::open_with(&path, &SerialConfig::new(250_000u32))
+            .await
+            .unwrap();
+        let termios = termios::tcgetattr(&port.file).unwrap();
+        assert_eq!(termios.output_speed(), 250_000);
+    }
+
+    #[test]
+    async fn test_read_write() {
+        let (mut controller, _terminal, path) = openpty();
+        let port = SerialPort::open_with(&path, &SerialConfig::default())
+            .await
+            .unwrap();
+
+        port.write(b"ping".to_vec()).await.0.unwrap();
+        let mut buf = [0u8; 4];
+        controller.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        controller.write_all(b"pong").unwrap();
+        let (res, buf) = port.read(vec![0u8; 4]).await;
+        assert_eq!(&buf[..res.unwrap()], b"pong");
+
+        port.send_break(Duration::from_millis(10)).await.unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+//! Async unix signals
+//!
+//! On Linux, [`Signals`] reads from a signalfd, so signals arrive as ordinary io instead of
+//! interrupting whichever thread they land on. The signals are blocked on the calling thread, and
+//! new threads inherit the signal mask of the thread that spawns them, so a [`Signals`] stream
+//! should be created before any other threads are started, typically at the top of `main`. A
+//! signal that is delivered to a thread that doesn't block it still runs its default action.
+//!
+//! Elsewhere, a signal handler writes the signal number to a pipe that the stream reads from.
+
+use crate::Error;
+
+/// A unix signal number
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SignalKind(i32);
+
+impl SignalKind {
+    pub const fn from_raw(signum: i32) -> Self {
+        Self(signum)
+    }
+
+    pub const fn as_raw(self) -> i32 {
+        self.0
+    }
+
+    /// `SIGINT`, sent by Ctrl-C
+    pub const fn interrupt() -> Self {
+        Self(libc::SIGINT)
+    }
+
+    /// `SIGTERM`, the polite way to ask a process to exit
+    pub const fn terminate() -> Self {
+        Self(libc::SIGTERM)
+    }
+
+    /// `SIGHUP`, sent when the controlling terminal goes away
+    pub const fn hangup() -> Self {
+        Self(libc::SIGHUP)
+    }
+
+    /// `SIGQUIT`, sent by Ctrl-\
+    pub const fn quit() -> Self {
+        Self(libc::SIGQUIT)
+    }
+
+    /// `SIGUSR1`
+    pub const fn user_defined1() -> Self {
+        Self(libc::SIGUSR1)
+    }
+
+    /// `SIGUSR2`
+    pub const fn user_defined2() -> Self {
+        Self(libc::SIGUSR2)
+    }
+
+    /// `SIGCHLD`, sent when a child process exits
+    pub const fn child() -> Self {
+        Self(libc::SIGCHLD)
+    }
+}
+
+/// A stream of incoming signals
+pub struct Signals {
+    inner: os::Signals,
+}
+
+impl Signals {
+    /// Starts receiving `kinds`, replacing their default actions
+    pub fn new(kinds: impl IntoIterator<Item = SignalKind>) -> Result<Self, Error> {
+        let kinds = kinds.into_iter().collect::<Vec<_>>();
+        Ok(Self {
+            inner: os::Signals::new(&kinds)?,
+        })
+    }
+
+    /// Receives `SIGINT` and `SIGTERM`, the signals a process is asked to shut down with
+    pub fn shutdown() -> Result<Self, Error> {
+        Self::new([SignalKind::interrupt(), SignalKind::terminate()])
+    }
+
+    /// Waits for the next signal
+    pub async fn recv(&self) -> Result<SignalKind, Error> {
+        self.inner.recv().await
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::{
+        io,
+        mem::MaybeUninit,
+        os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    };
+
+    use super::SignalKind;
+    use crate::{
+        Error,
+        os::BorrowedHandle,
+        reactor::{Completion, ops::PollAdd},
+    };
+
+    pub struct Signals {
+        fd: OwnedFd,
+    }
+
+    impl Signals {
+        pub fn new(kinds: &[SignalKind]) -> Result<Self, Error> {
+            let mut set = MaybeUninit::<libc::sigset_t>::uninit();
+            // safety: sigemptyset initializes the set, and every pointer passed is valid
+            let fd = unsafe {
+                libc::sigemptyset(set.as_mut_ptr());
+                for kind in kinds {
+                    if libc::sigaddset(set.as_mut_ptr(), kind.as_raw()) != 0 {
+                        return Err(io::Error::last_os_error().into());
+                    }
+                }
+                let res =
+                    libc::pthread_sigmask(libc::SIG_BLOCK, set.as_ptr(), std::ptr::null_mut());
+                if res != 0 {
+                    return Err(io::Error::from_raw_os_error(res).into());
+                }
+                libc::signalfd(-1, set.as_ptr(), libc::SFD_CLOEXEC | libc::SFD_NONBLOCK)
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            // safety: the kernel just handed us this fd, so nothing else owns it
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            Ok(Self { fd })
+        }
+
+        /// The fd is read directly rather than through io_uring, because signalfd dequeues the
+        /// signals pending for the thread that reads it, and io_uring may read from a worker thread
+        pub async fn recv(&self) -> Result<SignalKind, Error> {
+            loop {
+                let mut info = MaybeUninit::<libc::signalfd_siginfo>::uninit();
+                let len = size_of::<libc::signalfd_siginfo>();
+                // safety: info is valid for writes of len bytes
+                let res = unsafe { libc::read(self.fd.as_raw_fd(), info.as_mut_ptr().cast(), len) };
+                if res == len as isize {
+                    // safety: the kernel filled in the whole struct
+                    let info = unsafe { info.assume_init() };
+                    return Ok(SignalKind::from_raw(info.ssi_signo as i32));
+                }
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::WouldBlock => {
+                        let fd = BorrowedHandle::Fd(self.fd.as_fd());
+                        Completion::run(PollAdd::readable(fd)).await?;
+                    }
+                    io::ErrorKind::Interrupted => {}
+                    _ => return Err(err.into()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod os {
+    use std::{
+        io,
+        os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    use super::SignalKind;
+    use crate::{
+        Error,
+        os::BorrowedHandle,
+        reactor::{Completion, ops::Read},
+    };
+
+    /// The write end of the pipe each signal number is forwarded to, or -1
+    static PIPES: [AtomicI32; 32] = [const { AtomicI32::new(-1) }; 32];
+
+    extern "C" fn handler(signum: libc::c_int) {
+        let Some(fd) = PIPES.get(signum as usize) else {
+            return;
+        };
+        let fd = fd.load(Ordering::Relaxed);
+        if fd >= 0 {
+            let byte = signum as u8;
+            // safety: write is async-signal-safe, and a full pipe just drops the signal
+            unsafe { libc::write(fd, (&byte as *const u8).cast(), 1) };
+        }
+    }
+
+    pub struct Signals {
+        rx: OwnedFd,
+        tx: OwnedFd,
+    }
+
+    impl Signals {
+        pub fn new(kinds: &[SignalKind]) -> Result<Self, Error> {
+            let mut fds = [0; 2];
+            // safety: fds is valid for two ints
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            // safety: the kernel just handed us these fds, so nothing else owns them
+            let (rx, tx) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+            for fd in [&rx, &tx] {
+                // safety: fcntl only reads its arguments
+                unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+            }
+            // safety: as above
+            unsafe { libc::fcntl(tx.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) };
+            for kind in kinds {
+                let Some(pipe) = PIPES.get(kind.as_raw() as usize) else {
+                    return Err(io::Error::from(io::ErrorKind::InvalidInput).into());
+                };
+                pipe.store(tx.as_raw_fd(), Ordering::Relaxed);
+                // safety: the handler only calls async-signal-safe functions
+                unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction = handler as usize;
+                    action.sa_flags = libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    if libc::sigaction(kind.as_raw(), &action, std::ptr::null_mut()) != 0 {
+                        return Err(io::Error::last_os_error().into());
+                    }
+                }
+            }
+            Ok(Self { rx, tx })
+        }
+
+        pub async fn recv(&self) -> Result<SignalKind, Error> {
+            let fd = BorrowedHandle::Fd(self.rx.as_fd());
+            let (res, buf) = Completion::run(Read::new(fd, vec![0u8; 1], None)).await;
+            match res? {
+                0 => Err(Error::EOF),
+                _ => Ok(SignalKind::from_raw(buf[0] as i32)),
+            }
+        }
+    }
+
+    impl Drop for Signals {
+        fn drop(&mut self) {
+            let fd = self.tx.as_raw_fd();
+            for pipe in &PIPES {
+                let _ = pipe.compare_exchange(fd, -1, Ordering::Relaxed, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    async fn test_recv_signal() {
+        let signals = Signals::new([SignalKind::user_defined1()]).unwrap();
+        // the signal is only blocked on this thread, so it's sent to the thread rather than the
+        // whole process
+        unsafe { libc::pthread_kill(libc::pthread_self(), libc::SIGUSR1) };
+        assert_eq!(signals.recv().await.unwrap(), SignalKind::user_defined1());
+    }
+}
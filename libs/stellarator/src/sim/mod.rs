@@ -0,0 +1,231 @@
+//! Deterministic simulation mode for the executor
+//!
+//! [`run`] drives a future on a virtual clock: whenever every task is idle, the clock jumps
+//! straight to the next timer deadline instead of waiting for it. Tasks are also polled in an
+//! order shuffled by a seeded RNG, so interleavings that depend on scheduling can be replayed
+//! from the seed. Together with the in-memory [`net`] module this lets time dependent logic be
+//! tested without real sockets or wall-clock sleeps.
+//!
+//! Real io (files, tcp sockets) still works inside a simulation, but its completions aren't tied
+//! to the virtual clock, so it isn't deterministic.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use maitake::time::Clock;
+use pin_project::pin_project;
+
+use crate::{EXEC, Executor};
+
+pub mod net;
+
+thread_local! {
+    static SIM: RefCell<Option<SimState>> = const { RefCell::new(None) };
+}
+
+/// The chance out of 256 that a task yields back to the scheduler instead of being polled
+const SHUFFLE_CHANCE: u64 = 64;
+
+struct SimState {
+    now: Duration,
+    rng: SplitMix64,
+    net: net::Registry,
+}
+
+/// Runs `func` on a fresh executor in simulation mode, with the scheduling order seeded by `seed`
+///
+/// Like [`crate::run`], this replaces the executor of the current thread, so it must not be
+/// called from inside a running executor.
+pub fn run<R, F>(seed: u64, func: impl FnOnce() -> F) -> R
+where
+    F: Future<Output = R> + 'static,
+    R: 'static,
+{
+    SIM.with(|sim| {
+        *sim.borrow_mut() = Some(SimState {
+            now: Duration::ZERO,
+            rng: SplitMix64(seed),
+            net: net::Registry::default(),
+        })
+    });
+    let executor = Executor::with_clock(clock()).expect("failed to create executor");
+    // safety: no executor is running on this thread, so nothing borrows the old executor
+    let previous = EXEC.with(|exec| unsafe { (*exec.get()).replace(executor) });
+    let _guard = SimGuard { previous };
+    Executor::with(|e| e.run(func).unwrap())
+}
+
+/// Puts the thread's executor back and leaves simulation mode once [`run`] returns, or when the
+/// simulation panics
+struct SimGuard {
+    previous: Option<Executor>,
+}
+
+impl Drop for SimGuard {
+    fn drop(&mut self) {
+        // safety: the simulated executor has stopped running, so nothing borrows it
+        let simulated =
+            EXEC.with(|exec| unsafe { std::mem::replace(&mut *exec.get(), self.previous.take()) });
+        // tasks left on the simulated executor may still use the simulated network as they drop
+        drop(simulated);
+        SIM.with(|sim| sim.borrow_mut().take());
+    }
+}
+
+/// Returns true if the current thread is running a simulation
+pub fn is_active() -> bool {
+    SIM.with(|sim| sim.borrow().is_some())
+}
+
+/// Returns the virtual time elapsed since the simulation started, or `None` outside of one
+pub fn now() -> Option<Duration> {
+    SIM.with(|sim| sim.borrow().as_ref().map(|sim| sim.now))
+}
+
+/// Returns a random number from the simulation's seeded RNG, or `None` outside of one
+///
+/// Tests can use this to generate inputs that are replayed along with the scheduling order.
+pub fn random() -> Option<u64> {
+    with_state(|sim| sim.rng.next())
+}
+
+/// Moves the virtual clock forward by `duration`
+pub(crate) fn advance(duration: Duration) {
+    with_state(|sim| sim.now += duration);
+}
+
+fn with_state<R>(f: impl FnOnce(&mut SimState) -> R) -> Option<R> {
+    SIM.with(|sim| sim.borrow_mut().as_mut().map(f))
+}
+
+/// A clock reading the virtual time
+///
+/// It ticks in microseconds rather than nanoseconds like the os clock, since the timer wheel holds
+/// 2^36 ticks and a simulation jumping to a deadline further out than that would never reach it.
+fn clock() -> Clock {
+    Clock::new(Duration::from_micros(1), || {
+        now().unwrap_or_default().as_micros() as u64
+    })
+    .named("simulated")
+}
+
+/// Wraps a spawned task so it sometimes yields to the back of the run queue instead of being
+/// polled, shuffling the scheduling order of a simulation
+#[pin_project]
+pub(crate) struct Shuffled<F> {
+    #[pin]
+    inner: F,
+}
+
+impl<F> Shuffled<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: Future> Future for Shuffled<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if random().is_some_and(|r| r % 256 < SHUFFLE_CHANCE) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.project().inner.poll(cx)
+    }
+}
+
+/// A tiny, seedable RNG, good enough for shuffling tasks
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::time::{Interval, monotonic_now};
+
+    #[test]
+    fn test_virtual_clock() {
+        let start = Instant::now();
+        run(0, || async {
+            crate::sleep(Duration::from_secs(60 * 60)).await;
+            assert_eq!(monotonic_now(), Duration::from_secs(60 * 60));
+        });
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_panic_restores_executor() {
+        let res = std::panic::catch_unwind(|| {
+            run(0, || async {
+                panic!("simulation failed");
+            })
+        });
+        assert!(res.is_err());
+        assert!(!is_active());
+        // the thread's executor reads the os clock again, so this sleep finishes
+        let start = Instant::now();
+        crate::run(|| crate::sleep(Duration::from_millis(10)));
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_interval_has_no_jitter() {
+        run(0, || async {
+            let mut interval = Interval::new(Duration::from_millis(10));
+            for i in 0..100 {
+                assert_eq!(interval.tick().await, Duration::from_millis(10 * i));
+                crate::sleep(Duration::from_millis(3)).await;
+            }
+            assert_eq!(interval.stats().max_jitter, Duration::ZERO);
+        });
+    }
+
+    fn interleaving(seed: u64) -> Vec<usize> {
+        let order = Arc::new(Mutex::new(vec![]));
+        let out = order.clone();
+        run(seed, move || async move {
+            let tasks = (0..4)
+                .map(|task| {
+                    let order = order.clone();
+                    crate::spawn(async move {
+                        for _ in 0..8 {
+                            order.lock().unwrap().push(task);
+                            crate::yield_now().await;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+        out.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn test_seeded_scheduling() {
+        assert_eq!(interleaving(7), interleaving(7));
+        let orders = (0..8).map(interleaving).collect::<Vec<_>>();
+        assert!(orders.iter().any(|order| order != &orders[0]));
+    }
+}
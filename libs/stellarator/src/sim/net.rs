@@ -0,0 +1,262 @@
+//! An in-memory loopback network for simulations
+//!
+//! [`SimListener`] and [`SimStream`] mirror [`crate::net::TcpListener`] and
+//! [`crate::net::TcpStream`], but connections are pairs of in-memory pipes that only exist inside
+//! the simulation running on the current thread.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use maitake::sync::WaitQueue;
+
+use crate::{
+    BufResult, Error,
+    buf::{self, IoBuf, IoBufMut},
+    io::{AsyncRead, AsyncWrite},
+};
+
+/// The first port handed out to listeners bound to port 0
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+#[derive(Default)]
+pub(crate) struct Registry {
+    listeners: HashMap<SocketAddr, Arc<AcceptQueue>>,
+    next_port: u16,
+}
+
+impl Registry {
+    fn ephemeral_port(&mut self) -> u16 {
+        let port = EPHEMERAL_PORT_START + self.next_port % (u16::MAX - EPHEMERAL_PORT_START);
+        self.next_port = self.next_port.wrapping_add(1);
+        port
+    }
+}
+
+struct AcceptQueue {
+    pending: Mutex<VecDeque<SimStream>>,
+    wait: WaitQueue,
+}
+
+fn with_registry<R>(f: impl FnOnce(&mut Registry) -> R) -> Result<R, Error> {
+    super::with_state(|sim| f(&mut sim.net)).ok_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the sim network is only available inside a simulation",
+        ))
+    })
+}
+
+pub struct SimListener {
+    addr: SocketAddr,
+    queue: Arc<AcceptQueue>,
+}
+
+impl SimListener {
+    /// Binds a listener to `addr`, a port of 0 is replaced by a free ephemeral port
+    pub fn bind(addr: SocketAddr) -> Result<Self, Error> {
+        with_registry(|registry| {
+            let mut addr = addr;
+            if addr.port() == 0 {
+                addr.set_port(registry.ephemeral_port());
+            }
+            if registry.listeners.contains_key(&addr) {
+                return Err(Error::Io(io::ErrorKind::AddrInUse.into()));
+            }
+            let queue = Arc::new(AcceptQueue {
+                pending: Mutex::default(),
+                wait: WaitQueue::new(),
+            });
+            registry.listeners.insert(addr, queue.clone());
+            Ok(SimListener { addr, queue })
+        })?
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub async fn accept(&self) -> Result<SimStream, Error> {
+        loop {
+            if let Some(stream) = self.queue.pending.lock().unwrap().pop_front() {
+                return Ok(stream);
+            }
+            self.queue
+                .wait
+                .wait()
+                .await
+                .map_err(|_| Error::Io(io::ErrorKind::NotConnected.into()))?;
+        }
+    }
+}
+
+impl Drop for SimListener {
+    fn drop(&mut self) {
+        let _ = with_registry(|registry| registry.listeners.remove(&self.addr));
+        self.queue.wait.close();
+    }
+}
+
+/// One direction of a [`SimStream`]
+struct Pipe {
+    state: Mutex<PipeState>,
+    readable: WaitQueue,
+}
+
+#[derive(Default)]
+struct PipeState {
+    buf: VecDeque<u8>,
+    closed: bool,
+}
+
+impl Pipe {
+    fn new() -> Arc<Self> {
+        Arc::new(Pipe {
+            state: Mutex::default(),
+            readable: WaitQueue::new(),
+        })
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.wake_all();
+    }
+}
+
+pub struct SimStream {
+    rx: Arc<Pipe>,
+    tx: Arc<Pipe>,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl SimStream {
+    pub async fn connect(addr: SocketAddr) -> Result<SimStream, Error> {
+        let (queue, local_addr) = with_registry(|registry| {
+            let queue = registry.listeners.get(&addr).cloned();
+            (queue, SocketAddr::new(addr.ip(), registry.ephemeral_port()))
+        })?;
+        let queue = queue.ok_or(Error::Io(io::ErrorKind::ConnectionRefused.into()))?;
+        let (a, b) = (Pipe::new(), Pipe::new());
+        let server = SimStream {
+            rx: a.clone(),
+            tx: b.clone(),
+            local_addr: addr,
+            peer_addr: local_addr,
+        };
+        queue.pending.lock().unwrap().push_back(server);
+        queue.wait.wake();
+        Ok(SimStream {
+            rx: b,
+            tx: a,
+            local_addr,
+            peer_addr: addr,
+        })
+    }
+
+    pub async fn read<B: IoBufMut>(&self, mut buf: B) -> BufResult<usize, B> {
+        loop {
+            {
+                let mut state = self.rx.state.lock().unwrap();
+                if !state.buf.is_empty() {
+                    let out = buf::deref_mut(&mut buf);
+                    let len = out.len().min(state.buf.len());
+                    for (dst, src) in out.iter_mut().zip(state.buf.drain(..len)) {
+                        *dst = src;
+                    }
+                    return (Ok(len), buf);
+                }
+                if state.closed {
+                    return (Ok(0), buf);
+                }
+            }
+            if self.rx.readable.wait().await.is_err() {
+                return (Ok(0), buf);
+            }
+        }
+    }
+
+    pub async fn write<B: IoBuf>(&self, buf: B) -> BufResult<usize, B> {
+        let data = buf::deref(&buf);
+        {
+            let mut state = self.tx.state.lock().unwrap();
+            if state.closed {
+                return (Err(Error::Io(io::ErrorKind::BrokenPipe.into())), buf);
+            }
+            state.buf.extend(data);
+        }
+        self.tx.readable.wake_all();
+        (Ok(data.len()), buf)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+}
+
+impl Drop for SimStream {
+    fn drop(&mut self) {
+        self.tx.close();
+        self.rx.close();
+    }
+}
+
+impl AsyncRead for SimStream {
+    fn read<B: IoBufMut>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
+        self.read(buf)
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn write<B: IoBuf>(&self, buf: B) -> impl std::future::Future<Output = BufResult<usize, B>> {
+        self.write(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::SplitExt, sim::run};
+
+    #[test]
+    fn test_loopback() {
+        run(0, || async {
+            let listener = SimListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+            let addr = listener.local_addr();
+            let server = crate::spawn(async move {
+                let stream = listener.accept().await.unwrap();
+                let buf = vec![0u8; 5];
+                let (res, buf) = stream.read_exact(buf).await;
+                res.unwrap();
+                stream.write_all(buf).await.0.unwrap();
+            });
+            let (rx, tx) = SimStream::connect(addr).await.unwrap().split();
+            tx.write_all(&b"hello"[..]).await.0.unwrap();
+            let (res, buf) = rx.read_exact(vec![0u8; 5]).await;
+            res.unwrap();
+            assert_eq!(&buf[..], b"hello");
+            server.await.unwrap();
+            // the server's end was dropped
+            assert_eq!(rx.read(vec![0u8; 5]).await.0.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        run(0, || async {
+            let addr = SocketAddr::from(([127, 0, 0, 1], 2240));
+            assert!(SimStream::connect(addr).await.is_err());
+            let listener = SimListener::bind(addr).unwrap();
+            assert!(SimListener::bind(addr).is_err());
+            drop(listener);
+            assert!(SimStream::connect(addr).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,306 @@
+//! Timers for loops that need to run at a fixed rate, like flight software control loops
+//!
+//! Deadlines are measured against [`monotonic_now`], so they can be handed to io_uring as
+//! absolute timeouts.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project::pin_project;
+
+use crate::{Error, sim};
+
+/// Returns the current reading of the monotonic clock, or of the virtual clock when running a
+/// simulation
+pub fn monotonic_now() -> Duration {
+    sim::now().unwrap_or_else(crate::os::monotonic_now)
+}
+
+/// What an [`Interval`] does when a tick is taken after the following deadline has passed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Fires the missed ticks back to back until the interval has caught up with its schedule
+    #[default]
+    Burst,
+    /// Restarts the schedule from the late tick, shifting every later deadline back
+    Delay,
+    /// Drops the missed ticks and waits for the next deadline of the original schedule
+    Skip,
+}
+
+/// Overrun and jitter statistics of an [`Interval`]
+///
+/// Jitter is the time between a tick's deadline and the tick actually firing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IntervalStats {
+    /// The number of ticks fired
+    pub ticks: u64,
+    /// The number of ticks that fired after the following deadline had already passed
+    pub overruns: u64,
+    /// The number of deadlines dropped by [`MissedTickBehavior::Skip`]
+    pub skipped: u64,
+    pub last_jitter: Duration,
+    pub max_jitter: Duration,
+    total_jitter: Duration,
+}
+
+impl IntervalStats {
+    pub fn mean_jitter(&self) -> Duration {
+        let mean = self.total_jitter.as_nanos() / self.ticks.max(1) as u128;
+        Duration::from_nanos(mean as u64)
+    }
+
+    fn record(&mut self, jitter: Duration) {
+        self.ticks += 1;
+        self.last_jitter = jitter;
+        self.max_jitter = self.max_jitter.max(jitter);
+        self.total_jitter += jitter;
+    }
+}
+
+/// A periodic timer with an absolute deadline schedule
+///
+/// Unlike sleeping for the period after each iteration of a loop, the time spent in the loop body
+/// doesn't push back later ticks, so the rate doesn't drift.
+pub struct Interval {
+    schedule: Schedule,
+}
+
+/// Creates an [`Interval`] whose first tick fires immediately
+pub fn interval(period: Duration) -> Interval {
+    Interval::new(period)
+}
+
+impl Interval {
+    /// Creates an [`Interval`] whose first tick fires immediately
+    ///
+    /// # Panics
+    /// Panics if `period` is zero
+    pub fn new(period: Duration) -> Self {
+        Self::starting_at(monotonic_now(), period)
+    }
+
+    /// Creates an [`Interval`] whose first tick fires at `start`, a reading of [`monotonic_now`]
+    ///
+    /// # Panics
+    /// Panics if `period` is zero
+    pub fn starting_at(start: Duration, period: Duration) -> Self {
+        Self {
+            schedule: Schedule::new(start, period),
+        }
+    }
+
+    pub fn with_missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.schedule.behavior = behavior;
+        self
+    }
+
+    pub fn period(&self) -> Duration {
+        self.schedule.period
+    }
+
+    pub fn stats(&self) -> &IntervalStats {
+        &self.schedule.stats
+    }
+
+    /// Restarts the schedule so the next tick fires one period from now
+    pub fn reset(&mut self) {
+        self.schedule.next = monotonic_now() + self.schedule.period;
+    }
+
+    /// Waits for the next deadline, returning it
+    pub async fn tick(&mut self) -> Duration {
+        let deadline = self.schedule.next;
+        while monotonic_now() < deadline {
+            sleep_until(deadline).await;
+        }
+        self.schedule.fire(monotonic_now())
+    }
+}
+
+/// Waits until [`monotonic_now`] reaches `deadline`
+pub async fn sleep_until(deadline: Duration) {
+    #[cfg(target_os = "linux")]
+    if !sim::is_active() {
+        use crate::reactor::{Completion, ops::Timeout};
+        // the deadline is absolute, so the time between reading the clock and the timeout being
+        // submitted isn't added to the wait
+        if Completion::run(Timeout::at(deadline)).await.is_ok() {
+            return;
+        }
+    }
+    crate::sleep(deadline.saturating_sub(monotonic_now())).await;
+}
+
+/// Returned by [`timeout`] when the deadline passes before the future completes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("deadline elapsed")]
+pub struct Elapsed;
+
+impl From<Elapsed> for Error {
+    fn from(_: Elapsed) -> Self {
+        Error::TimedOut
+    }
+}
+
+/// Runs `future` for at most `duration`
+///
+/// If the deadline passes first, `future` is dropped, cancelling any io it has in flight.
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: crate::sleep(duration),
+    }
+}
+
+/// Runs `future` until [`monotonic_now`] reaches `deadline`, see [`timeout`]
+pub fn timeout_at<F: Future>(deadline: Duration, future: F) -> Timeout<F> {
+    timeout(deadline.saturating_sub(monotonic_now()), future)
+}
+
+#[pin_project]
+pub struct Timeout<F> {
+    #[pin]
+    future: F,
+    #[pin]
+    sleep: maitake::time::Sleep<'static>,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(out) = this.future.poll(cx) {
+            return Poll::Ready(Ok(out));
+        }
+        this.sleep.poll(cx).map(|_| Err(Elapsed))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Schedule {
+    period: Duration,
+    next: Duration,
+    behavior: MissedTickBehavior,
+    stats: IntervalStats,
+}
+
+impl Schedule {
+    fn new(start: Duration, period: Duration) -> Self {
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            period,
+            next: start,
+            behavior: MissedTickBehavior::default(),
+            stats: IntervalStats::default(),
+        }
+    }
+
+    /// Fires the tick due at `self.next` now that the clock reads `now`, returning its deadline
+    fn fire(&mut self, now: Duration) -> Duration {
+        let deadline = self.next;
+        let jitter = now.saturating_sub(deadline);
+        let missed = (jitter.as_nanos() / self.period.as_nanos()) as u64;
+        self.stats.record(jitter);
+        if missed > 0 {
+            self.stats.overruns += 1;
+        }
+        self.next = match self.behavior {
+            MissedTickBehavior::Burst => deadline + self.period,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                self.stats.skipped += missed;
+                let periods = (missed + 1) as u128 * self.period.as_nanos();
+                deadline + Duration::from_nanos(periods as u64)
+            }
+        };
+        deadline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn fire_all(behavior: MissedTickBehavior, clock: &[u64]) -> (Vec<u64>, IntervalStats) {
+        let mut schedule = Schedule::new(Duration::ZERO, PERIOD);
+        schedule.behavior = behavior;
+        let deadlines = clock
+            .iter()
+            .map(|&now| schedule.fire(ms(now)).as_millis() as u64)
+            .collect();
+        (deadlines, schedule.stats)
+    }
+
+    #[test]
+    fn test_burst() {
+        let (deadlines, stats) = fire_all(MissedTickBehavior::Burst, &[0, 35, 35, 35, 40]);
+        assert_eq!(deadlines, [0, 10, 20, 30, 40]);
+        assert_eq!(stats.overruns, 2);
+        assert_eq!(stats.skipped, 0);
+    }
+
+    #[test]
+    fn test_delay() {
+        let (deadlines, stats) = fire_all(MissedTickBehavior::Delay, &[0, 35, 45, 56]);
+        assert_eq!(deadlines, [0, 10, 45, 55]);
+        assert_eq!(stats.overruns, 1);
+    }
+
+    #[test]
+    fn test_skip() {
+        let (deadlines, stats) = fire_all(MissedTickBehavior::Skip, &[0, 35, 41, 50]);
+        assert_eq!(deadlines, [0, 10, 40, 50]);
+        assert_eq!(stats.overruns, 1);
+        assert_eq!(stats.skipped, 2);
+    }
+
+    #[test]
+    fn test_jitter_stats() {
+        let (_, stats) = fire_all(MissedTickBehavior::Burst, &[1, 10, 23]);
+        assert_eq!(stats.ticks, 3);
+        assert_eq!(stats.last_jitter, ms(3));
+        assert_eq!(stats.max_jitter, ms(3));
+        assert_eq!(stats.mean_jitter(), Duration::from_nanos(1_333_333));
+    }
+
+    #[crate::test]
+    async fn test_interval_does_not_drift() {
+        let start = Instant::now();
+        let mut interval = interval(Duration::from_millis(50));
+        for _ in 0..5 {
+            interval.tick().await;
+            crate::sleep(Duration::from_millis(20)).await;
+        }
+        // the first tick fires immediately, and the 20ms loop body isn't added to the period
+        let delta = start.elapsed().as_millis().abs_diff(220);
+        assert!(delta <= 10, "Δt ({}) > 10ms", delta);
+        assert_eq!(interval.stats().ticks, 5);
+        assert_eq!(interval.stats().overruns, 0);
+    }
+
+    #[crate::test]
+    async fn test_timeout() {
+        let res = timeout(ms(10), std::future::pending::<()>()).await;
+        assert_eq!(res, Err(Elapsed));
+        let res = timeout(ms(100), async {
+            crate::sleep(ms(5)).await;
+            1
+        })
+        .await;
+        assert_eq!(res, Ok(1));
+    }
+}
@@ -0,0 +1,240 @@
+use super::ops::CqeExt;
+use super::{SharedUring, UringReactor};
+use crate::{Error, Executor};
+use io_uring::cqueue;
+use io_uring::types::BufRingEntry;
+use std::alloc::{self, Layout};
+use std::cell::Cell;
+use std::io;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+/// A ring of buffers provided to the kernel, which picks one for each completion of a recv
+/// instead of the caller passing a buffer with every op
+///
+/// Requires Linux 5.19. The ring is registered with the current thread's io_uring, so only ops
+/// submitted from this thread can use it.
+#[derive(Clone)]
+pub struct BufRing {
+    inner: Rc<BufRingInner>,
+}
+
+struct BufRingInner {
+    group: u16,
+    entries: u16,
+    buf_len: usize,
+    ring: NonNull<BufRingEntry>,
+    ring_layout: Layout,
+    bufs: NonNull<u8>,
+    bufs_layout: Layout,
+    tail: Cell<u16>,
+    held: Cell<u16>,
+    uring: SharedUring,
+}
+
+impl BufRing {
+    /// Creates a ring of `entries` buffers that are `buf_len` bytes long
+    ///
+    /// `entries` must be a power of two no larger than 32768.
+    pub fn new(entries: u16, buf_len: usize) -> Result<BufRing, Error> {
+        if !entries.is_power_of_two() || entries > 1 << 15 {
+            return Err(invalid_input(
+                "buffer ring entries must be a power of two up to 32768",
+            ));
+        }
+        if buf_len == 0 || buf_len > u32::MAX as usize {
+            return Err(invalid_input("invalid buffer ring buffer length"));
+        }
+        let bufs_len = (entries as usize)
+            .checked_mul(buf_len)
+            .ok_or(Error::IntegerOverflow)?;
+        // the kernel requires the ring to be page aligned
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let ring_layout =
+            Layout::from_size_align(entries as usize * size_of::<BufRingEntry>(), page_size)
+                .map_err(|_| Error::IntegerOverflow)?;
+        let bufs_layout =
+            Layout::from_size_align(bufs_len, 64).map_err(|_| Error::IntegerOverflow)?;
+        let ring = alloc_layout(ring_layout).cast::<BufRingEntry>();
+        let bufs = alloc_layout(bufs_layout);
+        let (group, uring) =
+            match Executor::with_reactor(|reactor| reactor.register_buf_ring(ring, entries)) {
+                Ok(registered) => registered,
+                Err(err) => {
+                    unsafe {
+                        alloc::dealloc(ring.as_ptr().cast(), ring_layout);
+                        alloc::dealloc(bufs.as_ptr(), bufs_layout);
+                    }
+                    return Err(err);
+                }
+            };
+        let inner = BufRingInner {
+            group,
+            entries,
+            buf_len,
+            ring,
+            ring_layout,
+            bufs,
+            bufs_layout,
+            tail: Cell::new(0),
+            held: Cell::new(0),
+            uring,
+        };
+        for bid in 0..entries {
+            inner.push(bid);
+        }
+        inner.publish();
+        Ok(BufRing {
+            inner: Rc::new(inner),
+        })
+    }
+
+    /// The id of the ring's buffer group, passed to ops that select a buffer
+    pub fn group(&self) -> u16 {
+        self.inner.group
+    }
+
+    pub fn buf_len(&self) -> usize {
+        self.inner.buf_len
+    }
+
+    /// Whether any of the ring's buffers are back in the kernel's hands
+    pub fn has_free(&self) -> bool {
+        self.inner.held.get() < self.inner.entries
+    }
+
+    /// Takes the buffer the kernel picked for `cqe`
+    pub(crate) fn take(&self, cqe: &cqueue::Entry) -> Result<ProvidedBuf, Error> {
+        let len = cqe.as_result()? as usize;
+        // the kernel doesn't pick a buffer when the socket has reached end of file
+        let bid = cqueue::buffer_select(cqe.flags());
+        if bid.is_some() {
+            self.inner.held.set(self.inner.held.get() + 1);
+        }
+        Ok(ProvidedBuf {
+            ring: self.clone(),
+            bid,
+            len: if bid.is_some() { len } else { 0 },
+        })
+    }
+}
+
+impl BufRingInner {
+    fn push(&self, bid: u16) {
+        let tail = self.tail.get();
+        let index = (tail & (self.entries - 1)) as usize;
+        // safety: index is masked to the ring, and the kernel only reads entries behind the
+        // published tail
+        let entry = unsafe { &mut *self.ring.as_ptr().add(index) };
+        entry.set_addr(self.buf_ptr(bid) as u64);
+        entry.set_len(self.buf_len as u32);
+        entry.set_bid(bid);
+        self.tail.set(tail.wrapping_add(1));
+    }
+
+    /// Hands the pushed buffers to the kernel
+    fn publish(&self) {
+        // safety: the tail overlaps the reserved field of the first entry, which the kernel reads
+        // atomically
+        let tail = unsafe { &*(BufRingEntry::tail(self.ring.as_ptr()) as *const AtomicU16) };
+        tail.store(self.tail.get(), Ordering::Release);
+    }
+
+    fn buf_ptr(&self, bid: u16) -> *mut u8 {
+        unsafe { self.bufs.as_ptr().add(bid as usize * self.buf_len) }
+    }
+}
+
+impl Drop for BufRingInner {
+    fn drop(&mut self) {
+        // every op using the ring holds a reference to it, so the kernel is done with it here
+        let _ = self.uring.submitter().unregister_buf_ring(self.group);
+        unsafe {
+            alloc::dealloc(self.ring.as_ptr().cast(), self.ring_layout);
+            alloc::dealloc(self.bufs.as_ptr(), self.bufs_layout);
+        }
+    }
+}
+
+/// A buffer picked from a [`BufRing`] by the kernel, which goes back to the ring on drop
+pub struct ProvidedBuf {
+    ring: BufRing,
+    bid: Option<u16>,
+    len: usize,
+}
+
+impl Deref for ProvidedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.bid {
+            // safety: the kernel wrote `len` bytes to the buffer, and won't touch it again until
+            // it's pushed back to the ring
+            Some(bid) => unsafe {
+                std::slice::from_raw_parts(self.ring.inner.buf_ptr(bid), self.len)
+            },
+            None => &[],
+        }
+    }
+}
+
+impl AsRef<[u8]> for ProvidedBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl std::fmt::Debug for ProvidedBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProvidedBuf")
+            .field("bid", &self.bid)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Drop for ProvidedBuf {
+    fn drop(&mut self) {
+        if let Some(bid) = self.bid {
+            let inner = &self.ring.inner;
+            inner.held.set(inner.held.get() - 1);
+            inner.push(bid);
+            inner.publish();
+        }
+    }
+}
+
+impl UringReactor {
+    fn register_buf_ring(
+        &mut self,
+        ring: NonNull<BufRingEntry>,
+        entries: u16,
+    ) -> Result<(u16, SharedUring), Error> {
+        loop {
+            let group = self.next_buf_group;
+            self.next_buf_group = group.wrapping_add(1);
+            let res = unsafe {
+                self.uring
+                    .submitter()
+                    .register_buf_ring(ring.as_ptr() as u64, entries, group)
+            };
+            match res {
+                Ok(()) => return Ok((group, self.uring.clone())),
+                // the group id wrapped around to one that's still registered
+                Err(err) if err.raw_os_error() == Some(libc::EEXIST) => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+fn alloc_layout(layout: Layout) -> NonNull<u8> {
+    let ptr = unsafe { alloc::alloc_zeroed(layout) };
+    NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+}
+
+fn invalid_input(msg: &'static str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
@@ -0,0 +1,79 @@
+use super::SharedUring;
+use crate::{Error, Executor};
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+
+/// How many files each ring's table of fixed files holds
+const FIXED_FILES: u32 = 1024;
+
+#[derive(Default)]
+pub(super) struct FixedFiles {
+    registered: bool,
+    free: Vec<u32>,
+    next: u32,
+}
+
+/// A file registered in an io_uring's table of fixed files
+///
+/// Ops that target a fixed file skip looking up, and reference counting, the file descriptor on
+/// every submission. The table belongs to the ring of the thread that registered the file, so
+/// [`FixedFile::index`] is only `Some` on that thread.
+#[derive(Debug)]
+pub struct FixedFile {
+    index: u32,
+    uring: SharedUring,
+}
+
+impl FixedFile {
+    /// Registers `fd` with the current thread's io_uring
+    pub fn register(fd: BorrowedFd<'_>) -> Result<FixedFile, Error> {
+        let uring = Executor::with_reactor(|reactor| reactor.uring.clone());
+        let index = uring.register_file(fd.as_raw_fd())?;
+        Ok(FixedFile { index, uring })
+    }
+
+    /// The file's index in the current thread's table, or `None` if another thread registered it
+    pub fn index(&self) -> Option<u32> {
+        Executor::with_reactor(|reactor| reactor.uring.ptr_eq(&self.uring)).then_some(self.index)
+    }
+}
+
+impl Drop for FixedFile {
+    fn drop(&mut self) {
+        self.uring.unregister_file(self.index);
+    }
+}
+
+impl SharedUring {
+    fn register_file(&self, fd: RawFd) -> Result<u32, Error> {
+        let mut files = self.uring.files.lock();
+        if !files.registered {
+            self.submitter().register_files_sparse(FIXED_FILES)?;
+            files.registered = true;
+        }
+        let index = match files.free.pop() {
+            Some(index) => index,
+            None if files.next < FIXED_FILES => {
+                files.next += 1;
+                files.next - 1
+            }
+            None => {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    "fixed file table is full",
+                )));
+            }
+        };
+        if let Err(err) = self.submitter().register_files_update(index, &[fd]) {
+            files.free.push(index);
+            return Err(err.into());
+        }
+        Ok(index)
+    }
+
+    fn unregister_file(&self, index: u32) {
+        let mut files = self.uring.files.lock();
+        let _ = self.submitter().register_files_update(index, &[-1]);
+        files.free.push(index);
+    }
+}
@@ -1,10 +1,11 @@
 use crate::{Error, Executor, IoStates, Reactor};
 use io_uring::{cqueue, squeue};
 use maitake::scheduler::ExternalWaker as _;
-use maitake::time::Timer;
+use maitake::time::{Clock, Timer};
 use pin_project::{pin_project, pinned_drop};
 use slab::Slab;
 use std::any::Any;
+use std::collections::VecDeque;
 use std::future::Future;
 use std::mem;
 use std::pin::Pin;
@@ -14,21 +15,39 @@ use std::time::Duration;
 use std::{cell::RefCell, task::Waker};
 use waker_fn::waker_fn;
 
+mod buf_ring;
+mod fixed;
+mod multishot;
 pub mod ops;
 
+pub use buf_ring::*;
+pub use fixed::*;
+pub use multishot::*;
+
 pub struct UringReactor {
     uring: SharedUring,
     states: Slab<OpState>,
+    next_buf_group: u16,
 }
 
 impl UringReactor {
     pub fn submit_op<O: OpCode>(&mut self, mut op_code: O) -> Result<Completion<O>, (O, Error)> {
         let entry = self.states.vacant_entry();
         let sqe = unsafe { op_code.sqe().user_data(entry.key() as u64) };
-        unsafe {
-            if let Err(err) = self.uring.with_submission(|mut s| s.push(&sqe)) {
-                return Err((op_code, Error::from(err)));
+        let res = match op_code.link_timeout() {
+            Some(timespec) => {
+                // the linked timeout's completion has no state, so it is skipped like the waker's
+                let timeout = io_uring::opcode::LinkTimeout::new(timespec as *const _)
+                    .build()
+                    .user_data(u64::MAX);
+                let sqes = [sqe.flags(squeue::Flags::IO_LINK), timeout];
+                self.uring
+                    .with_submission(|mut s| unsafe { s.push_multiple(&sqes) })
             }
+            None => self.uring.with_submission(|mut s| unsafe { s.push(&sqe) }),
+        };
+        if let Err(err) = res {
+            return Err((op_code, Error::from(err)));
         }
         let id = CompletionId(entry.key());
 
@@ -69,6 +88,10 @@ impl UringReactor {
                 let op_code = take_completion_op_code(completion);
                 return Poll::Ready(op_code.output_from_error(Error::PolledIgnoredCompletion));
             }
+            OpState::Multishot(_) => {
+                let op_code = take_completion_op_code(completion);
+                return Poll::Ready(op_code.output_from_error(Error::CompletionStateMissing));
+            }
         }
         Poll::Pending
     }
@@ -82,16 +105,28 @@ impl UringReactor {
         let Some(op_code) = (unsafe { completion.op_code.get_unchecked_mut().take() }) else {
             return;
         };
-        let Some(value) =
-            stack_dst::Value::<dyn Any, _>::new_stable(op_code.into_buf(), |p| p as _).ok()
-        else {
-            // TODO: handle gracefully
-            return;
+        let value = match stack_dst::Value::<dyn Any, _>::new_stable(op_code.into_buf(), |p| p as _)
+        {
+            Ok(value) => value,
+            // too large to store inline, but the kernel may still write into it so it has to
+            // outlive the op
+            Err(buf) => {
+                let buf: Box<dyn Any> = Box::new(buf);
+                match stack_dst::Value::<dyn Any, _>::new_stable(buf, |p| p as _) {
+                    Ok(value) => value,
+                    Err(buf) => {
+                        std::mem::forget(buf);
+                        return;
+                    }
+                }
+            }
         };
 
         if matches!(state, OpState::Waiting(_)) {
             self.uring.with_submission(|mut s| {
-                let sqe = io_uring::opcode::AsyncCancel::new(completion.id.0 as u64).build();
+                let sqe = io_uring::opcode::AsyncCancel::new(completion.id.0 as u64)
+                    .build()
+                    .user_data(u64::MAX);
                 unsafe {
                     let _ = s.push(&sqe);
                 }
@@ -157,6 +192,12 @@ impl Reactor for UringReactor {
                     // submit a dummy operation to the reactor to make it complete instantly
                     continue;
                 };
+                if let OpState::Multishot(multishot) = state {
+                    if multishot.push(comp) {
+                        self.states.remove(id);
+                    }
+                    continue;
+                }
                 let prev_state = mem::replace(state, OpState::Completed(comp));
                 match prev_state {
                     OpState::Waiting(Some(waker)) => waker.wake(),
@@ -196,7 +237,12 @@ impl IoStates for Slab<OpState> {
     fn cancel(&mut self) {
         let mut count = 0;
         self.retain(|id, state| {
-            if matches!(state, OpState::Waiting(_)) {
+            let waiting = match state {
+                OpState::Waiting(_) => true,
+                OpState::Multishot(multishot) => !multishot.done,
+                _ => false,
+            };
+            if waiting {
                 let sqe = io_uring::opcode::AsyncCancel::new(id as u64).build();
                 count += 1;
                 Executor::with_reactor(|reactor| {
@@ -225,12 +271,45 @@ pub trait OpCode {
 
     type Buf: 'static;
     fn into_buf(self) -> Self::Buf;
+
+    /// A timeout linked to the op, the op is cancelled if it hasn't completed when it expires
+    fn link_timeout(&self) -> Option<&io_uring::types::Timespec> {
+        None
+    }
 }
 
 pub enum OpState {
     Waiting(Option<Waker>),
     Completed(cqueue::Entry),
     Ignored(stack_dst::Value<dyn core::any::Any, stack_dst::buffers::Ptr8>),
+    Multishot(MultishotState),
+}
+
+/// The state of an op that completes more than once, like a multishot recv or a zero copy send
+pub struct MultishotState {
+    cqes: VecDeque<cqueue::Entry>,
+    waker: Option<Waker>,
+    /// Set once the kernel has posted the op's last completion
+    done: bool,
+    /// Consumes the completions of an op whose handle was dropped before it finished
+    discard: Option<Box<dyn FnMut(cqueue::Entry)>>,
+}
+
+impl MultishotState {
+    /// Queues `cqe`, returning true if the state can be removed
+    fn push(&mut self, cqe: cqueue::Entry) -> bool {
+        let last = !cqueue::more(cqe.flags());
+        if let Some(discard) = &mut self.discard {
+            discard(cqe);
+            return last;
+        }
+        self.cqes.push_back(cqe);
+        self.done |= last;
+        if let Some(waker) = &self.waker {
+            waker.wake_by_ref();
+        }
+        false
+    }
 }
 
 pub struct CompletionId(pub usize);
@@ -289,16 +368,22 @@ impl Default for Executor<UringReactor> {
 
 impl Executor<UringReactor> {
     pub fn try_new() -> Result<Self, Error> {
+        Self::with_clock(crate::os::os_clock())
+    }
+
+    /// Creates an executor whose timers are driven by `clock`
+    pub fn with_clock(clock: Clock) -> Result<Self, Error> {
         let reactor = UringReactor {
             uring: SharedUring::with_uring(io_uring::IoUring::new(256)?),
             states: Slab::with_capacity(256),
+            next_buf_group: 0,
         };
         let scheduler =
             maitake::scheduler::LocalScheduler::with_external_waker(reactor.external_waker());
         Ok(Executor {
             reactor: RefCell::new(reactor),
             scheduler,
-            timer: Timer::new(crate::os::os_clock()),
+            timer: Timer::new(clock),
         })
     }
 }
@@ -313,6 +398,7 @@ impl SharedUring {
         let uring = SharedUringInner {
             completion_lock: spin::Mutex::new(()),
             submission_lock: spin::Mutex::new(()),
+            files: spin::Mutex::new(FixedFiles::default()),
             ring,
         };
         SharedUring {
@@ -337,11 +423,16 @@ impl SharedUring {
     fn submitter(&self) -> io_uring::Submitter<'_> {
         self.uring.submitter()
     }
+
+    fn ptr_eq(&self, other: &SharedUring) -> bool {
+        Arc::ptr_eq(&self.uring, &other.uring)
+    }
 }
 
 struct SharedUringInner {
     submission_lock: spin::Mutex<()>,
     completion_lock: spin::Mutex<()>,
+    files: spin::Mutex<FixedFiles>,
     ring: io_uring::IoUring,
 }
 
@@ -0,0 +1,141 @@
+use super::{MultishotState, OpState, UringReactor};
+use crate::{Error, Executor};
+use io_uring::{cqueue, squeue};
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::mem;
+use std::task::{Context, Poll, ready};
+
+/// An op that posts a completion each time it makes progress, until the kernel posts one without
+/// `IORING_CQE_F_MORE`
+pub trait MultishotOp: 'static {
+    type Item;
+
+    /// # Safety
+    /// Implementors of `sqe` must ensure that the buffers passed to io_uring stay valid, even if
+    /// the op is moved, until the op's last completion
+    unsafe fn sqe(&mut self) -> squeue::Entry;
+
+    /// Turns one of the op's completions into an item
+    ///
+    /// Items of an op whose [`Multishot`] was dropped are dropped as soon as they arrive, so any
+    /// resources they hold, like a provided buffer, are released.
+    fn item(&mut self, cqe: cqueue::Entry) -> Self::Item;
+}
+
+/// A submitted [`MultishotOp`], which yields an item for each of the op's completions
+pub struct Multishot<O: MultishotOp> {
+    id: usize,
+    op: Option<O>,
+    finished: bool,
+}
+
+impl<O: MultishotOp> Multishot<O> {
+    pub fn submit(op: O) -> Result<Self, (O, Error)> {
+        Executor::with_reactor(|reactor| reactor.submit_multishot(op))
+    }
+
+    /// Waits for the op's next completion, returning `None` once the op has finished
+    pub async fn next(&mut self) -> Option<O::Item> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<O::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        let cqe = ready!(Executor::with_reactor(|r| r.poll_multishot(cx, self.id)));
+        let Some(cqe) = cqe else {
+            self.finished = true;
+            return Poll::Ready(None);
+        };
+        let op = self
+            .op
+            .as_mut()
+            .expect("op taken before the multishot finished");
+        Poll::Ready(Some(op.item(cqe)))
+    }
+
+    /// Hands back the op once it has finished
+    pub fn into_op(mut self) -> Option<O> {
+        if self.finished { self.op.take() } else { None }
+    }
+}
+
+impl<O: MultishotOp> Drop for Multishot<O> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Some(op) = self.op.take() {
+            Executor::with_reactor(|r| r.drop_multishot(self.id, op));
+        }
+    }
+}
+
+impl UringReactor {
+    pub fn submit_multishot<O: MultishotOp>(
+        &mut self,
+        mut op: O,
+    ) -> Result<Multishot<O>, (O, Error)> {
+        let entry = self.states.vacant_entry();
+        let sqe = unsafe { op.sqe().user_data(entry.key() as u64) };
+        if let Err(err) = self.uring.with_submission(|mut s| unsafe { s.push(&sqe) }) {
+            return Err((op, Error::from(err)));
+        }
+        let id = entry.key();
+        entry.insert(OpState::Multishot(MultishotState {
+            cqes: VecDeque::new(),
+            waker: None,
+            done: false,
+            discard: None,
+        }));
+        Ok(Multishot {
+            id,
+            op: Some(op),
+            finished: false,
+        })
+    }
+
+    fn poll_multishot(&mut self, cx: &mut Context<'_>, id: usize) -> Poll<Option<cqueue::Entry>> {
+        let Some(OpState::Multishot(state)) = self.states.get_mut(id) else {
+            return Poll::Ready(None);
+        };
+        if let Some(cqe) = state.cqes.pop_front() {
+            return Poll::Ready(Some(cqe));
+        }
+        if state.done {
+            self.states.remove(id);
+            return Poll::Ready(None);
+        }
+        match &state.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            _ => state.waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+
+    fn drop_multishot<O: MultishotOp>(&mut self, id: usize, mut op: O) {
+        let Some(OpState::Multishot(state)) = self.states.get_mut(id) else {
+            return;
+        };
+        for cqe in mem::take(&mut state.cqes) {
+            drop(op.item(cqe));
+        }
+        if state.done {
+            self.states.remove(id);
+            return;
+        }
+        // the kernel may still be using the op's buffers, so it lives until the last completion
+        state.discard = Some(Box::new(move |cqe| drop(op.item(cqe))));
+        state.waker = None;
+        self.uring.with_submission(|mut s| {
+            let sqe = io_uring::opcode::AsyncCancel::new(id as u64)
+                .build()
+                .user_data(u64::MAX);
+            unsafe {
+                let _ = s.push(&sqe);
+            }
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use super::OpCode;
+use super::{BufRing, Multishot, MultishotOp, OpCode, ProvidedBuf};
 use crate::BufResult;
 use crate::Error;
 use crate::buf::IoBuf;
@@ -57,10 +57,41 @@ impl CqeExt for cqueue::Entry {
     }
 }
 
+/// The file an op acts on, either a plain file descriptor or an index into the ring's fixed files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Fd(RawFd),
+    Fixed(u32),
+}
+
+impl Target {
+    pub fn new(fd: RawFd, fixed: Option<u32>) -> Self {
+        fixed.map_or(Target::Fd(fd), Target::Fixed)
+    }
+}
+
+/// Builds an op for either kind of [`Target`], since io-uring's file types don't share a public
+/// trait
+macro_rules! with_target {
+    ($target:expr, |$fd:ident| $op:expr) => {
+        match $target {
+            Target::Fd(fd) => {
+                let $fd = types::Fd(fd);
+                $op
+            }
+            Target::Fixed(index) => {
+                let $fd = types::Fixed(index);
+                $op
+            }
+        }
+    };
+}
+
 pub struct Read<'fd, T> {
     fd: BorrowedHandle<'fd>,
     buf: T,
     offset: Option<u64>,
+    fixed: Option<u32>,
 }
 
 impl<T: IoBufMut> OpCode for Read<'_, T> {
@@ -78,11 +109,13 @@ impl<T: IoBufMut> OpCode for Read<'_, T> {
         let ptr = self.buf.stable_mut_ptr().as_ptr() as *mut u8;
         let len = self.buf.total_len() as u32;
 
-        let mut sqe = opcode::Read::new(types::Fd(self.fd.as_raw_fd()), ptr, len);
-        if let Some(offset) = self.offset {
-            sqe = sqe.offset(offset);
-        }
-        sqe.build()
+        with_target!(Target::new(self.fd.as_raw_fd(), self.fixed), |fd| {
+            let mut sqe = opcode::Read::new(fd, ptr, len);
+            if let Some(offset) = self.offset {
+                sqe = sqe.offset(offset);
+            }
+            sqe.build()
+        })
     }
 
     type Buf = T;
@@ -94,7 +127,18 @@ impl<T: IoBufMut> OpCode for Read<'_, T> {
 
 impl<'fd, T> Read<'fd, T> {
     pub fn new(fd: BorrowedHandle<'fd>, buf: T, offset: Option<u64>) -> Self {
-        Self { fd, buf, offset }
+        Self {
+            fd,
+            buf,
+            offset,
+            fixed: None,
+        }
+    }
+
+    /// Reads through the file's fixed file slot instead of its descriptor
+    pub fn fixed(mut self, index: Option<u32>) -> Self {
+        self.fixed = index;
+        self
     }
 }
 
@@ -102,6 +146,7 @@ pub struct Write<'fd, T> {
     fd: BorrowedHandle<'fd>,
     buf: T,
     offset: Option<u64>,
+    fixed: Option<u32>,
 }
 
 impl<T: IoBuf> OpCode for Write<'_, T> {
@@ -119,11 +164,13 @@ impl<T: IoBuf> OpCode for Write<'_, T> {
         let ptr = self.buf.stable_init_ptr();
         let len = self.buf.init_len() as u32;
 
-        let mut sqe = opcode::Write::new(types::Fd(self.fd.as_raw_fd()), ptr, len);
-        if let Some(offset) = self.offset {
-            sqe = sqe.offset(offset);
-        }
-        sqe.build()
+        with_target!(Target::new(self.fd.as_raw_fd(), self.fixed), |fd| {
+            let mut sqe = opcode::Write::new(fd, ptr, len);
+            if let Some(offset) = self.offset {
+                sqe = sqe.offset(offset);
+            }
+            sqe.build()
+        })
     }
 
     type Buf = T;
@@ -135,7 +182,18 @@ impl<T: IoBuf> OpCode for Write<'_, T> {
 
 impl<'fd, T> Write<'fd, T> {
     pub fn new(fd: BorrowedHandle<'fd>, buf: T, offset: Option<u64>) -> Self {
-        Self { fd, buf, offset }
+        Self {
+            fd,
+            buf,
+            offset,
+            fixed: None,
+        }
+    }
+
+    /// Writes through the file's fixed file slot instead of its descriptor
+    pub fn fixed(mut self, index: Option<u32>) -> Self {
+        self.fixed = index;
+        self
     }
 }
 
@@ -358,6 +416,11 @@ impl Timeout {
         }
     }
 
+    /// Creates a timeout that expires once `CLOCK_MONOTONIC` reaches `deadline`
+    pub fn at(deadline: Duration) -> Self {
+        Self::new(deadline).flags(TimeoutFlags::ABS)
+    }
+
     pub fn count(mut self, count: u32) -> Self {
         self.count = count;
         self
@@ -369,8 +432,377 @@ impl Timeout {
     }
 }
 
+/// Wraps an op with a linked timeout, the op fails with [`Error::TimedOut`] if it hasn't completed
+/// before the timeout expires
+pub struct WithTimeout<O> {
+    op: O,
+    timespec: Box<Timespec>,
+}
+
+impl<O: OpCode> OpCode for WithTimeout<O> {
+    type Output = O::Output;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        if entry.result() == -libc::ECANCELED {
+            self.op.output_from_error(Error::TimedOut)
+        } else {
+            self.op.output(entry)
+        }
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        self.op.output_from_error(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        unsafe { self.op.sqe() }
+    }
+
+    fn link_timeout(&self) -> Option<&Timespec> {
+        Some(&self.timespec)
+    }
+
+    type Buf = (O::Buf, Box<Timespec>);
+
+    fn into_buf(self) -> Self::Buf {
+        (self.op.into_buf(), self.timespec)
+    }
+}
+
+impl<O> WithTimeout<O> {
+    pub fn new(op: O, timeout: Duration) -> Self {
+        let timespec = Box::new(
+            Timespec::new()
+                .sec(timeout.as_secs())
+                .nsec(timeout.subsec_nanos()),
+        );
+        Self { op, timespec }
+    }
+}
+
+pub struct Fsync<'fd> {
+    fd: BorrowedHandle<'fd>,
+    flags: types::FsyncFlags,
+}
+
+impl OpCode for Fsync<'_> {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::Fsync::new(types::Fd(self.fd.as_raw_fd()))
+            .flags(self.flags)
+            .build()
+    }
+
+    type Buf = ();
+
+    fn into_buf(self) -> Self::Buf {}
+}
+
+impl<'fd> Fsync<'fd> {
+    /// Flushes the file's data and metadata, or only its data if `datasync` is set
+    pub fn new(fd: BorrowedHandle<'fd>, datasync: bool) -> Self {
+        let flags = if datasync {
+            types::FsyncFlags::DATASYNC
+        } else {
+            types::FsyncFlags::empty()
+        };
+        Self { fd, flags }
+    }
+}
+
+pub struct Fallocate<'fd> {
+    fd: BorrowedHandle<'fd>,
+    offset: u64,
+    len: u64,
+}
+
+impl OpCode for Fallocate<'_> {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::Fallocate::new(types::Fd(self.fd.as_raw_fd()), self.len)
+            .offset(self.offset)
+            .build()
+    }
+
+    type Buf = ();
+
+    fn into_buf(self) -> Self::Buf {}
+}
+
+impl<'fd> Fallocate<'fd> {
+    pub fn new(fd: BorrowedHandle<'fd>, offset: u64, len: u64) -> Self {
+        Self { fd, offset, len }
+    }
+}
+
+pub struct Ftruncate<'fd> {
+    fd: BorrowedHandle<'fd>,
+    len: u64,
+}
+
+impl OpCode for Ftruncate<'_> {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::Ftruncate::new(types::Fd(self.fd.as_raw_fd()), self.len).build()
+    }
+
+    type Buf = ();
+
+    fn into_buf(self) -> Self::Buf {}
+}
+
+impl<'fd> Ftruncate<'fd> {
+    pub fn new(fd: BorrowedHandle<'fd>, len: u64) -> Self {
+        Self { fd, len }
+    }
+}
+
+pub struct Statx {
+    dirfd: RawFd,
+    path: CString,
+    flags: i32,
+    statx: Box<libc::statx>,
+}
+
+impl OpCode for Statx {
+    type Output = Result<crate::fs::Metadata, Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result()?;
+        Ok(crate::fs::Metadata::from_statx(&self.statx))
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        let statx = &mut *self.statx as *mut libc::statx as *mut types::statx;
+        opcode::Statx::new(types::Fd(self.dirfd), self.path.as_ptr(), statx)
+            .flags(self.flags)
+            .mask(libc::STATX_BASIC_STATS)
+            .build()
+    }
+
+    type Buf = (CString, Box<libc::statx>);
+
+    fn into_buf(self) -> Self::Buf {
+        (self.path, self.statx)
+    }
+}
+
+impl Statx {
+    /// Reads the metadata of an open file
+    pub fn new(fd: BorrowedHandle<'_>) -> Self {
+        Self {
+            dirfd: fd.as_raw_fd(),
+            path: CString::default(),
+            flags: libc::AT_EMPTY_PATH,
+            // safety: statx is plain old data, so all zeros is a valid value
+            statx: Box::new(unsafe { std::mem::zeroed() }),
+        }
+    }
+
+    /// Reads the metadata of the file at `path`, following symlinks
+    pub fn path(path: PathBuf) -> Result<Self, Error> {
+        Ok(Self {
+            dirfd: libc::AT_FDCWD,
+            path: path_to_cstring(path)?,
+            flags: 0,
+            // safety: statx is plain old data, so all zeros is a valid value
+            statx: Box::new(unsafe { std::mem::zeroed() }),
+        })
+    }
+}
+
+pub struct Rename {
+    from: CString,
+    to: CString,
+}
+
+impl OpCode for Rename {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::RenameAt::new(
+            types::Fd(libc::AT_FDCWD),
+            self.from.as_ptr(),
+            types::Fd(libc::AT_FDCWD),
+            self.to.as_ptr(),
+        )
+        .build()
+    }
+
+    type Buf = (CString, CString);
+
+    fn into_buf(self) -> Self::Buf {
+        (self.from, self.to)
+    }
+}
+
+impl Rename {
+    pub fn new(from: PathBuf, to: PathBuf) -> Result<Self, Error> {
+        Ok(Self {
+            from: path_to_cstring(from)?,
+            to: path_to_cstring(to)?,
+        })
+    }
+}
+
+pub struct Unlink {
+    path: CString,
+    flags: i32,
+}
+
+impl OpCode for Unlink {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::UnlinkAt::new(types::Fd(libc::AT_FDCWD), self.path.as_ptr())
+            .flags(self.flags)
+            .build()
+    }
+
+    type Buf = CString;
+
+    fn into_buf(self) -> Self::Buf {
+        self.path
+    }
+}
+
+impl Unlink {
+    /// Removes the file at `path`, or the empty directory at `path` if `dir` is set
+    pub fn new(path: PathBuf, dir: bool) -> Result<Self, Error> {
+        Ok(Self {
+            path: path_to_cstring(path)?,
+            flags: if dir { libc::AT_REMOVEDIR } else { 0 },
+        })
+    }
+}
+
+pub struct MkDir {
+    path: CString,
+    mode: libc::mode_t,
+}
+
+impl OpCode for MkDir {
+    type Output = Result<(), Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result().map(|_| ())
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::MkDirAt::new(types::Fd(libc::AT_FDCWD), self.path.as_ptr())
+            .mode(self.mode)
+            .build()
+    }
+
+    type Buf = CString;
+
+    fn into_buf(self) -> Self::Buf {
+        self.path
+    }
+}
+
+impl MkDir {
+    pub fn new(path: PathBuf, mode: u32) -> Result<Self, Error> {
+        Ok(Self {
+            path: path_to_cstring(path)?,
+            mode: mode as libc::mode_t,
+        })
+    }
+}
+
+fn path_to_cstring(path: PathBuf) -> Result<CString, Error> {
+    Ok(CString::new(path.as_os_str().as_bytes()).map_err(std::io::Error::from)?)
+}
+
+/// Waits for a file descriptor to become ready, like `poll(2)` on a single fd
+pub struct PollAdd<'fd> {
+    fd: BorrowedHandle<'fd>,
+    events: u32,
+}
+
+impl OpCode for PollAdd<'_> {
+    type Output = Result<u32, Error>;
+
+    fn output(self, entry: cqueue::Entry) -> Self::Output {
+        entry.as_result()
+    }
+
+    fn output_from_error(self, err: Error) -> Self::Output {
+        Err(err)
+    }
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::PollAdd::new(types::Fd(self.fd.as_raw_fd()), self.events).build()
+    }
+
+    type Buf = ();
+
+    fn into_buf(self) -> Self::Buf {}
+}
+
+impl<'fd> PollAdd<'fd> {
+    pub fn readable(fd: BorrowedHandle<'fd>) -> Self {
+        Self {
+            fd,
+            events: libc::POLLIN as u32,
+        }
+    }
+}
+
 pub struct SendTo<'fd, T> {
     fd: BorrowedHandle<'fd>,
+    fixed: Option<u32>,
     buf: T,
     msghdr: Box<libc::msghdr>,
     #[allow(dead_code)]
@@ -391,11 +823,10 @@ impl<T: IoBuf> OpCode for SendTo<'_, T> {
     }
 
     unsafe fn sqe(&mut self) -> squeue::Entry {
-        opcode::SendMsg::new(
-            types::Fd(self.fd.as_raw_fd()),
-            self.msghdr.as_ref() as *const _,
-        )
-        .build()
+        let msghdr = self.msghdr.as_ref() as *const _;
+        with_target!(Target::new(self.fd.as_raw_fd(), self.fixed), |fd| {
+            opcode::SendMsg::new(fd, msghdr).build()
+        })
     }
 
     type Buf = T;
@@ -418,12 +849,134 @@ impl<'fd, T: IoBuf> SendTo<'fd, T> {
         msghdr.msg_namelen = sock_addr.len;
         Self {
             fd,
+            fixed: None,
             buf,
             msghdr,
             sock_addr,
             io_slices,
         }
     }
+
+    /// Sends through the socket's fixed file slot instead of its descriptor
+    pub fn fixed(mut self, index: Option<u32>) -> Self {
+        self.fixed = index;
+        self
+    }
+}
+
+/// A multishot recv that fills buffers picked from a [`BufRing`], requires Linux 6.0
+pub struct RecvMulti {
+    target: Target,
+    ring: BufRing,
+}
+
+impl MultishotOp for RecvMulti {
+    type Item = Result<ProvidedBuf, Error>;
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        let group = self.ring.group();
+        with_target!(self.target, |fd| opcode::RecvMulti::new(fd, group).build())
+    }
+
+    fn item(&mut self, cqe: cqueue::Entry) -> Self::Item {
+        self.ring.take(&cqe)
+    }
+}
+
+impl RecvMulti {
+    pub fn new(target: Target, ring: BufRing) -> Self {
+        Self { target, ring }
+    }
+}
+
+/// A multishot accept, requires Linux 5.19
+pub struct AcceptMulti {
+    fd: RawFd,
+}
+
+impl MultishotOp for AcceptMulti {
+    type Item = Result<Socket, Error>;
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        opcode::AcceptMulti::new(types::Fd(self.fd)).build()
+    }
+
+    fn item(&mut self, cqe: cqueue::Entry) -> Self::Item {
+        let fd = cqe.as_result()? as RawFd;
+        // safety: io_uring has just given a file-descriptor and we are the sole owner
+        Ok(unsafe { Socket::from_raw_fd(fd) })
+    }
+}
+
+impl AcceptMulti {
+    pub fn new(fd: &Socket) -> Self {
+        Self { fd: fd.as_raw_fd() }
+    }
+}
+
+/// A send that pins `buf`'s pages instead of copying them into the kernel, requires Linux 6.0
+///
+/// The kernel posts the send's result, then a notification once it's done with the buffer.
+pub struct SendZc<T> {
+    target: Target,
+    buf: T,
+    sock_addr: Option<Box<SockAddrRaw>>,
+}
+
+impl<T: IoBuf> MultishotOp for SendZc<T> {
+    /// The send's result, or `None` for the notification that the buffer was released
+    type Item = Option<Result<usize, Error>>;
+
+    unsafe fn sqe(&mut self) -> squeue::Entry {
+        let ptr = self.buf.stable_init_ptr();
+        let len = self.buf.init_len() as u32;
+        let (addr, addr_len) = match &self.sock_addr {
+            Some(addr) => (&raw const addr.storage as *const libc::sockaddr, addr.len),
+            None => (std::ptr::null(), 0),
+        };
+        with_target!(self.target, |fd| {
+            opcode::SendZc::new(fd, ptr, len)
+                .dest_addr(addr)
+                .dest_addr_len(addr_len)
+                .build()
+        })
+    }
+
+    fn item(&mut self, cqe: cqueue::Entry) -> Self::Item {
+        if cqueue::notif(cqe.flags()) {
+            return None;
+        }
+        Some(cqe.as_result().map(|res| res as usize))
+    }
+}
+
+impl<T: IoBuf> SendZc<T> {
+    pub fn new(target: Target, buf: T, sock_addr: Option<SocketAddr>) -> Self {
+        let sock_addr = sock_addr.map(|addr| Box::new(socket2::SockAddr::from(addr).into()));
+        Self {
+            target,
+            buf,
+            sock_addr,
+        }
+    }
+
+    /// Sends the buffer, handing it back once the kernel has released it
+    pub async fn run(self) -> BufResult<usize, T> {
+        let mut multishot = match Multishot::submit(self) {
+            Ok(multishot) => multishot,
+            Err((op, err)) => return (Err(err), op.buf),
+        };
+        let mut res = Err(Error::CompletionStateMissing);
+        while let Some(item) = multishot.next().await {
+            if let Some(sent) = item {
+                res = sent;
+            }
+        }
+        let op = multishot
+            .into_op()
+            .expect("op missing from finished multishot");
+        (res, op.buf)
+    }
 }
 
 #[cfg(test)]
@@ -449,4 +1002,31 @@ mod tests {
         let delta = start.elapsed().as_millis().abs_diff(250);
         assert!(delta <= 10, "Δt ({}) > 10ms", delta)
     }
+
+    #[test]
+    async fn test_timeout_at() {
+        let start = Instant::now();
+        let deadline = crate::os::monotonic_now() + Duration::from_millis(100);
+        Completion::run(Timeout::at(deadline)).await.unwrap();
+        let delta = start.elapsed().as_millis().abs_diff(100);
+        assert!(delta <= 10, "Δt ({}) > 10ms", delta)
+    }
+
+    #[test]
+    async fn test_read_with_timeout() {
+        let listener =
+            crate::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+        client.connect(&addr.into()).unwrap();
+        let _server = listener.accept().await.unwrap();
+
+        let start = Instant::now();
+        let read = Read::new(BorrowedHandle::Socket(&client), vec![0u8; 16], None);
+        let (res, buf) = Completion::run(WithTimeout::new(read, Duration::from_millis(50))).await;
+        assert!(matches!(res, Err(Error::TimedOut)), "{res:?}");
+        assert_eq!(buf.len(), 16);
+        let delta = start.elapsed().as_millis().abs_diff(50);
+        assert!(delta <= 10, "Δt ({}) > 10ms", delta)
+    }
 }
@@ -1,13 +1,17 @@
 use std::{
     cell::UnsafeCell,
+    future::Future,
     mem::MaybeUninit,
+    pin::Pin,
     sync::{
         Arc, Weak,
         atomic::{AtomicBool, Ordering},
     },
+    task::{Context, Poll},
 };
 
 use maitake::sync::WaitQueue;
+use pin_project::pin_project;
 pub use stellarator_buf::AtomicValue;
 
 pub struct CancelTokenInner {
@@ -242,8 +246,45 @@ impl<A: AtomicValue> AtomicCell<A> {
     }
 }
 
+/// The output of whichever future of a [`select`] completed first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// Waits for either `left` or `right` to complete, dropping the other one
+///
+/// `left` is polled first, so it wins if both are ready. See [`crate::select!`] for more than two
+/// futures.
+pub fn select<L: Future, R: Future>(left: L, right: R) -> Select<L, R> {
+    Select { left, right }
+}
+
+#[pin_project]
+pub struct Select<L, R> {
+    #[pin]
+    left: L,
+    #[pin]
+    right: R,
+}
+
+impl<L: Future, R: Future> Future for Select<L, R> {
+    type Output = Either<L::Output, R::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(out) = this.left.poll(cx) {
+            return Poll::Ready(Either::Left(out));
+        }
+        this.right.poll(cx).map(Either::Right)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
     use crate::test;
 
@@ -266,4 +307,29 @@ mod tests {
         parent.cancel();
         assert_eq!(a.is_cancelled(), true);
     }
+
+    #[test]
+    async fn test_select() {
+        let out = select(std::future::pending::<()>(), async { 2 }).await;
+        assert_eq!(out, Either::Right(2));
+        let out = select(async { 1 }, async { 2 }).await;
+        assert_eq!(out, Either::Left(1));
+    }
+
+    #[test]
+    async fn test_select_macro() {
+        let out = crate::select! {
+            _ = crate::sleep(Duration::from_secs(10)) => 0,
+            a = async { 1 } => a,
+            b = async { 2 } => b,
+        };
+        assert_eq!(out, 1);
+        let (tx, rx) = oneshot();
+        tx.send(3);
+        let out = crate::select! {
+            _ = crate::sleep(Duration::from_secs(10)) => 0,
+            val = rx.wait() => val.unwrap(),
+        };
+        assert_eq!(out, 3);
+    }
 }